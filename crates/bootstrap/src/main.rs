@@ -1,36 +1,137 @@
 use std::{env, sync::Arc};
 
 use dotenvy::dotenv;
+use iris::{archive::{ArchiveQuery, FileArchive}, fetch::{FileIrisClient, HttpIrisClient, IrisClient, ResilienceConfig, ResilientIrisClient}};
 use log::info;
 use web::build;
 use web::service::AppService;
-use wrapper_core::{data::{establish_default_pg_pool, run_migrations}, data::repos::{MessageRepo, StationRepo, StatusCodeRepo, StopRepo, TrainRepo}, service::ImportService};
+use wrapper_core::{import::{import_station_data, replay_archived_documents}, io::get_filter_policy, ports::UnitOfWorkPort, service::ImportService};
+use wrapper_core::data::memory::{MemoryDb, MemoryUnitOfWorkRepo};
+#[cfg(feature = "postgres")]
+use wrapper_core::data::{establish_default_pg_pool, run_migrations, repos::{MessageRepo, StationRepo, StatusCodeRepo, StopObservationRepo, StopRepo, TrainRepo, UnitOfWorkRepo}};
+#[cfg(feature = "sqlite")]
+use wrapper_core::data::{establish_sqlite_pool, run_sqlite_migrations, sqlite::{SqliteMessageRepo, SqliteStationRepo, SqliteStatusCodeRepo, SqliteStopObservationRepo, SqliteStopRepo, SqliteTrainRepo, SqliteUnitOfWorkRepo}};
 
-#[rocket::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    dotenv().ok();
-
-    pretty_env_logger::init();
-    info!("Logger init");
-    // Setup Database
+/// Repos backed by Postgres, migrated on startup.
+#[cfg(feature = "postgres")]
+fn postgres_backend(api_base: String) -> (AppService, Arc<dyn UnitOfWorkPort>) {
     let pool = establish_default_pg_pool();
     run_migrations(pool.clone());
 
     let service = AppService {
-        api_base: env::var("API_BASE").unwrap_or(String::from("http://localhost:8080/v1")), // TODO: Maybe the service should panic if this is not set
+        api_base,
         station_repo: Arc::new(StationRepo::new(pool.clone())),
         message_repo: Arc::new(MessageRepo::new(pool.clone())),
         train_repo: Arc::new(TrainRepo::new(pool.clone())),
         stop_repo: Arc::new(StopRepo::new(pool.clone())),
         status_code_repo: Arc::new(StatusCodeRepo::new(pool.clone())),
+        stop_observation_repo: Arc::new(StopObservationRepo::new(pool.clone())),
+    };
+    (service, Arc::new(UnitOfWorkRepo::new(pool)))
+}
+
+/// Repos backed by a SQLite file, created and migrated on startup.
+#[cfg(feature = "sqlite")]
+fn sqlite_backend(api_base: String, path: &str) -> (AppService, Arc<dyn UnitOfWorkPort>) {
+    let pool = establish_sqlite_pool(path);
+    run_sqlite_migrations(pool.clone());
+
+    let service = AppService {
+        api_base,
+        station_repo: Arc::new(SqliteStationRepo::new(pool.clone())),
+        message_repo: Arc::new(SqliteMessageRepo::new(pool.clone())),
+        train_repo: Arc::new(SqliteTrainRepo::new(pool.clone())),
+        stop_repo: Arc::new(SqliteStopRepo::new(pool.clone())),
+        status_code_repo: Arc::new(SqliteStatusCodeRepo::new(pool.clone())),
+        stop_observation_repo: Arc::new(SqliteStopObservationRepo::new(pool.clone())),
+    };
+    (service, Arc::new(SqliteUnitOfWorkRepo::new(pool)))
+}
+
+/// Repos keeping everything in memory, all data is lost on shutdown.
+fn memory_backend(api_base: String) -> (AppService, Arc<dyn UnitOfWorkPort>) {
+    let db = MemoryDb::new();
+    let service = AppService::in_memory(api_base, &db);
+    (service, Arc::new(MemoryUnitOfWorkRepo::new(db)))
+}
+
+/// `DATABASE_URL` schemes of the compiled in backends.
+const SCHEMES: &[&str] = &[
+    "memory:",
+    #[cfg(feature = "sqlite")]
+    "sqlite:",
+    #[cfg(feature = "postgres")]
+    "postgres:",
+];
+
+/// Timetable source from `TIMETABLES_SRC`: `API` (default) uses the IRIS API configured by the
+/// `IRIS_*` variables, `DIR:<path>` reads XML files from a directory (see [`FileIrisClient`]).
+fn iris_client() -> Arc<dyn IrisClient> {
+    let timetables_src = env::var("TIMETABLES_SRC").unwrap_or(String::from("API"));
+    match timetables_src.split_once(':') {
+        None if timetables_src == "API" => {
+            Arc::new(ResilientIrisClient::new(HttpIrisClient::from_env(), ResilienceConfig::from_env()))
+        }
+        Some(("DIR", path)) => {
+            info!("Reading timetables from {}", path);
+            Arc::new(FileIrisClient::new(path))
+        }
+        _ => panic!("Unsupported TIMETABLES_SRC {}, expected API or DIR:<path>", timetables_src),
+    }
+}
+
+#[rocket::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    dotenv().ok();
+
+    pretty_env_logger::init();
+    info!("Logger init");
+    let api_base = env::var("API_BASE").unwrap_or(String::from("http://localhost:8080/v1")); // TODO: Maybe the service should panic if this is not set
+    // Setup Database, the backend is chosen by the scheme of `DATABASE_URL`.
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    let (service, unit_of_work) = match database_url.split_once(':') {
+        Some(("memory", _)) => {
+            info!("Using in-memory backend");
+            memory_backend(api_base)
+        }
+        #[cfg(feature = "sqlite")]
+        Some(("sqlite", path)) => {
+            info!("Using SQLite backend");
+            sqlite_backend(api_base, path.trim_start_matches("//"))
+        }
+        #[cfg(feature = "postgres")]
+        Some(("postgres" | "postgresql", _)) => {
+            info!("Using Postgres backend");
+            postgres_backend(api_base)
+        }
+        _ => panic!("Unsupported DATABASE_URL scheme, this build supports: {}", SCHEMES.join(", ")),
     };
 
+    // `db-iris-wrapper replay` ingests the documents in `IRIS_ARCHIVE_DIR` again and exits.
+    if env::args().nth(1).as_deref() == Some("replay") {
+        let archive_dir = env::var("IRIS_ARCHIVE_DIR").expect("IRIS_ARCHIVE_DIR must be set to replay");
+        let policy = get_filter_policy().expect("Invalid filter policy");
+        let stations = import_station_data(service.station_repo.as_ref(), &policy)?;
+        info!("Station import: {}", stations);
+        let run = replay_archived_documents(
+            &FileArchive::new(archive_dir),
+            &ArchiveQuery::default(),
+            &policy,
+            service.station_repo.as_ref(),
+            service.stop_repo.as_ref(),
+            unit_of_work.as_ref(),
+        )?;
+        println!("{}", run);
+        return Ok(());
+    }
+
     let import_service = ImportService::new(
+        iris_client(),
         service.station_repo.clone(),
-        service.message_repo.clone(),
-        service.train_repo.clone(),
         service.stop_repo.clone(),
-        service.status_code_repo.clone()
+        service.status_code_repo.clone(),
+        unit_of_work,
+        get_filter_policy().expect("Invalid filter policy"),
     );
 
     import_service.start();
@@ -0,0 +1,25 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE stops DROP COLUMN arrival_changed_platform;
+ALTER TABLE stops DROP COLUMN arrival_line;
+ALTER TABLE stops DROP COLUMN arrival_changed_line;
+ALTER TABLE stops DROP COLUMN arrival_planned_status;
+ALTER TABLE stops DROP COLUMN arrival_changed_status;
+ALTER TABLE stops DROP COLUMN arrival_status_changed_at;
+ALTER TABLE stops DROP COLUMN arrival_hidden;
+ALTER TABLE stops DROP COLUMN arrival_wings;
+ALTER TABLE stops DROP COLUMN arrival_transition;
+ALTER TABLE stops DROP COLUMN arrival_planned_distant_endpoint;
+ALTER TABLE stops DROP COLUMN arrival_changed_distant_endpoint;
+ALTER TABLE stops DROP COLUMN arrival_distant_change;
+ALTER TABLE stops DROP COLUMN departure_changed_platform;
+ALTER TABLE stops DROP COLUMN departure_line;
+ALTER TABLE stops DROP COLUMN departure_changed_line;
+ALTER TABLE stops DROP COLUMN departure_planned_status;
+ALTER TABLE stops DROP COLUMN departure_changed_status;
+ALTER TABLE stops DROP COLUMN departure_status_changed_at;
+ALTER TABLE stops DROP COLUMN departure_hidden;
+ALTER TABLE stops DROP COLUMN departure_wings;
+ALTER TABLE stops DROP COLUMN departure_transition;
+ALTER TABLE stops DROP COLUMN departure_planned_distant_endpoint;
+ALTER TABLE stops DROP COLUMN departure_changed_distant_endpoint;
+ALTER TABLE stops DROP COLUMN departure_distant_change;
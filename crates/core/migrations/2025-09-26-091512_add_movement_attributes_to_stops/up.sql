@@ -0,0 +1,25 @@
+-- Your SQL goes here
+ALTER TABLE stops ADD COLUMN arrival_changed_platform TEXT;
+ALTER TABLE stops ADD COLUMN arrival_line TEXT;
+ALTER TABLE stops ADD COLUMN arrival_changed_line TEXT;
+ALTER TABLE stops ADD COLUMN arrival_planned_status TEXT;
+ALTER TABLE stops ADD COLUMN arrival_changed_status TEXT;
+ALTER TABLE stops ADD COLUMN arrival_status_changed_at TIMESTAMP;
+ALTER TABLE stops ADD COLUMN arrival_hidden BOOLEAN;
+ALTER TABLE stops ADD COLUMN arrival_wings TEXT;
+ALTER TABLE stops ADD COLUMN arrival_transition TEXT;
+ALTER TABLE stops ADD COLUMN arrival_planned_distant_endpoint TEXT;
+ALTER TABLE stops ADD COLUMN arrival_changed_distant_endpoint TEXT;
+ALTER TABLE stops ADD COLUMN arrival_distant_change INTEGER;
+ALTER TABLE stops ADD COLUMN departure_changed_platform TEXT;
+ALTER TABLE stops ADD COLUMN departure_line TEXT;
+ALTER TABLE stops ADD COLUMN departure_changed_line TEXT;
+ALTER TABLE stops ADD COLUMN departure_planned_status TEXT;
+ALTER TABLE stops ADD COLUMN departure_changed_status TEXT;
+ALTER TABLE stops ADD COLUMN departure_status_changed_at TIMESTAMP;
+ALTER TABLE stops ADD COLUMN departure_hidden BOOLEAN;
+ALTER TABLE stops ADD COLUMN departure_wings TEXT;
+ALTER TABLE stops ADD COLUMN departure_transition TEXT;
+ALTER TABLE stops ADD COLUMN departure_planned_distant_endpoint TEXT;
+ALTER TABLE stops ADD COLUMN departure_changed_distant_endpoint TEXT;
+ALTER TABLE stops ADD COLUMN departure_distant_change INTEGER;
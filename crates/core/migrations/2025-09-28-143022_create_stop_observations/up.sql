@@ -0,0 +1,13 @@
+-- Your SQL goes here
+CREATE TABLE stop_observations (
+    id          BIGSERIAL PRIMARY KEY,
+    stop_id     TEXT NOT NULL REFERENCES stops(id) ON DELETE CASCADE,
+    kind        TEXT NOT NULL,           -- "arrival" or "departure"
+    observed_at TIMESTAMP NOT NULL,
+    current     TIMESTAMP,
+    platform    TEXT,
+    status      TEXT,
+    CONSTRAINT stop_observations_kind CHECK (kind IN ('arrival', 'departure'))
+);
+
+CREATE INDEX stop_observations_stop_idx ON stop_observations (stop_id, kind, observed_at);
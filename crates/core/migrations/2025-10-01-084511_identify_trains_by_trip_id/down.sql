@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+-- Ids stay in the "<tripId>-<yymmdd>" format, mapping back to numbers is ambiguous.
+DROP INDEX trains_number_date_idx;
+ALTER TABLE trains DROP COLUMN trip_id;
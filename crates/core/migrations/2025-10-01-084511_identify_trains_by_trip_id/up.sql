@@ -0,0 +1,48 @@
+-- Your SQL goes here
+-- Trains were identified by "<number>-<yymmdd>", which collides if operators share a number.
+-- New ids are "<tripId>-<yymmdd>", taken from the stop ids "<tripId>-<yymmddHHMM>-<seq>".
+ALTER TABLE trains ADD COLUMN trip_id TEXT;
+
+CREATE TEMPORARY TABLE stop_trip_ids AS
+SELECT
+    id AS stop_id,
+    train_id AS old_train_id,
+    regexp_replace(id, '^(.+)-[0-9]{10}-[0-9]+$', '\1') AS trip_id,
+    regexp_replace(id, '^(.+)-([0-9]{6})[0-9]{4}-[0-9]+$', '\1-\2') AS new_train_id
+FROM stops
+WHERE id ~ '^.+-[0-9]{10}-[0-9]+$';
+
+INSERT INTO trains (id, operator, category, number, line, date, trip_id)
+SELECT DISTINCT ON (m.new_train_id)
+    m.new_train_id, t.operator, t.category, t.number, t.line, t.date, m.trip_id
+FROM stop_trip_ids m
+JOIN trains t ON t.id = m.old_train_id
+ORDER BY m.new_train_id, t.id
+ON CONFLICT (id) DO NOTHING;
+
+UPDATE stops s
+SET train_id = m.new_train_id
+FROM stop_trip_ids m
+WHERE s.id = m.stop_id;
+
+-- Messages only know the old train, pick the journey of its first stop.
+UPDATE messages msg
+SET train_id = m.new_train_id
+FROM (
+    SELECT DISTINCT ON (old_train_id) old_train_id, new_train_id
+    FROM stop_trip_ids
+    ORDER BY old_train_id, stop_id
+) m
+WHERE msg.train_id = m.old_train_id;
+
+-- Old trains without a mappable stop cannot be identified anymore.
+DELETE FROM messages_to_stations mts
+USING messages msg
+JOIN trains t ON t.id = msg.train_id
+WHERE mts.message_id = msg.id AND t.trip_id IS NULL;
+DELETE FROM stops s USING trains t WHERE s.train_id = t.id AND t.trip_id IS NULL;
+DELETE FROM trains WHERE trip_id IS NULL;
+DROP TABLE stop_trip_ids;
+
+ALTER TABLE trains ALTER COLUMN trip_id SET NOT NULL;
+CREATE INDEX trains_number_date_idx ON trains (number, date);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM messages WHERE train_id IS NULL;
+ALTER TABLE messages ALTER COLUMN train_id SET NOT NULL;
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- Station-wide messages (construction, closures, ...) are not tied to a train.
+ALTER TABLE messages ALTER COLUMN train_id DROP NOT NULL;
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX messages_validity_idx;
+ALTER TABLE messages DROP COLUMN revoked_at;
+ALTER TABLE messages DROP COLUMN last_seen;
+ALTER TABLE messages DROP COLUMN first_seen;
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+ALTER TABLE messages ADD COLUMN first_seen TIMESTAMPTZ;
+ALTER TABLE messages ADD COLUMN last_seen TIMESTAMPTZ;
+ALTER TABLE messages ADD COLUMN revoked_at TIMESTAMPTZ;
+
+UPDATE messages SET first_seen = last_updated, last_seen = last_updated;
+
+CREATE INDEX messages_validity_idx ON messages (valid_from, valid_to) WHERE revoked_at IS NULL;
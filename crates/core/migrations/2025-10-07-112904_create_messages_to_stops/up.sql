@@ -0,0 +1,10 @@
+-- Your SQL goes here
+CREATE TABLE messages_to_stops (
+    message_id  TEXT NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    stop_id     TEXT NOT NULL REFERENCES stops(id)    ON DELETE CASCADE,
+    scope       TEXT NOT NULL,                        -- "stop", "arrival" or "departure"
+    PRIMARY KEY (message_id, stop_id, scope),
+    CONSTRAINT messages_to_stops_scope CHECK (scope IN ('stop', 'arrival', 'departure'))
+);
+
+CREATE INDEX messages_to_stops_stop_id_idx ON messages_to_stops (stop_id);
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+CREATE TABLE station_groups (
+    station_id  INT NOT NULL REFERENCES stations(id) ON DELETE CASCADE,
+    member_id   INT NOT NULL,                         -- related EVA, not necessarily an imported station
+    PRIMARY KEY (station_id, member_id)
+);
+
+CREATE INDEX station_groups_member_id_idx ON station_groups (member_id);
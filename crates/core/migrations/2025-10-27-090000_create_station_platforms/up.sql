@@ -0,0 +1,6 @@
+-- Your SQL goes here
+CREATE TABLE station_platforms (
+    station_id  INT NOT NULL REFERENCES stations(id) ON DELETE CASCADE,
+    platform    TEXT NOT NULL,                        -- normalized, e.g. "5a" and "5b" for "5a/b"
+    PRIMARY KEY (station_id, platform)
+);
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+ALTER TABLE messages_to_stations ADD COLUMN listed BOOLEAN NOT NULL DEFAULT TRUE; -- in the latest fchg of the station
+
+UPDATE messages_to_stations SET listed = FALSE
+WHERE message_id IN (SELECT id FROM messages WHERE revoked_at IS NOT NULL);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX messages_iris_id_idx;
+ALTER TABLE messages DROP COLUMN superseded_by;
@@ -0,0 +1,15 @@
+-- Your SQL goes here
+ALTER TABLE messages ADD COLUMN superseded_by TEXT; -- newest revision with the same iris_id
+
+UPDATE messages SET superseded_by = (
+    SELECT newest.id FROM messages newest
+    WHERE newest.iris_id = messages.iris_id
+    ORDER BY newest.timestamp DESC
+    LIMIT 1
+)
+WHERE EXISTS (
+    SELECT 1 FROM messages newer
+    WHERE newer.iris_id = messages.iris_id AND newer.timestamp > messages.timestamp
+);
+
+CREATE INDEX messages_iris_id_idx ON messages (iris_id);
@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE stop_observations;
+DROP TABLE messages_to_stops;
+DROP TABLE messages_to_stations;
+DROP TABLE status_codes;
+DROP TABLE stops;
+DROP TABLE messages;
+DROP TABLE trains;
+DROP TABLE stations;
@@ -0,0 +1,133 @@
+-- State of the Postgres migrations up to 2025-10-07 in SQLite syntax.
+-- Timestamps are stored as text, `TIMESTAMPTZ` columns in UTC.
+
+CREATE TABLE stations (
+    id      INTEGER PRIMARY KEY,
+    lat     DOUBLE PRECISION,
+    lon     DOUBLE PRECISION,
+    name    TEXT NOT NULL,
+    ds100   TEXT NOT NULL,
+    CONSTRAINT stations_lat_range CHECK (lat IS NULL OR lat BETWEEN -90 AND 90),
+    CONSTRAINT stations_lon_range CHECK (lon IS NULL OR lon BETWEEN -180 AND 180),
+    CONSTRAINT stations_ds100_len CHECK (length(ds100) BETWEEN 1 AND 16)
+);
+
+CREATE INDEX stations_ds100_idx ON stations (ds100);
+
+CREATE TABLE trains (
+    id        TEXT PRIMARY KEY,          -- "<trip_id>-<date>"
+    operator  TEXT,
+    category  TEXT NOT NULL,
+    number    TEXT NOT NULL,
+    line      TEXT,
+    date      DATE NOT NULL,
+    trip_id   TEXT NOT NULL
+);
+
+CREATE INDEX trains_date_idx ON trains (date);
+CREATE INDEX trains_cat_num_idx ON trains (category, number);
+CREATE INDEX trains_number_date_idx ON trains (number, date);
+
+CREATE TABLE messages (
+    id           TEXT PRIMARY KEY,
+    train_id     TEXT REFERENCES trains(id) ON DELETE CASCADE,
+    valid_from   TIMESTAMP,
+    valid_to     TIMESTAMP,
+    priority     SMALLINT,
+    category     TEXT,
+    code         INTEGER,
+    "timestamp"  TIMESTAMP NOT NULL,
+    m_type       TEXT,
+    last_updated TIMESTAMPTZ,
+    iris_id      TEXT NOT NULL,
+    first_seen   TIMESTAMPTZ,
+    last_seen    TIMESTAMPTZ,
+    revoked_at   TIMESTAMPTZ,
+    CONSTRAINT messages_priority_range CHECK (priority IS NULL OR priority BETWEEN 0 AND 255)
+);
+
+CREATE INDEX messages_train_id_idx ON messages (train_id);
+CREATE INDEX messages_ts_idx ON messages ("timestamp");
+CREATE INDEX messages_validity_idx ON messages (valid_from, valid_to) WHERE revoked_at IS NULL;
+
+CREATE TABLE stops (
+    id                      TEXT PRIMARY KEY,
+    train_id                TEXT    NOT NULL REFERENCES trains(id)   ON DELETE CASCADE,
+    station_id              INTEGER NOT NULL REFERENCES stations(id) ON DELETE RESTRICT,
+
+    arrival_platform                    TEXT,
+    arrival_planned                     TIMESTAMP,
+    arrival_planned_path                TEXT,
+    arrival_changed_path                TEXT,
+    departure_platform                  TEXT,
+    departure_planned                   TIMESTAMP,
+    departure_planned_path              TEXT,
+    departure_changed_path              TEXT,
+    arrival_current                     TIMESTAMP,
+    departure_current                   TIMESTAMP,
+    arrival_changed_platform            TEXT,
+    arrival_line                        TEXT,
+    arrival_changed_line                TEXT,
+    arrival_planned_status              TEXT,
+    arrival_changed_status              TEXT,
+    arrival_status_changed_at           TIMESTAMP,
+    arrival_hidden                      BOOLEAN,
+    arrival_wings                       TEXT,
+    arrival_transition                  TEXT,
+    arrival_planned_distant_endpoint    TEXT,
+    arrival_changed_distant_endpoint    TEXT,
+    arrival_distant_change              INTEGER,
+    departure_changed_platform          TEXT,
+    departure_line                      TEXT,
+    departure_changed_line              TEXT,
+    departure_planned_status            TEXT,
+    departure_changed_status            TEXT,
+    departure_status_changed_at         TIMESTAMP,
+    departure_hidden                    BOOLEAN,
+    departure_wings                     TEXT,
+    departure_transition                TEXT,
+    departure_planned_distant_endpoint  TEXT,
+    departure_changed_distant_endpoint  TEXT,
+    departure_distant_change            INTEGER
+);
+
+CREATE INDEX stops_train_id_idx          ON stops (train_id);
+CREATE INDEX stops_station_id_idx        ON stops (station_id);
+CREATE INDEX stops_train_station_idx     ON stops (train_id, station_id);
+CREATE INDEX stops_arrival_planned_idx   ON stops (arrival_planned);
+CREATE INDEX stops_departure_planned_idx ON stops (departure_planned);
+
+CREATE TABLE status_codes (
+    code       SMALLINT PRIMARY KEY,
+    c_type     TEXT,
+    long_text  TEXT NOT NULL
+);
+
+CREATE TABLE messages_to_stations (
+    station_id  INTEGER NOT NULL REFERENCES stations(id) ON DELETE RESTRICT,
+    message_id  TEXT NOT NULL REFERENCES messages(id) ON DELETE RESTRICT,
+    PRIMARY KEY (message_id, station_id)
+);
+
+CREATE TABLE messages_to_stops (
+    message_id  TEXT NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    stop_id     TEXT NOT NULL REFERENCES stops(id)    ON DELETE CASCADE,
+    scope       TEXT NOT NULL,                        -- "stop", "arrival" or "departure"
+    PRIMARY KEY (message_id, stop_id, scope),
+    CONSTRAINT messages_to_stops_scope CHECK (scope IN ('stop', 'arrival', 'departure'))
+);
+
+CREATE INDEX messages_to_stops_stop_id_idx ON messages_to_stops (stop_id);
+
+CREATE TABLE stop_observations (
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    stop_id     TEXT NOT NULL REFERENCES stops(id) ON DELETE CASCADE,
+    kind        TEXT NOT NULL,           -- "arrival" or "departure"
+    observed_at TIMESTAMP NOT NULL,
+    current     TIMESTAMP,
+    platform    TEXT,
+    status      TEXT,
+    CONSTRAINT stop_observations_kind CHECK (kind IN ('arrival', 'departure'))
+);
+
+CREATE INDEX stop_observations_stop_idx ON stop_observations (stop_id, kind, observed_at);
@@ -0,0 +1,7 @@
+CREATE TABLE station_groups (
+    station_id  INTEGER NOT NULL REFERENCES stations(id) ON DELETE CASCADE,
+    member_id   INTEGER NOT NULL,                     -- related EVA, not necessarily an imported station
+    PRIMARY KEY (station_id, member_id)
+);
+
+CREATE INDEX station_groups_member_id_idx ON station_groups (member_id);
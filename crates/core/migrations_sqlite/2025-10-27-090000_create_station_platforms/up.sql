@@ -0,0 +1,5 @@
+CREATE TABLE station_platforms (
+    station_id  INTEGER NOT NULL REFERENCES stations(id) ON DELETE CASCADE,
+    platform    TEXT NOT NULL,                        -- normalized, e.g. "5a" and "5b" for "5a/b"
+    PRIMARY KEY (station_id, platform)
+);
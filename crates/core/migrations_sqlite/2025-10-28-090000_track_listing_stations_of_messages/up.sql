@@ -0,0 +1,4 @@
+ALTER TABLE messages_to_stations ADD COLUMN listed BOOLEAN NOT NULL DEFAULT 1; -- in the latest fchg of the station
+
+UPDATE messages_to_stations SET listed = 0
+WHERE message_id IN (SELECT id FROM messages WHERE revoked_at IS NOT NULL);
@@ -1,12 +1,12 @@
 use std::error::Error;
 
-use iris::{self, dto::Stop};
+use iris::{self, dto::Stop, fetch::IrisClient};
 
 use crate::model::Station;
 
-pub fn get_all_stops_with_codes(id: &str, codes: Vec<i32>) -> Result<Vec<Stop>, Box<dyn Error>> {
-    let station = iris::fetch::get_station(id).map(Station::from_iris)??;
-    let messages = iris::fetch::get_timetable_changes_for_station(station.id)?;
+pub fn get_all_stops_with_codes(client: &dyn IrisClient, id: &str, codes: Vec<i32>) -> Result<Vec<Stop>, Box<dyn Error>> {
+    let station = client.get_station(id).map(Station::from_iris)??;
+    let messages = client.get_timetable_changes(station.id)?;
 
     let stops = messages.stops.into_iter().filter(|s| {
         if s.msgs.iter().any(|m| codes.contains(&m.code.unwrap_or(200))) {
@@ -1,6 +1,17 @@
+#[cfg(not(any(feature = "postgres", feature = "sqlite")))]
+compile_error!("wrapper-core needs the `postgres` or the `sqlite` feature");
+
 mod db;
+mod utils;
 
+#[cfg(feature = "postgres")]
 pub mod repos;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod memory;
 
 // Export for testing
+#[cfg(feature = "postgres")]
 pub use db::{run_migrations, run_sql_file, establish_default_pg_pool, establish_pg_pool};
+#[cfg(feature = "sqlite")]
+pub use db::{run_sqlite_migrations, establish_sqlite_pool, SqlitePool};
@@ -1,28 +1,46 @@
+#[cfg(feature = "postgres")]
 use std::{env, fs};
 
-use diesel::{r2d2::{self, ConnectionManager}, PgConnection, QueryableByName, RunQueryDsl};
+use diesel::r2d2::{self, ConnectionManager};
+#[cfg(feature = "postgres")]
+use diesel::{PgConnection, QueryableByName, RunQueryDsl};
+#[cfg(feature = "sqlite")]
+use diesel::{connection::SimpleConnection, SqliteConnection};
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+#[cfg(feature = "postgres")]
 use dotenvy::dotenv;
 
 pub mod schema;
 pub mod row;
+pub mod sql_types;
 
+#[cfg(feature = "postgres")]
 pub type PgPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+#[cfg(feature = "postgres")]
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("./migrations");
 
+#[cfg(feature = "sqlite")]
+pub type SqlitePool = r2d2::Pool<ConnectionManager<SqliteConnection>>;
+/// The Postgres migrations squashed into SQLite syntax.
+#[cfg(feature = "sqlite")]
+pub const SQLITE_MIGRATIONS: EmbeddedMigrations = embed_migrations!("./migrations_sqlite");
 
+
+#[cfg(feature = "postgres")]
 pub fn run_migrations(pool: PgPool) {
     let mut conn = pool.get().unwrap();
     conn.run_pending_migrations(MIGRATIONS)
         .expect("Migrations failed");
 }
 
+#[cfg(feature = "postgres")]
 pub fn establish_default_pg_pool() -> PgPool {
     dotenv().ok();
     let db_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
     establish_pg_pool(&db_url)
 }
 
+#[cfg(feature = "postgres")]
 pub fn establish_pg_pool(db_url: &str) -> PgPool {
     let manager = ConnectionManager::<PgConnection>::new(db_url);
     r2d2::Pool::builder()
@@ -32,6 +50,7 @@ pub fn establish_pg_pool(db_url: &str) -> PgPool {
 }
 
 
+#[cfg(feature = "postgres")]
 pub fn run_sql_file<T>(pool: &PgPool, path: &str) -> Result<Vec<T>, Box<dyn std::error::Error>>
 where
     T: QueryableByName<diesel::pg::Pg> + 'static,
@@ -57,3 +76,35 @@ where
 
     Ok(last_result.unwrap_or_default())
 }
+
+#[cfg(feature = "sqlite")]
+pub fn run_sqlite_migrations(pool: SqlitePool) {
+    let mut conn = pool.get().unwrap();
+    conn.run_pending_migrations(SQLITE_MIGRATIONS)
+        .expect("Migrations failed");
+}
+
+/// Pool for the SQLite file at `path`, created if missing.
+#[cfg(feature = "sqlite")]
+pub fn establish_sqlite_pool(path: &str) -> SqlitePool {
+    let manager = ConnectionManager::<SqliteConnection>::new(path);
+    r2d2::Pool::builder()
+        .max_size(8)
+        .connection_customizer(Box::new(SqlitePragmas))
+        .build(manager)
+        .expect("Failed to create pool.")
+}
+
+/// SQLite checks foreign keys only if asked to. WAL and the busy timeout let the
+/// importer write while the API reads.
+#[cfg(feature = "sqlite")]
+#[derive(Debug)]
+struct SqlitePragmas;
+
+#[cfg(feature = "sqlite")]
+impl r2d2::CustomizeConnection<SqliteConnection, r2d2::Error> for SqlitePragmas {
+    fn on_acquire(&self, conn: &mut SqliteConnection) -> Result<(), r2d2::Error> {
+        conn.batch_execute("PRAGMA foreign_keys = ON; PRAGMA journal_mode = WAL; PRAGMA busy_timeout = 5000;")
+            .map_err(r2d2::Error::QueryError)
+    }
+}
@@ -1,14 +1,22 @@
 
 mod stop;
+mod stop_observation;
 mod status_code;
 mod message;
 mod station;
+mod station_group;
+mod station_platform;
 mod train;
 mod message_to_station;
+mod message_to_stop;
 
 pub use stop::{*};
+pub use stop_observation::{*};
 pub use status_code::{*};
 pub use message::{*};
 pub use station::{*};
+pub use station_group::{*};
+pub use station_platform::{*};
 pub use train::{*};
 pub use message_to_station::{*};
+pub use message_to_stop::{*};
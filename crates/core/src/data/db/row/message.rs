@@ -1,17 +1,17 @@
-use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono::NaiveDateTime;
 use diesel::{prelude::{Identifiable, Insertable, Queryable}, Selectable};
 
 
-use crate::{data::db::row::MessageToStationRow, model::Message};
+use crate::{data::db::{row::{MessageToStationRow, MessageToStopRow}, sql_types::UtcDateTime}, model::Message};
 
 #[derive(Debug, Clone)]
 #[derive(Queryable, Selectable, Insertable, Identifiable)]
 #[diesel(table_name = crate::data::db::schema::messages)]
-#[diesel(check_for_backend(diesel::pg::Pg))]
+#[cfg_attr(feature = "postgres", diesel(check_for_backend(diesel::pg::Pg)))]
 pub struct MessageRow {
     pub id: String,
     pub iris_id: String,
-    pub train_id: String,
+    pub train_id: Option<String>,
     pub valid_from: Option<NaiveDateTime>,
     pub valid_to: Option<NaiveDateTime>,
     pub priority: Option<i16>,
@@ -19,7 +19,11 @@ pub struct MessageRow {
     pub code: Option<i32>,
     pub timestamp: NaiveDateTime,
     pub m_type: Option<String>,
-    pub last_updated: Option<DateTime<Utc>>,
+    pub last_updated: Option<UtcDateTime>,
+    pub first_seen: Option<UtcDateTime>,
+    pub last_seen: Option<UtcDateTime>,
+    pub revoked_at: Option<UtcDateTime>,
+    pub superseded_by: Option<String>,
 }
 
 impl From<Message> for MessageRow {
@@ -41,13 +45,17 @@ impl From<&Message> for MessageRow {
             code: msg.code,
             timestamp: msg.timestamp,
             m_type: msg.m_type.clone(),
-            last_updated: msg.last_updated,
+            last_updated: msg.last_updated.map(UtcDateTime),
+            first_seen: msg.first_seen.map(UtcDateTime),
+            last_seen: msg.last_seen.map(UtcDateTime),
+            revoked_at: msg.revoked_at.map(UtcDateTime),
+            superseded_by: msg.superseded_by.clone(),
         }
     }
 }
 
 impl MessageRow {
-    pub fn to_message(&self, stations: &[MessageToStationRow]) -> Message {
+    pub fn to_message(&self, stations: &[MessageToStationRow], stops: &[MessageToStopRow]) -> Message {
         Message {
             id: self.id.clone(),
             iris_id: self.iris_id.clone(),
@@ -59,8 +67,13 @@ impl MessageRow {
             code: self.code,
             timestamp: self.timestamp,
             m_type: self.m_type.clone(),
-            last_updated: self.last_updated,
-            stations: stations.iter().map(|s| s.station_id).collect::<Vec<i32>>()
+            last_updated: self.last_updated.map(|t| t.0),
+            first_seen: self.first_seen.map(|t| t.0),
+            last_seen: self.last_seen.map(|t| t.0),
+            revoked_at: self.revoked_at.map(|t| t.0),
+            superseded_by: self.superseded_by.clone(),
+            stations: stations.iter().map(|s| s.station_id).collect::<Vec<i32>>(),
+            stops: stops.iter().filter_map(MessageToStopRow::to_message_stop).collect(),
         }
     }
 }
@@ -5,10 +5,19 @@ use crate::data::db::row::MessageRow;
 #[derive(Debug, Clone)]
 #[derive(Queryable, Selectable, Insertable, Identifiable, Associations)]
 #[diesel(table_name = crate::data::db::schema::messages_to_stations)]
-#[diesel(check_for_backend(diesel::pg::Pg))]
+#[cfg_attr(feature = "postgres", diesel(check_for_backend(diesel::pg::Pg)))]
 #[diesel(primary_key(message_id, station_id))]
 #[diesel(belongs_to(MessageRow, foreign_key = message_id))]
 pub struct MessageToStationRow {
     pub station_id: i32,
     pub message_id: String,
+    /// Whether the latest changes document of the station contains the message.
+    pub listed: bool,
+}
+
+impl MessageToStationRow {
+    /// Link of a message listed by the station.
+    pub fn new(message_id: &str, station_id: i32) -> Self {
+        MessageToStationRow { station_id, message_id: message_id.to_string(), listed: true }
+    }
 }
@@ -0,0 +1,32 @@
+use diesel::{prelude::{Associations, Identifiable, Insertable, Queryable}, Selectable};
+
+use crate::{data::db::row::MessageRow, model::{MessageScope, MessageStop}};
+
+#[derive(Debug, Clone)]
+#[derive(Queryable, Selectable, Insertable, Identifiable, Associations)]
+#[diesel(table_name = crate::data::db::schema::messages_to_stops)]
+#[cfg_attr(feature = "postgres", diesel(check_for_backend(diesel::pg::Pg)))]
+#[diesel(primary_key(message_id, stop_id, scope))]
+#[diesel(belongs_to(MessageRow, foreign_key = message_id))]
+pub struct MessageToStopRow {
+    pub message_id: String,
+    pub stop_id: String,
+    pub scope: String,
+}
+
+impl MessageToStopRow {
+    pub fn new(message_id: &str, stop: &MessageStop) -> Self {
+        MessageToStopRow {
+            message_id: message_id.to_string(),
+            stop_id: stop.stop_id.clone(),
+            scope: stop.scope.as_str().to_string(),
+        }
+    }
+
+    pub fn to_message_stop(&self) -> Option<MessageStop> {
+        Some(MessageStop {
+            stop_id: self.stop_id.clone(),
+            scope: MessageScope::from_code(&self.scope)?,
+        })
+    }
+}
@@ -5,7 +5,7 @@ use crate::model::Station;
 #[derive(Debug, Clone)]
 #[derive(Queryable, QueryableByName, Selectable, Insertable)]
 #[diesel(table_name = crate::data::db::schema::stations)]
-#[diesel(check_for_backend(diesel::pg::Pg))]
+#[cfg_attr(feature = "postgres", diesel(check_for_backend(diesel::pg::Pg)))]
 pub struct StationRow {
     pub id: i32,
     pub lat: Option<f64>,
@@ -0,0 +1,21 @@
+use diesel::{prelude::{Insertable, Queryable}, Selectable};
+
+use crate::model::StationGroup;
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = crate::data::db::schema::station_groups)]
+#[cfg_attr(feature = "postgres", diesel(check_for_backend(diesel::pg::Pg)))]
+pub struct StationGroupRow {
+    pub station_id: i32,
+    pub member_id: i32,
+}
+
+impl StationGroupRow {
+    pub fn from_group(group: &StationGroup) -> Vec<Self> {
+        group.member_ids
+            .iter()
+            .map(|member_id| StationGroupRow { station_id: group.station_id, member_id: *member_id })
+            .collect()
+    }
+}
@@ -0,0 +1,21 @@
+use diesel::{prelude::{Insertable, Queryable}, Selectable};
+
+use crate::model::StationPlatforms;
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = crate::data::db::schema::station_platforms)]
+#[cfg_attr(feature = "postgres", diesel(check_for_backend(diesel::pg::Pg)))]
+pub struct StationPlatformRow {
+    pub station_id: i32,
+    pub platform: String,
+}
+
+impl StationPlatformRow {
+    pub fn from_platforms(platforms: &StationPlatforms) -> Vec<Self> {
+        platforms.platforms
+            .iter()
+            .map(|platform| StationPlatformRow { station_id: platforms.station_id, platform: platform.clone() })
+            .collect()
+    }
+}
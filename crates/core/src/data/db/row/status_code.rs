@@ -4,7 +4,7 @@ use crate::model::{StatusCode, StatusCodeType};
 #[derive(Debug, Clone)]
 #[derive(Queryable, QueryableByName, Selectable, Insertable)]
 #[diesel(table_name = crate::data::db::schema::status_codes)]
-#[diesel(check_for_backend(diesel::pg::Pg))]
+#[cfg_attr(feature = "postgres", diesel(check_for_backend(diesel::pg::Pg)))]
 pub struct StatusCodeRow {
     pub code: i16,
     pub c_type: Option<String>,
@@ -1,10 +1,12 @@
 use chrono::NaiveDateTime;
 use diesel::*;
-use crate::model::{Movement, Stop, StopUpdate};
+use crate::model::{EventStatus, Movement, Stop, StopUpdate};
 
-#[derive(Queryable, Selectable, Insertable)]
+#[derive(Debug, Clone)]
+#[derive(Queryable, Selectable, Insertable, AsChangeset)]
 #[diesel(table_name = crate::data::db::schema::stops)]
-#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(treat_none_as_null = true)]
+#[cfg_attr(feature = "postgres", diesel(check_for_backend(diesel::pg::Pg)))]
 pub struct StopRow {
     pub id: String,
     pub train_id: String,
@@ -19,6 +21,30 @@ pub struct StopRow {
     pub departure_changed_path: Option<String>,
     pub arrival_current: Option<NaiveDateTime>,
     pub departure_current: Option<NaiveDateTime>,
+    pub arrival_changed_platform: Option<String>,
+    pub arrival_line: Option<String>,
+    pub arrival_changed_line: Option<String>,
+    pub arrival_planned_status: Option<String>,
+    pub arrival_changed_status: Option<String>,
+    pub arrival_status_changed_at: Option<NaiveDateTime>,
+    pub arrival_hidden: Option<bool>,
+    pub arrival_wings: Option<String>,
+    pub arrival_transition: Option<String>,
+    pub arrival_planned_distant_endpoint: Option<String>,
+    pub arrival_changed_distant_endpoint: Option<String>,
+    pub arrival_distant_change: Option<i32>,
+    pub departure_changed_platform: Option<String>,
+    pub departure_line: Option<String>,
+    pub departure_changed_line: Option<String>,
+    pub departure_planned_status: Option<String>,
+    pub departure_changed_status: Option<String>,
+    pub departure_status_changed_at: Option<NaiveDateTime>,
+    pub departure_hidden: Option<bool>,
+    pub departure_wings: Option<String>,
+    pub departure_transition: Option<String>,
+    pub departure_planned_distant_endpoint: Option<String>,
+    pub departure_changed_distant_endpoint: Option<String>,
+    pub departure_distant_change: Option<i32>,
 }
 
 impl From<&Stop> for StopRow {
@@ -26,23 +52,47 @@ impl From<&Stop> for StopRow {
         stop: &Stop,
     ) -> Self {
 
-        let dep_mov = movement_to_columns(&stop.departure);
-        let arr_mov = movement_to_columns(&stop.arrival);
+        let dep_mov = MovementColumns::from(&stop.departure);
+        let arr_mov = MovementColumns::from(&stop.arrival);
 
         StopRow {
             id: stop.id.to_owned(),
             train_id: stop.train_id.to_owned(),
             station_id: stop.station_id,
-            arrival_platform: arr_mov.0,
-            arrival_planned: arr_mov.1,
-            arrival_current: arr_mov.2,
-            arrival_planned_path: arr_mov.3,
-            arrival_changed_path: arr_mov.4,
-            departure_platform: dep_mov.0,
-            departure_planned: dep_mov.1,
-            departure_current: dep_mov.2,
-            departure_planned_path: dep_mov.3,
-            departure_changed_path: dep_mov.4
+            arrival_platform: arr_mov.platform,
+            arrival_planned: arr_mov.planned,
+            arrival_current: arr_mov.current,
+            arrival_planned_path: arr_mov.planned_path,
+            arrival_changed_path: arr_mov.changed_path,
+            arrival_changed_platform: arr_mov.changed_platform,
+            arrival_line: arr_mov.line,
+            arrival_changed_line: arr_mov.changed_line,
+            arrival_planned_status: arr_mov.planned_status,
+            arrival_changed_status: arr_mov.changed_status,
+            arrival_status_changed_at: arr_mov.status_changed_at,
+            arrival_hidden: arr_mov.hidden,
+            arrival_wings: arr_mov.wings,
+            arrival_transition: arr_mov.transition,
+            arrival_planned_distant_endpoint: arr_mov.planned_distant_endpoint,
+            arrival_changed_distant_endpoint: arr_mov.changed_distant_endpoint,
+            arrival_distant_change: arr_mov.distant_change,
+            departure_platform: dep_mov.platform,
+            departure_planned: dep_mov.planned,
+            departure_current: dep_mov.current,
+            departure_planned_path: dep_mov.planned_path,
+            departure_changed_path: dep_mov.changed_path,
+            departure_changed_platform: dep_mov.changed_platform,
+            departure_line: dep_mov.line,
+            departure_changed_line: dep_mov.changed_line,
+            departure_planned_status: dep_mov.planned_status,
+            departure_changed_status: dep_mov.changed_status,
+            departure_status_changed_at: dep_mov.status_changed_at,
+            departure_hidden: dep_mov.hidden,
+            departure_wings: dep_mov.wings,
+            departure_transition: dep_mov.transition,
+            departure_planned_distant_endpoint: dep_mov.planned_distant_endpoint,
+            departure_changed_distant_endpoint: dep_mov.changed_distant_endpoint,
+            departure_distant_change: dep_mov.distant_change,
         }
     }
 }
@@ -53,90 +103,373 @@ impl StopRow {
             id: self.id.clone(),
             train_id: self.train_id.clone(),
             station_id: self.station_id,
-            departure: movement_from_columns(
-                self.departure_platform.clone(),
-                self.departure_planned,
-                self.departure_current,
-                self.departure_planned_path.clone(),
-                self.departure_changed_path.clone()
-            ),
-            arrival: movement_from_columns(
-                self.arrival_platform.clone(),
-                self.arrival_planned,
-                self.arrival_current,
-                self.arrival_planned_path.clone(),
-                self.arrival_changed_path.clone()
-            )
+            departure: MovementColumns {
+                platform: self.departure_platform.clone(),
+                changed_platform: self.departure_changed_platform.clone(),
+                planned: self.departure_planned,
+                current: self.departure_current,
+                planned_path: self.departure_planned_path.clone(),
+                changed_path: self.departure_changed_path.clone(),
+                line: self.departure_line.clone(),
+                changed_line: self.departure_changed_line.clone(),
+                planned_status: self.departure_planned_status.clone(),
+                changed_status: self.departure_changed_status.clone(),
+                status_changed_at: self.departure_status_changed_at,
+                hidden: self.departure_hidden,
+                wings: self.departure_wings.clone(),
+                transition: self.departure_transition.clone(),
+                planned_distant_endpoint: self.departure_planned_distant_endpoint.clone(),
+                changed_distant_endpoint: self.departure_changed_distant_endpoint.clone(),
+                distant_change: self.departure_distant_change,
+            }.into_movement(),
+            arrival: MovementColumns {
+                platform: self.arrival_platform.clone(),
+                changed_platform: self.arrival_changed_platform.clone(),
+                planned: self.arrival_planned,
+                current: self.arrival_current,
+                planned_path: self.arrival_planned_path.clone(),
+                changed_path: self.arrival_changed_path.clone(),
+                line: self.arrival_line.clone(),
+                changed_line: self.arrival_changed_line.clone(),
+                planned_status: self.arrival_planned_status.clone(),
+                changed_status: self.arrival_changed_status.clone(),
+                status_changed_at: self.arrival_status_changed_at,
+                hidden: self.arrival_hidden,
+                wings: self.arrival_wings.clone(),
+                transition: self.arrival_transition.clone(),
+                planned_distant_endpoint: self.arrival_planned_distant_endpoint.clone(),
+                changed_distant_endpoint: self.arrival_changed_distant_endpoint.clone(),
+                distant_change: self.arrival_distant_change,
+            }.into_movement(),
         }
     }
-}
-
-type MovementRowPart = (Option<String>, Option<NaiveDateTime>, Option<NaiveDateTime>, Option<String>, Option<String>);
 
-fn movement_to_columns(movement: &Option<Movement>) -> MovementRowPart {
-    match movement {
-        Some(movement) => {
-            let planned_path = movement.planned_path.as_ref().map(|p| p.join(","));
-            let current_path = movement.changed_path.as_ref().map(|p| p.join(","));
-            (movement.platform.clone(), movement.planned, movement.current, planned_path, current_path)
+    /// Copies the planned timetable fields of `other`, like the upsert in the stop repo does.
+    /// Returns whether one of them changed.
+    pub fn update_planned_from(&mut self, other: &StopRow) -> bool {
+        let mut changed = false;
+        macro_rules! planned {
+            ($($field:ident),* $(,)?) => {$(
+                if self.$field != other.$field {
+                    self.$field = other.$field.clone();
+                    changed = true;
+                }
+            )*};
         }
-        None => (None, None, None, None, None)
+        planned!(
+            arrival_platform,
+            arrival_planned,
+            arrival_planned_path,
+            arrival_line,
+            arrival_planned_status,
+            arrival_hidden,
+            arrival_wings,
+            arrival_transition,
+            arrival_planned_distant_endpoint,
+            departure_platform,
+            departure_planned,
+            departure_planned_path,
+            departure_line,
+            departure_planned_status,
+            departure_hidden,
+            departure_wings,
+            departure_transition,
+            departure_planned_distant_endpoint,
+        );
+        changed
     }
 }
 
-fn movement_from_columns(
+/// Column values of one movement (arrival or departure), lists joined by `,`.
+#[derive(Default)]
+struct MovementColumns {
     platform: Option<String>,
+    changed_platform: Option<String>,
     planned: Option<NaiveDateTime>,
     current: Option<NaiveDateTime>,
     planned_path: Option<String>,
-    changed_path: Option<String>
-) -> Option<Movement>  {
-    if platform.is_none() && planned.is_none() && planned_path.is_none() && changed_path.is_none() {
-        None
-    } else {
+    changed_path: Option<String>,
+    line: Option<String>,
+    changed_line: Option<String>,
+    planned_status: Option<String>,
+    changed_status: Option<String>,
+    status_changed_at: Option<NaiveDateTime>,
+    hidden: Option<bool>,
+    wings: Option<String>,
+    transition: Option<String>,
+    planned_distant_endpoint: Option<String>,
+    changed_distant_endpoint: Option<String>,
+    distant_change: Option<i32>,
+}
+
+impl From<&Option<Movement>> for MovementColumns {
+    fn from(movement: &Option<Movement>) -> Self {
+        let Some(movement) = movement else {
+            return MovementColumns::default();
+        };
+
+        MovementColumns {
+            platform: movement.platform.clone(),
+            changed_platform: movement.changed_platform.clone(),
+            planned: movement.planned,
+            current: movement.current,
+            planned_path: movement.planned_path.as_ref().map(|p| p.join(",")),
+            changed_path: movement.changed_path.as_ref().map(|p| p.join(",")),
+            line: movement.line.clone(),
+            changed_line: movement.changed_line.clone(),
+            planned_status: movement.planned_status.map(|s| s.as_str().to_string()),
+            changed_status: movement.changed_status.map(|s| s.as_str().to_string()),
+            status_changed_at: movement.status_changed_at,
+            hidden: movement.hidden,
+            wings: movement.wings.as_ref().map(|w| w.join(",")),
+            transition: movement.transition.clone(),
+            planned_distant_endpoint: movement.planned_distant_endpoint.clone(),
+            changed_distant_endpoint: movement.changed_distant_endpoint.clone(),
+            distant_change: movement.distant_change,
+        }
+    }
+}
+
+impl MovementColumns {
+    fn into_movement(self) -> Option<Movement> {
+        let is_empty = self.platform.is_none()
+            && self.changed_platform.is_none()
+            && self.planned.is_none()
+            && self.current.is_none()
+            && self.planned_path.is_none()
+            && self.changed_path.is_none()
+            && self.line.is_none()
+            && self.changed_line.is_none()
+            && self.planned_status.is_none()
+            && self.changed_status.is_none()
+            && self.status_changed_at.is_none()
+            && self.hidden.is_none()
+            && self.wings.is_none()
+            && self.transition.is_none()
+            && self.planned_distant_endpoint.is_none()
+            && self.changed_distant_endpoint.is_none()
+            && self.distant_change.is_none();
+        if is_empty {
+            return None;
+        }
+
         Some(Movement {
-            platform,
-            planned,
-            current,
-            planned_path: planned_path.map(|p| p.split(',').map(String::from).collect()),
-            changed_path: changed_path.map(|p| p.split(',').map(String::from).collect())
+            platform: self.platform,
+            changed_platform: self.changed_platform,
+            planned: self.planned,
+            current: self.current,
+            planned_path: self.planned_path.map(|p| p.split(',').map(String::from).collect()),
+            changed_path: self.changed_path.map(|p| p.split(',').map(String::from).collect()),
+            line: self.line,
+            changed_line: self.changed_line,
+            planned_status: self.planned_status.as_deref().and_then(EventStatus::from_code),
+            changed_status: self.changed_status.as_deref().and_then(EventStatus::from_code),
+            status_changed_at: self.status_changed_at,
+            hidden: self.hidden,
+            wings: self.wings.map(|w| w.split(',').map(String::from).collect()),
+            transition: self.transition,
+            planned_distant_endpoint: self.planned_distant_endpoint,
+            changed_distant_endpoint: self.changed_distant_endpoint,
+            distant_change: self.distant_change,
         })
     }
 }
 
 
+/// Columns are only written if set, `Some(None)` clears a changed column.
 #[derive(Debug, Clone, AsChangeset)]
 #[diesel(table_name = crate::data::db::schema::stops)]
 pub struct StopUpdateRow {
     pub arrival_platform: Option<String>,
     pub arrival_planned: Option<NaiveDateTime>,
     pub arrival_planned_path: Option<String>,
-    pub arrival_changed_path: Option<String>,
+    pub arrival_changed_path: Option<Option<String>>,
     pub departure_platform: Option<String>,
     pub departure_planned: Option<NaiveDateTime>,
     pub departure_planned_path: Option<String>,
-    pub departure_changed_path: Option<String>,
-    pub arrival_current: Option<NaiveDateTime>,
-    pub departure_current: Option<NaiveDateTime>,
+    pub departure_changed_path: Option<Option<String>>,
+    pub arrival_current: Option<Option<NaiveDateTime>>,
+    pub departure_current: Option<Option<NaiveDateTime>>,
+    pub arrival_changed_platform: Option<Option<String>>,
+    pub arrival_line: Option<String>,
+    pub arrival_changed_line: Option<Option<String>>,
+    pub arrival_planned_status: Option<String>,
+    pub arrival_changed_status: Option<Option<String>>,
+    pub arrival_status_changed_at: Option<Option<NaiveDateTime>>,
+    pub arrival_hidden: Option<bool>,
+    pub arrival_wings: Option<String>,
+    pub arrival_transition: Option<String>,
+    pub arrival_planned_distant_endpoint: Option<String>,
+    pub arrival_changed_distant_endpoint: Option<Option<String>>,
+    pub arrival_distant_change: Option<Option<i32>>,
+    pub departure_changed_platform: Option<Option<String>>,
+    pub departure_line: Option<String>,
+    pub departure_changed_line: Option<Option<String>>,
+    pub departure_planned_status: Option<String>,
+    pub departure_changed_status: Option<Option<String>>,
+    pub departure_status_changed_at: Option<Option<NaiveDateTime>>,
+    pub departure_hidden: Option<bool>,
+    pub departure_wings: Option<String>,
+    pub departure_transition: Option<String>,
+    pub departure_planned_distant_endpoint: Option<String>,
+    pub departure_changed_distant_endpoint: Option<Option<String>>,
+    pub departure_distant_change: Option<Option<i32>>,
 }
 
 
+/// Changed column of an update, cleared if `replace` and unset otherwise.
+fn change<T>(value: Option<T>, replace: bool) -> Option<Option<T>> {
+    if replace {
+        Some(value)
+    } else {
+        value.map(Some)
+    }
+}
+
 impl From<&StopUpdate> for StopUpdateRow {
     fn from(stop: &StopUpdate) -> Self {
-        let dep_mov = movement_to_columns(&stop.departure);
-        let arr_mov = movement_to_columns(&stop.arrival);
+        let row = StopRow::from(&Stop {
+            id: stop.id.clone(),
+            train_id: String::new(),
+            station_id: 0,
+            arrival: stop.arrival.clone(),
+            departure: stop.departure.clone(),
+        });
+        let arrival = stop.replaces_changes && stop.arrival.is_some();
+        let departure = stop.replaces_changes && stop.departure.is_some();
 
         StopUpdateRow {
-            arrival_platform: arr_mov.0,
-            arrival_planned: arr_mov.1,
-            arrival_current: arr_mov.2,
-            arrival_planned_path: arr_mov.3,
-            arrival_changed_path: arr_mov.4,
-            departure_platform: dep_mov.0,
-            departure_planned: dep_mov.1,
-            departure_current: dep_mov.2,
-            departure_planned_path: dep_mov.3,
-            departure_changed_path: dep_mov.4,
+            arrival_platform: row.arrival_platform,
+            arrival_planned: row.arrival_planned,
+            arrival_current: change(row.arrival_current, arrival),
+            arrival_planned_path: row.arrival_planned_path,
+            arrival_changed_path: change(row.arrival_changed_path, arrival),
+            arrival_changed_platform: change(row.arrival_changed_platform, arrival),
+            arrival_line: row.arrival_line,
+            arrival_changed_line: change(row.arrival_changed_line, arrival),
+            arrival_planned_status: row.arrival_planned_status,
+            arrival_changed_status: change(row.arrival_changed_status, arrival),
+            arrival_status_changed_at: change(row.arrival_status_changed_at, arrival),
+            arrival_hidden: row.arrival_hidden,
+            arrival_wings: row.arrival_wings,
+            arrival_transition: row.arrival_transition,
+            arrival_planned_distant_endpoint: row.arrival_planned_distant_endpoint,
+            arrival_changed_distant_endpoint: change(row.arrival_changed_distant_endpoint, arrival),
+            arrival_distant_change: change(row.arrival_distant_change, arrival),
+            departure_platform: row.departure_platform,
+            departure_planned: row.departure_planned,
+            departure_current: change(row.departure_current, departure),
+            departure_planned_path: row.departure_planned_path,
+            departure_changed_path: change(row.departure_changed_path, departure),
+            departure_changed_platform: change(row.departure_changed_platform, departure),
+            departure_line: row.departure_line,
+            departure_changed_line: change(row.departure_changed_line, departure),
+            departure_planned_status: row.departure_planned_status,
+            departure_changed_status: change(row.departure_changed_status, departure),
+            departure_status_changed_at: change(row.departure_status_changed_at, departure),
+            departure_hidden: row.departure_hidden,
+            departure_wings: row.departure_wings,
+            departure_transition: row.departure_transition,
+            departure_planned_distant_endpoint: row.departure_planned_distant_endpoint,
+            departure_changed_distant_endpoint: change(row.departure_changed_distant_endpoint, departure),
+            departure_distant_change: change(row.departure_distant_change, departure),
+        }
+    }
+}
+
+impl StopUpdateRow {
+    /// Whether the update sets no column. Diesel rejects such changesets.
+    pub fn is_empty(&self) -> bool {
+        self.arrival_platform.is_none()
+            && self.arrival_planned.is_none()
+            && self.arrival_planned_path.is_none()
+            && self.arrival_changed_path.is_none()
+            && self.departure_platform.is_none()
+            && self.departure_planned.is_none()
+            && self.departure_planned_path.is_none()
+            && self.departure_changed_path.is_none()
+            && self.arrival_current.is_none()
+            && self.departure_current.is_none()
+            && self.arrival_changed_platform.is_none()
+            && self.arrival_line.is_none()
+            && self.arrival_changed_line.is_none()
+            && self.arrival_planned_status.is_none()
+            && self.arrival_changed_status.is_none()
+            && self.arrival_status_changed_at.is_none()
+            && self.arrival_hidden.is_none()
+            && self.arrival_wings.is_none()
+            && self.arrival_transition.is_none()
+            && self.arrival_planned_distant_endpoint.is_none()
+            && self.arrival_changed_distant_endpoint.is_none()
+            && self.arrival_distant_change.is_none()
+            && self.departure_changed_platform.is_none()
+            && self.departure_line.is_none()
+            && self.departure_changed_line.is_none()
+            && self.departure_planned_status.is_none()
+            && self.departure_changed_status.is_none()
+            && self.departure_status_changed_at.is_none()
+            && self.departure_hidden.is_none()
+            && self.departure_wings.is_none()
+            && self.departure_transition.is_none()
+            && self.departure_planned_distant_endpoint.is_none()
+            && self.departure_changed_distant_endpoint.is_none()
+            && self.departure_distant_change.is_none()
+    }
+
+    /// Writes the set columns to `row`, columns that are `None` are left untouched.
+    /// Changed columns set to `Some(None)` are cleared.
+    pub fn apply_to(&self, row: &mut StopRow) {
+        macro_rules! apply {
+            ($($field:ident),* $(,)?) => {$(
+                if let Some(value) = &self.$field {
+                    row.$field = Some(value.clone());
+                }
+            )*};
+        }
+        macro_rules! apply_change {
+            ($($field:ident),* $(,)?) => {$(
+                if let Some(value) = &self.$field {
+                    row.$field = value.clone();
+                }
+            )*};
         }
+        apply!(
+            arrival_platform,
+            arrival_planned,
+            arrival_planned_path,
+            departure_platform,
+            departure_planned,
+            departure_planned_path,
+            arrival_line,
+            arrival_planned_status,
+            arrival_hidden,
+            arrival_wings,
+            arrival_transition,
+            arrival_planned_distant_endpoint,
+            departure_line,
+            departure_planned_status,
+            departure_hidden,
+            departure_wings,
+            departure_transition,
+            departure_planned_distant_endpoint,
+        );
+        apply_change!(
+            arrival_current,
+            arrival_changed_platform,
+            arrival_changed_path,
+            arrival_changed_line,
+            arrival_changed_status,
+            arrival_status_changed_at,
+            arrival_changed_distant_endpoint,
+            arrival_distant_change,
+            departure_current,
+            departure_changed_platform,
+            departure_changed_path,
+            departure_changed_line,
+            departure_changed_status,
+            departure_status_changed_at,
+            departure_changed_distant_endpoint,
+            departure_distant_change,
+        );
     }
 }
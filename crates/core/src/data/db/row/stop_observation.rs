@@ -0,0 +1,54 @@
+use chrono::NaiveDateTime;
+use diesel::*;
+
+use crate::model::{EventStatus, MovementKind, StopObservation};
+
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = crate::data::db::schema::stop_observations)]
+#[cfg_attr(feature = "postgres", diesel(check_for_backend(diesel::pg::Pg)))]
+pub struct StopObservationRow {
+    pub stop_id: String,
+    pub kind: String,
+    pub observed_at: NaiveDateTime,
+    pub current: Option<NaiveDateTime>,
+    pub platform: Option<String>,
+    pub status: Option<String>,
+}
+
+impl StopObservationRow {
+    /// `None` for rows with an unknown kind.
+    pub fn to_stop_observation(&self) -> Option<StopObservation> {
+        Some(StopObservation {
+            stop_id: self.stop_id.clone(),
+            kind: MovementKind::from_code(&self.kind)?,
+            observed_at: self.observed_at,
+            current: self.current,
+            platform: self.platform.clone(),
+            status: self.status.as_deref().and_then(EventStatus::from_code),
+        })
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::data::db::schema::stop_observations)]
+pub struct NewStopObservationRow {
+    pub stop_id: String,
+    pub kind: String,
+    pub observed_at: NaiveDateTime,
+    pub current: Option<NaiveDateTime>,
+    pub platform: Option<String>,
+    pub status: Option<String>,
+}
+
+impl From<&StopObservation> for NewStopObservationRow {
+    fn from(observation: &StopObservation) -> Self {
+        NewStopObservationRow {
+            stop_id: observation.stop_id.clone(),
+            kind: observation.kind.as_str().to_string(),
+            observed_at: observation.observed_at,
+            current: observation.current,
+            platform: observation.platform.clone(),
+            status: observation.status.map(|s| s.as_str().to_string()),
+        }
+    }
+}
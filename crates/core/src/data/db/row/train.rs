@@ -5,22 +5,24 @@ use crate::model::Train;
 
 #[derive(Queryable, Selectable, Insertable)]
 #[diesel(table_name = crate::data::db::schema::trains)]
-#[diesel(check_for_backend(diesel::pg::Pg))]
+#[cfg_attr(feature = "postgres", diesel(check_for_backend(diesel::pg::Pg)))]
 #[derive(Debug, Clone)]
 pub struct TrainRow {
-    pub id: String, // Custom ID: format: number-date
+    pub id: String, // Journey key: format: tripId-date
     pub operator: Option<String>,
     pub category: String,
     pub number: String,
     pub line: Option<String>,
     // pub stops: Vec<Stop>,
     pub date: NaiveDate,
+    pub trip_id: String,
 }
 
 impl From<&TrainRow> for Train {
     fn from(row: &TrainRow) -> Self {
         Train {
             id: row.id.clone(),
+            trip_id: row.trip_id.clone(),
             operator: row.operator.clone(),
             category: row.category.clone(),
             number: row.number.clone(),
@@ -40,6 +42,7 @@ impl From<&Train> for TrainRow {
     fn from(train: &Train) -> Self {
         TrainRow {
             id: train.id.clone(),
+            trip_id: train.trip_id.clone(),
             operator: train.operator.clone(),
             category: train.category.clone(),
             number: train.number.clone(),
@@ -1,9 +1,12 @@
 // @generated automatically by Diesel CLI.
 
 diesel::table! {
+    use diesel::sql_types::*;
+    use crate::data::db::sql_types::Timestamptz;
+
     messages (id) {
         id -> Text,
-        train_id -> Text,
+        train_id -> Nullable<Text>,
         valid_from -> Nullable<Timestamp>,
         valid_to -> Nullable<Timestamp>,
         priority -> Nullable<Int2>,
@@ -13,6 +16,10 @@ diesel::table! {
         m_type -> Nullable<Text>,
         last_updated -> Nullable<Timestamptz>,
         iris_id -> Text,
+        first_seen -> Nullable<Timestamptz>,
+        last_seen -> Nullable<Timestamptz>,
+        revoked_at -> Nullable<Timestamptz>,
+        superseded_by -> Nullable<Text>,
     }
 }
 
@@ -20,6 +27,15 @@ diesel::table! {
     messages_to_stations (message_id, station_id) {
         station_id -> Int4,
         message_id -> Text,
+        listed -> Bool,
+    }
+}
+
+diesel::table! {
+    messages_to_stops (message_id, stop_id, scope) {
+        message_id -> Text,
+        stop_id -> Text,
+        scope -> Text,
     }
 }
 
@@ -33,6 +49,20 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    station_groups (station_id, member_id) {
+        station_id -> Int4,
+        member_id -> Int4,
+    }
+}
+
+diesel::table! {
+    station_platforms (station_id, platform) {
+        station_id -> Int4,
+        platform -> Text,
+    }
+}
+
 diesel::table! {
     status_codes (code) {
         code -> Int2,
@@ -41,6 +71,18 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    stop_observations (id) {
+        id -> Int8,
+        stop_id -> Text,
+        kind -> Text,
+        observed_at -> Timestamp,
+        current -> Nullable<Timestamp>,
+        platform -> Nullable<Text>,
+        status -> Nullable<Text>,
+    }
+}
+
 diesel::table! {
     stops (id) {
         id -> Text,
@@ -56,6 +98,30 @@ diesel::table! {
         departure_changed_path -> Nullable<Text>,
         arrival_current -> Nullable<Timestamp>,
         departure_current -> Nullable<Timestamp>,
+        arrival_changed_platform -> Nullable<Text>,
+        arrival_line -> Nullable<Text>,
+        arrival_changed_line -> Nullable<Text>,
+        arrival_planned_status -> Nullable<Text>,
+        arrival_changed_status -> Nullable<Text>,
+        arrival_status_changed_at -> Nullable<Timestamp>,
+        arrival_hidden -> Nullable<Bool>,
+        arrival_wings -> Nullable<Text>,
+        arrival_transition -> Nullable<Text>,
+        arrival_planned_distant_endpoint -> Nullable<Text>,
+        arrival_changed_distant_endpoint -> Nullable<Text>,
+        arrival_distant_change -> Nullable<Int4>,
+        departure_changed_platform -> Nullable<Text>,
+        departure_line -> Nullable<Text>,
+        departure_changed_line -> Nullable<Text>,
+        departure_planned_status -> Nullable<Text>,
+        departure_changed_status -> Nullable<Text>,
+        departure_status_changed_at -> Nullable<Timestamp>,
+        departure_hidden -> Nullable<Bool>,
+        departure_wings -> Nullable<Text>,
+        departure_transition -> Nullable<Text>,
+        departure_planned_distant_endpoint -> Nullable<Text>,
+        departure_changed_distant_endpoint -> Nullable<Text>,
+        departure_distant_change -> Nullable<Int4>,
     }
 }
 
@@ -67,20 +133,30 @@ diesel::table! {
         number -> Text,
         line -> Nullable<Text>,
         date -> Date,
+        trip_id -> Text,
     }
 }
 
 diesel::joinable!(messages -> trains (train_id));
 diesel::joinable!(messages_to_stations -> messages (message_id));
 diesel::joinable!(messages_to_stations -> stations (station_id));
+diesel::joinable!(messages_to_stops -> messages (message_id));
+diesel::joinable!(messages_to_stops -> stops (stop_id));
+diesel::joinable!(station_groups -> stations (station_id));
+diesel::joinable!(station_platforms -> stations (station_id));
+diesel::joinable!(stop_observations -> stops (stop_id));
 diesel::joinable!(stops -> stations (station_id));
 diesel::joinable!(stops -> trains (train_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     messages,
     messages_to_stations,
+    messages_to_stops,
+    station_groups,
+    station_platforms,
     stations,
     status_codes,
+    stop_observations,
     stops,
     trains,
 );
@@ -0,0 +1,56 @@
+//! SQL types shared by the Postgres and SQLite schema.
+
+use chrono::{DateTime, Utc};
+use diesel::{deserialize::{self, FromSql, FromSqlRow}, expression::AsExpression, serialize::{self, Output, ToSql}, sql_types::{ops, Interval, SqlType}, query_builder::QueryId};
+
+/// Timestamp with time zone: `timestamptz` on Postgres, text in UTC on SQLite.
+///
+/// Diesel only offers a type per backend, this one lets both share the `messages` table.
+#[derive(SqlType, QueryId, Clone, Copy, Debug)]
+#[cfg_attr(feature = "postgres", diesel(postgres_type(oid = 1184, array_oid = 1185)))]
+#[cfg_attr(feature = "sqlite", diesel(sqlite_type(name = "Text")))]
+pub struct Timestamptz;
+
+// `table!` expects these for columns named like the Diesel type.
+impl ops::Add for Timestamptz {
+    type Rhs = Interval;
+    type Output = Timestamptz;
+}
+
+impl ops::Sub for Timestamptz {
+    type Rhs = Interval;
+    type Output = Timestamptz;
+}
+
+/// A [`DateTime<Utc>`] stored as [`Timestamptz`].
+#[derive(AsExpression, FromSqlRow, Clone, Copy, Debug, PartialEq)]
+#[diesel(sql_type = Timestamptz)]
+pub struct UtcDateTime(pub DateTime<Utc>);
+
+#[cfg(feature = "postgres")]
+impl ToSql<Timestamptz, diesel::pg::Pg> for UtcDateTime {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, diesel::pg::Pg>) -> serialize::Result {
+        <DateTime<Utc> as ToSql<diesel::sql_types::Timestamptz, diesel::pg::Pg>>::to_sql(&self.0, out)
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl FromSql<Timestamptz, diesel::pg::Pg> for UtcDateTime {
+    fn from_sql(value: diesel::pg::PgValue<'_>) -> deserialize::Result<Self> {
+        <DateTime<Utc> as FromSql<diesel::sql_types::Timestamptz, diesel::pg::Pg>>::from_sql(value).map(UtcDateTime)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl ToSql<Timestamptz, diesel::sqlite::Sqlite> for UtcDateTime {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, diesel::sqlite::Sqlite>) -> serialize::Result {
+        <DateTime<Utc> as ToSql<diesel::sql_types::TimestamptzSqlite, diesel::sqlite::Sqlite>>::to_sql(&self.0, out)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl FromSql<Timestamptz, diesel::sqlite::Sqlite> for UtcDateTime {
+    fn from_sql(value: diesel::sqlite::SqliteValue<'_, '_, '_>) -> deserialize::Result<Self> {
+        <DateTime<Utc> as FromSql<diesel::sql_types::TimestamptzSqlite, diesel::sqlite::Sqlite>>::from_sql(value).map(UtcDateTime)
+    }
+}
@@ -0,0 +1,197 @@
+//! Thread-safe in-memory backend implementing every port with the semantics of the
+//! Postgres repos, including their foreign keys and conflict handling.
+//!
+//! All repos created from the same [`MemoryDb`] share its tables.
+
+use std::{collections::BTreeMap, sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard}};
+
+use crate::{data::db::row::{MessageRow, MessageToStationRow, MessageToStopRow, StopRow}, model::{Station, StatusCode, StopObservation, Train}, ports::PortError};
+
+mod station_repo;
+mod train_repo;
+mod stop_repo;
+mod stop_observation_repo;
+mod message_repo;
+mod status_code_repo;
+mod unit_of_work_repo;
+
+pub use {
+    station_repo::*,
+    train_repo::*,
+    stop_repo::*,
+    stop_observation_repo::*,
+    message_repo::*,
+    status_code_repo::*,
+    unit_of_work_repo::*,
+};
+
+
+/// Shared storage of the memory repos. Cloning is cheap and yields a handle to the same tables.
+#[derive(Clone, Default)]
+pub struct MemoryDb {
+    tables: Arc<RwLock<Tables>>,
+}
+
+impl MemoryDb {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, Tables> {
+        self.tables.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Runs `f` under the write lock, all of its writes are undone if it fails.
+    fn transaction<T, F>(&self, f: F) -> Result<T, PortError>
+    where F: FnOnce(&mut Tables) -> Result<T, PortError> {
+        let mut tables: RwLockWriteGuard<'_, Tables> = self.tables.write().unwrap_or_else(PoisonError::into_inner);
+        tables.begin();
+        let result = f(&mut tables);
+        match result {
+            Ok(_) => tables.end(),
+            Err(_) => tables.rollback(),
+        }
+        result
+    }
+}
+
+/// Rows as stored by the Postgres repos. Relations and observations are append-only,
+/// only the `listed` flag of station links changes.
+#[derive(Default)]
+struct Tables {
+    stations: BTreeMap<i32, Station>,
+    /// Members per station, like the rows of `station_groups`.
+    station_groups: BTreeMap<i32, Vec<i32>>,
+    /// Platforms per station, like the rows of `station_platforms`.
+    station_platforms: BTreeMap<i32, Vec<String>>,
+    trains: BTreeMap<String, Train>,
+    stops: BTreeMap<String, StopRow>,
+    status_codes: BTreeMap<i16, StatusCode>,
+    messages: BTreeMap<String, MessageRow>,
+    message_stations: Vec<MessageToStationRow>,
+    message_stops: Vec<MessageToStopRow>,
+    observations: Vec<StopObservation>,
+
+    savepoint: Option<Savepoint>,
+}
+
+/// State to restore on rollback: previous values of all touched rows and the table lengths.
+#[derive(Default)]
+struct Savepoint {
+    undo: Vec<Undo>,
+    message_stations: usize,
+    message_stops: usize,
+    observations: usize,
+}
+
+enum Undo {
+    Station(i32, Option<Station>),
+    StationGroup(i32, Option<Vec<i32>>),
+    StationPlatforms(i32, Option<Vec<String>>),
+    Train(String, Option<Train>),
+    Stop(String, Option<Box<StopRow>>),
+    StatusCode(i16, Option<StatusCode>),
+    Message(String, Option<MessageRow>),
+    /// Index into `message_stations` and the previous `listed` flag.
+    MessageListed(usize, bool),
+}
+
+impl Tables {
+    fn begin(&mut self) {
+        self.savepoint = Some(Savepoint {
+            undo: Vec::new(),
+            message_stations: self.message_stations.len(),
+            message_stops: self.message_stops.len(),
+            observations: self.observations.len(),
+        });
+    }
+
+    fn end(&mut self) {
+        self.savepoint = None;
+    }
+
+    fn rollback(&mut self) {
+        let Some(savepoint) = self.savepoint.take() else {
+            return;
+        };
+        for undo in savepoint.undo.into_iter().rev() {
+            match undo {
+                Undo::Station(id, old) => restore(&mut self.stations, id, old),
+                Undo::StationGroup(id, old) => restore(&mut self.station_groups, id, old),
+                Undo::StationPlatforms(id, old) => restore(&mut self.station_platforms, id, old),
+                Undo::Train(id, old) => restore(&mut self.trains, id, old),
+                Undo::Stop(id, old) => restore(&mut self.stops, id, old.map(|row| *row)),
+                Undo::StatusCode(id, old) => restore(&mut self.status_codes, id, old),
+                Undo::Message(id, old) => restore(&mut self.messages, id, old),
+                Undo::MessageListed(index, old) => self.message_stations[index].listed = old,
+            }
+        }
+        self.message_stations.truncate(savepoint.message_stations);
+        self.message_stops.truncate(savepoint.message_stops);
+        self.observations.truncate(savepoint.observations);
+    }
+
+    fn log(&mut self, undo: Undo) {
+        if let Some(savepoint) = &mut self.savepoint {
+            savepoint.undo.push(undo);
+        }
+    }
+
+    fn put_station(&mut self, station: Station) {
+        let id = station.id;
+        let old = self.stations.insert(id, station);
+        self.log(Undo::Station(id, old));
+    }
+
+    fn put_station_group(&mut self, station_id: i32, member_ids: Vec<i32>) {
+        let old = self.station_groups.insert(station_id, member_ids);
+        self.log(Undo::StationGroup(station_id, old));
+    }
+
+    fn put_station_platforms(&mut self, station_id: i32, platforms: Vec<String>) {
+        let old = self.station_platforms.insert(station_id, platforms);
+        self.log(Undo::StationPlatforms(station_id, old));
+    }
+
+    fn put_train(&mut self, train: Train) {
+        let id = train.id.clone();
+        let old = self.trains.insert(id.clone(), train);
+        self.log(Undo::Train(id, old));
+    }
+
+    fn put_stop(&mut self, stop: StopRow) {
+        let id = stop.id.clone();
+        let old = self.stops.insert(id.clone(), stop);
+        self.log(Undo::Stop(id, old.map(Box::new)));
+    }
+
+    fn put_status_code(&mut self, code: StatusCode) {
+        let id = code.code;
+        let old = self.status_codes.insert(id, code);
+        self.log(Undo::StatusCode(id, old));
+    }
+
+    fn put_message(&mut self, message: MessageRow) {
+        let id = message.id.clone();
+        let old = self.messages.insert(id.clone(), message);
+        self.log(Undo::Message(id, old));
+    }
+
+    fn set_message_listed(&mut self, index: usize, listed: bool) {
+        let old = std::mem::replace(&mut self.message_stations[index].listed, listed);
+        self.log(Undo::MessageListed(index, old));
+    }
+}
+
+fn restore<K: Ord, V>(table: &mut BTreeMap<K, V>, id: K, old: Option<V>) {
+    match old {
+        Some(value) => table.insert(id, value),
+        None => table.remove(&id),
+    };
+}
+
+/// Foreign key and check constraint violations, mapped like database errors of the Postgres repos.
+fn constraint_violation(what: &str) -> PortError {
+    error!("Constraint violation: {}", what);
+    PortError::Database
+}
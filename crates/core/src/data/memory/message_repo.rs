@@ -0,0 +1,247 @@
+use std::{cmp::Reverse, collections::HashSet};
+
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+
+use crate::{data::{db::{row::{MessageRow, MessageToStationRow, MessageToStopRow}, sql_types::UtcDateTime}, memory::{constraint_violation, MemoryDb, Tables}, utils::superseded_revisions}, model::Message, ports::{MessagePort, Port, PortError, Upserted}};
+
+
+pub struct MemoryMessageRepo {
+    db: MemoryDb,
+}
+
+impl MemoryMessageRepo {
+    pub fn new(db: MemoryDb) -> Self {
+        Self { db }
+    }
+}
+
+impl Tables {
+    fn build_message(&self, row: &MessageRow) -> Message {
+        let stations: Vec<MessageToStationRow> = self.message_stations.iter()
+            .filter(|r| r.message_id == row.id)
+            .cloned()
+            .collect();
+        let stops: Vec<MessageToStopRow> = self.message_stops.iter()
+            .filter(|r| r.message_id == row.id)
+            .cloned()
+            .collect();
+        row.to_message(&stations, &stops)
+    }
+
+    fn build_messages<'a>(&self, rows: impl Iterator<Item = &'a MessageRow>) -> Vec<Message> {
+        rows.map(|row| self.build_message(row)).collect()
+    }
+
+    /// Inserts new messages and refreshes content, `last_updated` and `last_seen` of known ones.
+    /// `first_seen` is kept, `revoked_at` is cleared since the message is back in IRIS.
+    /// Returns whether the message was inserted.
+    fn upsert_message(&mut self, message: &Message) -> Result<bool, PortError> {
+        let given = MessageRow::from(message);
+        let row = match self.messages.get(&given.id) {
+            Some(stored) => MessageRow {
+                valid_from: given.valid_from,
+                valid_to: given.valid_to,
+                priority: given.priority,
+                category: given.category,
+                code: given.code,
+                m_type: given.m_type,
+                last_updated: given.last_updated,
+                last_seen: given.last_seen,
+                revoked_at: None,
+                ..stored.clone()
+            },
+            None => {
+                if given.train_id.as_ref().is_some_and(|id| !self.trains.contains_key(id)) {
+                    return Err(constraint_violation(&format!("message {}", given.id)));
+                }
+                given
+            }
+        };
+        let inserted = !self.messages.contains_key(&row.id);
+        self.put_message(row);
+        Ok(inserted)
+    }
+
+    /// Adds missing station and stop links of `message`, its stations list it again.
+    fn link_message(&mut self, message: &Message) -> Result<(), PortError> {
+        for station_id in &message.stations {
+            if !self.stations.contains_key(station_id) {
+                return Err(constraint_violation(&format!("message {} at station {}", message.id, station_id)));
+            }
+            match self.message_stations.iter().position(|r| r.message_id == message.id && r.station_id == *station_id) {
+                Some(index) => self.set_message_listed(index, true),
+                None => self.message_stations.push(MessageToStationRow::new(&message.id, *station_id)),
+            }
+        }
+        for stop in &message.stops {
+            if !self.stops.contains_key(&stop.stop_id) {
+                return Err(constraint_violation(&format!("message {} at stop {}", message.id, stop.stop_id)));
+            }
+            let link = MessageToStopRow::new(&message.id, stop);
+            let exists = self.message_stops.iter()
+                .any(|r| r.message_id == link.message_id && r.stop_id == link.stop_id && r.scope == link.scope);
+            if !exists {
+                self.message_stops.push(link);
+            }
+        }
+        Ok(())
+    }
+
+    /// Marks earlier revisions of the messages as superseded by the latest one, see [`Message::superseded_by`].
+    fn supersede_revisions(&mut self, messages: &[Message]) {
+        let iris_ids: HashSet<&str> = messages.iter().map(|m| m.iris_id.as_str()).collect();
+        let superseded = superseded_revisions(self.messages.values().filter(|m| iris_ids.contains(m.iris_id.as_str())));
+        for (id, latest) in superseded {
+            if let Some(row) = self.messages.get(&id) {
+                self.put_message(MessageRow { superseded_by: Some(latest), ..row.clone() });
+            }
+        }
+    }
+
+    /// Upserts messages with their station and stop links, see [`Tables::upsert_message`],
+    /// and supersedes their earlier revisions.
+    /// Like Postgres, an upsert may not touch the same message twice.
+    pub(super) fn persist_messages(&mut self, messages: &[Message]) -> Result<Upserted<Message>, PortError> {
+        let mut ids = HashSet::with_capacity(messages.len());
+        if !messages.iter().all(|m| ids.insert(m.id.as_str())) {
+            return Err(constraint_violation("message upserted twice"));
+        }
+
+        let mut inserted = HashSet::new();
+        for message in messages {
+            if self.upsert_message(message)? {
+                inserted.insert(message.id.as_str());
+            }
+        }
+        for message in messages {
+            self.link_message(message)?;
+        }
+        self.supersede_revisions(messages);
+        let (inserted, updated) = self.build_messages(messages.iter().filter_map(|m| self.messages.get(&m.id)))
+            .into_iter()
+            .partition(|m| inserted.contains(m.id.as_str()));
+        Ok(Upserted { inserted, updated, unchanged: Vec::new() })
+    }
+
+    /// See [`MessagePort::revoke_missing`].
+    pub(super) fn revoke_missing_messages(&mut self, station_id: i32, seen_ids: &[String], now: &NaiveDateTime) -> Vec<Message> {
+        let unlisted: Vec<usize> = self.message_stations.iter()
+            .enumerate()
+            .filter(|(_, r)| r.station_id == station_id && r.listed && !seen_ids.contains(&r.message_id))
+            .map(|(index, _)| index)
+            .collect();
+        for index in unlisted {
+            self.set_message_listed(index, false);
+        }
+
+        let station_messages: HashSet<&str> = self.message_stations.iter()
+            .filter(|r| r.station_id == station_id)
+            .map(|r| r.message_id.as_str())
+            .collect();
+        let listed_messages: HashSet<&str> = self.message_stations.iter()
+            .filter(|r| r.listed)
+            .map(|r| r.message_id.as_str())
+            .collect();
+        let upcoming_trains: HashSet<&str> = self.stops.values()
+            .filter(|s| s.station_id == station_id)
+            .filter(|s| s.arrival_planned.is_some_and(|t| t > *now) || s.departure_planned.is_some_and(|t| t > *now))
+            .map(|s| s.train_id.as_str())
+            .collect();
+
+        let revoked: Vec<MessageRow> = self.messages.values()
+            .filter(|m| station_messages.contains(m.id.as_str()))
+            .filter(|m| !seen_ids.contains(&m.id))
+            .filter(|m| !listed_messages.contains(m.id.as_str()))
+            .filter(|m| m.revoked_at.is_none())
+            .filter(|m| m.valid_to.is_none_or(|t| t > *now))
+            .filter(|m| m.train_id.as_deref().is_none_or(|id| upcoming_trains.contains(id)))
+            .map(|m| MessageRow { revoked_at: Some(UtcDateTime(Utc::now())), ..m.clone() })
+            .collect();
+
+        for row in &revoked {
+            self.put_message(row.clone());
+        }
+        self.build_messages(revoked.iter())
+    }
+}
+
+impl MessagePort for MemoryMessageRepo {
+    fn get_by_date_and_code(&self, date: &NaiveDate, code: i32) -> Result<Vec<Message>, PortError> {
+        let start: NaiveDateTime = date.and_hms_opt(0, 0, 0).unwrap();
+        let end: NaiveDateTime = (date.succ_opt().unwrap()).and_hms_opt(0, 0, 0).unwrap();
+
+        let tables = self.db.read();
+        Ok(tables.build_messages(tables.messages.values()
+            .filter(|m| start <= m.timestamp && m.timestamp < end && m.code == Some(code))))
+    }
+
+    fn get_by_train_id(&self, train_id: &str) -> Result<Vec<Message>, PortError> {
+        let tables = self.db.read();
+        Ok(tables.build_messages(tables.messages.values().filter(|m| m.train_id.as_deref() == Some(train_id))))
+    }
+
+    fn get_station_messages(&self, station_id: i32) -> Result<Vec<Message>, PortError> {
+        let tables = self.db.read();
+        let mut rows: Vec<&MessageRow> = tables.message_stations.iter()
+            .filter(|r| r.station_id == station_id)
+            .filter_map(|r| tables.messages.get(&r.message_id))
+            .filter(|m| m.train_id.is_none())
+            .collect();
+        rows.sort_by_key(|m| Reverse(m.timestamp));
+        Ok(tables.build_messages(rows.into_iter()))
+    }
+
+    fn get_by_stop_ids(&self, stop_ids: &[String]) -> Result<Vec<Message>, PortError> {
+        let tables = self.db.read();
+        let message_ids: HashSet<&str> = tables.message_stops.iter()
+            .filter(|r| stop_ids.contains(&r.stop_id))
+            .map(|r| r.message_id.as_str())
+            .collect();
+        let mut rows: Vec<&MessageRow> = tables.messages.values()
+            .filter(|m| message_ids.contains(m.id.as_str()))
+            .collect();
+        rows.sort_by_key(|m| m.timestamp);
+        Ok(tables.build_messages(rows.into_iter()))
+    }
+
+    fn get_active_at(&self, at: &NaiveDateTime) -> Result<Vec<Message>, PortError> {
+        let tables = self.db.read();
+        let mut messages: Vec<Message> = tables.build_messages(tables.messages.values())
+            .into_iter()
+            .filter(|m| m.is_active_at(at))
+            .collect();
+        messages.sort_by_key(|m| Reverse(m.timestamp));
+        Ok(messages)
+    }
+
+    fn revoke_missing(&self, station_id: i32, seen_ids: &[String], now: &NaiveDateTime) -> Result<Vec<Message>, PortError> {
+        self.db.transaction(|t| Ok(t.revoke_missing_messages(station_id, seen_ids, now)))
+    }
+
+    fn upsert_all(&self, messages: &[Message]) -> Result<Upserted<Message>, PortError> {
+        self.db.transaction(|t| t.persist_messages(messages))
+    }
+}
+
+impl Port<Message, String> for MemoryMessageRepo {
+    fn persist(&self, message: &Message) -> Result<Message, PortError> {
+        self.db.transaction(|t| t.persist_messages(std::slice::from_ref(message)))?
+            .into_written()
+            .pop()
+            .ok_or(PortError::NotFound)
+    }
+
+    fn persist_all(&self, messages: &[Message]) -> Result<Vec<Message>, PortError> {
+        self.db.transaction(|t| t.persist_messages(messages)).map(Upserted::into_written)
+    }
+
+    fn get_by_id(&self, id: String) -> Result<Message, PortError> {
+        let tables = self.db.read();
+        tables.messages.get(&id).map(|row| tables.build_message(row)).ok_or(PortError::NotFound)
+    }
+
+    fn get_all(&self) -> Result<Vec<Message>, PortError> {
+        let tables = self.db.read();
+        Ok(tables.build_messages(tables.messages.values()))
+    }
+}
@@ -0,0 +1,217 @@
+use std::{collections::BTreeSet, fs};
+
+use crate::{data::memory::{constraint_violation, MemoryDb, Tables}, model::{search_stations, Station, StationGroup, StationPlatforms}, ports::{Port, PortError, StationPort}};
+
+
+pub struct MemoryStationRepo {
+    db: MemoryDb,
+}
+
+impl MemoryStationRepo {
+    pub fn new(db: MemoryDb) -> Self {
+        Self { db }
+    }
+}
+
+/// Mirrors the check constraints of the `stations` table.
+fn check_station(station: &Station) -> Result<(), PortError> {
+    let lat_ok = station.lat.is_none_or(|lat| (-90.0..=90.0).contains(&lat));
+    let lon_ok = station.lon.is_none_or(|lon| (-180.0..=180.0).contains(&lon));
+    let ds100_ok = (1..=16).contains(&station.ds100.chars().count());
+    if lat_ok && lon_ok && ds100_ok {
+        Ok(())
+    } else {
+        Err(constraint_violation(&format!("station {}", station.id)))
+    }
+}
+
+impl Tables {
+    /// Inserts new stations, known ones are skipped. Returns the inserted stations.
+    fn insert_stations(&mut self, stations: &[Station]) -> Result<Vec<Station>, PortError> {
+        let mut out = Vec::with_capacity(stations.len());
+        for station in stations {
+            if self.stations.contains_key(&station.id) {
+                continue;
+            }
+            check_station(station)?;
+            self.put_station(station.clone());
+            out.push(station.clone());
+        }
+        Ok(out)
+    }
+
+    fn put_station_groups(&mut self, groups: &[StationGroup]) -> Result<Vec<StationGroup>, PortError> {
+        for group in groups {
+            if !self.stations.contains_key(&group.station_id) {
+                return Err(constraint_violation(&format!("group of station {}", group.station_id)));
+            }
+            self.put_station_group(group.station_id, group.member_ids.clone());
+        }
+        Ok(groups.to_vec())
+    }
+
+    fn put_all_station_platforms(&mut self, platforms: &[StationPlatforms]) -> Result<Vec<StationPlatforms>, PortError> {
+        for station in platforms {
+            if !self.stations.contains_key(&station.station_id) {
+                return Err(constraint_violation(&format!("platforms of station {}", station.station_id)));
+            }
+            self.put_station_platforms(station.station_id, station.platforms.clone());
+        }
+        Ok(platforms.to_vec())
+    }
+}
+
+/// Splits `sql` into statements at `;` outside of string literals.
+fn split_statements(sql: &str) -> Vec<&str> {
+    let mut statements = Vec::new();
+    let mut in_string = false;
+    let mut start = 0;
+    for (i, c) in sql.char_indices() {
+        match c {
+            '\'' => in_string = !in_string,
+            ';' if !in_string => {
+                statements.push(sql[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    statements.push(sql[start..].trim());
+    statements.into_iter().filter(|s| !s.is_empty()).collect()
+}
+
+/// Splits a comma separated SQL list, keeping quoted strings (with `''` escapes) intact.
+fn split_values(list: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    let mut current = String::new();
+    let mut in_string = false;
+    let mut chars = list.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' if in_string && chars.peek() == Some(&'\'') => {
+                current.push('\'');
+                chars.next();
+            }
+            '\'' => {
+                in_string = !in_string;
+                current.push(c);
+            }
+            ',' if !in_string => values.push(std::mem::take(&mut current).trim().to_string()),
+            _ => current.push(c),
+        }
+    }
+    values.push(current.trim().to_string());
+    values
+}
+
+fn parse_text(value: &str) -> Option<String> {
+    value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')).map(String::from)
+}
+
+fn parse_float(value: &str) -> Result<Option<f64>, PortError> {
+    if value.eq_ignore_ascii_case("NULL") {
+        return Ok(None);
+    }
+    value.parse().map(Some).map_err(|_| PortError::MalformedData)
+}
+
+/// Parses `INSERT INTO [public.]stations (columns) VALUES (values)` as written by `pg_dump --inserts`.
+fn parse_station_insert(statement: &str) -> Result<Station, PortError> {
+    let (head, values) = statement.split_once(" VALUES ").ok_or(PortError::InvalidInput)?;
+    let table = head.strip_prefix("INSERT INTO ").ok_or(PortError::InvalidInput)?;
+    let (table, columns) = table.split_once('(').ok_or(PortError::InvalidInput)?;
+    if !matches!(table.trim(), "stations" | "public.stations") {
+        return Err(PortError::InvalidInput);
+    }
+
+    let columns = split_values(columns.trim().strip_suffix(')').ok_or(PortError::InvalidInput)?);
+    let values = split_values(values.trim().strip_prefix('(').and_then(|v| v.strip_suffix(')')).ok_or(PortError::InvalidInput)?);
+    if columns.len() != values.len() {
+        return Err(PortError::MalformedData);
+    }
+
+    let mut station = Station { id: 0, lat: None, lon: None, name: String::new(), ds100: String::new() };
+    for (column, value) in columns.iter().zip(&values) {
+        match column.as_str() {
+            "id" => station.id = value.parse().map_err(|_| PortError::MalformedData)?,
+            "lat" => station.lat = parse_float(value)?,
+            "lon" => station.lon = parse_float(value)?,
+            "name" => station.name = parse_text(value).ok_or(PortError::MalformedData)?,
+            "ds100" => station.ds100 = parse_text(value).ok_or(PortError::MalformedData)?,
+            _ => return Err(PortError::InvalidInput),
+        }
+    }
+    Ok(station)
+}
+
+impl Port<Station, i32> for MemoryStationRepo {
+    fn persist(&self, station: &Station) -> Result<Station, PortError> {
+        self.db.transaction(|t| t.insert_stations(std::slice::from_ref(station)))?
+            .pop()
+            .ok_or(PortError::NotFound)
+    }
+
+    fn persist_all(&self, stations: &[Station]) -> Result<Vec<Station>, PortError> {
+        self.db.transaction(|t| t.insert_stations(stations))
+    }
+
+    fn get_by_id(&self, id: i32) -> Result<Station, PortError> {
+        self.db.read().stations.get(&id).cloned().ok_or(PortError::NotFound)
+    }
+
+    fn get_all(&self) -> Result<Vec<Station>, PortError> {
+        Ok(self.db.read().stations.values().cloned().collect())
+    }
+}
+
+impl StationPort for MemoryStationRepo {
+    fn get_by_ds100(&self, ds100: &str) -> Result<Station, PortError> {
+        self.db.read().stations.values()
+            .find(|s| s.ds100 == ds100)
+            .cloned()
+            .ok_or(PortError::NotFound)
+    }
+
+    fn search(&self, query: &str, limit: usize) -> Result<Vec<Station>, PortError> {
+        Ok(search_stations(self.db.read().stations.values().cloned(), query, limit))
+    }
+
+    fn persist_groups(&self, groups: &[StationGroup]) -> Result<Vec<StationGroup>, PortError> {
+        self.db.transaction(|t| t.put_station_groups(groups))
+    }
+
+    fn get_group(&self, station: &Station) -> Result<Vec<Station>, PortError> {
+        let tables = self.db.read();
+        let members = tables.station_groups.get(&station.id).into_iter().flatten().copied();
+        let listing = tables.station_groups.iter()
+            .filter(|(_, members)| members.contains(&station.id))
+            .map(|(id, _)| *id);
+        let ids: BTreeSet<i32> = members.chain(listing).filter(|id| *id != station.id).collect();
+
+        let mut group = vec![station.clone()];
+        group.extend(ids.iter().filter_map(|id| tables.stations.get(id)).cloned());
+        Ok(group)
+    }
+
+    fn persist_platforms(&self, platforms: &[StationPlatforms]) -> Result<Vec<StationPlatforms>, PortError> {
+        self.db.transaction(|t| t.put_all_station_platforms(platforms))
+    }
+
+    fn get_platforms(&self, station: &Station) -> Result<StationPlatforms, PortError> {
+        let tables = self.db.read();
+        Ok(StationPlatforms::new(station.id, tables.station_platforms.get(&station.id).into_iter().flatten()))
+    }
+
+    /// Only understands the station inserts of a `pg_dump --inserts` file.
+    fn import_from_sql(&self, path: &str) -> Result<Vec<Station>, PortError> {
+        let sql = fs::read_to_string(path).map_err(|e| {
+            error!("Error importing stations from SQL file: {}", e);
+            PortError::Custom(Box::new(e))
+        })?;
+        let stations = split_statements(&sql)
+            .into_iter()
+            .map(parse_station_insert)
+            .collect::<Result<Vec<Station>, PortError>>()?;
+        self.db.transaction(|t| t.insert_stations(&stations))
+    }
+}
@@ -0,0 +1,50 @@
+use crate::{data::memory::{MemoryDb, Tables}, model::StatusCode, ports::{Port, PortError, StatusCodePort}};
+
+
+pub struct MemoryStatusCodeRepo {
+    db: MemoryDb,
+}
+
+impl MemoryStatusCodeRepo {
+    pub fn new(db: MemoryDb) -> Self {
+        Self { db }
+    }
+}
+
+impl Tables {
+    /// Inserts new status codes, known ones are skipped. Returns the inserted codes.
+    fn insert_status_codes(&mut self, codes: &[StatusCode]) -> Vec<StatusCode> {
+        let mut out = Vec::with_capacity(codes.len());
+        for code in codes {
+            if self.status_codes.contains_key(&code.code) {
+                continue;
+            }
+            self.put_status_code(code.clone());
+            out.push(code.clone());
+        }
+        out
+    }
+}
+
+impl Port<StatusCode, i16> for MemoryStatusCodeRepo {
+    fn persist(&self, code: &StatusCode) -> Result<StatusCode, PortError> {
+        self.db.transaction(|t| Ok(t.insert_status_codes(std::slice::from_ref(code))))?
+            .pop()
+            .ok_or(PortError::NotFound)
+    }
+
+    fn persist_all(&self, codes: &[StatusCode]) -> Result<Vec<StatusCode>, PortError> {
+        self.db.transaction(|t| Ok(t.insert_status_codes(codes)))
+    }
+
+    fn get_by_id(&self, id: i16) -> Result<StatusCode, PortError> {
+        self.db.read().status_codes.get(&id).cloned().ok_or(PortError::NotFound)
+    }
+
+    fn get_all(&self) -> Result<Vec<StatusCode>, PortError> {
+        Ok(self.db.read().status_codes.values().cloned().collect())
+    }
+}
+
+impl StatusCodePort for MemoryStatusCodeRepo {
+}
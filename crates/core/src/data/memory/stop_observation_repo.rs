@@ -0,0 +1,60 @@
+use crate::{data::memory::{constraint_violation, MemoryDb, Tables}, model::StopObservation, ports::{PortError, StopObservationPort}};
+
+
+pub struct MemoryStopObservationRepo {
+    db: MemoryDb,
+}
+
+impl MemoryStopObservationRepo {
+    pub fn new(db: MemoryDb) -> Self {
+        Self { db }
+    }
+}
+
+/// Sorts by observation time, observations recorded earlier first on ties.
+fn timeline<'a>(observations: impl Iterator<Item = &'a StopObservation>) -> Vec<StopObservation> {
+    let mut out: Vec<StopObservation> = observations.cloned().collect();
+    out.sort_by_key(|o| o.observed_at);
+    out
+}
+
+impl Tables {
+    /// Records observations that differ from the latest known one of their movement.
+    pub(super) fn record_observations(&mut self, observations: &[StopObservation]) -> Result<Vec<StopObservation>, PortError> {
+        let mut out = Vec::with_capacity(observations.len());
+        for observation in observations {
+            if !self.stops.contains_key(&observation.stop_id) {
+                return Err(constraint_violation(&format!("observation of stop {}", observation.stop_id)));
+            }
+
+            // `max_by_key` keeps the last maximum, i.e. the one recorded last.
+            let latest = self.observations.iter()
+                .filter(|o| o.stop_id == observation.stop_id && o.kind == observation.kind)
+                .max_by_key(|o| o.observed_at);
+            if latest.is_some_and(|l| l.same_forecast(observation)) {
+                continue;
+            }
+
+            self.observations.push(observation.clone());
+            out.push(observation.clone());
+        }
+        Ok(out)
+    }
+}
+
+impl StopObservationPort for MemoryStopObservationRepo {
+    fn record_all(&self, observations: &[StopObservation]) -> Result<Vec<StopObservation>, PortError> {
+        self.db.transaction(|t| t.record_observations(observations))
+    }
+
+    fn get_for_stop(&self, stop_id: &str) -> Result<Vec<StopObservation>, PortError> {
+        let tables = self.db.read();
+        Ok(timeline(tables.observations.iter().filter(|o| o.stop_id == stop_id)))
+    }
+
+    fn get_for_train(&self, train_id: &str) -> Result<Vec<StopObservation>, PortError> {
+        let tables = self.db.read();
+        Ok(timeline(tables.observations.iter()
+            .filter(|o| tables.stops.get(&o.stop_id).is_some_and(|s| s.train_id == train_id))))
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::{data::{db::row::{StopRow, StopUpdateRow}, memory::{constraint_violation, MemoryDb, Tables}, utils::dedup_by_id}, model::{EventStatus, PlatformUsage, Station, Stop, StopUpdate, StopWithStation}, ports::{Port, PortError, StopPort, Upserted}};
+
+
+pub struct MemoryStopRepo {
+    db: MemoryDb,
+}
+
+impl MemoryStopRepo {
+    pub fn new(db: MemoryDb) -> Self {
+        Self { db }
+    }
+}
+
+fn to_stops<'a>(rows: impl Iterator<Item = &'a StopRow>) -> Vec<Stop> {
+    rows.map(StopRow::to_stop).collect()
+}
+
+impl Tables {
+    /// Mirrors the foreign keys of the `stops` table.
+    fn check_stop(&self, row: &StopRow) -> Result<(), PortError> {
+        if !self.trains.contains_key(&row.train_id) || !self.stations.contains_key(&row.station_id) {
+            return Err(constraint_violation(&format!("stop {}", row.id)));
+        }
+        Ok(())
+    }
+
+    /// Inserts new stops, known ones are skipped. Returns the inserted stops.
+    fn insert_stops(&mut self, stops: &[Stop]) -> Result<Vec<Stop>, PortError> {
+        let mut out = Vec::with_capacity(stops.len());
+        for stop in stops {
+            if self.stops.contains_key(&stop.id) {
+                continue;
+            }
+            let row = StopRow::from(stop);
+            self.check_stop(&row)?;
+            out.push(row.to_stop());
+            self.put_stop(row);
+        }
+        Ok(out)
+    }
+
+    /// See [`StopPort::upsert_all`]. Only the fields of the planned timetable are updated,
+    /// stops are only written if one of them changed.
+    pub(super) fn upsert_stops(&mut self, stops: &[Stop]) -> Result<Upserted<Stop>, PortError> {
+        let mut upserted = Upserted::default();
+        for stop in dedup_by_id(stops, |s| &s.id) {
+            let row = StopRow::from(stop);
+            match self.stops.get(&stop.id) {
+                None => {
+                    self.check_stop(&row)?;
+                    upserted.inserted.push(row.to_stop());
+                    self.put_stop(row);
+                }
+                Some(stored) => {
+                    let mut updated = stored.clone();
+                    if updated.update_planned_from(&row) {
+                        upserted.updated.push(updated.to_stop());
+                        self.put_stop(updated);
+                    } else {
+                        upserted.unchanged.push(stop.clone());
+                    }
+                }
+            }
+        }
+        Ok(upserted)
+    }
+
+    /// Applies `update`, `None` if it sets nothing.
+    fn update_stop(&mut self, update: &StopUpdate) -> Result<Option<Stop>, PortError> {
+        let changes = StopUpdateRow::from(update);
+        if changes.is_empty() {
+            return Ok(None);
+        }
+        let mut row = self.stops.get(&update.id).cloned().ok_or(PortError::NotFound)?;
+        changes.apply_to(&mut row);
+        let stop = row.to_stop();
+        self.put_stop(row);
+        Ok(Some(stop))
+    }
+
+    /// Returns the updated stops, empty updates are skipped.
+    pub(super) fn update_stops(&mut self, updates: &[StopUpdate]) -> Result<Vec<Stop>, PortError> {
+        let mut out = Vec::with_capacity(updates.len());
+        for update in updates {
+            out.extend(self.update_stop(update)?);
+        }
+        Ok(out)
+    }
+
+    /// Stops at any of `stations` of trains running on `date`.
+    pub(super) fn stops_by_stations_and_date<'a>(&'a self, stations: &'a [Station], date: &'a NaiveDate) -> impl Iterator<Item = &'a StopRow> {
+        self.stops.values()
+            .filter(move |s| stations.iter().any(|station| station.id == s.station_id))
+            .filter(move |s| self.trains.get(&s.train_id).is_some_and(|t| t.date == *date))
+    }
+}
+
+impl StopPort for MemoryStopRepo {
+    fn get_for_date(&self, date: &NaiveDate) -> Result<Vec<Stop>, PortError> {
+        let start: NaiveDateTime = date.and_hms_opt(0, 0, 0).unwrap();
+        let end: NaiveDateTime = (date.succ_opt().unwrap()).and_hms_opt(0, 0, 0).unwrap();
+        let in_day = |t: Option<NaiveDateTime>| t.is_some_and(|t| start <= t && t < end);
+
+        // First stops of a train only have a departure.
+        let tables = self.db.read();
+        Ok(to_stops(tables.stops.values().filter(|s| in_day(s.arrival_planned) || in_day(s.departure_planned))))
+    }
+
+    fn get_for_train(&self, train_id: &str) -> Result<Vec<Stop>, PortError> {
+        let tables = self.db.read();
+        Ok(to_stops(tables.stops.values().filter(|s| s.train_id == train_id)))
+    }
+
+    fn get_for_train_with_station(&self, train_id: &str) -> Result<Vec<StopWithStation>, PortError> {
+        let tables = self.db.read();
+        Ok(tables.stops.values()
+            .filter(|s| s.train_id == train_id)
+            .filter_map(|s| tables.stations.get(&s.station_id).map(|station| StopWithStation {
+                stop: s.to_stop(),
+                station: station.clone(),
+            }))
+            .collect())
+    }
+
+    fn get_by_ids(&self, ids: &[String]) -> Result<Vec<Stop>, PortError> {
+        let tables = self.db.read();
+        Ok(to_stops(tables.stops.values().filter(|s| ids.contains(&s.id))))
+    }
+
+    fn get_by_stations_and_date(&self, stations: &[Station], date: &NaiveDate) -> Result<Vec<Stop>, PortError> {
+        let tables = self.db.read();
+        Ok(to_stops(tables.stops_by_stations_and_date(stations, date)))
+    }
+
+    fn get_cancelled_by_stations_and_date(&self, stations: &[Station], date: &NaiveDate) -> Result<Vec<Stop>, PortError> {
+        let cancelled = Some(EventStatus::Cancelled.as_str());
+        let tables = self.db.read();
+        Ok(to_stops(tables.stops_by_stations_and_date(stations, date)
+            .filter(|s| s.arrival_changed_status.as_deref() == cancelled || s.departure_changed_status.as_deref() == cancelled)))
+    }
+
+    fn get_platform_changes_by_stations_and_date(&self, stations: &[Station], date: &NaiveDate) -> Result<Vec<Stop>, PortError> {
+        let tables = self.db.read();
+        let results = to_stops(tables.stops_by_stations_and_date(stations, date)
+            .filter(|s| s.arrival_changed_platform.is_some() || s.departure_changed_platform.is_some()));
+
+        // cp can repeat the planned platform, only keep actual changes.
+        Ok(results.into_iter().filter(|s| !s.platform_changes().is_empty()).collect())
+    }
+
+    /// Counts like the grouped queries of the Postgres repo.
+    fn get_platform_usage(&self, station: &Station) -> Result<Vec<PlatformUsage>, PortError> {
+        let tables = self.db.read();
+        let mut used: HashMap<String, i64> = HashMap::new();
+        let mut changed: HashMap<String, i64> = HashMap::new();
+        let movements = tables.stops.values()
+            .filter(|s| s.station_id == station.id)
+            .flat_map(|s| [(&s.arrival_platform, &s.arrival_changed_platform), (&s.departure_platform, &s.departure_changed_platform)]);
+        for (platform, changed_platform) in movements {
+            let Some(platform) = platform else {
+                continue;
+            };
+            *used.entry(platform.clone()).or_default() += 1;
+            if changed_platform.as_ref().is_some_and(|c| c != platform) {
+                *changed.entry(platform.clone()).or_default() += 1;
+            }
+        }
+        Ok(PlatformUsage::from_counts(used, changed))
+    }
+
+    fn upsert_all(&self, stops: &[Stop]) -> Result<Upserted<Stop>, PortError> {
+        self.db.transaction(|t| t.upsert_stops(stops))
+    }
+
+    /// Fails with [`PortError::InvalidInput`] if the update sets nothing, like the Postgres repo.
+    fn update(&self, update: &StopUpdate) -> Result<Stop, PortError> {
+        self.db.transaction(|t| t.update_stop(update))?
+            .ok_or(PortError::InvalidInput)
+    }
+
+    fn update_many(&self, updates: &[StopUpdate]) -> Result<Vec<Stop>, PortError> {
+        self.db.transaction(|t| t.update_stops(updates))
+    }
+}
+
+impl Port<Stop, String> for MemoryStopRepo {
+    fn persist(&self, stop: &Stop) -> Result<Stop, PortError> {
+        self.db.transaction(|t| t.insert_stops(std::slice::from_ref(stop)))?
+            .pop()
+            .ok_or(PortError::NotFound)
+    }
+
+    fn persist_all(&self, stops: &[Stop]) -> Result<Vec<Stop>, PortError> {
+        self.db.transaction(|t| t.insert_stops(stops))
+    }
+
+    fn get_by_id(&self, id: String) -> Result<Stop, PortError> {
+        self.db.read().stops.get(&id).map(StopRow::to_stop).ok_or(PortError::NotFound)
+    }
+
+    fn get_all(&self) -> Result<Vec<Stop>, PortError> {
+        Ok(to_stops(self.db.read().stops.values()))
+    }
+}
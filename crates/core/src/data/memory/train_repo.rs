@@ -0,0 +1,110 @@
+use std::collections::BTreeSet;
+
+use chrono::NaiveDate;
+
+use crate::{data::{memory::{MemoryDb, Tables}, utils::dedup_by_id}, model::{Station, Train}, ports::{Port, PortError, TrainPort, Upserted}};
+
+
+pub struct MemoryTrainRepo {
+    db: MemoryDb,
+}
+
+impl MemoryTrainRepo {
+    pub fn new(db: MemoryDb) -> Self {
+        Self { db }
+    }
+}
+
+/// Whether one of the columns written by [`TrainPort::upsert_all`] differs.
+fn changed(stored: &Train, given: &Train) -> bool {
+    stored.operator != given.operator
+        || stored.category != given.category
+        || stored.number != given.number
+        || stored.line != given.line
+}
+
+impl Tables {
+    /// Inserts new trains, known ones are skipped. Returns the inserted trains.
+    fn insert_trains(&mut self, trains: &[Train]) -> Vec<Train> {
+        let mut out = Vec::with_capacity(trains.len());
+        for train in trains {
+            if self.trains.contains_key(&train.id) {
+                continue;
+            }
+            self.put_train(train.clone());
+            out.push(train.clone());
+        }
+        out
+    }
+
+    /// See [`TrainPort::upsert_all`]. Trains are only written if a column changed.
+    pub(super) fn upsert_trains(&mut self, trains: &[Train]) -> Upserted<Train> {
+        let mut upserted = Upserted::default();
+        for train in dedup_by_id(trains, |t| &t.id) {
+            match self.trains.get(&train.id) {
+                None => {
+                    self.put_train(train.clone());
+                    upserted.inserted.push(train.clone());
+                }
+                Some(stored) if changed(stored, train) => {
+                    let updated = Train {
+                        operator: train.operator.clone(),
+                        category: train.category.clone(),
+                        number: train.number.clone(),
+                        line: train.line.clone(),
+                        ..stored.clone()
+                    };
+                    self.put_train(updated.clone());
+                    upserted.updated.push(updated);
+                }
+                Some(_) => upserted.unchanged.push(train.clone()),
+            }
+        }
+        upserted
+    }
+}
+
+impl TrainPort for MemoryTrainRepo {
+    fn get_by_stations_and_date(&self, stations: &[Station], date: &NaiveDate) -> Result<Vec<Train>, PortError> {
+        let tables = self.db.read();
+        let train_ids: BTreeSet<&str> = tables.stops_by_stations_and_date(stations, date)
+            .map(|s| s.train_id.as_str())
+            .collect();
+        Ok(train_ids.into_iter().filter_map(|id| tables.trains.get(id)).cloned().collect())
+    }
+
+    fn get_by_date(&self, date: &NaiveDate) -> Result<Vec<Train>, PortError> {
+        Ok(self.db.read().trains.values().filter(|t| t.date == *date).cloned().collect())
+    }
+
+    fn get_by_number_and_date(&self, number: &str, date: &NaiveDate) -> Result<Vec<Train>, PortError> {
+        Ok(self.db.read().trains.values()
+            .filter(|t| t.number == number && t.date == *date)
+            .cloned()
+            .collect())
+    }
+
+    fn upsert_all(&self, trains: &[Train]) -> Result<Upserted<Train>, PortError> {
+        self.db.transaction(|t| Ok(t.upsert_trains(trains)))
+    }
+}
+
+impl Port<Train, String> for MemoryTrainRepo {
+    fn persist(&self, train: &Train) -> Result<Train, PortError> {
+        self.db.transaction(|t| Ok(t.insert_trains(std::slice::from_ref(train))))?
+            .pop()
+            .ok_or(PortError::NotFound)
+    }
+
+    fn persist_all(&self, trains: &[Train]) -> Result<Vec<Train>, PortError> {
+        self.db.transaction(|t| Ok(t.insert_trains(trains)))
+    }
+
+    fn get_by_id(&self, id: String) -> Result<Train, PortError> {
+        self.db.read().trains.get(&id).cloned().ok_or(PortError::NotFound)
+    }
+
+    fn get_all(&self) -> Result<Vec<Train>, PortError> {
+        Ok(self.db.read().trains.values().cloned().collect())
+    }
+}
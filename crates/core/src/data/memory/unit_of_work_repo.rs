@@ -0,0 +1,32 @@
+use crate::{data::memory::MemoryDb, ports::{PortError, UnitOfWork, UnitOfWorkPort, UnitOfWorkResult}};
+
+
+/// Commits a [`UnitOfWork`] under a single write lock, rolling back all writes on error.
+pub struct MemoryUnitOfWorkRepo {
+    db: MemoryDb,
+}
+
+impl MemoryUnitOfWorkRepo {
+    pub fn new(db: MemoryDb) -> Self {
+        Self { db }
+    }
+}
+
+impl UnitOfWorkPort for MemoryUnitOfWorkRepo {
+    fn commit(&self, work: &UnitOfWork) -> Result<UnitOfWorkResult, PortError> {
+        self.db.transaction(|t| {
+            let mut result = UnitOfWorkResult {
+                trains: t.upsert_trains(&work.trains),
+                stops: t.upsert_stops(&work.stops)?,
+                updated_stops: t.update_stops(&work.stop_updates)?,
+                messages: t.persist_messages(&work.messages)?,
+                observations: t.record_observations(&work.observations)?,
+                ..Default::default()
+            };
+            if let Some(revocation) = &work.revocation {
+                result.revoked_messages = t.revoke_missing_messages(revocation.station_id, &revocation.seen_ids, &revocation.now);
+            }
+            Ok(result)
+        })
+    }
+}
@@ -1,14 +1,17 @@
-mod utils;
 mod station_repo;
 mod train_repo;
 mod stop_repo;
+mod stop_observation_repo;
 mod message_repo;
 mod status_code_repo;
+mod unit_of_work_repo;
 
 pub use {
     station_repo::*,
     train_repo::*,
     stop_repo::*,
+    stop_observation_repo::*,
     message_repo::*,
     status_code_repo::*,
+    unit_of_work_repo::*,
 };
@@ -1,8 +1,10 @@
- use chrono::NaiveDateTime;
-use diesel::{r2d2::{ConnectionManager, PooledConnection}, upsert::excluded, BelongingToDsl, ExpressionMethods, GroupedBy, PgConnection, QueryDsl, RunQueryDsl, SelectableHelper};
+ use std::collections::HashMap;
 
- use crate::{data::{db::{row::MessageToStationRow}, repos::utils::{map_pool_err, map_query_result_err}}, model::Message, ports::{MessagePort, Port, PortError}};
- use crate::data::db::{schema::{messages, messages_to_stations}, PgPool, row::MessageRow};
+ use chrono::{NaiveDateTime, Utc};
+use diesel::{dsl::{not, sql}, sql_types::Bool, upsert::excluded, BelongingToDsl, Connection, BoolExpressionMethods, ExpressionMethods, GroupedBy, NullableExpressionMethods, PgConnection, QueryDsl, QueryResult, RunQueryDsl, SelectableHelper};
+
+ use crate::{data::{db::{row::{MessageToStationRow, MessageToStopRow}, sql_types::UtcDateTime}, utils::{map_pool_err, map_query_result_err, split_upserted, superseded_revisions}}, model::{Message, DEFAULT_MESSAGE_VALIDITY}, ports::{MessagePort, Port, PortError, Upserted}};
+ use crate::data::db::{schema::{messages, messages_to_stations, messages_to_stops, stops}, PgPool, row::MessageRow};
 
 
  pub struct MessageRepo {
@@ -16,56 +18,172 @@ use diesel::{r2d2::{ConnectionManager, PooledConnection}, upsert::excluded, Belo
  }
 
 
-fn fetch_station_mappings(conn: &mut PooledConnection<ConnectionManager<PgConnection>>, msgs: &[MessageRow]) -> Result<Vec<MessageToStationRow>, PortError> {
+fn fetch_station_mappings(conn: &mut PgConnection, msgs: &[MessageRow]) -> Result<Vec<MessageToStationRow>, PortError> {
     MessageToStationRow::belonging_to(msgs)
         .load(conn).map_err(map_query_result_err)
 }
 
-fn fetch_station_mapping(conn: &mut PooledConnection<ConnectionManager<PgConnection>>, msg: &MessageRow) -> Result<Vec<MessageToStationRow>, PortError> {
+fn fetch_station_mapping(conn: &mut PgConnection, msg: &MessageRow) -> Result<Vec<MessageToStationRow>, PortError> {
     messages_to_stations::table
         .filter(messages_to_stations::message_id.eq(&msg.id))
         .select(MessageToStationRow::as_select())
         .load(conn).map_err(map_query_result_err)
 }
 
-fn fetch_stations_and_build_models(conn: &mut PooledConnection<ConnectionManager<PgConnection>>, msgs: &[MessageRow]) -> Result<Vec<Message>, PortError> {
-    let mappings = fetch_station_mappings(conn, msgs)?;
-    let results: Vec<Message> =
-        mappings.grouped_by(msgs).into_iter().zip(msgs).map(|(s, m)| m.to_message(&s)).collect();
+fn fetch_stop_mappings(conn: &mut PgConnection, msgs: &[MessageRow]) -> Result<Vec<MessageToStopRow>, PortError> {
+    MessageToStopRow::belonging_to(msgs)
+        .load(conn).map_err(map_query_result_err)
+}
+
+fn fetch_stations_and_build_models(conn: &mut PgConnection, msgs: &[MessageRow]) -> Result<Vec<Message>, PortError> {
+    let station_mappings = fetch_station_mappings(conn, msgs)?.grouped_by(msgs);
+    let stop_mappings = fetch_stop_mappings(conn, msgs)?.grouped_by(msgs);
+    let results: Vec<Message> = station_mappings
+        .into_iter()
+        .zip(stop_mappings)
+        .zip(msgs)
+        .map(|((stations, stops), m)| m.to_message(&stations, &stops))
+        .collect();
     Ok(results)
 }
 
-fn fetch_stations_and_build_model(conn: &mut PooledConnection<ConnectionManager<PgConnection>>, msg: &MessageRow) -> Result<Message, PortError> {
+fn fetch_stations_and_build_model(conn: &mut PgConnection, msg: &MessageRow) -> Result<Message, PortError> {
     let mappings = fetch_station_mapping(conn, msg)?;
-    Ok(msg.to_message(&mappings))
+    let stops = messages_to_stops::table
+        .filter(messages_to_stops::message_id.eq(&msg.id))
+        .select(MessageToStopRow::as_select())
+        .load(conn).map_err(map_query_result_err)?;
+    Ok(msg.to_message(&mappings, &stops))
 }
 
-fn update_stations_relation(conn: &mut PooledConnection<ConnectionManager<PgConnection>>, msg: &Message) -> Result<(), PortError> {
-    let _ = diesel::insert_into(messages_to_stations::table)
-        .values(msg.stations.iter().map(|s_id| MessageToStationRow {
-            message_id: msg.id.clone(),
-            station_id: *s_id,
-        }).collect::<Vec<MessageToStationRow>>())
-       .on_conflict_do_nothing()
-       .execute(conn)
-       .map_err(map_query_result_err)?;
+/// Inserts new messages and refreshes content, `last_updated` and `last_seen` of known ones.
+/// `first_seen` is kept, `revoked_at` is cleared since the message is back in IRIS.
+/// Returns the rows and whether they were inserted.
+fn upsert_messages(conn: &mut PgConnection, rows: &[MessageRow]) -> QueryResult<Vec<(MessageRow, bool)>> {
+    diesel::insert_into(messages::table)
+        .values(rows)
+        .on_conflict(messages::id)
+        .do_update()
+        .set((
+            messages::valid_from.eq(excluded(messages::valid_from)),
+            messages::valid_to.eq(excluded(messages::valid_to)),
+            messages::priority.eq(excluded(messages::priority)),
+            messages::category.eq(excluded(messages::category)),
+            messages::code.eq(excluded(messages::code)),
+            messages::m_type.eq(excluded(messages::m_type)),
+            messages::last_updated.eq(excluded(messages::last_updated)),
+            messages::last_seen.eq(excluded(messages::last_seen)),
+            messages::revoked_at.eq(None::<UtcDateTime>),
+        ))
+        // xmax is only 0 for freshly inserted rows.
+        .returning((MessageRow::as_returning(), sql::<Bool>("xmax = 0")))
+        .get_results::<(MessageRow, bool)>(conn)
+}
+
+/// Marks earlier revisions of the messages as superseded by the latest one, see [`Message::superseded_by`].
+/// Returns the newly superseded ids with the id of the latest revision.
+fn supersede_revisions(conn: &mut PgConnection, messages: &[Message]) -> QueryResult<HashMap<String, String>> {
+    let revisions = messages::table
+        .filter(messages::iris_id.eq_any(messages.iter().map(|m| &m.iris_id)))
+        .select(MessageRow::as_select())
+        .load(conn)?;
+    let superseded = superseded_revisions(&revisions);
+    for (id, latest) in &superseded {
+        diesel::update(messages::table.find(id))
+            .set(messages::superseded_by.eq(latest))
+            .execute(conn)?;
+    }
+    Ok(superseded)
+}
+
+/// Applies [`supersede_revisions`] to rows returned before it.
+fn mark_superseded(rows: &mut [MessageRow], superseded: &HashMap<String, String>) {
+    for row in rows {
+        if let Some(latest) = superseded.get(&row.id) {
+            row.superseded_by = Some(latest.clone());
+        }
+    }
+}
+
+fn update_stops_relations(conn: &mut PgConnection, msgs: &[Message]) -> Result<(), PortError> {
+    let inserts = msgs.iter()
+        .flat_map(|msg| msg.stops.iter().map(|stop| MessageToStopRow::new(&msg.id, stop)))
+        .collect::<Vec<MessageToStopRow>>();
+
+    let _ = diesel::insert_into(messages_to_stops::table)
+        .values(&inserts)
+        .on_conflict_do_nothing()
+        .execute(conn)
+        .map_err(map_query_result_err)?;
     Ok(())
 }
 
-fn update_stations_relations(conn: &mut PooledConnection<ConnectionManager<PgConnection>>, msgs: &[Message]) -> Result<(), PortError> {
-    let inserts = msgs.iter().flat_map(|msg| msg.stations.iter().map(|s_id| MessageToStationRow {
-        message_id: msg.id.clone(),
-        station_id: *s_id,
-    })).collect::<Vec<MessageToStationRow>>();
+/// Links the messages to their stations, which list them again.
+fn update_stations_relations(conn: &mut PgConnection, msgs: &[Message]) -> Result<(), PortError> {
+    let inserts = msgs.iter()
+        .flat_map(|msg| msg.stations.iter().map(|s_id| MessageToStationRow::new(&msg.id, *s_id)))
+        .collect::<Vec<MessageToStationRow>>();
 
     let _ = diesel::insert_into(messages_to_stations::table)
         .values(&inserts)
-        .on_conflict_do_nothing()
+        .on_conflict((messages_to_stations::message_id, messages_to_stations::station_id))
+        .do_update()
+        .set(messages_to_stations::listed.eq(true))
         .execute(conn)
         .map_err(map_query_result_err)?;
     Ok(())
 }
 
+/// Upserts messages with their station and stop links, see [`upsert_messages`],
+/// and supersedes their earlier revisions.
+pub(crate) fn persist_messages(conn: &mut PgConnection, messages: &[Message]) -> Result<Upserted<Message>, PortError> {
+    let (mut rows, inserted): (Vec<MessageRow>, Vec<bool>) = upsert_messages(conn, &messages.iter().map(MessageRow::from).collect::<Vec<MessageRow>>())
+        .map_err(map_query_result_err)?
+        .into_iter()
+        .unzip();
+    mark_superseded(&mut rows, &supersede_revisions(conn, messages).map_err(map_query_result_err)?);
+
+    update_stations_relations(conn, messages)?;
+    update_stops_relations(conn, messages)?;
+    let written = fetch_stations_and_build_models(conn, &rows)?.into_iter().zip(inserted).collect();
+    Ok(split_upserted(&messages.iter().collect::<Vec<_>>(), written, |m| &m.id))
+}
+
+/// See [`MessagePort::revoke_missing`].
+pub(crate) fn revoke_missing_messages(conn: &mut PgConnection, station_id: i32, seen_ids: &[String], now: &NaiveDateTime) -> Result<Vec<Message>, PortError> {
+    diesel::update(messages_to_stations::table)
+        .filter(messages_to_stations::station_id.eq(station_id))
+        .filter(messages_to_stations::message_id.ne_all(seen_ids))
+        .filter(messages_to_stations::listed)
+        .set(messages_to_stations::listed.eq(false))
+        .execute(conn)
+        .map_err(map_query_result_err)?;
+
+    let station_messages = messages_to_stations::table
+        .filter(messages_to_stations::station_id.eq(station_id))
+        .select(messages_to_stations::message_id);
+    let listed_messages = messages_to_stations::table
+        .filter(messages_to_stations::listed)
+        .select(messages_to_stations::message_id);
+    let upcoming_trains = stops::table
+        .filter(stops::station_id.eq(station_id))
+        .filter(stops::arrival_planned.gt(now).or(stops::departure_planned.gt(now)))
+        .select(stops::train_id.nullable());
+
+    let msgs = diesel::update(messages::table)
+        .filter(messages::id.eq_any(station_messages))
+        .filter(messages::id.ne_all(seen_ids))
+        .filter(not(messages::id.eq_any(listed_messages)))
+        .filter(messages::revoked_at.is_null())
+        .filter(messages::valid_to.is_null().or(messages::valid_to.gt(now)))
+        .filter(messages::train_id.is_null().or(messages::train_id.eq_any(upcoming_trains)))
+        .set(messages::revoked_at.eq(UtcDateTime(Utc::now())))
+        .returning(MessageRow::as_returning())
+        .get_results::<MessageRow>(conn)
+        .map_err(map_query_result_err)?;
+
+    fetch_stations_and_build_models(conn, &msgs)
+}
 
  impl MessagePort for MessageRepo {
      fn get_by_date_and_code(&self, date: &chrono::NaiveDate, code: i32) -> Result<Vec<Message>, PortError> {
@@ -97,37 +215,83 @@ fn update_stations_relations(conn: &mut PooledConnection<ConnectionManager<PgCon
 
          fetch_stations_and_build_models(&mut conn, &msgs)
      }
+
+     fn get_station_messages(&self, station_id: i32) -> Result<Vec<Message>, PortError> {
+         let mut conn = self.pool.get().map_err(map_pool_err)?;
+
+         let msgs = messages::table
+            .inner_join(messages_to_stations::table)
+            .filter(messages_to_stations::station_id.eq(station_id))
+            .filter(messages::train_id.is_null())
+            .order(messages::timestamp.desc())
+            .select(MessageRow::as_select())
+            .get_results(&mut conn)
+            .map_err(map_query_result_err)?;
+
+         fetch_stations_and_build_models(&mut conn, &msgs)
+     }
+
+     fn get_by_stop_ids(&self, stop_ids: &[String]) -> Result<Vec<Message>, PortError> {
+         let mut conn = self.pool.get().map_err(map_pool_err)?;
+
+         let message_ids = messages_to_stops::table
+            .filter(messages_to_stops::stop_id.eq_any(stop_ids))
+            .select(messages_to_stops::message_id);
+         let msgs = messages::table
+            .filter(messages::id.eq_any(message_ids))
+            .order(messages::timestamp.asc())
+            .select(MessageRow::as_select())
+            .get_results(&mut conn)
+            .map_err(map_query_result_err)?;
+
+         fetch_stations_and_build_models(&mut conn, &msgs)
+     }
+
+     fn get_active_at(&self, at: &NaiveDateTime) -> Result<Vec<Message>, PortError> {
+         let mut conn = self.pool.get().map_err(map_pool_err)?;
+
+         let msgs = messages::table
+            .filter(messages::revoked_at.is_null())
+            .filter(messages::superseded_by.is_null())
+            .filter(messages::valid_from.le(at).or(messages::valid_from.is_null().and(messages::timestamp.le(at))))
+            .filter(messages::valid_to.ge(at).or(messages::valid_to.is_null().and(messages::timestamp.ge(*at - DEFAULT_MESSAGE_VALIDITY))))
+            .order(messages::timestamp.desc())
+            .select(MessageRow::as_select())
+            .get_results(&mut conn)
+            .map_err(map_query_result_err)?;
+
+         fetch_stations_and_build_models(&mut conn, &msgs)
+     }
+
+     fn revoke_missing(&self, station_id: i32, seen_ids: &[String], now: &NaiveDateTime) -> Result<Vec<Message>, PortError> {
+         let mut conn = self.pool.get().map_err(map_pool_err)?;
+         revoke_missing_messages(&mut conn, station_id, seen_ids, now)
+     }
+
+     fn upsert_all(&self, messages: &[Message]) -> Result<Upserted<Message>, PortError> {
+         let mut conn = self.pool.get().map_err(map_pool_err)?;
+         conn.transaction(|tx| persist_messages(tx, messages))
+     }
  }
 
  impl Port<Message, String> for MessageRepo {
      fn persist(&self, message: &Message) -> Result<Message, PortError> {
          let mut conn = self.pool.get().map_err(map_pool_err)?;
-         let msg = diesel::insert_into(messages::table)
-             .values(MessageRow::from(message))
-             .on_conflict_do_nothing()
-             .returning(MessageRow::as_returning())
-             .get_result::<MessageRow>(&mut conn)
-             .map_err(map_query_result_err)?;
+         let (mut msg, _) = upsert_messages(&mut conn, &[MessageRow::from(message)])
+             .map_err(map_query_result_err)?
+             .pop()
+             .ok_or(PortError::NotFound)?;
+         let superseded = supersede_revisions(&mut conn, std::slice::from_ref(message)).map_err(map_query_result_err)?;
+         mark_superseded(std::slice::from_mut(&mut msg), &superseded);
 
-         update_stations_relation(&mut conn, message)?;
+         update_stations_relations(&mut conn, std::slice::from_ref(message))?;
+         update_stops_relations(&mut conn, std::slice::from_ref(message))?;
          fetch_stations_and_build_model(&mut conn, &msg)
      }
 
      fn persist_all(&self, messages: &[Message]) -> Result<Vec<Message>, PortError> {
          let mut conn = self.pool.get().map_err(map_pool_err)?;
-         let result = diesel::insert_into(messages::table)
-             .values(messages.iter().map(MessageRow::from).collect::<Vec<MessageRow>>())
-             .on_conflict(messages::id)
-             .do_update()
-             .set((
-                 messages::last_updated.eq(excluded(messages::last_updated)),
-             ))
-             .returning(MessageRow::as_returning())
-             .get_results::<MessageRow>(&mut conn)
-             .map_err(map_query_result_err)?;
-
-         update_stations_relations(&mut conn, messages)?;
-         fetch_stations_and_build_models(&mut conn, &result)
+         persist_messages(&mut conn, messages).map(Upserted::into_written)
      }
 
      fn get_by_id(&self, id: String) -> Result<Message, PortError> {
@@ -1,7 +1,9 @@
-use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl, SelectableHelper};
+use std::collections::HashSet;
 
-use crate::{data::repos::utils::{map_pool_err, map_query_result_err}, model::Station, ports::{Port, PortError, StationPort}};
-use crate::data::db::{schema::stations, PgPool, run_sql_file, row::StationRow};
+use diesel::{BoolExpressionMethods, Connection, ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl, SelectableHelper};
+
+use crate::{data::utils::{map_pool_err, map_query_result_err}, model::{search_stations, Station, StationGroup, StationPlatforms}, ports::{Port, PortError, StationPort}};
+use crate::data::db::{schema::{stations, station_groups, station_platforms}, PgPool, run_sql_file, row::{StationGroupRow, StationPlatformRow, StationRow}};
 
 
 pub struct StationRepo {
@@ -14,6 +16,60 @@ impl StationRepo {
     }
 }
 
+/// Replaces the rows of each group's station.
+fn replace_station_groups(conn: &mut PgConnection, groups: &[StationGroup]) -> Result<Vec<StationGroup>, PortError> {
+    for group in groups {
+        diesel::delete(station_groups::table.filter(station_groups::station_id.eq(group.station_id)))
+            .execute(conn)
+            .map_err(map_query_result_err)?;
+        if group.is_empty() {
+            continue;
+        }
+        diesel::insert_into(station_groups::table)
+            .values(StationGroupRow::from_group(group))
+            .execute(conn)
+            .map_err(map_query_result_err)?;
+    }
+    Ok(groups.to_vec())
+}
+
+fn select_group(conn: &mut PgConnection, station: &Station) -> Result<Vec<Station>, PortError> {
+    let members = station_groups::table
+        .filter(station_groups::station_id.eq(station.id))
+        .select(station_groups::member_id);
+    let listing = station_groups::table
+        .filter(station_groups::member_id.eq(station.id))
+        .select(station_groups::station_id);
+    let rows = stations::table
+        .filter(stations::id.eq_any(members).or(stations::id.eq_any(listing)))
+        .filter(stations::id.ne(station.id))
+        .order(stations::id.asc())
+        .select(StationRow::as_select())
+        .get_results::<StationRow>(conn)
+        .map_err(map_query_result_err)?;
+
+    let mut group = vec![station.clone()];
+    group.extend(rows.iter().map(Station::from));
+    Ok(group)
+}
+
+/// Replaces the rows of each station's platforms.
+fn replace_station_platforms(conn: &mut PgConnection, platforms: &[StationPlatforms]) -> Result<Vec<StationPlatforms>, PortError> {
+    for station in platforms {
+        diesel::delete(station_platforms::table.filter(station_platforms::station_id.eq(station.station_id)))
+            .execute(conn)
+            .map_err(map_query_result_err)?;
+        if station.is_empty() {
+            continue;
+        }
+        diesel::insert_into(station_platforms::table)
+            .values(StationPlatformRow::from_platforms(station))
+            .execute(conn)
+            .map_err(map_query_result_err)?;
+    }
+    Ok(platforms.to_vec())
+}
+
 impl Port<Station, i32> for StationRepo {
     fn persist(&self, station: &Station) -> Result<Station, PortError> {
         let mut conn = self.pool.get().map_err(map_pool_err)?;
@@ -68,12 +124,44 @@ impl StationPort for StationRepo {
         Ok(Station::from(row))
     }
 
+    /// Folding accents and umlauts is not possible in plain SQL, so the names are matched
+    /// in Rust. The table holds a few thousand stations.
+    fn search(&self, query: &str, limit: usize) -> Result<Vec<Station>, PortError> {
+        Ok(search_stations(self.get_all()?, query, limit))
+    }
+
+    fn persist_groups(&self, groups: &[StationGroup]) -> Result<Vec<StationGroup>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        conn.transaction(|tx| replace_station_groups(tx, groups))
+    }
+
+    fn get_group(&self, station: &Station) -> Result<Vec<Station>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        select_group(&mut conn, station)
+    }
+
+    fn persist_platforms(&self, platforms: &[StationPlatforms]) -> Result<Vec<StationPlatforms>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        conn.transaction(|tx| replace_station_platforms(tx, platforms))
+    }
+
+    fn get_platforms(&self, station: &Station) -> Result<StationPlatforms, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        let platforms = station_platforms::table
+            .filter(station_platforms::station_id.eq(station.id))
+            .select(station_platforms::platform)
+            .load::<String>(&mut conn)
+            .map_err(map_query_result_err)?;
+        Ok(StationPlatforms::new(station.id, platforms))
+    }
+
     fn import_from_sql(&self, path: &str) -> Result<Vec<Station>, PortError> {
-        run_sql_file::<StationRow>(&self.pool, path)
-            .map(|rows| rows.into_iter().map(Station::from).collect())
-            .map_err(|e| {
-                error!("Error importing stations from SQL file: {}", e);
-                PortError::Custom(e)
-            })
+        // Plain inserts return no rows, the new stations are told apart by their ids.
+        let known: HashSet<i32> = self.get_all()?.iter().map(|s| s.id).collect();
+        run_sql_file::<StationRow>(&self.pool, path).map_err(|e| {
+            error!("Error importing stations from SQL file: {}", e);
+            PortError::Custom(e)
+        })?;
+        Ok(self.get_all()?.into_iter().filter(|s| !known.contains(&s.id)).collect())
     }
 }
@@ -1,6 +1,6 @@
  use diesel::{QueryDsl, RunQueryDsl, SelectableHelper};
 
- use crate::{data::repos::utils::{map_pool_err, map_query_result_err}, model::StatusCode, ports::{Port, PortError, StatusCodePort}};
+ use crate::{data::utils::{map_pool_err, map_query_result_err}, model::StatusCode, ports::{Port, PortError, StatusCodePort}};
  use crate::data::db::{schema::status_codes, PgPool, row::StatusCodeRow};
 
 
@@ -0,0 +1,77 @@
+use diesel::{Connection, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl, SelectableHelper};
+
+use crate::{data::{db::row::{NewStopObservationRow, StopObservationRow}, utils::{map_pool_err, map_query_result_err}}, model::StopObservation, ports::{PortError, StopObservationPort}};
+use crate::data::db::{schema::{stop_observations, stops}, PgPool};
+
+
+pub struct StopObservationRepo {
+    pool: PgPool
+}
+
+impl StopObservationRepo {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+fn to_observations(rows: Vec<StopObservationRow>) -> Vec<StopObservation> {
+    rows.iter().filter_map(|r| r.to_stop_observation()).collect()
+}
+
+/// Records observations that differ from the latest known one of their movement.
+pub(crate) fn record_observations(conn: &mut PgConnection, observations: &[StopObservation]) -> Result<Vec<StopObservation>, PortError> {
+    conn.transaction::<_, diesel::result::Error, _>(|tx| {
+        let mut out = Vec::with_capacity(observations.len());
+        for observation in observations {
+            let latest = stop_observations::table
+                .filter(stop_observations::stop_id.eq(&observation.stop_id))
+                .filter(stop_observations::kind.eq(observation.kind.as_str()))
+                .order((stop_observations::observed_at.desc(), stop_observations::id.desc()))
+                .select(StopObservationRow::as_select())
+                .first(tx)
+                .optional()?
+                .and_then(|r| r.to_stop_observation());
+
+            if latest.is_some_and(|l| l.same_forecast(observation)) {
+                continue;
+            }
+
+            let row = diesel::insert_into(stop_observations::table)
+                .values(NewStopObservationRow::from(observation))
+                .returning(StopObservationRow::as_returning())
+                .get_result(tx)?;
+            out.extend(row.to_stop_observation());
+        }
+        Ok(out)
+    }).map_err(map_query_result_err)
+}
+
+impl StopObservationPort for StopObservationRepo {
+    fn record_all(&self, observations: &[StopObservation]) -> Result<Vec<StopObservation>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        record_observations(&mut conn, observations)
+    }
+
+    fn get_for_stop(&self, stop_id: &str) -> Result<Vec<StopObservation>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        stop_observations::table
+            .filter(stop_observations::stop_id.eq(stop_id))
+            .order((stop_observations::observed_at.asc(), stop_observations::id.asc()))
+            .select(StopObservationRow::as_select())
+            .get_results(&mut conn)
+            .map_err(map_query_result_err)
+            .map(to_observations)
+    }
+
+    fn get_for_train(&self, train_id: &str) -> Result<Vec<StopObservation>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        stop_observations::table
+            .inner_join(stops::table)
+            .filter(stops::train_id.eq(train_id))
+            .order((stop_observations::observed_at.asc(), stop_observations::id.asc()))
+            .select(StopObservationRow::as_select())
+            .get_results(&mut conn)
+            .map_err(map_query_result_err)
+            .map(to_observations)
+    }
+}
@@ -1,7 +1,7 @@
 use chrono::NaiveDateTime;
-use diesel::{BoolExpressionMethods, Connection, ExpressionMethods, JoinOnDsl, OptionalEmptyChangesetExtension, QueryDsl, RunQueryDsl, SelectableHelper};
+use diesel::{dsl::{count_star, sql}, PgExpressionMethods, sql_types::Bool, upsert::excluded, BoolExpressionMethods, Connection, ExpressionMethods, JoinOnDsl, OptionalEmptyChangesetExtension, PgConnection, QueryDsl, RunQueryDsl, SelectableHelper};
 
-use crate::{data::{db::row::{StationRow, StopUpdateRow}, repos::utils::{map_pool_err, map_query_result_err}}, model::{Station, Stop, StopUpdate, StopWithStation}, ports::{Port, PortError, StopPort}};
+use crate::{data::{db::row::{StationRow, StopUpdateRow}, utils::{dedup_by_id, map_pool_err, map_query_result_err, split_upserted}}, model::{EventStatus, PlatformUsage, Station, Stop, StopUpdate, StopWithStation}, ports::{Port, PortError, StopPort, Upserted}};
 use crate::data::db::{schema::{stops, stations, trains}, PgPool, row::StopRow};
 
 
@@ -15,16 +15,144 @@ impl StopRepo {
     }
 }
 
- impl StopPort for StopRepo {
+/// Inserts new stops, known ones are skipped. Returns the inserted stops.
+fn insert_stops(conn: &mut PgConnection, stops: &[Stop]) -> Result<Vec<Stop>, PortError> {
+    diesel::insert_into(stops::table)
+        .values(stops.iter().map(StopRow::from).collect::<Vec<StopRow>>())
+        .on_conflict_do_nothing()
+        .returning(StopRow::as_returning())
+        .get_results::<StopRow>(conn)
+        .map_err(map_query_result_err)
+        .map(|v| v.iter().map(|s| s.to_stop()).collect())
+}
+
+/// See [`StopPort::upsert_all`]. Only the fields of the planned timetable are updated,
+/// rows are only written if one of them changed.
+pub(crate) fn upsert_stops(conn: &mut PgConnection, stops: &[Stop]) -> Result<Upserted<Stop>, PortError> {
+    // `QueryDsl` does not cover insert statements.
+    use diesel::query_dsl::methods::{FilterDsl, OrFilterDsl};
+
+    let stops = dedup_by_id(stops, |s| &s.id);
+    let rows = diesel::insert_into(stops::table)
+        .values(stops.iter().map(|s| StopRow::from(*s)).collect::<Vec<StopRow>>())
+        .on_conflict(stops::id)
+        .do_update()
+        .set((
+            stops::arrival_platform.eq(excluded(stops::arrival_platform)),
+            stops::arrival_planned.eq(excluded(stops::arrival_planned)),
+            stops::arrival_planned_path.eq(excluded(stops::arrival_planned_path)),
+            stops::arrival_line.eq(excluded(stops::arrival_line)),
+            stops::arrival_planned_status.eq(excluded(stops::arrival_planned_status)),
+            stops::arrival_hidden.eq(excluded(stops::arrival_hidden)),
+            stops::arrival_wings.eq(excluded(stops::arrival_wings)),
+            stops::arrival_transition.eq(excluded(stops::arrival_transition)),
+            stops::arrival_planned_distant_endpoint.eq(excluded(stops::arrival_planned_distant_endpoint)),
+            stops::departure_platform.eq(excluded(stops::departure_platform)),
+            stops::departure_planned.eq(excluded(stops::departure_planned)),
+            stops::departure_planned_path.eq(excluded(stops::departure_planned_path)),
+            stops::departure_line.eq(excluded(stops::departure_line)),
+            stops::departure_planned_status.eq(excluded(stops::departure_planned_status)),
+            stops::departure_hidden.eq(excluded(stops::departure_hidden)),
+            stops::departure_wings.eq(excluded(stops::departure_wings)),
+            stops::departure_transition.eq(excluded(stops::departure_transition)),
+            stops::departure_planned_distant_endpoint.eq(excluded(stops::departure_planned_distant_endpoint)),
+        ))
+        .filter(stops::arrival_platform.is_distinct_from(excluded(stops::arrival_platform)))
+        .or_filter(stops::arrival_planned.is_distinct_from(excluded(stops::arrival_planned)))
+        .or_filter(stops::arrival_planned_path.is_distinct_from(excluded(stops::arrival_planned_path)))
+        .or_filter(stops::arrival_line.is_distinct_from(excluded(stops::arrival_line)))
+        .or_filter(stops::arrival_planned_status.is_distinct_from(excluded(stops::arrival_planned_status)))
+        .or_filter(stops::arrival_hidden.is_distinct_from(excluded(stops::arrival_hidden)))
+        .or_filter(stops::arrival_wings.is_distinct_from(excluded(stops::arrival_wings)))
+        .or_filter(stops::arrival_transition.is_distinct_from(excluded(stops::arrival_transition)))
+        .or_filter(stops::arrival_planned_distant_endpoint.is_distinct_from(excluded(stops::arrival_planned_distant_endpoint)))
+        .or_filter(stops::departure_platform.is_distinct_from(excluded(stops::departure_platform)))
+        .or_filter(stops::departure_planned.is_distinct_from(excluded(stops::departure_planned)))
+        .or_filter(stops::departure_planned_path.is_distinct_from(excluded(stops::departure_planned_path)))
+        .or_filter(stops::departure_line.is_distinct_from(excluded(stops::departure_line)))
+        .or_filter(stops::departure_planned_status.is_distinct_from(excluded(stops::departure_planned_status)))
+        .or_filter(stops::departure_hidden.is_distinct_from(excluded(stops::departure_hidden)))
+        .or_filter(stops::departure_wings.is_distinct_from(excluded(stops::departure_wings)))
+        .or_filter(stops::departure_transition.is_distinct_from(excluded(stops::departure_transition)))
+        .or_filter(stops::departure_planned_distant_endpoint.is_distinct_from(excluded(stops::departure_planned_distant_endpoint)))
+        // xmax is only 0 for freshly inserted rows.
+        .returning((StopRow::as_returning(), sql::<Bool>("xmax = 0")))
+        .get_results::<(StopRow, bool)>(conn)
+        .map_err(map_query_result_err)?;
+
+    let written = rows.into_iter().map(|(row, inserted)| (row.to_stop(), inserted)).collect();
+    Ok(split_upserted(&stops, written, |s| &s.id))
+}
+
+/// Applies all updates or none. Returns the updated stops, empty updates are skipped
+/// and unknown stops fail with [`PortError::NotFound`].
+pub(crate) fn update_stops(conn: &mut PgConnection, updates: &[StopUpdate]) -> Result<Vec<Stop>, PortError> {
+    conn.transaction::<_, diesel::result::Error, _>(|tx| {
+        let mut out = Vec::with_capacity(updates.len());
+        for patch in updates {
+            let result = diesel::update(stops::table.find(&patch.id))
+                .set(StopUpdateRow::from(patch))
+                .returning(StopRow::as_returning())
+                .get_result(tx).optional_empty_changeset()?;
+            if let Some(row) = result {
+                out.push(row.to_stop());
+            }
+        }
+        Ok(out)
+    }).map_err(map_query_result_err) // TODO: Map Update Result error?
+}
+
+ /// Stops at `station_id` per planned platform, all of them and those moved to another platform.
+/// NULL platforms form their own group and are dropped.
+fn count_platforms(conn: &mut PgConnection, station_id: i32) -> Result<Vec<PlatformUsage>, PortError> {
+    let arrivals = stops::table
+        .filter(stops::station_id.eq(station_id))
+        .group_by(stops::arrival_platform)
+        .select((stops::arrival_platform, count_star()))
+        .load::<(Option<String>, i64)>(conn)
+        .map_err(map_query_result_err)?;
+    let departures = stops::table
+        .filter(stops::station_id.eq(station_id))
+        .group_by(stops::departure_platform)
+        .select((stops::departure_platform, count_star()))
+        .load::<(Option<String>, i64)>(conn)
+        .map_err(map_query_result_err)?;
+    // cp can repeat the planned platform, only count actual changes.
+    let changed_arrivals = stops::table
+        .filter(stops::station_id.eq(station_id))
+        .filter(stops::arrival_changed_platform.ne(stops::arrival_platform))
+        .group_by(stops::arrival_platform)
+        .select((stops::arrival_platform, count_star()))
+        .load::<(Option<String>, i64)>(conn)
+        .map_err(map_query_result_err)?;
+    let changed_departures = stops::table
+        .filter(stops::station_id.eq(station_id))
+        .filter(stops::departure_changed_platform.ne(stops::departure_platform))
+        .group_by(stops::departure_platform)
+        .select((stops::departure_platform, count_star()))
+        .load::<(Option<String>, i64)>(conn)
+        .map_err(map_query_result_err)?;
+
+    let known = |(platform, count): (Option<String>, i64)| Some((platform?, count));
+    Ok(PlatformUsage::from_counts(
+        arrivals.into_iter().chain(departures).filter_map(known),
+        changed_arrivals.into_iter().chain(changed_departures).filter_map(known),
+    ))
+}
+
+impl StopPort for StopRepo {
      fn get_for_date(&self, date: &chrono::NaiveDate) -> Result<Vec<Stop>, PortError> {
          let mut conn = self.pool.get().map_err(map_pool_err)?;
 
          let start: NaiveDateTime = date.and_hms_opt(0, 0, 0).unwrap();
          let end: NaiveDateTime = (date.succ_opt().unwrap()).and_hms_opt(0, 0, 0).unwrap();
 
+         // First stops of a train only have a departure.
          stops::table
-             .filter(stops::arrival_planned.ge(start))
-             .filter(stops::arrival_planned.lt(end))
+             .filter(
+                 stops::arrival_planned.ge(start).and(stops::arrival_planned.lt(end))
+                     .or(stops::departure_planned.ge(start).and(stops::departure_planned.lt(end)))
+             )
              .select(StopRow::as_select())
              .get_results(&mut conn)
              .map_err(map_query_result_err)
@@ -59,13 +187,24 @@ impl StopRepo {
         Ok(results)
     }
 
-    fn get_by_station_and_date(&self, station: &Station, date: &chrono::NaiveDate) -> Result<Vec<Stop>, PortError> {
+    fn get_by_ids(&self, ids: &[String]) -> Result<Vec<Stop>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        stops::table
+            .filter(stops::id.eq_any(ids))
+            .select(StopRow::as_select())
+            .get_results(&mut conn)
+            .map_err(map_query_result_err)
+            .map(|v| v.iter().map(|s| s.to_stop()).collect())
+    }
+
+    fn get_by_stations_and_date(&self, stations: &[Station], date: &chrono::NaiveDate) -> Result<Vec<Stop>, PortError> {
         let mut conn = self.pool.get().map_err(map_pool_err)?;
+        let ids: Vec<i32> = stations.iter().map(|s| s.id).collect();
 
         let results = stops::table
                 .inner_join(trains::table.on(stops::train_id.eq(trains::id)))
                 .inner_join(stations::table.on(stops::station_id.eq(stations::id)))
-                .filter(stations::id.eq(station.id).and(trains::date.eq(date)))
+                .filter(stations::id.eq_any(&ids).and(trains::date.eq(date)))
                 .select(stops::all_columns)
                 .load::<StopRow>(&mut conn)
                 .map_err(map_query_result_err)?;
@@ -73,6 +212,48 @@ impl StopRepo {
         Ok(results.iter().map(|s| s.to_stop()).collect())
     }
 
+    fn get_cancelled_by_stations_and_date(&self, stations: &[Station], date: &chrono::NaiveDate) -> Result<Vec<Stop>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        let ids: Vec<i32> = stations.iter().map(|s| s.id).collect();
+        let cancelled = EventStatus::Cancelled.as_str();
+
+        let results = stops::table
+                .inner_join(trains::table.on(stops::train_id.eq(trains::id)))
+                .filter(stops::station_id.eq_any(&ids).and(trains::date.eq(date)))
+                .filter(stops::arrival_changed_status.eq(cancelled).or(stops::departure_changed_status.eq(cancelled)))
+                .select(stops::all_columns)
+                .load::<StopRow>(&mut conn)
+                .map_err(map_query_result_err)?;
+
+        Ok(results.iter().map(|s| s.to_stop()).collect())
+    }
+
+    fn get_platform_changes_by_stations_and_date(&self, stations: &[Station], date: &chrono::NaiveDate) -> Result<Vec<Stop>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        let ids: Vec<i32> = stations.iter().map(|s| s.id).collect();
+
+        let results = stops::table
+                .inner_join(trains::table.on(stops::train_id.eq(trains::id)))
+                .filter(stops::station_id.eq_any(&ids).and(trains::date.eq(date)))
+                .filter(stops::arrival_changed_platform.is_not_null().or(stops::departure_changed_platform.is_not_null()))
+                .select(stops::all_columns)
+                .load::<StopRow>(&mut conn)
+                .map_err(map_query_result_err)?;
+
+        // cp can repeat the planned platform, only keep actual changes.
+        Ok(results.iter().map(|s| s.to_stop()).filter(|s| !s.platform_changes().is_empty()).collect())
+    }
+
+    fn get_platform_usage(&self, station: &Station) -> Result<Vec<PlatformUsage>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        count_platforms(&mut conn, station.id)
+    }
+
+    fn upsert_all(&self, stops: &[Stop]) -> Result<Upserted<Stop>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        upsert_stops(&mut conn, stops)
+    }
+
     fn update(&self, update: &StopUpdate) -> Result<Stop, PortError> {
         let mut conn = self.pool.get().map_err(map_pool_err)?;
 
@@ -87,22 +268,7 @@ impl StopRepo {
 
     fn update_many(&self, updates: &[StopUpdate]) -> Result<Vec<Stop>, PortError> {
         let mut conn = self.pool.get().map_err(map_pool_err)?;
-
-        let results = conn.transaction::<_, diesel::result::Error, _>(|tx| {
-            let mut out = Vec::with_capacity(updates.iter().len());
-            for patch in updates {
-                let result = diesel::update(stops::table.find(&patch.id))
-                    .set(StopUpdateRow::from(patch))
-                    .returning(StopRow::as_returning())
-                    .get_result(tx).optional_empty_changeset()?;
-                if let Some(row) = result {
-                    out.push(row.to_stop());
-                }
-            }
-            Ok(out)
-        }).map_err(map_query_result_err)?; // TODO: Map Update Result error?
-
-        Ok(results)
+        update_stops(&mut conn, updates)
     }
 
  }
@@ -120,12 +286,7 @@ impl Port<Stop, String> for StopRepo {
 
     fn persist_all(&self, stops: &[Stop]) -> Result<Vec<Stop>, PortError> {
         let mut conn = self.pool.get().map_err(map_pool_err)?;
-        let result = diesel::insert_into(stops::table)
-            .values(stops.iter().map(StopRow::from).collect::<Vec<StopRow>>())
-            .on_conflict_do_nothing()
-            .returning(StopRow::as_returning())
-            .get_results::<StopRow>(&mut conn).map_err(map_query_result_err).map(|v| v.iter().map(|s| s.to_stop()).collect())?;
-        Ok(result)
+        insert_stops(&mut conn, stops)
     }
 
     fn get_by_id(&self, id: String) -> Result<Stop, PortError> {
@@ -1,7 +1,7 @@
 use chrono::NaiveDate;
-use diesel::{BoolExpressionMethods, ExpressionMethods, JoinOnDsl, QueryDsl, RunQueryDsl, SelectableHelper};
+use diesel::{dsl::sql, PgExpressionMethods, sql_types::Bool, upsert::excluded, BoolExpressionMethods, ExpressionMethods, JoinOnDsl, PgConnection, QueryDsl, RunQueryDsl, SelectableHelper};
 
-use crate::{data::repos::utils::{map_pool_err, map_query_result_err}, model::{Station, Train}, ports::{Port, PortError, TrainPort}};
+use crate::{data::utils::{dedup_by_id, map_pool_err, map_query_result_err, split_upserted}, model::{Station, Train}, ports::{Port, PortError, TrainPort, Upserted}};
 use crate::data::db::{schema::{trains, stops, stations}, PgPool, row::TrainRow};
 
 
@@ -15,15 +15,57 @@ impl TrainRepo {
     }
 }
 
+/// Inserts new trains, known ones are skipped. Returns the inserted trains.
+fn insert_trains(conn: &mut PgConnection, trains: &[Train]) -> Result<Vec<Train>, PortError> {
+    let rows = diesel::insert_into(trains::table)
+        .values(trains.iter().map(TrainRow::from).collect::<Vec<TrainRow>>())
+        .on_conflict_do_nothing()
+        .returning(TrainRow::as_returning())
+        .get_results::<TrainRow>(conn)
+        .map_err(map_query_result_err)?;
+    Ok(rows.iter().map(Train::from).collect())
+}
+
+/// See [`TrainPort::upsert_all`]. Rows are only written if a column changed.
+pub(crate) fn upsert_trains(conn: &mut PgConnection, trains: &[Train]) -> Result<Upserted<Train>, PortError> {
+    // `QueryDsl` does not cover insert statements.
+    use diesel::query_dsl::methods::{FilterDsl, OrFilterDsl};
+
+    let trains = dedup_by_id(trains, |t| &t.id);
+    let rows = diesel::insert_into(trains::table)
+        .values(trains.iter().map(|t| TrainRow::from(*t)).collect::<Vec<TrainRow>>())
+        .on_conflict(trains::id)
+        .do_update()
+        .set((
+            trains::operator.eq(excluded(trains::operator)),
+            trains::category.eq(excluded(trains::category)),
+            trains::number.eq(excluded(trains::number)),
+            trains::line.eq(excluded(trains::line)),
+        ))
+        .filter(trains::operator.is_distinct_from(excluded(trains::operator)))
+        .or_filter(trains::category.is_distinct_from(excluded(trains::category)))
+        .or_filter(trains::number.is_distinct_from(excluded(trains::number)))
+        .or_filter(trains::line.is_distinct_from(excluded(trains::line)))
+        // xmax is only 0 for freshly inserted rows.
+        .returning((TrainRow::as_returning(), sql::<Bool>("xmax = 0")))
+        .get_results::<(TrainRow, bool)>(conn)
+        .map_err(map_query_result_err)?;
+
+    let written = rows.into_iter().map(|(row, inserted)| (Train::from(row), inserted)).collect();
+    Ok(split_upserted(&trains, written, |t| &t.id))
+}
+
 impl TrainPort for TrainRepo {
-    fn get_by_station_and_date(&self, station: &Station, date: &NaiveDate) -> Result<Vec<Train>, PortError> {
+    fn get_by_stations_and_date(&self, stations: &[Station], date: &NaiveDate) -> Result<Vec<Train>, PortError> {
         let mut conn = self.pool.get().map_err(map_pool_err)?;
+        let ids: Vec<i32> = stations.iter().map(|s| s.id).collect();
 
         let results = trains::table
             .inner_join(stops::table.on(stops::train_id.eq(trains::id)))
             .inner_join(stations::table.on(stops::station_id.eq(stations::id)))
-            .filter(stations::id.eq(station.id).and(trains::date.eq(date)))
+            .filter(stations::id.eq_any(&ids).and(trains::date.eq(date)))
             .select(TrainRow::as_select())
+            .distinct()
             .load::<TrainRow>(&mut conn)
             .map_err(map_query_result_err)?;
 
@@ -39,6 +81,22 @@ impl TrainPort for TrainRepo {
             .map_err(map_query_result_err)?;
         Ok(rows.iter().map(Train::from).collect())
     }
+
+    fn get_by_number_and_date(&self, number: &str, date: &NaiveDate) -> Result<Vec<Train>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        let rows = trains::table
+            .filter(trains::number.eq(number).and(trains::date.eq(date)))
+            .order(trains::id.asc())
+            .select(TrainRow::as_select())
+            .get_results::<TrainRow>(&mut conn)
+            .map_err(map_query_result_err)?;
+        Ok(rows.iter().map(Train::from).collect())
+    }
+
+    fn upsert_all(&self, trains: &[Train]) -> Result<Upserted<Train>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        upsert_trains(&mut conn, trains)
+    }
 }
 
 impl Port<Train, String> for TrainRepo {
@@ -55,13 +113,7 @@ impl Port<Train, String> for TrainRepo {
 
     fn persist_all(&self, trains: &[Train]) -> Result<Vec<Train>, PortError> {
         let mut conn = self.pool.get().map_err(map_pool_err)?;
-        let rows = diesel::insert_into(trains::table)
-            .values(trains.iter().map(TrainRow::from).collect::<Vec<TrainRow>>())
-            .on_conflict_do_nothing()
-            .returning(TrainRow::as_returning())
-            .get_results::<TrainRow>(&mut conn)
-            .map_err(map_query_result_err)?;
-        Ok(rows.iter().map(Train::from).collect())
+        insert_trains(&mut conn, trains)
     }
 
     fn get_by_id(&self, id: String) -> Result<Train, PortError> {
@@ -0,0 +1,37 @@
+use diesel::Connection;
+
+use crate::{data::{repos::{upsert_stops, upsert_trains, persist_messages, record_observations, revoke_missing_messages, update_stops}, utils::map_pool_err}, ports::{PortError, UnitOfWork, UnitOfWorkPort, UnitOfWorkResult}};
+use crate::data::db::PgPool;
+
+
+/// Commits a [`UnitOfWork`] in a single Postgres transaction.
+pub struct UnitOfWorkRepo {
+    pool: PgPool,
+}
+
+impl UnitOfWorkRepo {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl UnitOfWorkPort for UnitOfWorkRepo {
+    fn commit(&self, work: &UnitOfWork) -> Result<UnitOfWorkResult, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+
+        conn.transaction::<_, PortError, _>(|tx| {
+            let mut result = UnitOfWorkResult {
+                trains: upsert_trains(tx, &work.trains)?,
+                stops: upsert_stops(tx, &work.stops)?,
+                updated_stops: update_stops(tx, &work.stop_updates)?,
+                messages: persist_messages(tx, &work.messages)?,
+                observations: record_observations(tx, &work.observations)?,
+                ..Default::default()
+            };
+            if let Some(revocation) = &work.revocation {
+                result.revoked_messages = revoke_missing_messages(tx, revocation.station_id, &revocation.seen_ids, &revocation.now)?;
+            }
+            Ok(result)
+        })
+    }
+}
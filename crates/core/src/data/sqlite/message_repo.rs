@@ -0,0 +1,294 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{NaiveDateTime, Utc};
+use diesel::{dsl::not, upsert::excluded, BelongingToDsl, BoolExpressionMethods, Connection, ExpressionMethods, GroupedBy, NullableExpressionMethods, QueryDsl, QueryResult, RunQueryDsl, SelectableHelper, SqliteConnection};
+
+use crate::{data::{db::{row::{MessageToStationRow, MessageToStopRow}, sql_types::UtcDateTime}, utils::{map_pool_err, map_query_result_err, superseded_revisions}}, model::{Message, DEFAULT_MESSAGE_VALIDITY}, ports::{MessagePort, Port, PortError, Upserted}};
+use crate::data::db::{schema::{messages, messages_to_stations, messages_to_stops, stops}, SqlitePool, row::MessageRow};
+
+
+pub struct SqliteMessageRepo {
+    pool: SqlitePool,
+}
+
+impl SqliteMessageRepo {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+fn build_models(conn: &mut SqliteConnection, msgs: &[MessageRow]) -> Result<Vec<Message>, PortError> {
+    let station_mappings = MessageToStationRow::belonging_to(msgs)
+        .select(MessageToStationRow::as_select())
+        .load(conn)
+        .map_err(map_query_result_err)?
+        .grouped_by(msgs);
+    let stop_mappings = MessageToStopRow::belonging_to(msgs)
+        .select(MessageToStopRow::as_select())
+        .load(conn)
+        .map_err(map_query_result_err)?
+        .grouped_by(msgs);
+    Ok(station_mappings
+        .into_iter()
+        .zip(stop_mappings)
+        .zip(msgs)
+        .map(|((stations, stops), m)| m.to_message(&stations, &stops))
+        .collect())
+}
+
+/// Inserts a new message or refreshes content, `last_updated` and `last_seen` of a known one.
+/// `first_seen` is kept, `revoked_at` is cleared since the message is back in IRIS.
+fn upsert_message(conn: &mut SqliteConnection, row: &MessageRow) -> QueryResult<MessageRow> {
+    diesel::insert_into(messages::table)
+        .values(row)
+        .on_conflict(messages::id)
+        .do_update()
+        .set((
+            messages::valid_from.eq(excluded(messages::valid_from)),
+            messages::valid_to.eq(excluded(messages::valid_to)),
+            messages::priority.eq(excluded(messages::priority)),
+            messages::category.eq(excluded(messages::category)),
+            messages::code.eq(excluded(messages::code)),
+            messages::m_type.eq(excluded(messages::m_type)),
+            messages::last_updated.eq(excluded(messages::last_updated)),
+            messages::last_seen.eq(excluded(messages::last_seen)),
+            messages::revoked_at.eq(None::<UtcDateTime>),
+        ))
+        .returning(MessageRow::as_returning())
+        .get_result::<MessageRow>(conn)
+}
+
+/// Marks earlier revisions of the messages as superseded by the latest one, see [`Message::superseded_by`].
+/// Returns the newly superseded ids with the id of the latest revision.
+fn supersede_revisions(conn: &mut SqliteConnection, messages: &[Message]) -> QueryResult<HashMap<String, String>> {
+    let revisions = messages::table
+        .filter(messages::iris_id.eq_any(messages.iter().map(|m| &m.iris_id)))
+        .select(MessageRow::as_select())
+        .load(conn)?;
+    let superseded = superseded_revisions(&revisions);
+    for (id, latest) in &superseded {
+        diesel::update(messages::table.find(id))
+            .set(messages::superseded_by.eq(latest))
+            .execute(conn)?;
+    }
+    Ok(superseded)
+}
+
+/// Adds missing station and stop links of `msg`, its stations list it again.
+fn link_message(conn: &mut SqliteConnection, msg: &Message) -> QueryResult<()> {
+    for station_id in &msg.stations {
+        diesel::insert_into(messages_to_stations::table)
+            .values(MessageToStationRow::new(&msg.id, *station_id))
+            .on_conflict((messages_to_stations::message_id, messages_to_stations::station_id))
+            .do_update()
+            .set(messages_to_stations::listed.eq(true))
+            .execute(conn)?;
+    }
+    for stop in &msg.stops {
+        diesel::insert_into(messages_to_stops::table)
+            .values(MessageToStopRow::new(&msg.id, stop))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+    }
+    Ok(())
+}
+
+/// Upserts messages with their station and stop links, see [`upsert_message`],
+/// and supersedes their earlier revisions.
+/// Like Postgres, an upsert may not touch the same message twice.
+pub(crate) fn persist_messages(conn: &mut SqliteConnection, messages: &[Message]) -> Result<Upserted<Message>, PortError> {
+    let mut ids = HashSet::with_capacity(messages.len());
+    if !messages.iter().all(|m| ids.insert(m.id.as_str())) {
+        error!("Constraint violation: message upserted twice");
+        return Err(PortError::Database);
+    }
+
+    let (rows, known) = conn.transaction::<_, diesel::result::Error, _>(|tx| {
+        // SQLite has no `xmax`, messages stored before the upsert were updated.
+        let known: HashSet<String> = messages::table
+            .filter(messages::id.eq_any(messages.iter().map(|m| &m.id)))
+            .select(messages::id)
+            .load(tx)?
+            .into_iter()
+            .collect();
+        let mut rows = Vec::with_capacity(messages.len());
+        for message in messages {
+            rows.push(upsert_message(tx, &MessageRow::from(message))?);
+        }
+        for message in messages {
+            link_message(tx, message)?;
+        }
+        let superseded = supersede_revisions(tx, messages)?;
+        for row in &mut rows {
+            if let Some(latest) = superseded.get(&row.id) {
+                row.superseded_by = Some(latest.clone());
+            }
+        }
+        Ok((rows, known))
+    }).map_err(map_query_result_err)?;
+
+    let (updated, inserted) = build_models(conn, &rows)?.into_iter().partition(|m| known.contains(&m.id));
+    Ok(Upserted { inserted, updated, unchanged: Vec::new() })
+}
+
+/// See [`MessagePort::revoke_missing`].
+pub(crate) fn revoke_missing_messages(conn: &mut SqliteConnection, station_id: i32, seen_ids: &[String], now: &NaiveDateTime) -> Result<Vec<Message>, PortError> {
+    diesel::update(messages_to_stations::table)
+        .filter(messages_to_stations::station_id.eq(station_id))
+        .filter(not(messages_to_stations::message_id.eq_any(seen_ids)))
+        .filter(messages_to_stations::listed)
+        .set(messages_to_stations::listed.eq(false))
+        .execute(conn)
+        .map_err(map_query_result_err)?;
+
+    let station_messages = messages_to_stations::table
+        .filter(messages_to_stations::station_id.eq(station_id))
+        .select(messages_to_stations::message_id);
+    let listed_messages = messages_to_stations::table
+        .filter(messages_to_stations::listed)
+        .select(messages_to_stations::message_id);
+    let upcoming_trains = stops::table
+        .filter(stops::station_id.eq(station_id))
+        .filter(stops::arrival_planned.gt(now).or(stops::departure_planned.gt(now)))
+        .select(stops::train_id.nullable());
+
+    // SQLite has no `!= ALL`.
+    let msgs = diesel::update(messages::table)
+        .filter(messages::id.eq_any(station_messages))
+        .filter(not(messages::id.eq_any(seen_ids)))
+        .filter(not(messages::id.eq_any(listed_messages)))
+        .filter(messages::revoked_at.is_null())
+        .filter(messages::valid_to.is_null().or(messages::valid_to.gt(now)))
+        .filter(messages::train_id.is_null().or(messages::train_id.eq_any(upcoming_trains)))
+        .set(messages::revoked_at.eq(UtcDateTime(Utc::now())))
+        .returning(MessageRow::as_returning())
+        .get_results::<MessageRow>(conn)
+        .map_err(map_query_result_err)?;
+
+    build_models(conn, &msgs)
+}
+
+impl MessagePort for SqliteMessageRepo {
+    fn get_by_date_and_code(&self, date: &chrono::NaiveDate, code: i32) -> Result<Vec<Message>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+
+        let start: NaiveDateTime = date.and_hms_opt(0, 0, 0).unwrap();
+        let end: NaiveDateTime = (date.succ_opt().unwrap()).and_hms_opt(0, 0, 0).unwrap();
+
+        let msgs: Vec<MessageRow> = messages::table
+            .filter(messages::timestamp.ge(start))
+            .filter(messages::timestamp.lt(end))
+            .filter(messages::code.eq(code))
+            .select(MessageRow::as_select())
+            .get_results(&mut conn)
+            .map_err(map_query_result_err)?;
+
+        build_models(&mut conn, &msgs)
+    }
+
+    fn get_by_train_id(&self, train_id: &str) -> Result<Vec<Message>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+
+        let msgs = messages::table
+            .filter(messages::train_id.eq(train_id))
+            .select(MessageRow::as_select())
+            .get_results(&mut conn)
+            .map_err(map_query_result_err)?;
+
+        build_models(&mut conn, &msgs)
+    }
+
+    fn get_station_messages(&self, station_id: i32) -> Result<Vec<Message>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+
+        let msgs = messages::table
+            .inner_join(messages_to_stations::table)
+            .filter(messages_to_stations::station_id.eq(station_id))
+            .filter(messages::train_id.is_null())
+            .order(messages::timestamp.desc())
+            .select(MessageRow::as_select())
+            .get_results(&mut conn)
+            .map_err(map_query_result_err)?;
+
+        build_models(&mut conn, &msgs)
+    }
+
+    fn get_by_stop_ids(&self, stop_ids: &[String]) -> Result<Vec<Message>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+
+        let message_ids = messages_to_stops::table
+            .filter(messages_to_stops::stop_id.eq_any(stop_ids))
+            .select(messages_to_stops::message_id);
+        let msgs = messages::table
+            .filter(messages::id.eq_any(message_ids))
+            .order(messages::timestamp.asc())
+            .select(MessageRow::as_select())
+            .get_results(&mut conn)
+            .map_err(map_query_result_err)?;
+
+        build_models(&mut conn, &msgs)
+    }
+
+    fn get_active_at(&self, at: &NaiveDateTime) -> Result<Vec<Message>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+
+        let msgs = messages::table
+            .filter(messages::revoked_at.is_null())
+            .filter(messages::superseded_by.is_null())
+            .filter(messages::valid_from.le(at).or(messages::valid_from.is_null().and(messages::timestamp.le(at))))
+            .filter(messages::valid_to.ge(at).or(messages::valid_to.is_null().and(messages::timestamp.ge(*at - DEFAULT_MESSAGE_VALIDITY))))
+            .order(messages::timestamp.desc())
+            .select(MessageRow::as_select())
+            .get_results(&mut conn)
+            .map_err(map_query_result_err)?;
+
+        build_models(&mut conn, &msgs)
+    }
+
+    fn revoke_missing(&self, station_id: i32, seen_ids: &[String], now: &NaiveDateTime) -> Result<Vec<Message>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        revoke_missing_messages(&mut conn, station_id, seen_ids, now)
+    }
+
+    fn upsert_all(&self, messages: &[Message]) -> Result<Upserted<Message>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        persist_messages(&mut conn, messages)
+    }
+}
+
+impl Port<Message, String> for SqliteMessageRepo {
+    fn persist(&self, message: &Message) -> Result<Message, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        persist_messages(&mut conn, std::slice::from_ref(message))?
+            .into_written()
+            .pop()
+            .ok_or(PortError::NotFound)
+    }
+
+    fn persist_all(&self, messages: &[Message]) -> Result<Vec<Message>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        persist_messages(&mut conn, messages).map(Upserted::into_written)
+    }
+
+    fn get_by_id(&self, id: String) -> Result<Message, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        let row = messages::table
+            .find(id)
+            .select(MessageRow::as_select())
+            .first(&mut conn)
+            .map_err(map_query_result_err)?;
+
+        build_models(&mut conn, std::slice::from_ref(&row))?
+            .pop()
+            .ok_or(PortError::NotFound)
+    }
+
+    fn get_all(&self) -> Result<Vec<Message>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        let msgs = messages::table
+            .select(MessageRow::as_select())
+            .get_results(&mut conn)
+            .map_err(map_query_result_err)?;
+        build_models(&mut conn, &msgs)
+    }
+}
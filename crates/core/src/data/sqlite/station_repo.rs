@@ -0,0 +1,189 @@
+use std::fs;
+
+use diesel::{BoolExpressionMethods, Connection, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper, SqliteConnection};
+
+use crate::{data::utils::{map_pool_err, map_query_result_err}, model::{search_stations, Station, StationGroup, StationPlatforms}, ports::{Port, PortError, StationPort}};
+use crate::data::db::{schema::{stations, station_groups, station_platforms}, SqlitePool, row::{StationGroupRow, StationPlatformRow, StationRow}};
+
+
+pub struct SqliteStationRepo {
+    pool: SqlitePool,
+}
+
+impl SqliteStationRepo {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+/// Inserts the station unless it is known, `None` if it was skipped.
+fn insert_station(conn: &mut SqliteConnection, station: &Station) -> Result<Option<Station>, PortError> {
+    diesel::insert_into(stations::table)
+        .values(StationRow::from(station))
+        .on_conflict_do_nothing()
+        .returning(StationRow::as_returning())
+        .get_result::<StationRow>(conn)
+        .optional()
+        .map(|row| row.map(Station::from))
+        .map_err(map_query_result_err)
+}
+
+/// Runs the inserts of a `pg_dump --inserts` file, known stations are skipped.
+/// SQLite has no `public` schema, so it is stripped from the table names.
+fn run_station_inserts(conn: &mut SqliteConnection, sql: &str) -> Result<Vec<Station>, diesel::result::Error> {
+    conn.transaction(|tx| {
+        let mut out = Vec::new();
+        for stmt in sql.split(';') {
+            let trimmed = stmt.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            let stmt = trimmed.replacen("INSERT INTO public.", "INSERT INTO ", 1);
+            let rows = diesel::sql_query(format!("{} ON CONFLICT DO NOTHING RETURNING *", stmt))
+                .get_results::<StationRow>(tx)?;
+            out.extend(rows.into_iter().map(Station::from));
+        }
+        Ok(out)
+    })
+}
+
+/// Replaces the rows of each group's station.
+fn replace_station_groups(conn: &mut SqliteConnection, groups: &[StationGroup]) -> Result<Vec<StationGroup>, PortError> {
+    for group in groups {
+        diesel::delete(station_groups::table.filter(station_groups::station_id.eq(group.station_id)))
+            .execute(conn)
+            .map_err(map_query_result_err)?;
+        if group.is_empty() {
+            continue;
+        }
+        diesel::insert_into(station_groups::table)
+            .values(StationGroupRow::from_group(group))
+            .execute(conn)
+            .map_err(map_query_result_err)?;
+    }
+    Ok(groups.to_vec())
+}
+
+fn select_group(conn: &mut SqliteConnection, station: &Station) -> Result<Vec<Station>, PortError> {
+    let members = station_groups::table
+        .filter(station_groups::station_id.eq(station.id))
+        .select(station_groups::member_id);
+    let listing = station_groups::table
+        .filter(station_groups::member_id.eq(station.id))
+        .select(station_groups::station_id);
+    let rows = stations::table
+        .filter(stations::id.eq_any(members).or(stations::id.eq_any(listing)))
+        .filter(stations::id.ne(station.id))
+        .order(stations::id.asc())
+        .select(StationRow::as_select())
+        .get_results::<StationRow>(conn)
+        .map_err(map_query_result_err)?;
+
+    let mut group = vec![station.clone()];
+    group.extend(rows.iter().map(Station::from));
+    Ok(group)
+}
+
+/// Replaces the rows of each station's platforms.
+fn replace_station_platforms(conn: &mut SqliteConnection, platforms: &[StationPlatforms]) -> Result<Vec<StationPlatforms>, PortError> {
+    for station in platforms {
+        diesel::delete(station_platforms::table.filter(station_platforms::station_id.eq(station.station_id)))
+            .execute(conn)
+            .map_err(map_query_result_err)?;
+        if station.is_empty() {
+            continue;
+        }
+        diesel::insert_into(station_platforms::table)
+            .values(StationPlatformRow::from_platforms(station))
+            .execute(conn)
+            .map_err(map_query_result_err)?;
+    }
+    Ok(platforms.to_vec())
+}
+
+impl Port<Station, i32> for SqliteStationRepo {
+    fn persist(&self, station: &Station) -> Result<Station, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        insert_station(&mut conn, station)?.ok_or(PortError::NotFound)
+    }
+
+    fn persist_all(&self, stations: &[Station]) -> Result<Vec<Station>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        conn.transaction(|tx| {
+            let mut out = Vec::with_capacity(stations.len());
+            for station in stations {
+                out.extend(insert_station(tx, station)?);
+            }
+            Ok(out)
+        })
+    }
+
+    fn get_by_id(&self, id: i32) -> Result<Station, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        let row = stations::table
+            .find(id)
+            .select(StationRow::as_select())
+            .first(&mut conn)
+            .map_err(map_query_result_err)?;
+        Ok(Station::from(row))
+    }
+
+    fn get_all(&self) -> Result<Vec<Station>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        let rows = stations::table
+            .select(StationRow::as_select())
+            .get_results::<StationRow>(&mut conn)
+            .map_err(map_query_result_err)?;
+        Ok(rows.iter().map(Station::from).collect())
+    }
+}
+
+impl StationPort for SqliteStationRepo {
+    fn get_by_ds100(&self, ds100: &str) -> Result<Station, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        let row = stations::table
+            .filter(stations::ds100.eq(ds100))
+            .select(StationRow::as_select())
+            .first::<StationRow>(&mut conn)
+            .map_err(map_query_result_err)?;
+        Ok(Station::from(row))
+    }
+
+    fn search(&self, query: &str, limit: usize) -> Result<Vec<Station>, PortError> {
+        Ok(search_stations(self.get_all()?, query, limit))
+    }
+
+    fn persist_groups(&self, groups: &[StationGroup]) -> Result<Vec<StationGroup>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        conn.transaction(|tx| replace_station_groups(tx, groups))
+    }
+
+    fn get_group(&self, station: &Station) -> Result<Vec<Station>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        select_group(&mut conn, station)
+    }
+
+    fn persist_platforms(&self, platforms: &[StationPlatforms]) -> Result<Vec<StationPlatforms>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        conn.transaction(|tx| replace_station_platforms(tx, platforms))
+    }
+
+    fn get_platforms(&self, station: &Station) -> Result<StationPlatforms, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        let platforms = station_platforms::table
+            .filter(station_platforms::station_id.eq(station.id))
+            .select(station_platforms::platform)
+            .load::<String>(&mut conn)
+            .map_err(map_query_result_err)?;
+        Ok(StationPlatforms::new(station.id, platforms))
+    }
+
+    fn import_from_sql(&self, path: &str) -> Result<Vec<Station>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        let sql = fs::read_to_string(path).map_err(|e| {
+            error!("Error importing stations from SQL file: {}", e);
+            PortError::Custom(Box::new(e))
+        })?;
+        run_station_inserts(&mut conn, &sql).map_err(map_query_result_err)
+    }
+}
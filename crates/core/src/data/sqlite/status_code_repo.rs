@@ -0,0 +1,67 @@
+use diesel::{Connection, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper, SqliteConnection};
+
+use crate::{data::utils::{map_pool_err, map_query_result_err}, model::StatusCode, ports::{Port, PortError, StatusCodePort}};
+use crate::data::db::{schema::status_codes, SqlitePool, row::StatusCodeRow};
+
+
+pub struct SqliteStatusCodeRepo {
+    pool: SqlitePool,
+}
+
+impl SqliteStatusCodeRepo {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+/// Inserts the code unless it is known, `None` if it was skipped.
+fn insert_status_code(conn: &mut SqliteConnection, code: &StatusCode) -> Result<Option<StatusCode>, PortError> {
+    diesel::insert_into(status_codes::table)
+        .values(StatusCodeRow::from(code))
+        .on_conflict_do_nothing()
+        .returning(StatusCodeRow::as_returning())
+        .get_result::<StatusCodeRow>(conn)
+        .optional()
+        .map(|row| row.map(StatusCode::from))
+        .map_err(map_query_result_err)
+}
+
+impl Port<StatusCode, i16> for SqliteStatusCodeRepo {
+    fn persist(&self, code: &StatusCode) -> Result<StatusCode, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        insert_status_code(&mut conn, code)?.ok_or(PortError::NotFound)
+    }
+
+    fn persist_all(&self, codes: &[StatusCode]) -> Result<Vec<StatusCode>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        conn.transaction(|tx| {
+            let mut out = Vec::with_capacity(codes.len());
+            for code in codes {
+                out.extend(insert_status_code(tx, code)?);
+            }
+            Ok(out)
+        })
+    }
+
+    fn get_by_id(&self, id: i16) -> Result<StatusCode, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        status_codes::table
+            .find(id)
+            .select(StatusCodeRow::as_select())
+            .first(&mut conn)
+            .map(StatusCode::from)
+            .map_err(map_query_result_err)
+    }
+
+    fn get_all(&self) -> Result<Vec<StatusCode>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        status_codes::table
+            .select(StatusCodeRow::as_select())
+            .get_results(&mut conn)
+            .map(|rows| rows.iter().map(StatusCode::from).collect::<Vec<StatusCode>>())
+            .map_err(map_query_result_err)
+    }
+}
+
+impl StatusCodePort for SqliteStatusCodeRepo {
+}
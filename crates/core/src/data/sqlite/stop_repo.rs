@@ -0,0 +1,287 @@
+use chrono::NaiveDateTime;
+use diesel::{dsl::count_star, BoolExpressionMethods, Connection, ExpressionMethods, JoinOnDsl, OptionalEmptyChangesetExtension, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper, SqliteConnection};
+
+use crate::{data::{db::row::{StationRow, StopUpdateRow}, utils::{dedup_by_id, map_pool_err, map_query_result_err}}, model::{EventStatus, PlatformUsage, Station, Stop, StopUpdate, StopWithStation}, ports::{Port, PortError, StopPort, Upserted}};
+use crate::data::db::{schema::{stops, stations, trains}, SqlitePool, row::StopRow};
+
+
+pub struct SqliteStopRepo {
+    pool: SqlitePool
+}
+
+impl SqliteStopRepo {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+/// Inserts the stop unless it is known, `None` if it was skipped.
+fn insert_stop(conn: &mut SqliteConnection, stop: &Stop) -> Result<Option<Stop>, PortError> {
+    diesel::insert_into(stops::table)
+        .values(StopRow::from(stop))
+        .on_conflict_do_nothing()
+        .returning(StopRow::as_returning())
+        .get_result::<StopRow>(conn)
+        .optional()
+        .map(|row| row.map(|s| s.to_stop()))
+        .map_err(map_query_result_err)
+}
+
+/// See [`StopPort::upsert_all`]. Only the fields of the planned timetable are updated,
+/// stored rows are compared in Rust and only written if one of them changed.
+pub(crate) fn upsert_stops(conn: &mut SqliteConnection, stops: &[Stop]) -> Result<Upserted<Stop>, PortError> {
+    let mut upserted = Upserted::default();
+    for stop in dedup_by_id(stops, |s| &s.id) {
+        let row = StopRow::from(stop);
+        let stored = stops::table
+            .find(&stop.id)
+            .select(StopRow::as_select())
+            .first::<StopRow>(conn)
+            .optional()
+            .map_err(map_query_result_err)?;
+        match stored {
+            None => {
+                diesel::insert_into(stops::table)
+                    .values(&row)
+                    .execute(conn)
+                    .map_err(map_query_result_err)?;
+                upserted.inserted.push(row.to_stop());
+            }
+            Some(mut updated) => {
+                if updated.update_planned_from(&row) {
+                    diesel::update(stops::table.find(&stop.id))
+                        .set(&updated)
+                        .execute(conn)
+                        .map_err(map_query_result_err)?;
+                    upserted.updated.push(updated.to_stop());
+                } else {
+                    upserted.unchanged.push(stop.clone());
+                }
+            }
+        }
+    }
+    Ok(upserted)
+}
+
+/// Returns the updated stops, empty updates are skipped and unknown stops fail
+/// with [`PortError::NotFound`]. Callers run it in a transaction.
+pub(crate) fn update_stops(conn: &mut SqliteConnection, updates: &[StopUpdate]) -> Result<Vec<Stop>, PortError> {
+    let mut out = Vec::with_capacity(updates.len());
+    for patch in updates {
+        let result = diesel::update(stops::table.find(&patch.id))
+            .set(StopUpdateRow::from(patch))
+            .returning(StopRow::as_returning())
+            .get_result(conn)
+            .optional_empty_changeset()
+            .map_err(map_query_result_err)?;
+        if let Some(row) = result {
+            out.push(row.to_stop());
+        }
+    }
+    Ok(out)
+}
+
+/// Stops at `station_id` per planned platform, all of them and those moved to another platform.
+/// NULL platforms form their own group and are dropped.
+fn count_platforms(conn: &mut SqliteConnection, station_id: i32) -> Result<Vec<PlatformUsage>, PortError> {
+    let arrivals = stops::table
+        .filter(stops::station_id.eq(station_id))
+        .group_by(stops::arrival_platform)
+        .select((stops::arrival_platform, count_star()))
+        .load::<(Option<String>, i64)>(conn)
+        .map_err(map_query_result_err)?;
+    let departures = stops::table
+        .filter(stops::station_id.eq(station_id))
+        .group_by(stops::departure_platform)
+        .select((stops::departure_platform, count_star()))
+        .load::<(Option<String>, i64)>(conn)
+        .map_err(map_query_result_err)?;
+    // cp can repeat the planned platform, only count actual changes.
+    let changed_arrivals = stops::table
+        .filter(stops::station_id.eq(station_id))
+        .filter(stops::arrival_changed_platform.ne(stops::arrival_platform))
+        .group_by(stops::arrival_platform)
+        .select((stops::arrival_platform, count_star()))
+        .load::<(Option<String>, i64)>(conn)
+        .map_err(map_query_result_err)?;
+    let changed_departures = stops::table
+        .filter(stops::station_id.eq(station_id))
+        .filter(stops::departure_changed_platform.ne(stops::departure_platform))
+        .group_by(stops::departure_platform)
+        .select((stops::departure_platform, count_star()))
+        .load::<(Option<String>, i64)>(conn)
+        .map_err(map_query_result_err)?;
+
+    let known = |(platform, count): (Option<String>, i64)| Some((platform?, count));
+    Ok(PlatformUsage::from_counts(
+        arrivals.into_iter().chain(departures).filter_map(known),
+        changed_arrivals.into_iter().chain(changed_departures).filter_map(known),
+    ))
+}
+
+impl StopPort for SqliteStopRepo {
+    fn get_for_date(&self, date: &chrono::NaiveDate) -> Result<Vec<Stop>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+
+        let start: NaiveDateTime = date.and_hms_opt(0, 0, 0).unwrap();
+        let end: NaiveDateTime = (date.succ_opt().unwrap()).and_hms_opt(0, 0, 0).unwrap();
+
+        // First stops of a train only have a departure.
+        stops::table
+            .filter(
+                stops::arrival_planned.ge(start).and(stops::arrival_planned.lt(end))
+                    .or(stops::departure_planned.ge(start).and(stops::departure_planned.lt(end)))
+            )
+            .select(StopRow::as_select())
+            .get_results(&mut conn)
+            .map_err(map_query_result_err)
+            .map(|v| v.iter().map(|s| s.to_stop()).collect())
+    }
+
+    fn get_for_train(&self, train_id: &str) -> Result<Vec<Stop>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        stops::table
+            .filter(stops::train_id.eq(train_id))
+            .select(StopRow::as_select())
+            .get_results(&mut conn)
+            .map_err(map_query_result_err)
+            .map(|v| v.iter().map(|s| s.to_stop()).collect())
+    }
+
+    fn get_for_train_with_station(&self, train_id: &str) -> Result<Vec<StopWithStation>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        stops::table
+            .inner_join(stations::table)
+            .filter(stops::train_id.eq(train_id))
+            .select((StopRow::as_select(), StationRow::as_select()))
+            .load::<(StopRow, StationRow)>(&mut conn)
+            .map(|rows| {
+                rows.into_iter()
+                    .map(|(stop_row, station_row)| StopWithStation {
+                        stop: stop_row.to_stop(),
+                        station: Station::from(station_row)
+                    })
+                    .collect()
+            }).map_err(map_query_result_err)
+    }
+
+    fn get_by_ids(&self, ids: &[String]) -> Result<Vec<Stop>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        stops::table
+            .filter(stops::id.eq_any(ids))
+            .select(StopRow::as_select())
+            .get_results(&mut conn)
+            .map_err(map_query_result_err)
+            .map(|v| v.iter().map(|s| s.to_stop()).collect())
+    }
+
+    fn get_by_stations_and_date(&self, stations: &[Station], date: &chrono::NaiveDate) -> Result<Vec<Stop>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        let ids: Vec<i32> = stations.iter().map(|s| s.id).collect();
+
+        let results = stops::table
+            .inner_join(trains::table.on(stops::train_id.eq(trains::id)))
+            .filter(stops::station_id.eq_any(&ids).and(trains::date.eq(date)))
+            .select(stops::all_columns)
+            .load::<StopRow>(&mut conn)
+            .map_err(map_query_result_err)?;
+
+        Ok(results.iter().map(|s| s.to_stop()).collect())
+    }
+
+    fn get_cancelled_by_stations_and_date(&self, stations: &[Station], date: &chrono::NaiveDate) -> Result<Vec<Stop>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        let ids: Vec<i32> = stations.iter().map(|s| s.id).collect();
+        let cancelled = EventStatus::Cancelled.as_str();
+
+        let results = stops::table
+            .inner_join(trains::table.on(stops::train_id.eq(trains::id)))
+            .filter(stops::station_id.eq_any(&ids).and(trains::date.eq(date)))
+            .filter(stops::arrival_changed_status.eq(cancelled).or(stops::departure_changed_status.eq(cancelled)))
+            .select(stops::all_columns)
+            .load::<StopRow>(&mut conn)
+            .map_err(map_query_result_err)?;
+
+        Ok(results.iter().map(|s| s.to_stop()).collect())
+    }
+
+    fn get_platform_changes_by_stations_and_date(&self, stations: &[Station], date: &chrono::NaiveDate) -> Result<Vec<Stop>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        let ids: Vec<i32> = stations.iter().map(|s| s.id).collect();
+
+        let results = stops::table
+            .inner_join(trains::table.on(stops::train_id.eq(trains::id)))
+            .filter(stops::station_id.eq_any(&ids).and(trains::date.eq(date)))
+            .filter(stops::arrival_changed_platform.is_not_null().or(stops::departure_changed_platform.is_not_null()))
+            .select(stops::all_columns)
+            .load::<StopRow>(&mut conn)
+            .map_err(map_query_result_err)?;
+
+        // cp can repeat the planned platform, only keep actual changes.
+        Ok(results.iter().map(|s| s.to_stop()).filter(|s| !s.platform_changes().is_empty()).collect())
+    }
+
+    fn get_platform_usage(&self, station: &Station) -> Result<Vec<PlatformUsage>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        count_platforms(&mut conn, station.id)
+    }
+
+    fn upsert_all(&self, stops: &[Stop]) -> Result<Upserted<Stop>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        conn.transaction(|tx| upsert_stops(tx, stops))
+    }
+
+    fn update(&self, update: &StopUpdate) -> Result<Stop, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+
+        let row = diesel::update(stops::table.find(update.id.clone()))
+            .set(StopUpdateRow::from(update))
+            .returning(StopRow::as_returning())
+            .get_result::<StopRow>(&mut conn)
+            .map_err(map_query_result_err)?;
+
+        Ok(row.to_stop())
+    }
+
+    fn update_many(&self, updates: &[StopUpdate]) -> Result<Vec<Stop>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        conn.transaction(|tx| update_stops(tx, updates))
+    }
+}
+
+impl Port<Stop, String> for SqliteStopRepo {
+    fn persist(&self, stop: &Stop) -> Result<Stop, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        insert_stop(&mut conn, stop)?.ok_or(PortError::NotFound)
+    }
+
+    fn persist_all(&self, stops: &[Stop]) -> Result<Vec<Stop>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        conn.transaction(|tx| {
+            let mut out = Vec::with_capacity(stops.len());
+            for stop in stops {
+                out.extend(insert_stop(tx, stop)?);
+            }
+            Ok(out)
+        })
+    }
+
+    fn get_by_id(&self, id: String) -> Result<Stop, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        stops::table
+            .find(id)
+            .select(StopRow::as_select())
+            .first(&mut conn)
+            .map_err(map_query_result_err)
+            .map(|s| s.to_stop())
+    }
+
+    fn get_all(&self) -> Result<Vec<Stop>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        stops::table
+            .select(StopRow::as_select())
+            .get_results(&mut conn)
+            .map_err(map_query_result_err)
+            .map(|v| v.iter().map(|s| s.to_stop()).collect())
+    }
+}
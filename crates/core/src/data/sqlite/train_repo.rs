@@ -0,0 +1,150 @@
+use chrono::NaiveDate;
+use diesel::{BoolExpressionMethods, Connection, ExpressionMethods, JoinOnDsl, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper, SqliteConnection};
+
+use crate::{data::utils::{dedup_by_id, map_pool_err, map_query_result_err}, model::{Station, Train}, ports::{Port, PortError, TrainPort, Upserted}};
+use crate::data::db::{schema::{trains, stops, stations}, SqlitePool, row::TrainRow};
+
+
+pub struct SqliteTrainRepo {
+    pool: SqlitePool,
+}
+
+impl SqliteTrainRepo {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+/// Inserts the train unless it is known, `None` if it was skipped.
+fn insert_train(conn: &mut SqliteConnection, train: &Train) -> Result<Option<Train>, PortError> {
+    diesel::insert_into(trains::table)
+        .values(TrainRow::from(train))
+        .on_conflict_do_nothing()
+        .returning(TrainRow::as_returning())
+        .get_result::<TrainRow>(conn)
+        .optional()
+        .map(|row| row.map(Train::from))
+        .map_err(map_query_result_err)
+}
+
+/// See [`TrainPort::upsert_all`]. SQLite has no `xmax`, so stored rows are
+/// compared in Rust and only written if a column changed.
+pub(crate) fn upsert_trains(conn: &mut SqliteConnection, trains: &[Train]) -> Result<Upserted<Train>, PortError> {
+    let mut upserted = Upserted::default();
+    for train in dedup_by_id(trains, |t| &t.id) {
+        let stored = trains::table
+            .find(&train.id)
+            .select(TrainRow::as_select())
+            .first::<TrainRow>(conn)
+            .optional()
+            .map_err(map_query_result_err)?;
+        match stored {
+            None => {
+                diesel::insert_into(trains::table)
+                    .values(TrainRow::from(train))
+                    .execute(conn)
+                    .map_err(map_query_result_err)?;
+                upserted.inserted.push(train.clone());
+            }
+            Some(row) if row.operator != train.operator
+                || row.category != train.category
+                || row.number != train.number
+                || row.line != train.line => {
+                let row = diesel::update(trains::table.find(&train.id))
+                    .set((
+                        trains::operator.eq(&train.operator),
+                        trains::category.eq(&train.category),
+                        trains::number.eq(&train.number),
+                        trains::line.eq(&train.line),
+                    ))
+                    .returning(TrainRow::as_returning())
+                    .get_result::<TrainRow>(conn)
+                    .map_err(map_query_result_err)?;
+                upserted.updated.push(Train::from(row));
+            }
+            Some(_) => upserted.unchanged.push(train.clone()),
+        }
+    }
+    Ok(upserted)
+}
+
+impl TrainPort for SqliteTrainRepo {
+    fn get_by_stations_and_date(&self, stations: &[Station], date: &NaiveDate) -> Result<Vec<Train>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        let ids: Vec<i32> = stations.iter().map(|s| s.id).collect();
+
+        let results = trains::table
+            .inner_join(stops::table.on(stops::train_id.eq(trains::id)))
+            .inner_join(stations::table.on(stops::station_id.eq(stations::id)))
+            .filter(stations::id.eq_any(&ids).and(trains::date.eq(date)))
+            .select(TrainRow::as_select())
+            .distinct()
+            .load::<TrainRow>(&mut conn)
+            .map_err(map_query_result_err)?;
+
+        Ok(results.iter().map(Train::from).collect())
+    }
+
+    fn get_by_date(&self, date: &NaiveDate) -> Result<Vec<Train>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        let rows = trains::table
+            .filter(trains::date.eq(date))
+            .select(TrainRow::as_select())
+            .get_results::<TrainRow>(&mut conn)
+            .map_err(map_query_result_err)?;
+        Ok(rows.iter().map(Train::from).collect())
+    }
+
+    fn get_by_number_and_date(&self, number: &str, date: &NaiveDate) -> Result<Vec<Train>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        let rows = trains::table
+            .filter(trains::number.eq(number).and(trains::date.eq(date)))
+            .order(trains::id.asc())
+            .select(TrainRow::as_select())
+            .get_results::<TrainRow>(&mut conn)
+            .map_err(map_query_result_err)?;
+        Ok(rows.iter().map(Train::from).collect())
+    }
+
+    fn upsert_all(&self, trains: &[Train]) -> Result<Upserted<Train>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        conn.transaction(|tx| upsert_trains(tx, trains))
+    }
+}
+
+impl Port<Train, String> for SqliteTrainRepo {
+    fn persist(&self, train: &Train) -> Result<Train, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        insert_train(&mut conn, train)?.ok_or(PortError::NotFound)
+    }
+
+    fn persist_all(&self, trains: &[Train]) -> Result<Vec<Train>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        conn.transaction(|tx| {
+            let mut out = Vec::with_capacity(trains.len());
+            for train in trains {
+                out.extend(insert_train(tx, train)?);
+            }
+            Ok(out)
+        })
+    }
+
+    fn get_by_id(&self, id: String) -> Result<Train, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        let row = trains::table
+            .find(id)
+            .select(TrainRow::as_select())
+            .first::<TrainRow>(&mut conn)
+            .map_err(map_query_result_err)?;
+        Ok(Train::from(row))
+    }
+
+    fn get_all(&self) -> Result<Vec<Train>, PortError> {
+        let mut conn = self.pool.get().map_err(map_pool_err)?;
+        let rows = trains::table
+            .select(TrainRow::as_select())
+            .get_results::<TrainRow>(&mut conn)
+            .map_err(map_query_result_err)?;
+        Ok(rows.iter().map(Train::from).collect())
+    }
+}
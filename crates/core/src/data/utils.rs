@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+#[cfg(feature = "postgres")]
+use std::collections::HashSet;
+
+use crate::{data::db::row::MessageRow, ports::PortError};
+#[cfg(feature = "postgres")]
+use crate::ports::Upserted;
+
+pub fn map_pool_err<E>(err: E) -> PortError
+where E: std::error::Error {
+    error!("{}", err);
+    PortError::Connection
+}
+
+pub fn map_query_result_err(err: diesel::result::Error) -> PortError {
+    error!("QueryResultError: {:#?}, {}", err, err);
+    match err {
+        diesel::result::Error::DatabaseError(_, _) => PortError::Database, // TODO: Handle kind
+        diesel::result::Error::NotFound => PortError::NotFound,
+        diesel::result::Error::QueryBuilderError(_) => PortError::InvalidInput,
+        diesel::result::Error::DeserializationError(_) => PortError::MalformedData,
+        diesel::result::Error::SerializationError(_) => PortError::InvalidInput,
+        err => PortError::Custom(Box::new(err)),
+    }
+}
+
+impl From<diesel::result::Error> for PortError {
+    fn from(err: diesel::result::Error) -> Self {
+        map_query_result_err(err)
+    }
+}
+
+/// Keeps the last value per id, in order of first occurrence.
+/// Postgres rejects upserts that touch the same row twice.
+pub fn dedup_by_id<T, F>(values: &[T], id: F) -> Vec<&T>
+where F: Fn(&T) -> &str {
+    let mut positions: HashMap<&str, usize> = HashMap::new();
+    let mut out: Vec<&T> = Vec::with_capacity(values.len());
+    for value in values {
+        match positions.get(id(value)) {
+            Some(&pos) => out[pos] = value,
+            None => {
+                positions.insert(id(value), out.len());
+                out.push(value);
+            }
+        }
+    }
+    out
+}
+
+/// Splits the rows returned by an upsert into inserted and updated ones,
+/// given values without a returned row were left unchanged.
+#[cfg(feature = "postgres")]
+pub fn split_upserted<T, F>(given: &[&T], written: Vec<(T, bool)>, id: F) -> Upserted<T>
+where T: Clone, F: Fn(&T) -> &str {
+    let mut upserted = Upserted::default();
+    let mut written_ids: HashSet<String> = HashSet::with_capacity(written.len());
+    for (value, inserted) in written {
+        written_ids.insert(id(&value).to_string());
+        if inserted {
+            upserted.inserted.push(value);
+        } else {
+            upserted.updated.push(value);
+        }
+    }
+    upserted.unchanged = given
+        .iter()
+        .filter(|v| !written_ids.contains(id(v)))
+        .map(|v| (*v).clone())
+        .collect();
+    upserted
+}
+
+/// Revisions of messages, i.e. rows sharing their `iris_id` with a later one, that are not yet
+/// marked as superseded by the latest revision, mapped to the id of that revision.
+pub fn superseded_revisions<'a>(rows: impl IntoIterator<Item = &'a MessageRow>) -> HashMap<String, String> {
+    let rows: Vec<&MessageRow> = rows.into_iter().collect();
+    let mut latest: HashMap<&str, &MessageRow> = HashMap::new();
+    for row in &rows {
+        let current = latest.entry(row.iris_id.as_str()).or_insert(row);
+        if row.timestamp > current.timestamp {
+            *current = row;
+        }
+    }
+    rows.iter()
+        .filter_map(|row| {
+            let newest = latest[row.iris_id.as_str()];
+            (newest.id != row.id && row.superseded_by.as_ref() != Some(&newest.id)).then(|| (row.id.clone(), newest.id.clone()))
+        })
+        .collect()
+}
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+
+use iris::dto::{StationInfo, Stop as IrisStop};
+use serde::Deserialize;
+
+/// Identifier used in `StationInfo.available_transports` for long-distance trains.
+pub const INTERCITY_TRAIN: &str = "INTERCITY_TRAIN";
+
+/// Declarative rules for which stations and trains are imported.
+///
+/// A list that is `None` allows everything, an empty list allows nothing.
+/// The default keeps long-distance stations and every train category except `Bus`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FilterPolicy {
+    /// Stations need at least one of these `available_transports`.
+    pub transports: Option<Vec<String>>,
+    /// Allowed train categories (`tl.c`), e.g. `ICE`, `RE`, `S`.
+    pub categories: Option<Vec<String>>,
+    /// Categories that are never imported, checked after `categories`.
+    pub excluded_categories: Vec<String>,
+    /// Allowed operators (`tl.o`).
+    pub operators: Option<Vec<String>>,
+    /// Overrides by DS100.
+    pub stations: HashMap<String, StationFilter>,
+}
+
+/// Per-station override of a [`FilterPolicy`]. Unset fields fall back to the policy.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct StationFilter {
+    /// Always (`true`) or never (`false`) import the station, regardless of its transports.
+    pub include: Option<bool>,
+    pub categories: Option<Vec<String>>,
+    pub excluded_categories: Option<Vec<String>>,
+    pub operators: Option<Vec<String>>,
+}
+
+impl Default for FilterPolicy {
+    fn default() -> Self {
+        FilterPolicy {
+            transports: Some(vec![INTERCITY_TRAIN.to_string()]),
+            categories: None,
+            excluded_categories: vec!["Bus".to_string()],
+            operators: None,
+            stations: HashMap::new(),
+        }
+    }
+}
+
+fn allows(list: Option<&Vec<String>>, value: &str) -> bool {
+    list.is_none_or(|l| l.iter().any(|v| v == value))
+}
+
+impl FilterPolicy {
+    /// Whether a station from the station list should be imported.
+    /// Stations without DS100, starting with `X` or inactive in IRIS are always dropped.
+    pub fn allows_station(&self, station: &StationInfo) -> bool {
+        let ds100 = match station.ds100.as_deref() {
+            Some(ds100) if station.is_active_iris && !ds100.starts_with('X') => ds100,
+            _ => return false,
+        };
+
+        match self.stations.get(ds100).and_then(|s| s.include) {
+            Some(include) => include,
+            None => self.transports.as_ref()
+                .is_none_or(|t| station.available_transports.iter().any(|a| t.contains(a))),
+        }
+    }
+
+    /// Whether a timetable stop at the station `ds100` should be imported.
+    /// Stops without a category are always dropped.
+    pub fn allows_stop(&self, stop: &IrisStop, ds100: &str) -> bool {
+        let tl = match stop.tl.as_ref() {
+            Some(tl) => tl,
+            None => return false,
+        };
+        let category = match tl.category.as_deref() {
+            Some(category) => category,
+            None => return false,
+        };
+
+        let station = self.stations.get(ds100);
+        let categories = station.and_then(|s| s.categories.as_ref()).or(self.categories.as_ref());
+        let excluded = station.and_then(|s| s.excluded_categories.as_ref()).unwrap_or(&self.excluded_categories);
+        let operators = station.and_then(|s| s.operators.as_ref()).or(self.operators.as_ref());
+
+        allows(categories, category)
+            && !excluded.iter().any(|c| c == category)
+            && tl.operator.as_deref().map_or(operators.is_none(), |o| allows(operators, o))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iris::dto::TrainLine;
+
+    fn station_info(ds100: &str, transports: &[&str]) -> StationInfo {
+        StationInfo {
+            eva: 8002549,
+            ds100: Some(ds100.to_string()),
+            lat: 53.55,
+            lon: 10.0,
+            name: "Hamburg Hbf".to_string(),
+            is_active_ris: true,
+            is_active_iris: true,
+            meta_evas: vec![],
+            available_transports: transports.iter().map(|t| t.to_string()).collect(),
+            number_of_events: None,
+        }
+    }
+
+    fn iris_stop(category: &str, operator: &str) -> IrisStop {
+        IrisStop {
+            id: "trip-2509100700-1".to_string(),
+            tl: Some(TrainLine {
+                category: Some(category.to_string()),
+                operator: Some(operator.to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn default_policy_keeps_long_distance_stations_and_drops_buses() {
+        let policy = FilterPolicy::default();
+
+        assert!(policy.allows_station(&station_info("AH", &[INTERCITY_TRAIN, "REGIONAL_TRAIN"])));
+        assert!(!policy.allows_station(&station_info("AHAR", &["REGIONAL_TRAIN"])));
+        assert!(!policy.allows_station(&station_info("XAB", &[INTERCITY_TRAIN])));
+
+        assert!(policy.allows_stop(&iris_stop("RE", "R1"), "AH"));
+        assert!(!policy.allows_stop(&iris_stop("Bus", "R1"), "AH"));
+        assert!(!policy.allows_stop(&IrisStop::default(), "AH"));
+    }
+
+    #[test]
+    fn station_overrides_take_precedence() {
+        let policy: FilterPolicy = serde_json::from_str(r#"{
+            "categories": ["ICE", "IC"],
+            "operators": ["80"],
+            "stations": {
+                "AHAR": { "include": true, "categories": ["S"] },
+                "AH": { "include": false }
+            }
+        }"#).unwrap();
+
+        assert!(policy.allows_station(&station_info("AHAR", &["CITY_TRAIN"])));
+        assert!(!policy.allows_station(&station_info("AH", &[INTERCITY_TRAIN])));
+
+        assert!(policy.allows_stop(&iris_stop("ICE", "80"), "FF"));
+        assert!(!policy.allows_stop(&iris_stop("ICE", "R1"), "FF"));
+        assert!(!policy.allows_stop(&iris_stop("S", "80"), "FF"));
+        assert!(policy.allows_stop(&iris_stop("S", "80"), "AHAR"));
+        assert!(!policy.allows_stop(&iris_stop("ICE", "80"), "AHAR"));
+    }
+}
@@ -1,45 +1,102 @@
-use std::env;
+use std::{collections::{hash_map::Entry, HashMap, HashSet}, env, time::{Duration, Instant}};
 
-use chrono::{NaiveDate, NaiveDateTime};
+use chrono::{NaiveDate, NaiveDateTime, TimeDelta};
 use iris::{
+    archive::{ArchiveError, ArchiveQuery, DocumentArchive, DocumentKind, RawDocument},
     dto::{IRISStationError, IRISTimetableError, StationInfo},
-    fetch::get_station_infos,
+    fetch::{get_station_infos, parse_timetable, IrisClient},
 };
 
 use crate::{
-    ingest::{ingest_timetable, ingest_timetable_changes},
-    io::get_status_codes,
-    model::{Message, Station, Stop, StopUpdate, Train},
-    ports::{MessagePort, PortError, StationPort, StatusCodePort, StopPort, TrainPort},
-    utils::HourIter,
+    filter::FilterPolicy,
+    ingest::{ingest_recent_timetable_changes, ingest_stop_observations, ingest_timetable, ingest_timetable_changes, message_ids, ChangesSyncMode},
+    io::{get_status_codes, IOError},
+    model::{Message, MessageBuildError, Station, StationBuildError, StationGroup, StationPlatforms, Stop, StopObservation, StopUpdate, Train, TrainBuildError},
+    ports::{MessageRevocation, PortError, StationPort, StatusCodePort, StopPort, UnitOfWork, UnitOfWorkPort},
+    report::{EntityCounts, ImportReport, RunReport, StationFailure},
+    utils::{now_local, to_local, HourIter},
 };
 
-/// Identifier used in `StationInfo.available_transports` for long-distance trains.
-const INTERCITY_TRAIN: &str = "INTERCITY_TRAIN";
-
 #[derive(thiserror::Error, Debug)]
 pub enum ImportError {
     #[error("invalid src format {0}")]
     InvalidSourceFormat(String),
+    /// Fetching or parsing station data failed.
     #[error(transparent)]
     StationError(#[from] IRISStationError),
+    /// Fetching or parsing a timetable failed.
     #[error(transparent)]
     TimetableError(#[from] IRISTimetableError),
+    /// Reading a local source (status codes, filter policy) failed.
+    #[error(transparent)]
+    SourceError(#[from] IOError),
+    #[error(transparent)]
+    StationBuildError(#[from] StationBuildError),
+    #[error(transparent)]
+    TrainBuildError(#[from] TrainBuildError),
+    #[error(transparent)]
+    MessageBuildError(#[from] MessageBuildError),
     #[error(transparent)]
     PersistanceError(#[from] PortError),
+    /// Reading archived IRIS documents failed.
     #[error(transparent)]
-    Custom(#[from] Box<dyn std::error::Error>),
+    ArchiveError(#[from] ArchiveError),
+}
+
+/// How an [`ImportError`] behaves when the import is run again.
+enum RetryClass {
+    /// The IRIS circuit breaker rejected the request, later runs may pass.
+    CircuitOpen,
+    Transient,
+    Permanent,
+}
+
+impl ImportError {
+    /// The only place deciding which errors are retryable.
+    fn retry_class(&self) -> RetryClass {
+        match self {
+            ImportError::StationError(IRISStationError::CircuitOpen(_))
+            | ImportError::TimetableError(IRISTimetableError::CircuitOpen(_)) => RetryClass::CircuitOpen,
+            ImportError::StationError(err) if err.is_retryable() => RetryClass::Transient,
+            ImportError::TimetableError(err) if err.is_retryable() => RetryClass::Transient,
+            ImportError::PersistanceError(err) if err.is_retryable() => RetryClass::Transient,
+            _ => RetryClass::Permanent,
+        }
+    }
+
+    /// Whether the import may succeed when run again later: network failures, `429`/`5xx`
+    /// responses, an open IRIS circuit breaker and lost database connections.
+    /// Malformed upstream data, build and configuration errors are permanent.
+    pub fn is_retryable(&self) -> bool {
+        !matches!(self.retry_class(), RetryClass::Permanent)
+    }
+
+    /// Whether the IRIS circuit breaker rejected the request.
+    pub fn is_circuit_open(&self) -> bool {
+        matches!(self.retry_class(), RetryClass::CircuitOpen)
+    }
+}
+
+/// Runs `f` and adds the time it took to `duration`.
+fn timed<T>(duration: &mut Duration, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = f();
+    *duration += start.elapsed();
+    result
 }
 
 /// Import IRIS-active stations and persist **only newly inserted** ones.
 ///
 /// Source is taken from `STATIONS_SRC` as `API:<url>`, `JSON:<path>`, or `SQL:<file>`.
 /// Filters: `ds100` present, not starting with `X`; `is_active_iris == true`;
-/// `available_transports` contains `INTERCITY_TRAIN`.
+/// everything else is decided by `policy` (see [`FilterPolicy::allows_station`]).
 ///
-/// Returns: newly persisted `Station`s.
+/// Returns: a report with station counts.
 /// Errors: network/parse/repo errors are propagated.
-pub fn import_station_data(port: &dyn StationPort) -> Result<Vec<Station>, ImportError> {
+pub fn import_station_data(port: &dyn StationPort, policy: &FilterPolicy) -> Result<ImportReport, ImportError> {
+    let started = Instant::now();
+    let mut report = ImportReport::default();
+
     let stations_src = env::var("STATIONS_SRC")
         .unwrap_or("API:https://bahnvorhersage.de/api/stations.json".to_string());
 
@@ -54,15 +111,17 @@ pub fn import_station_data(port: &dyn StationPort) -> Result<Vec<Station>, Impor
 
     let station_infos = match src_type.as_str() {
         "API" => {
-            get_station_infos(src, true)
+            timed(&mut report.fetch_duration, || get_station_infos(src, true))
         }
         "JSON" => {
             get_station_infos(src, false)
         }
         "SQL" => {
             println!("{}", src);
-            let results = port.import_from_sql(src)?;
-            return Ok(results);
+            let results = timed(&mut report.persist_duration, || port.import_from_sql(src))?;
+            report.stations = EntityCounts { fetched: results.len(), inserted: results.len(), ..Default::default() };
+            report.duration = started.elapsed();
+            return Ok(report);
         }
         _ => {
             return Err(ImportError::InvalidSourceFormat(stations_src.clone()));
@@ -71,49 +130,119 @@ pub fn import_station_data(port: &dyn StationPort) -> Result<Vec<Station>, Impor
 
     let iris_stations: Vec<StationInfo> = station_infos?
         .into_iter()
-        .filter(|s| {
-            if s.ds100.is_none() {
-                return false;
-            }
-
-            s.is_active_iris
-                && !s.ds100.as_deref().unwrap().starts_with('X')
-                && s.available_transports.contains(&INTERCITY_TRAIN.to_string())
-        })
+        .filter(|s| policy.allows_station(s))
         .collect();
+    report.stations.fetched = iris_stations.len();
 
+    // Empty groups clear the members IRIS no longer lists.
+    let groups: Vec<StationGroup> = iris_stations.iter().map(StationGroup::from_info).collect();
     let stations: Vec<Station> = iris_stations
         .into_iter()
         .filter_map(|s| Station::from_info(s).ok())
         .collect();
+    report.stations.build_errors = report.stations.fetched - stations.len();
 
     info!("Persisting stations");
-    let result = port.persist_all(&stations)?;
+    report.stations.inserted = timed(&mut report.persist_duration, || port.persist_all(&stations))?.len();
+
+    // Groups reference their station, skip those of stations that could not be built.
+    let built: HashSet<i32> = stations.iter().map(|s| s.id).collect();
+    let groups: Vec<StationGroup> = groups.into_iter().filter(|g| built.contains(&g.station_id)).collect();
+    let groups = timed(&mut report.persist_duration, || port.persist_groups(&groups))?;
+    info!("Persisted {} station groups", groups.len());
+    report.duration = started.elapsed();
+
+    info!("Imported stations: {}", report.stations);
+
+    Ok(report)
+}
+
+/// Messages, stop changes and observations built from one changes document.
+struct IngestedChanges {
+    messages: Vec<Message>,
+    stop_updates: Vec<StopUpdate>,
+    observations: Vec<StopObservation>,
+    message_build_errors: usize,
+    /// Ids of all messages in an `fchg` document, `None` for `rchg` deltas.
+    seen_message_ids: Option<Vec<String>>,
+}
+
+impl IngestedChanges {
+    fn empty() -> Self {
+        IngestedChanges {
+            messages: Vec::new(),
+            stop_updates: Vec::new(),
+            observations: Vec::new(),
+            message_build_errors: 0,
+            seen_message_ids: None,
+        }
+    }
+
+    fn ingest(tt: &iris::dto::Timetable, station_id: i32, stops_by_id: HashMap<String, &Stop>, fetched_at: &NaiveDateTime, mode: ChangesSyncMode) -> Self {
+        let observations = ingest_stop_observations(tt, &stops_by_id, fetched_at);
+        let (messages, stop_changes, message_build_errors) = match mode {
+            ChangesSyncMode::Full => ingest_timetable_changes(tt, station_id, stops_by_id),
+            ChangesSyncMode::Recent => ingest_recent_timetable_changes(tt, station_id, stops_by_id),
+        };
+        let (stop_updates, seen_message_ids) = match mode {
+            ChangesSyncMode::Full => (stop_changes.iter().map(StopUpdate::replacing_changes).collect(), Some(message_ids(tt))),
+            ChangesSyncMode::Recent => (stop_changes.iter().map(StopUpdate::from).collect(), None),
+        };
 
-    info!("Imported {} stations, {} new", stations.len(), result.len());
+        IngestedChanges { messages, stop_updates, observations, message_build_errors, seen_message_ids }
+    }
+}
 
-    Ok(result)
+/// Adds ingested changes of `station` to `work` and counts them in `report`.
+fn add_changes(report: &mut ImportReport, work: &mut UnitOfWork, station: &Station, changes: IngestedChanges, fetched_at: &NaiveDateTime) {
+    report.messages.fetched += changes.messages.len();
+    report.messages.build_errors += changes.message_build_errors;
+    report.observations.fetched += changes.observations.len();
+
+    work.stop_updates.extend(changes.stop_updates);
+    work.messages.extend(changes.messages);
+    work.observations.extend(changes.observations);
+    work.revocation = changes.seen_message_ids.map(|seen_ids| MessageRevocation {
+        station_id: station.id,
+        seen_ids,
+        now: *fetched_at,
+    });
 }
 
-/// Shorthand for `(trains, stops, messages)` returned by timetable ingestion.
-type ImportResult = (Vec<Train>, Vec<Stop>, Vec<Message>);
+/// Commits `work` in one transaction and records what was written in `report`.
+fn commit(report: &mut ImportReport, unit_of_work: &dyn UnitOfWorkPort, work: &UnitOfWork) -> Result<(), PortError> {
+    let result = timed(&mut report.persist_duration, || unit_of_work.commit(work))?;
+
+    report.trains.inserted += result.trains.inserted.len();
+    report.trains.updated += result.trains.updated.len();
+    report.trains.unchanged += result.trains.unchanged.len();
+    report.stops.inserted += result.stops.inserted.len();
+    report.stops.updated += result.stops.updated.len() + result.updated_stops.len();
+    report.stops.unchanged += result.stops.unchanged.len();
+    report.messages.inserted += result.messages.inserted.len();
+    report.messages.updated += result.messages.updated.len();
+    report.observations.inserted += result.observations.len();
+    report.revoked_messages += result.revoked_messages.len();
+    Ok(())
+}
 
 /// Import timetable (trains, stops) and messages for one station over hourly windows.
 ///
 /// Iterates hours from `start` for `hours_in_advance`. Skips empty timetables.
-/// Persists all collected entities; returns the **fetched** domain values
-/// (independent of deduplication).
+/// Persists all collected entities in one unit of work, nothing is written on error.
 ///
-/// Returns: `(trains, stops, messages)`.
+/// Returns: a report with the fetched windows and entity counts.
 /// Errors: fetch/persistence errors are propagated.
 pub fn import_iris_data_for_station(
     station: &Station,
     start: &NaiveDateTime,
     hours_in_advance: u16,
-    message_port: &dyn MessagePort,
-    train_port: &dyn TrainPort,
-    stop_port: &dyn StopPort,
-) -> Result<ImportResult, Box<dyn std::error::Error>> {
+    client: &dyn IrisClient,
+    policy: &FilterPolicy,
+    unit_of_work: &dyn UnitOfWorkPort,
+) -> Result<ImportReport, ImportError> {
+    let started = Instant::now();
+    let mut report = ImportReport::for_station(station);
     let mut trains: Vec<Train> = Vec::new();
     let mut stops: Vec<Stop> = Vec::new();
 
@@ -124,178 +253,512 @@ pub fn import_iris_data_for_station(
             date.format("%Y-%m-%d"),
             hour
         );
-        let tt = match iris::fetch::get_timetable_for_station(station.id, &date, hour) {
+        report.windows.push((date, hour));
+        let tt = match timed(&mut report.fetch_duration, || client.get_timetable(station.id, &date, hour)) {
             Ok(tt) => tt,
-            Err(iris::dto::IRISTimetableError::EmptyTimetable(_)) => continue,
+            Err(iris::dto::IRISTimetableError::EmptyTimetable(_)) => {
+                report.empty_windows.push((date, hour));
+                continue;
+            }
             Err(e) => return Err(e.into()),
         };
 
         info!("Ingesting timetable");
 
-        let (mut new_trains, mut new_stops) = ingest_timetable(&tt, station);
+        let (mut new_trains, mut new_stops, build_errors) = ingest_timetable(&tt, station, policy);
+        report.trains.build_errors += build_errors;
         trains.append(&mut new_trains);
         stops.append(&mut new_stops);
     }
+    report.trains.fetched = trains.len();
+    report.stops.fetched = stops.len();
+
+    let stops_by_id: HashMap<String, &Stop> = stops.iter().map(|s| (s.id.clone(), s)).collect();
+    report.mode = Some(ChangesSyncMode::Full);
+    let changes = timed(&mut report.fetch_duration, || client.get_timetable_changes(station.id));
+    let fetched_at = now_local();
+    report.fetched_at = Some(fetched_at);
+    let changes = match changes {
+        Ok(tt) => IngestedChanges::ingest(&tt, station.id, stops_by_id, &fetched_at, ChangesSyncMode::Full),
+        Err(IRISTimetableError::EmptyTimetable(_)) => IngestedChanges::empty(),
+        Err(err) => return Err(err.into()),
+    };
 
-    let (messages, stop_changes) =
-        match iris::fetch::get_timetable_changes_for_station(station.id) {
-            Ok(tt) => ingest_timetable_changes(&tt, stops.iter().map(|s| (s.id.clone(), s)).collect()),
-            Err(IRISTimetableError::EmptyTimetable(_)) => (Vec::new(), Vec::new()),
-            Err(err) => return Err(err.into()),
-        };
-
-    info!("Ingested {} messages", messages.len());
-
-    let new_trains = train_port.persist_all(&trains)?.len();
-    let new_stops = stop_port.persist_all(&stops)?.len();
-    let new_messages = message_port.persist_all(&messages)?.len();
-
-    let stop_updates = stop_changes
-        .iter()
-        .map(StopUpdate::from)
-        .collect::<Vec<StopUpdate>>();
-    let updated_stops_count = stop_port.update_many(&stop_updates)?.len();
+    info!("Ingested {} messages", changes.messages.len());
 
-    info!(
-        "{} new messages, {} new stops, {} new_trains, {} updated_stops",
-        new_messages, new_stops, new_trains, updated_stops_count
-    );
+    let mut work = UnitOfWork { trains, stops, ..Default::default() };
+    add_changes(&mut report, &mut work, station, changes, &fetched_at);
+    commit(&mut report, unit_of_work, &work)?;
 
-    info!("Import finished");
+    report.duration = started.elapsed();
+    info!("Import finished: {}", report);
 
-    Ok((trains, stops, messages))
+    Ok(report)
 }
 
 /// Convenience wrapper: import by DS100 code (fetch station first).
 ///
-/// Returns fetched `(trains, stops, messages)`.
+/// Returns the station report.
 /// Errors: lookup/mapping/import errors are propagated.
 pub fn import_iris_data_for_station_by_ds100(
     ds100: &str,
     start: &NaiveDateTime,
     hours_in_advance: u16,
-    message_port: &dyn MessagePort,
-    train_port: &dyn TrainPort,
-    stop_port: &dyn StopPort,
-) -> Result<ImportResult, Box<dyn std::error::Error>> {
-    let station = iris::fetch::get_station(ds100).map(Station::from_iris)??;
+    client: &dyn IrisClient,
+    policy: &FilterPolicy,
+    unit_of_work: &dyn UnitOfWorkPort,
+) -> Result<ImportReport, ImportError> {
+    let station = client.get_station(ds100).map(Station::from_iris)??;
     import_iris_data_for_station(
         &station,
         start,
         hours_in_advance,
-        message_port,
-        train_port,
-        stop_port,
+        client,
+        policy,
+        unit_of_work,
     )
 }
 
 /// Import timetables and messages for **all** persisted stations.
 ///
-/// Returns `Ok(())` on success.
-/// Errors: per-station import errors are propagated.
+/// Records per-station errors and continues with the next station. Stops early
+/// once the IRIS circuit breaker is open and marks the run as aborted.
+/// Errors: only if the stations cannot be loaded.
 pub fn import_iris_data(
     start: &NaiveDateTime,
     hours_in_advance: u16,
+    client: &dyn IrisClient,
+    policy: &FilterPolicy,
     station_port: &dyn StationPort,
-    message_port: &dyn MessagePort,
-    train_port: &dyn TrainPort,
-    stop_port: &dyn StopPort,
-) -> Result<(), Box<dyn std::error::Error>> {
+    unit_of_work: &dyn UnitOfWorkPort,
+) -> Result<RunReport, ImportError> {
+    let started = Instant::now();
+    let mut run = RunReport::new(now_local());
     let stations = station_port.get_all()?;
     for station in stations {
-        import_iris_data_for_station(
+        match import_iris_data_for_station(
             &station,
             start,
             hours_in_advance,
-            message_port,
-            train_port,
-            stop_port,
-        )?;
+            client,
+            policy,
+            unit_of_work,
+        ) {
+            Ok(report) => run.stations.push(report),
+            Err(err) => {
+                run.add_failure(&station, &err);
+                if err.is_circuit_open() {
+                    warn!("IRIS circuit open, pausing import at station {}", station.id);
+                    run.aborted = true;
+                    break;
+                }
+                error!("Error while importing iris data for station {}: {}", station.id, err);
+            }
+        }
     }
-    Ok(())
+    run.duration = started.elapsed();
+    Ok(run)
+}
+
+/// Look up a station in IRIS and persist its platforms and its group from `meta`.
+///
+/// Returns a report counting the station as inserted if IRIS lists platforms for it.
+/// Errors: lookup/mapping/persistence errors are propagated.
+pub fn import_station_details(
+    station: &Station,
+    client: &dyn IrisClient,
+    station_port: &dyn StationPort,
+) -> Result<ImportReport, ImportError> {
+    let started = Instant::now();
+    let mut report = ImportReport::for_station(station);
+
+    let iris_station = timed(&mut report.fetch_duration, || client.get_station(&station.ds100))?;
+    report.stations.fetched = 1;
+    let platforms = StationPlatforms::from_iris(&iris_station)?;
+    let group = StationGroup::from_iris(&iris_station)?;
+    if platforms.station_id != station.id {
+        // IRIS resolved the DS100 to another station, keep what we know.
+        warn!("IRIS returned station {} for {}, expected {}", platforms.station_id, station.ds100, station.id);
+        report.stations.build_errors = 1;
+        report.duration = started.elapsed();
+        return Ok(report);
+    }
+
+    timed(&mut report.persist_duration, || {
+        station_port.persist_platforms(std::slice::from_ref(&platforms))?;
+        // An empty group clears the members IRIS no longer lists.
+        station_port.persist_groups(std::slice::from_ref(&group))?;
+        Ok::<_, PortError>(())
+    })?;
+    report.stations.inserted = usize::from(!platforms.is_empty());
+    report.duration = started.elapsed();
+    Ok(report)
+}
+
+/// Import platforms and groups ([`import_station_details`]) of `stations` without known platforms.
+///
+/// Needs one IRIS station lookup per station. Records per-station errors and stops once
+/// the IRIS circuit breaker is open.
+/// Errors: only if the known platforms cannot be loaded.
+pub fn import_station_platforms(
+    stations: &[Station],
+    client: &dyn IrisClient,
+    station_port: &dyn StationPort,
+) -> Result<RunReport, ImportError> {
+    let started = Instant::now();
+    let mut run = RunReport::new(now_local());
+    for station in stations {
+        if !station_port.get_platforms(station)?.is_empty() {
+            continue;
+        }
+        match import_station_details(station, client, station_port) {
+            Ok(report) => run.stations.push(report),
+            Err(err) => {
+                run.add_failure(station, &err);
+                if err.is_circuit_open() {
+                    warn!("IRIS circuit open, pausing platform import at station {}", station.id);
+                    run.aborted = true;
+                    break;
+                }
+                error!("Error while importing platforms for station {}: {}", station.id, err);
+            }
+        }
+    }
+    run.duration = started.elapsed();
+    Ok(run)
 }
 
 /// Import **timetable changes/messages** for a station and update affected stops.
 ///
-/// Uses existing stops for `date` as context. Returns fetched `Message`s.
+/// Uses existing stops for `date` as context.
+/// Returns: a report with message, stop update and observation counts.
 /// Errors: fetch/mapping/persistence errors are propagated.
 pub fn import_iris_changes_for_station(
     station: &Station,
     date: &NaiveDate,
-    message_port: &dyn MessagePort,
+    client: &dyn IrisClient,
     stop_port: &dyn StopPort,
-) -> Result<Vec<Message>, Box<dyn std::error::Error>> {
-    let tt_changes = iris::fetch::get_timetable_changes_for_station(station.id)?;
+    unit_of_work: &dyn UnitOfWorkPort,
+) -> Result<ImportReport, ImportError> {
+    let started = Instant::now();
+    let mut report = ImportReport::for_station(station);
+    report.mode = Some(ChangesSyncMode::Full);
 
-    let stops = stop_port.get_for_date(date)?;
+    let tt_changes = timed(&mut report.fetch_duration, || client.get_timetable_changes(station.id))?;
+    let fetched_at = now_local();
+    report.fetched_at = Some(fetched_at);
 
-    let (messages, stop_changes) =
-        ingest_timetable_changes(&tt_changes, stops.iter().map(|s| (s.id.clone(), s)).collect());
-    info!("Ingested {} messages", messages.len());
+    let stops = stop_port.get_for_date(date)?;
+    let stops_by_id: HashMap<String, &Stop> = stops.iter().map(|s| (s.id.clone(), s)).collect();
 
-    let updates = stop_changes
-        .iter()
-        .map(StopUpdate::from)
-        .collect::<Vec<StopUpdate>>();
-    let updated_stops_count = stop_port.update_many(&updates)?.len();
+    let changes = IngestedChanges::ingest(&tt_changes, station.id, stops_by_id, &fetched_at, ChangesSyncMode::Full);
+    info!("Ingested {} messages", changes.messages.len());
 
-    let new_messages = message_port.persist_all(&messages)?.len();
+    let mut work = UnitOfWork::default();
+    add_changes(&mut report, &mut work, station, changes, &fetched_at);
+    commit(&mut report, unit_of_work, &work)?;
 
-    info!("{} new messages, {} updated stops", new_messages, updated_stops_count);
-    info!("Import finished");
+    report.duration = started.elapsed();
+    info!("Import finished: {}", report);
 
-    Ok(messages)
+    Ok(report)
 }
 
 /// Convenience wrapper: import **changes/messages** by DS100 code.
 ///
-/// Returns fetched `Message`s.
+/// Returns the station report.
 /// Errors: lookup/mapping/import errors are propagated.
 pub fn import_iris_changes_for_station_by_ds100(
     ds100: &str,
     date: &NaiveDate,
-    message_port: &dyn MessagePort,
+    client: &dyn IrisClient,
     stop_port: &dyn StopPort,
-) -> Result<Vec<Message>, Box<dyn std::error::Error>> {
-    let station = iris::fetch::get_station(ds100).map(Station::from_iris)??;
-    import_iris_changes_for_station(&station, date, message_port, stop_port)
+    unit_of_work: &dyn UnitOfWorkPort,
+) -> Result<ImportReport, ImportError> {
+    let station = client.get_station(ds100).map(Station::from_iris)??;
+    import_iris_changes_for_station(&station, date, client, stop_port, unit_of_work)
 }
 
 /// Import **changes/messages** for **all** stations on a given date.
 ///
-/// Logs per-station results; continues on per-station errors.
-/// Returns `Ok(())` on success.
+/// Records per-station errors; continues with the next station and stops
+/// once the IRIS circuit breaker is open.
+/// Errors: only if the stations cannot be loaded.
 pub fn import_iris_changes(
     date: &NaiveDate,
+    client: &dyn IrisClient,
     station_port: &dyn StationPort,
-    message_port: &dyn MessagePort,
     stop_port: &dyn StopPort,
-) -> Result<(), Box<dyn std::error::Error>> {
+    unit_of_work: &dyn UnitOfWorkPort,
+) -> Result<RunReport, ImportError> {
+    let started = Instant::now();
+    let mut run = RunReport::new(now_local());
     let stations = station_port.get_all()?;
     for station in stations {
-        let _ = import_iris_changes_for_station(&station, date, message_port, stop_port)
-            .inspect_err(|e| {
-                error!(
-                    "Error while importing iris_messages for station {}: {}",
-                    station.id, e
-                )
-            });
+        match import_iris_changes_for_station(&station, date, client, stop_port, unit_of_work) {
+            Ok(report) => run.stations.push(report),
+            Err(err) => {
+                run.add_failure(&station, &err);
+                if err.is_circuit_open() {
+                    warn!("IRIS circuit open, pausing changes import at station {}", station.id);
+                    run.aborted = true;
+                    break;
+                }
+                error!("Error while importing iris_messages for station {}: {}", station.id, err);
+            }
+        }
     }
-    Ok(())
+    run.duration = started.elapsed();
+    Ok(run)
+}
+
+/// Import **recent changes/messages** (`rchg`) for a station and apply them as deltas.
+///
+/// Falls back to a full `fchg` sync via [`import_iris_changes_for_station`] when
+/// `last_poll`, the `fetched_at` of the last successful sync, is missing or older than `max_age`
+/// right before the request.
+/// Returns: a report, its `mode` tells which sync was used.
+/// Errors: fetch/mapping/persistence errors are propagated.
+pub fn import_iris_recent_changes_for_station(
+    station: &Station,
+    last_poll: Option<&NaiveDateTime>,
+    max_age: TimeDelta,
+    client: &dyn IrisClient,
+    stop_port: &dyn StopPort,
+    unit_of_work: &dyn UnitOfWorkPort,
+) -> Result<ImportReport, ImportError> {
+    let now = now_local();
+    let mode = ChangesSyncMode::for_last_poll(last_poll, &now, max_age);
+    if mode == ChangesSyncMode::Full {
+        info!("Last poll for {} too old, syncing full changes", station.ds100);
+        return import_iris_changes_for_station(station, &now.date(), client, stop_port, unit_of_work);
+    }
+
+    let started = Instant::now();
+    let mut report = ImportReport::for_station(station);
+    report.mode = Some(mode);
+
+    let tt_changes = timed(&mut report.fetch_duration, || client.get_recent_timetable_changes(station.id));
+    let fetched_at = now_local();
+    let tt_changes = match tt_changes {
+        Ok(tt) => tt,
+        Err(IRISTimetableError::EmptyTimetable(_)) => {
+            info!("No recent changes for {}", station.ds100);
+            report.fetched_at = Some(fetched_at);
+            report.duration = started.elapsed();
+            return Ok(report);
+        }
+        Err(err) => return Err(err.into()),
+    };
+    report.fetched_at = Some(fetched_at);
+
+    let ids: Vec<String> = tt_changes.stops.iter().map(|s| s.id.clone()).collect();
+    let stops = stop_port.get_by_ids(&ids)?;
+    let stops_by_id: HashMap<String, &Stop> = stops.iter().map(|s| (s.id.clone(), s)).collect();
+
+    let changes = IngestedChanges::ingest(&tt_changes, station.id, stops_by_id, &fetched_at, mode);
+    info!("Ingested {} recent messages", changes.messages.len());
+
+    let mut work = UnitOfWork::default();
+    add_changes(&mut report, &mut work, station, changes, &fetched_at);
+    commit(&mut report, unit_of_work, &work)?;
+
+    report.duration = started.elapsed();
+    info!("Import finished: {}", report);
+
+    Ok(report)
+}
+
+/// Convenience wrapper: import **recent changes/messages** by DS100 code.
+///
+/// Returns the station report.
+/// Errors: lookup/mapping/import errors are propagated.
+pub fn import_iris_recent_changes_for_station_by_ds100(
+    ds100: &str,
+    last_poll: Option<&NaiveDateTime>,
+    max_age: TimeDelta,
+    client: &dyn IrisClient,
+    stop_port: &dyn StopPort,
+    unit_of_work: &dyn UnitOfWorkPort,
+) -> Result<ImportReport, ImportError> {
+    let station = client.get_station(ds100).map(Station::from_iris)??;
+    import_iris_recent_changes_for_station(&station, last_poll, max_age, client, stop_port, unit_of_work)
+}
+
+/// Import **recent changes/messages** for **all** stations.
+///
+/// `last_polls` maps station ids to the fetch time of their last successful sync and is
+/// updated for every station that was synced successfully. `now` is the start of the run.
+/// Records per-station errors; continues with the next station and stops
+/// once the IRIS circuit breaker is open.
+/// Errors: only if the stations cannot be loaded.
+pub fn import_iris_recent_changes(
+    now: &NaiveDateTime,
+    last_polls: &mut HashMap<i32, NaiveDateTime>,
+    max_age: TimeDelta,
+    client: &dyn IrisClient,
+    station_port: &dyn StationPort,
+    stop_port: &dyn StopPort,
+    unit_of_work: &dyn UnitOfWorkPort,
+) -> Result<RunReport, ImportError> {
+    let started = Instant::now();
+    let mut run = RunReport::new(*now);
+    let stations = station_port.get_all()?;
+    for station in stations {
+        match import_iris_recent_changes_for_station(
+            &station,
+            last_polls.get(&station.id),
+            max_age,
+            client,
+            stop_port,
+            unit_of_work,
+        ) {
+            Ok(report) => {
+                if let Some(fetched_at) = report.fetched_at {
+                    last_polls.insert(station.id, fetched_at);
+                }
+                run.stations.push(report);
+            }
+            Err(err) => {
+                run.add_failure(&station, &err);
+                if err.is_circuit_open() {
+                    warn!("IRIS circuit open, pausing recent changes import at station {}", station.id);
+                    run.aborted = true;
+                    break;
+                }
+                error!("Error while importing recent iris changes for station {}: {}", station.id, err);
+            }
+        }
+    }
+    run.duration = started.elapsed();
+    Ok(run)
+}
+
+/// Ingests one archived plan or changes document of `station` as if it had just been fetched.
+fn replay_document(
+    doc: &RawDocument,
+    station: &Station,
+    policy: &FilterPolicy,
+    stop_port: &dyn StopPort,
+    unit_of_work: &dyn UnitOfWorkPort,
+) -> Result<ImportReport, ImportError> {
+    let started = Instant::now();
+    let mut report = ImportReport::for_station(station);
+    let fetched_at = to_local(&doc.fetched_at);
+
+    let mut work = UnitOfWork::default();
+    match (doc.kind, doc.window) {
+        (DocumentKind::Plan, Some((date, hour))) => {
+            report.windows.push((date, hour));
+            let tt = match parse_timetable(&doc.body, hour.into()) {
+                Ok(tt) => tt,
+                Err(IRISTimetableError::EmptyTimetable(_)) => {
+                    report.empty_windows.push((date, hour));
+                    return Ok(report);
+                }
+                Err(err) => return Err(err.into()),
+            };
+            let (trains, stops, build_errors) = ingest_timetable(&tt, station, policy);
+            report.trains.fetched = trains.len();
+            report.trains.build_errors = build_errors;
+            report.stops.fetched = stops.len();
+            work.trains = trains;
+            work.stops = stops;
+        }
+        (DocumentKind::Fchg | DocumentKind::Rchg, _) => {
+            let mode = match doc.kind {
+                DocumentKind::Fchg => ChangesSyncMode::Full,
+                _ => ChangesSyncMode::Recent,
+            };
+            report.mode = Some(mode);
+            report.fetched_at = Some(fetched_at);
+            let tt = match parse_timetable(&doc.body, station.id) {
+                Ok(tt) => tt,
+                Err(IRISTimetableError::EmptyTimetable(_)) => return Ok(report),
+                Err(err) => return Err(err.into()),
+            };
+            // Stops of the documents replayed before, the plans precede their changes.
+            let ids: Vec<String> = tt.stops.iter().map(|s| s.id.clone()).collect();
+            let stops = stop_port.get_by_ids(&ids)?;
+            let stops_by_id: HashMap<String, &Stop> = stops.iter().map(|s| (s.id.clone(), s)).collect();
+
+            let changes = IngestedChanges::ingest(&tt, station.id, stops_by_id, &fetched_at, mode);
+            add_changes(&mut report, &mut work, station, changes, &fetched_at);
+        }
+        _ => return Ok(report),
+    }
+    commit(&mut report, unit_of_work, &work)?;
+
+    report.duration = started.elapsed();
+    Ok(report)
+}
+
+/// Ingest archived IRIS documents again, e.g. after fixing the parser.
+///
+/// Replays the `plan`, `fchg` and `rchg` documents matching `query` in the order they were
+/// fetched, each in its own unit of work. Observations and message revocations use the
+/// archived fetch time. Station lookups and failed requests are skipped.
+/// Records per-document errors and continues with the next document.
+/// Errors: only if the archive cannot be read.
+pub fn replay_archived_documents(
+    archive: &dyn DocumentArchive,
+    query: &ArchiveQuery,
+    policy: &FilterPolicy,
+    station_port: &dyn StationPort,
+    stop_port: &dyn StopPort,
+    unit_of_work: &dyn UnitOfWorkPort,
+) -> Result<RunReport, ImportError> {
+    let started = Instant::now();
+    let mut run = RunReport::new(now_local());
+    let docs = archive.load(query)?;
+    info!("Replaying {} archived IRIS documents", docs.len());
+
+    let mut stations: HashMap<i32, Station> = HashMap::new();
+    for doc in docs.iter().filter(|d| d.kind != DocumentKind::Station && d.status == 200) {
+        let Some(eva) = doc.eva() else {
+            warn!("Skipping archived {} document of unknown station {}", doc.kind, doc.station);
+            continue;
+        };
+        let station = match stations.entry(eva) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => match station_port.get_by_id(eva) {
+                Ok(station) => entry.insert(station),
+                Err(err) => {
+                    error!("Error replaying {} document of station {}: {}", doc.kind, eva, err);
+                    run.failures.push(StationFailure { station_id: Some(eva), ds100: String::new(), error: err.to_string() });
+                    continue;
+                }
+            },
+        };
+
+        match replay_document(doc, station, policy, stop_port, unit_of_work) {
+            Ok(report) => run.stations.push(report),
+            Err(err) => {
+                error!("Error replaying {} document of {} fetched at {}: {}", doc.kind, station.ds100, doc.fetched_at, err);
+                run.add_failure(station, &err);
+            }
+        }
+    }
+    run.duration = started.elapsed();
+    info!("Replay finished: {}", run);
+    Ok(run)
 }
 
 /// Import status codes from the configured source and persist them.
 ///
-/// Returns `Ok(())` on success.
-/// Errors: source/Excel/persistence errors are mapped to `ImportError`.
+/// Returns: a report with status code counts.
+/// Errors: source/Excel/persistence errors are propagated.
 pub fn import_status_codes(
     status_code_port: &dyn StatusCodePort,
-) -> Result<(), ImportError> {
-    let codes = get_status_codes().map_err(|e| match e {
-        crate::io::IOError::InvalidSourceFormat(err) => ImportError::InvalidSourceFormat(err),
-        crate::io::IOError::ExcelError(err) => ImportError::Custom(Box::new(err)),
-    })?;
+) -> Result<ImportReport, ImportError> {
+    let started = Instant::now();
+    let mut report = ImportReport::default();
 
-    status_code_port.persist_all(&codes)?;
-    Ok(())
+    let codes = get_status_codes()?;
+
+    report.status_codes.fetched = codes.len();
+
+    report.status_codes.inserted = timed(&mut report.persist_duration, || status_code_port.persist_all(&codes))?.len();
+    report.duration = started.elapsed();
+    Ok(report)
 }
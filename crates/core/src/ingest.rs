@@ -1,14 +1,42 @@
 use std::collections::HashMap;
 
+use chrono::{NaiveDateTime, TimeDelta};
 use iris::dto::Timetable;
 
-use crate::model::{Message, Train, Station, Stop};
+use crate::filter::FilterPolicy;
+use crate::model::{Message, MessageScope, MessageStop, MovementKind, Train, Station, Stop, StopObservation};
 
-pub fn ingest_timetable(tt: &iris::dto::Timetable, station: &Station) -> (Vec<Train>, Vec<Stop>) {
+/// IRIS only serves changes of the last two minutes via `rchg`.
+pub const RECENT_CHANGES_WINDOW: TimeDelta = TimeDelta::minutes(2);
+
+/// Which changes document is used to sync stops and messages of a station.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangesSyncMode {
+    /// `fchg`: every known change, replaces what we know about a stop.
+    Full,
+    /// `rchg`: only changes that became known recently, applied as deltas.
+    Recent,
+}
+
+impl ChangesSyncMode {
+    /// Deltas are only safe if the last successful poll is still within `max_age`,
+    /// otherwise changes in between could have been missed.
+    pub fn for_last_poll(last_poll: Option<&NaiveDateTime>, now: &NaiveDateTime, max_age: TimeDelta) -> Self {
+        match last_poll {
+            Some(last_poll) if *now - *last_poll <= max_age => ChangesSyncMode::Recent,
+            _ => ChangesSyncMode::Full,
+        }
+    }
+}
+
+/// Builds trains and stops from a planned timetable, keeping only stops allowed by `policy`.
+/// Also returns the number of stops whose train could not be built.
+pub fn ingest_timetable(tt: &iris::dto::Timetable, station: &Station, policy: &FilterPolicy) -> (Vec<Train>, Vec<Stop>, usize) {
     let mut trains: Vec<Train> = Vec::with_capacity(tt.stops.len());
     let mut stops: Vec<Stop> = Vec::with_capacity(tt.stops.len());
+    let mut build_errors = 0;
     for stop in tt.stops.iter() {
-        if stop.tl.as_ref().and_then(|tl| tl.category.as_ref()).is_none_or(|c| c == "Bus") {
+        if !policy.allows_stop(stop, &station.ds100) {
             continue;
         }
 
@@ -18,6 +46,7 @@ pub fn ingest_timetable(tt: &iris::dto::Timetable, station: &Station) -> (Vec<Tr
             }
             Err(err) => {
                 error!("Error building train from stop: {}", err);
+                build_errors += 1;
                 continue;
             }
         };
@@ -27,18 +56,65 @@ pub fn ingest_timetable(tt: &iris::dto::Timetable, station: &Station) -> (Vec<Tr
         trains.push(train);
     }
 
-    (trains, stops)
+    (trains, stops, build_errors)
+}
+
+
+/// Builds messages from IRIS `msgs` and adds them to `messages_set`, skipping invalid ones.
+/// A message attached to several stops or movements is kept once with all of its `stop` links.
+///
+/// Returns the number of messages that could not be built.
+fn collect_messages(
+    msgs: &[iris::dto::Msg],
+    train_id: Option<&str>,
+    station_id: i32,
+    stop: Option<MessageStop>,
+    messages_set: &mut HashMap<String, Message>,
+) -> usize {
+    let mut build_errors = 0;
+    for msg in msgs.iter() {
+        let message = match Message::from_iris_msg(msg, train_id, station_id) {
+            Ok(message) => message,
+            Err(err) => {
+                error!("Error building message from iris message: {}", err);
+                build_errors += 1;
+                continue;
+            }
+        };
+
+        let entry = messages_set.entry(message.id.clone()).or_insert(message);
+        if let Some(stop) = &stop {
+            if !entry.stops.contains(stop) {
+                entry.stops.push(stop.clone());
+            }
+        }
+    }
+    build_errors
 }
 
+/// Stops without train line and movements only carry station-wide messages.
+fn is_station_message_stop(stop: &iris::dto::Stop) -> bool {
+    stop.tl.is_none() && stop.arrival.is_none() && stop.departure.is_none()
+}
 
-pub fn ingest_timetable_changes(tt_changes: &Timetable, stops: HashMap<String, &Stop>) -> (Vec<Message>, Vec<Stop>) {
+/// Builds messages and stop changes from an `fchg` document of the station `station_id`.
+/// Also returns the number of messages that could not be built.
+pub fn ingest_timetable_changes(tt_changes: &Timetable, station_id: i32, stops: HashMap<String, &Stop>) -> (Vec<Message>, Vec<Stop>, usize) {
     let mut messages_set: HashMap<String, Message> = HashMap::new();
+    let mut build_errors = 0;
 
     let mut stop_changes: Vec<Stop> = Vec::with_capacity(tt_changes.stops.len());
 
-    let station_id = tt_changes.eva.as_deref().unwrap().parse::<i32>().unwrap(); // Changes Timetable always has an eva
+    if tt_changes.eva.as_deref().and_then(|eva| eva.parse::<i32>().ok()) != Some(station_id) {
+        warn!("Changes for station {} have eva {:?}", station_id, tt_changes.eva);
+    }
 
     for iris_stop_change in tt_changes.stops.iter() {
+        if is_station_message_stop(iris_stop_change) {
+            build_errors += collect_messages(&iris_stop_change.msgs, None, station_id, None, &mut messages_set);
+            continue;
+        }
+
         let known_stop = match stops.get(&iris_stop_change.id) {
             Some(st) => *st,
             None => { // This can happen if the message is for a stop thats outside the time window the stops are fetched for
@@ -46,50 +122,64 @@ pub fn ingest_timetable_changes(tt_changes: &Timetable, stops: HashMap<String, &
                 continue;
             }
         };
-        let train_id = known_stop.train_id.clone();
+        let train_id = Some(known_stop.train_id.as_str());
         stop_changes.push(Stop::from_iris_stop(iris_stop_change, &known_stop.train_id, known_stop.station_id));
 
-        for msg in iris_stop_change.msgs.iter() {
-            let message = match Message::from_iris_msg(msg, &train_id, station_id) {
-                Ok(message) => message,
-                Err(err) => {
-                    error!("Error building message from iris message: {}", err);
-                    continue;
-                }
-            };
-            messages_set.insert(message.id.clone(), message);
-        }
+        let link = |scope| Some(MessageStop { stop_id: known_stop.id.clone(), scope });
 
-        if iris_stop_change.arrival.is_some() {
-            for msg in iris_stop_change.arrival.as_ref().unwrap().msgs.iter() {
-                let message = match Message::from_iris_msg(msg, &train_id, station_id) {
-                    Ok(message) => message,
-                    Err(err) => {
-                        error!("Error building message from iris message: {}", err);
-                        continue;
-                    }
-                };
-                messages_set.insert(message.id.clone(), message);
-            }
+        build_errors += collect_messages(&iris_stop_change.msgs, train_id, station_id, link(MessageScope::Stop), &mut messages_set);
+
+        if let Some(arrival) = &iris_stop_change.arrival {
+            build_errors += collect_messages(&arrival.msgs, train_id, station_id, link(MessageScope::Arrival), &mut messages_set);
         }
 
-        if iris_stop_change.departure.is_some() {
-            for msg in iris_stop_change.departure.as_ref().unwrap().msgs.iter() {
-                let message = match Message::from_iris_msg(msg, &train_id, station_id) {
-                    Ok(message) => message,
-                    Err(err) => {
-                        error!("Error building message from iris message: {}", err);
-                        continue;
-                    }
-                };
-                messages_set.insert(message.id.clone(), message);
-            }
+        if let Some(departure) = &iris_stop_change.departure {
+            build_errors += collect_messages(&departure.msgs, train_id, station_id, link(MessageScope::Departure), &mut messages_set);
         }
     }
 
     let messages = messages_set.into_values().collect();
 
-    (messages, stop_changes)
+    (messages, stop_changes, build_errors)
+}
+
+/// Ids of all messages in a changes document, including those of stops we do not know.
+/// Used to find messages that were withdrawn from `fchg`.
+pub fn message_ids(tt_changes: &Timetable) -> Vec<String> {
+    tt_changes.stops
+        .iter()
+        .flat_map(|s| {
+            s.msgs.iter()
+                .chain(s.arrival.iter().flat_map(|a| a.msgs.iter()))
+                .chain(s.departure.iter().flat_map(|d| d.msgs.iter()))
+        })
+        .filter_map(|msg| Some(Message::build_id(msg.id.as_deref()?, msg.ts.as_ref()?)))
+        .collect()
+}
+
+/// Ingest an `rchg` document. Unlike `fchg`, it only contains stops that changed recently,
+/// so stops without movement changes only contribute their messages.
+pub fn ingest_recent_timetable_changes(tt_changes: &Timetable, station_id: i32, stops: HashMap<String, &Stop>) -> (Vec<Message>, Vec<Stop>, usize) {
+    let (messages, stop_changes, build_errors) = ingest_timetable_changes(tt_changes, station_id, stops);
+    let stop_changes = stop_changes
+        .into_iter()
+        .filter(|s| s.arrival.is_some() || s.departure.is_some())
+        .collect();
+
+    (messages, stop_changes, build_errors)
+}
+
+/// Forecasts of the known stops in a changes document, observed at `observed_at`.
+pub fn ingest_stop_observations(tt_changes: &Timetable, stops: &HashMap<String, &Stop>, observed_at: &NaiveDateTime) -> Vec<StopObservation> {
+    tt_changes.stops
+        .iter()
+        .filter(|s| stops.contains_key(&s.id))
+        .flat_map(|s| {
+            [(MovementKind::Arrival, &s.arrival), (MovementKind::Departure, &s.departure)]
+                .into_iter()
+                .filter_map(|(kind, m)| StopObservation::from_iris_movement(&s.id, kind, m.as_ref()?, *observed_at))
+        })
+        .collect()
 }
 
 
@@ -126,15 +216,7 @@ mod tests {
         IrisMovement {
             planned: Some(planned_dt),
             current: current.map(|ts| NaiveDateTime::parse_from_str(ts, "%Y-%m-%d %H:%M:%S").unwrap()),
-            platform: None,
-            line: None,
-            hi: None,
-            ppth: None,
-            cpth: None,
-            cs: None,
-            clt: None,
-            wings: None,
-            msgs: Vec::new(),
+            ..Default::default()
         }
     }
 
@@ -149,6 +231,17 @@ mod tests {
         }
     }
 
+    #[test]
+    fn changes_sync_mode_falls_back_to_full_when_poll_is_too_old() {
+        let now = NaiveDateTime::parse_from_str("2025-09-10 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        let recent = now - TimeDelta::seconds(90);
+        let old = now - TimeDelta::minutes(20);
+
+        assert_eq!(ChangesSyncMode::Full, ChangesSyncMode::for_last_poll(None, &now, RECENT_CHANGES_WINDOW));
+        assert_eq!(ChangesSyncMode::Recent, ChangesSyncMode::for_last_poll(Some(&recent), &now, RECENT_CHANGES_WINDOW));
+        assert_eq!(ChangesSyncMode::Full, ChangesSyncMode::for_last_poll(Some(&old), &now, RECENT_CHANGES_WINDOW));
+    }
+
     #[test]
     fn ingest_timetable_skips_stops_that_cannot_build_trains() {
         let station = sample_station();
@@ -162,11 +255,11 @@ mod tests {
             stops: vec![valid_stop.clone(), invalid_stop],
         };
 
-        let (trains, stops) = ingest_timetable(&timetable, &station);
+        let (trains, stops, _) = ingest_timetable(&timetable, &station, &FilterPolicy::default());
 
         assert_eq!(1, trains.len());
         assert_eq!(1, stops.len());
-        assert_eq!("123-250910", trains[0].id);
+        assert_eq!("test-stop-250910", trains[0].id);
         assert_eq!(trains[0].id, stops[0].train_id);
     }
 
@@ -174,7 +267,7 @@ mod tests {
     fn ingest_timetable_changes_deduplicates_messages_and_updates_stop() {
         let station = sample_station();
         let iris_stop = base_iris_stop("test-stop-2509100800-1");
-        let train_id = Train::new_id("123", &NaiveDate::from_ymd_opt(2025, 9, 10).unwrap());
+        let train_id = Train::new_id("test-stop", &NaiveDate::from_ymd_opt(2025, 9, 10).unwrap());
         let existing_stop: DomainStop = DomainStop::from_iris_stop(&iris_stop, &train_id, station.id);
 
         let mut stops_map: HashMap<String, &DomainStop> = HashMap::new();
@@ -201,15 +294,8 @@ mod tests {
             arrival: Some(IrisMovement {
                 planned: Some(NaiveDateTime::parse_from_str("2025-09-10 08:00:00", "%Y-%m-%d %H:%M:%S").unwrap()),
                 current: Some(NaiveDateTime::parse_from_str("2025-09-10 08:05:00", "%Y-%m-%d %H:%M:%S").unwrap()),
-                platform: None,
-                line: None,
-                hi: None,
-                ppth: None,
-                cpth: None,
-                cs: None,
-                clt: None,
-                wings: None,
                 msgs: vec![duplicate_msg.clone()],
+                ..Default::default()
             }),
             departure: None,
         };
@@ -220,13 +306,133 @@ mod tests {
             stops: vec![change_stop],
         };
 
-        let (messages, stop_updates) = ingest_timetable_changes(&changes, stops_map);
+        let (messages, stop_updates, _) = ingest_timetable_changes(&changes, station.id, stops_map);
 
         assert_eq!(1, messages.len());
-        assert_eq!("duplicate", messages[0].id);
+        assert_eq!("duplicate-20250910", messages[0].id);
+        assert_eq!("duplicate", messages[0].iris_id);
+        assert_eq!(vec![MessageScope::Stop, MessageScope::Arrival], messages[0].scopes_for_stop(&existing_stop.id));
 
         assert_eq!(1, stop_updates.len());
         let updated_arrival = stop_updates[0].arrival.as_ref().expect("arrival should be present");
         assert_eq!(Some(NaiveDateTime::parse_from_str("2025-09-10 08:05:00", "%Y-%m-%d %H:%M:%S").unwrap()), updated_arrival.current);
     }
+
+    #[test]
+    fn ingest_timetable_changes_keeps_station_messages_without_train() {
+        let station = sample_station();
+        let station_msg = Msg {
+            id: Some("r1234".to_string()),
+            kind: Some("r".to_string()),
+            from: None,
+            to: None,
+            cat: Some("Bauarbeiten".to_string()),
+            pr: None,
+            code: None,
+            ts: Some(NaiveDateTime::parse_from_str("2025-09-10 06:00:00", "%Y-%m-%d %H:%M:%S").unwrap()),
+            ts_tts: None,
+        };
+
+        let changes = Timetable {
+            station: station.ds100.clone(),
+            eva: Some(station.id.to_string()),
+            stops: vec![IrisStop {
+                id: "-7673535960193990364".to_string(),
+                msgs: vec![station_msg],
+                ..Default::default()
+            }],
+        };
+
+        let (messages, stop_updates, build_errors) = ingest_timetable_changes(&changes, station.id, HashMap::new());
+
+        assert_eq!(1, messages.len());
+        assert_eq!(None, messages[0].train_id);
+        assert_eq!(vec![station.id], messages[0].stations);
+        assert!(messages[0].stops.is_empty());
+        assert!(stop_updates.is_empty());
+        assert_eq!(0, build_errors);
+    }
+
+    #[test]
+    fn ingest_timetable_changes_tolerates_missing_eva() {
+        let station = sample_station();
+        let changes = Timetable {
+            station: station.ds100.clone(),
+            eva: None,
+            stops: vec![base_iris_stop("unknown-stop")],
+        };
+
+        let (messages, stop_updates, build_errors) = ingest_timetable_changes(&changes, station.id, HashMap::new());
+
+        assert!(messages.is_empty());
+        assert!(stop_updates.is_empty());
+        assert_eq!(0, build_errors);
+    }
+
+    #[test]
+    fn ingest_timetable_changes_keeps_changed_platform() {
+        let station = sample_station();
+        let mut iris_stop = base_iris_stop("test-stop-2509100800-1");
+        iris_stop.arrival.as_mut().unwrap().platform = Some("5".to_string());
+        let existing_stop = DomainStop::from_iris_stop(&iris_stop, "123-250910", station.id);
+
+        let mut stops_map: HashMap<String, &DomainStop> = HashMap::new();
+        stops_map.insert(existing_stop.id.clone(), &existing_stop);
+
+        let change_stop = IrisStop {
+            id: existing_stop.id.clone(),
+            eva: Some("8002549".to_string()),
+            tl: None,
+            msgs: Vec::new(),
+            arrival: Some(IrisMovement {
+                changed_platform: Some("7".to_string()),
+                ..Default::default()
+            }),
+            departure: None,
+        };
+
+        let changes = Timetable {
+            station: station.ds100.clone(),
+            eva: Some(station.id.to_string()),
+            stops: vec![change_stop],
+        };
+
+        let (_, stop_updates, _) = ingest_timetable_changes(&changes, station.id, stops_map);
+
+        assert_eq!(1, stop_updates.len());
+        let arrival = stop_updates[0].arrival.as_ref().unwrap();
+        assert_eq!(Some("7"), arrival.changed_platform.as_deref());
+        // The planned platform is left untouched by the update.
+        assert!(arrival.platform.is_none());
+    }
+
+    #[test]
+    fn ingest_stop_observations_only_covers_known_stops() {
+        let station = sample_station();
+        let known = DomainStop::from_iris_stop(&base_iris_stop("known-2509100800-1"), "123-250910", station.id);
+        let mut stops_map: HashMap<String, &DomainStop> = HashMap::new();
+        stops_map.insert(known.id.clone(), &known);
+
+        let change = |id: &str| IrisStop {
+            id: id.to_string(),
+            eva: Some("8002549".to_string()),
+            tl: None,
+            msgs: Vec::new(),
+            arrival: Some(sample_movement("2025-09-10 08:00:00", Some("2025-09-10 08:25:00"))),
+            departure: Some(IrisMovement::default()),
+        };
+        let changes = Timetable {
+            station: station.ds100.clone(),
+            eva: Some(station.id.to_string()),
+            stops: vec![change("known-2509100800-1"), change("unknown-2509100800-1")],
+        };
+        let observed_at = NaiveDateTime::parse_from_str("2025-09-10 07:40:00", "%Y-%m-%d %H:%M:%S").unwrap();
+
+        let observations = ingest_stop_observations(&changes, &stops_map, &observed_at);
+
+        assert_eq!(1, observations.len());
+        assert_eq!("known-2509100800-1", observations[0].stop_id);
+        assert_eq!(MovementKind::Arrival, observations[0].kind);
+        assert_eq!(observed_at, observations[0].observed_at);
+    }
 }
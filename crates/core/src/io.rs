@@ -1,8 +1,8 @@
 // Contains all functions to get data thats neither from a database nor the IRIS API
 
-use std::env;
+use std::{env, fs};
 
-use crate::{io::status_code_excel::{get_codes_from_excel, ExcelImportError}, model::StatusCode};
+use crate::{filter::FilterPolicy, io::status_code_excel::{get_codes_from_excel, ExcelImportError}, model::StatusCode};
 mod status_code_excel;
 
 
@@ -12,6 +12,10 @@ pub enum IOError {
     InvalidSourceFormat(String),
     #[error(transparent)]
     ExcelError(#[from] ExcelImportError),
+    #[error(transparent)]
+    FileError(#[from] std::io::Error),
+    #[error(transparent)]
+    JsonError(#[from] serde_json::Error),
 }
 
 pub fn get_status_codes() -> Result<Vec<StatusCode>, IOError> {
@@ -34,6 +38,23 @@ pub fn get_status_codes() -> Result<Vec<StatusCode>, IOError> {
     }
 }
 
+/// Loads the ingest filter policy from `FILTER_POLICY_SRC` (`JSON:<path>`).
+/// Without the variable the default policy (long-distance stations, no buses) is used.
+pub fn get_filter_policy() -> Result<FilterPolicy, IOError> {
+    let filter_policy_src = match env::var("FILTER_POLICY_SRC") {
+        Ok(src) => src,
+        Err(_) => return Ok(FilterPolicy::default()),
+    };
+
+    let (src_type, src) = filter_policy_src
+        .split_once(':')
+        .ok_or(IOError::InvalidSourceFormat(filter_policy_src.clone()))?;
+
+    match src_type {
+        "JSON" => Ok(serde_json::from_str(&fs::read_to_string(src)?)?),
+        _ => Err(IOError::InvalidSourceFormat(filter_policy_src.clone())),
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -85,4 +106,20 @@ mod tests {
 
         env::set_current_dir(original_dir).expect("restore current_dir");
     }
+
+    #[test]
+    fn get_filter_policy_reads_json_source() {
+        let _guard = env_guard();
+        let path = env::temp_dir().join("filter_policy_test.json");
+        std::fs::write(&path, r#"{ "transports": null, "categories": ["S"] }"#).expect("write policy");
+        env::set_var("FILTER_POLICY_SRC", format!("JSON:{}", path.display()));
+
+        let policy = get_filter_policy().expect("policy should load");
+        assert!(policy.transports.is_none());
+        assert_eq!(Some(vec!["S".to_string()]), policy.categories);
+        assert_eq!(vec!["Bus".to_string()], policy.excluded_categories);
+
+        env::remove_var("FILTER_POLICY_SRC");
+        assert_eq!(FilterPolicy::default().transports, get_filter_policy().unwrap().transports);
+    }
 }
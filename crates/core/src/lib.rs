@@ -9,5 +9,8 @@ pub mod utils;
 pub mod ports;
 pub mod service;
 pub mod io;
+pub mod route;
+pub mod filter;
+pub mod report;
 
 pub mod model;
@@ -1,13 +1,17 @@
 mod station;
+mod platform;
 mod train;
 mod stop;
+mod stop_observation;
 mod error;
 mod message;
 mod status_code;
 
 pub use station::{*};
+pub use platform::{*};
 pub use train::{*};
 pub use stop::{*};
+pub use stop_observation::{*};
 pub use error::{*};
 pub use message::{*};
 pub use status_code::{*};
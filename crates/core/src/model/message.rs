@@ -1,10 +1,14 @@
-use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
+
+/// Messages without `valid_to` are considered in effect for this long after their timestamp.
+pub const DEFAULT_MESSAGE_VALIDITY: TimeDelta = TimeDelta::days(1);
 
 #[derive(Debug, Clone)]
 pub struct Message {
     pub id: String,
     pub iris_id: String,
-    pub train_id: String,
+    /// `None` for station-wide messages.
+    pub train_id: Option<String>,
     pub valid_from: Option<NaiveDateTime>,
     pub valid_to: Option<NaiveDateTime>,
     pub priority: Option<i16>,
@@ -13,7 +17,51 @@ pub struct Message {
     pub timestamp: NaiveDateTime,
     pub m_type: Option<String>,
     pub last_updated: Option<DateTime<Utc>>,
+    /// First import that contained the message.
+    pub first_seen: Option<DateTime<Utc>>,
+    /// Last import that contained the message.
+    pub last_seen: Option<DateTime<Utc>>,
+    /// Set once the message disappeared from `fchg`, cleared if it shows up again.
+    pub revoked_at: Option<DateTime<Utc>>,
+    /// Id of the newest revision of the message, i.e. one with the same `iris_id` and a later
+    /// timestamp. IRIS ids repeat over days, see [`Message::build_id`].
+    pub superseded_by: Option<String>,
     pub stations: Vec<i32>,
+    /// Stops (and their movements) the message was attached to.
+    pub stops: Vec<MessageStop>,
+}
+
+/// Part of a stop a message was attached to in IRIS: the `<s>` element itself or its `<ar>`/`<dp>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageScope {
+    Stop,
+    Arrival,
+    Departure,
+}
+
+impl MessageScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessageScope::Stop => "stop",
+            MessageScope::Arrival => "arrival",
+            MessageScope::Departure => "departure",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "stop" => Some(MessageScope::Stop),
+            "arrival" => Some(MessageScope::Arrival),
+            "departure" => Some(MessageScope::Departure),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MessageStop {
+    pub stop_id: String,
+    pub scope: MessageScope,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -28,16 +76,18 @@ pub enum MessageBuildError {
 
 
 impl Message {
-    pub fn from_iris_msg(msg: &iris::dto::Msg, train_id: &str, station_id: i32) -> Result<Message, MessageBuildError> {
+    /// Pass `None` as `train_id` for messages that apply to the whole station.
+    pub fn from_iris_msg(msg: &iris::dto::Msg, train_id: Option<&str>, station_id: i32) -> Result<Message, MessageBuildError> {
         let iris_id = msg.id.as_ref().ok_or(MessageBuildError::MissingId)?.clone();
         let ts = msg.ts.ok_or(MessageBuildError::MissingTimestamp)?;
 
-        let id = format!("{}-{}", iris_id, ts.format("%Y%m%d")); // In case there are duplicates over time
+        let id = Message::build_id(&iris_id, &ts);
+        let now = Utc::now();
 
         Ok(Message {
             id,
             iris_id,
-            train_id: train_id.to_string(),
+            train_id: train_id.map(str::to_string),
             valid_from: msg.from,
             valid_to: msg.to,
             priority: msg.pr.map(|p| p as i16), // TODO: Test overflows
@@ -45,11 +95,33 @@ impl Message {
             code: msg.code,
             timestamp: ts,
             m_type: msg.kind.clone(),
-            last_updated: Some(Utc::now()),
-            stations: vec![station_id]
-            // It probably makes sense to update the last_updated timestamp when a message is imported from Iris.
+            last_updated: Some(now),
+            first_seen: Some(now),
+            last_seen: Some(now),
+            revoked_at: None,
+            superseded_by: None,
+            stations: vec![station_id],
+            stops: Vec::new(),
         })
     }
+
+    /// `{iris_id}-{yyyymmdd}` of the message timestamp, in case IRIS ids repeat over time.
+    pub fn build_id(iris_id: &str, ts: &NaiveDateTime) -> String {
+        format!("{}-{}", iris_id, ts.format("%Y%m%d"))
+    }
+
+    /// Scopes in which the message is attached to `stop_id`.
+    pub fn scopes_for_stop(&self, stop_id: &str) -> Vec<MessageScope> {
+        self.stops.iter().filter(|s| s.stop_id == stop_id).map(|s| s.scope).collect()
+    }
+
+    /// Whether the message is in effect at `at` (local time): neither revoked nor superseded and within
+    /// `valid_from`/`valid_to`, falling back to the timestamp and [`DEFAULT_MESSAGE_VALIDITY`].
+    pub fn is_active_at(&self, at: &NaiveDateTime) -> bool {
+        let from = self.valid_from.unwrap_or(self.timestamp);
+        let to = self.valid_to.unwrap_or(self.timestamp + DEFAULT_MESSAGE_VALIDITY);
+        self.revoked_at.is_none() && self.superseded_by.is_none() && from <= *at && *at <= to
+    }
 }
 
 
@@ -59,3 +131,60 @@ pub struct StopInfo {
     pub id: String,
     pub station_name: String,
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ts(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn sample_msg() -> iris::dto::Msg {
+        iris::dto::Msg {
+            id: Some("r1234".to_string()),
+            kind: Some("h".to_string()),
+            from: Some(ts("2025-09-10 06:00:00")),
+            to: Some(ts("2025-09-12 22:00:00")),
+            cat: Some("Bauarbeiten".to_string()),
+            pr: Some(2),
+            code: None,
+            ts: Some(ts("2025-09-09 12:00:00")),
+            ts_tts: None,
+        }
+    }
+
+    #[test]
+    fn is_active_at_respects_validity_and_revocation() {
+        let mut message = Message::from_iris_msg(&sample_msg(), None, 8002549).unwrap();
+
+        assert_eq!("r1234-20250909", message.id);
+        assert!(!message.is_active_at(&ts("2025-09-10 05:59:00")));
+        assert!(message.is_active_at(&ts("2025-09-11 12:00:00")));
+        assert!(!message.is_active_at(&ts("2025-09-13 00:00:00")));
+
+        message.revoked_at = Some(Utc::now());
+        assert!(!message.is_active_at(&ts("2025-09-11 12:00:00")));
+    }
+
+    #[test]
+    fn is_active_at_excludes_superseded_revisions() {
+        let mut message = Message::from_iris_msg(&sample_msg(), None, 8002549).unwrap();
+        message.superseded_by = Some("r1234-20250910".to_string());
+
+        assert!(!message.is_active_at(&ts("2025-09-11 12:00:00")));
+    }
+
+    #[test]
+    fn is_active_at_falls_back_to_timestamp_without_validity() {
+        let mut msg = sample_msg();
+        msg.from = None;
+        msg.to = None;
+        let message = Message::from_iris_msg(&msg, Some("trip-250909"), 8002549).unwrap();
+
+        assert!(!message.is_active_at(&ts("2025-09-09 11:59:00")));
+        assert!(message.is_active_at(&ts("2025-09-10 11:00:00")));
+        assert!(!message.is_active_at(&ts("2025-09-10 12:01:00")));
+    }
+}
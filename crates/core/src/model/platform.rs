@@ -0,0 +1,179 @@
+use std::{cmp::Ordering, collections::BTreeMap};
+
+use crate::model::StationBuildError;
+
+/// Platforms of a station from the IRIS station `p` attribute, normalized and sorted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StationPlatforms {
+    pub station_id: i32,
+    /// Platforms and platform sections, e.g. `5`, `5a` and `5b`, see [`normalize_platform`].
+    pub platforms: Vec<String>,
+}
+
+impl StationPlatforms {
+    pub fn new<S: AsRef<str>>(station_id: i32, platforms: impl IntoIterator<Item = S>) -> Self {
+        let mut platforms: Vec<String> = platforms.into_iter().flat_map(|p| normalize_platform(p.as_ref())).collect();
+        platforms.sort_by(|a, b| compare_platforms(a, b));
+        platforms.dedup();
+        StationPlatforms { station_id, platforms }
+    }
+
+    pub fn from_iris(station: &iris::dto::IRISStation) -> Result<Self, StationBuildError> {
+        let station_id = station.eva.parse::<i32>().map_err(StationBuildError::IdParsingError)?;
+        Ok(StationPlatforms::new(station_id, station.platforms.iter().flatten()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.platforms.is_empty()
+    }
+
+    /// Usage of every listed platform, unused ones with zero counts, followed by
+    /// platforms that only appear in `usage`. Sorted like [`compare_platforms`].
+    pub fn with_usage(&self, usage: &[PlatformUsage]) -> Vec<PlatformUsage> {
+        let mut all: Vec<PlatformUsage> = self.platforms
+            .iter()
+            .map(|platform| usage.iter()
+                .find(|u| u.platform == *platform)
+                .cloned()
+                .unwrap_or(PlatformUsage { platform: platform.clone(), used: 0, changed: 0 }))
+            .collect();
+        all.extend(usage.iter().filter(|u| !self.platforms.contains(&u.platform)).cloned());
+        all.sort_by(|a, b| compare_platforms(&a.platform, &b.platform));
+        all
+    }
+}
+
+/// How often movements at a station are planned at a platform and how often they are
+/// moved away from it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlatformUsage {
+    pub platform: String,
+    pub used: usize,
+    pub changed: usize,
+}
+
+impl PlatformUsage {
+    /// Sums counts per planned platform as stored into counts per normalized platform.
+    /// A movement planned at `5a/b` counts for `5a` and `5b`.
+    pub fn from_counts(
+        used: impl IntoIterator<Item = (String, i64)>,
+        changed: impl IntoIterator<Item = (String, i64)>,
+    ) -> Vec<Self> {
+        let mut counts: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+        for (platform, count) in used {
+            for name in normalize_platform(&platform) {
+                counts.entry(name).or_default().0 += count.max(0) as usize;
+            }
+        }
+        for (platform, count) in changed {
+            for name in normalize_platform(&platform) {
+                counts.entry(name).or_default().1 += count.max(0) as usize;
+            }
+        }
+
+        let mut usage: Vec<PlatformUsage> = counts
+            .into_iter()
+            .map(|(platform, (used, changed))| PlatformUsage { platform, used, changed })
+            .collect();
+        usage.sort_by(|a, b| compare_platforms(&a.platform, &b.platform));
+        usage
+    }
+}
+
+/// Splits an IRIS platform into the platforms or sections it covers.
+///
+/// Sections after a `/` belong to the platform before it, so `5a/b` becomes `5a` and `5b`,
+/// `5a-c` covers `5a` to `5c`. Other parts stand on their own, `5/6` becomes `5` and `6`.
+pub fn normalize_platform(raw: &str) -> Vec<String> {
+    let mut parts = raw.split('/').map(str::trim).filter(|p| !p.is_empty());
+    let Some(first) = parts.next() else {
+        return Vec::new();
+    };
+
+    let (number, section) = split_section(first);
+    let mut out = expand_sections(number, section);
+    for part in parts {
+        let is_section = !number.is_empty() && !section.is_empty() && split_section(part).0.is_empty();
+        if is_section {
+            out.extend(expand_sections(number, part));
+        } else {
+            let (number, section) = split_section(part);
+            out.extend(expand_sections(number, section));
+        }
+    }
+    out
+}
+
+/// `5a` into `("5", "a")`, platforms without a leading number have no section.
+fn split_section(platform: &str) -> (&str, &str) {
+    let digits = platform.len() - platform.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits == 0 {
+        ("", platform)
+    } else {
+        platform.split_at(digits)
+    }
+}
+
+fn expand_sections(number: &str, sections: &str) -> Vec<String> {
+    let range: Vec<char> = sections.chars().collect();
+    match range.as_slice() {
+        [from, '-', to] if !number.is_empty() && from.is_ascii_alphabetic() && from < to => {
+            (*from..=*to).map(|s| format!("{}{}", number, s)).collect()
+        }
+        _ => vec![format!("{}{}", number, sections)],
+    }
+}
+
+/// Numbered platforms first in numeric order, a platform before its sections, then all others.
+pub fn compare_platforms(a: &str, b: &str) -> Ordering {
+    let (a_number, a_section) = split_section(a);
+    let (b_number, b_section) = split_section(b);
+    match (a_number.parse::<u32>(), b_number.parse::<u32>()) {
+        (Ok(a_number), Ok(b_number)) => a_number.cmp(&b_number).then_with(|| a_section.cmp(b_section)),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn platforms_are_expanded_to_their_sections() {
+        assert_eq!(vec!["5a", "5b"], normalize_platform("5a/b"));
+        assert_eq!(vec!["13a", "13b", "13c"], normalize_platform("13a-c"));
+        assert_eq!(vec!["5", "6"], normalize_platform("5/6"));
+        assert_eq!(vec!["S"], normalize_platform(" S "));
+        assert!(normalize_platform("").is_empty());
+
+        let body = std::fs::read_to_string("tests/data/iris/station_AH.xml").unwrap();
+        let iris_station = iris::fetch::parse_station(&body, "AH").unwrap();
+        let platforms = StationPlatforms::from_iris(&iris_station).unwrap();
+        assert_eq!(8002549, platforms.station_id);
+        assert_eq!(
+            vec!["5", "5a", "5b", "6", "6a", "6b", "7", "7a", "7b", "8", "8a", "8b", "11", "11a", "11b",
+                 "12", "12a", "12b", "13", "13a", "13b", "14", "14a", "14b", "S"],
+            platforms.platforms,
+        );
+    }
+
+    #[test]
+    fn platform_usage_is_counted_per_section_and_merged_with_the_catalogue() {
+        let usage = PlatformUsage::from_counts(
+            [("5a/b".to_string(), 2), ("5a".to_string(), 1), ("9".to_string(), 4)],
+            [("5a".to_string(), 1)],
+        );
+        assert_eq!(vec![
+            PlatformUsage { platform: "5a".to_string(), used: 3, changed: 1 },
+            PlatformUsage { platform: "5b".to_string(), used: 2, changed: 0 },
+            PlatformUsage { platform: "9".to_string(), used: 4, changed: 0 },
+        ], usage);
+
+        let catalogue = StationPlatforms::new(8002549, ["5a/b", "5"]);
+        let names: Vec<String> = catalogue.with_usage(&usage).into_iter().map(|u| u.platform).collect();
+        assert_eq!(vec!["5", "5a", "5b", "9"], names);
+    }
+}
@@ -1,5 +1,7 @@
 use std::num::ParseIntError;
 
+use iris::fetch::wildcard_matches;
+
 
 #[derive(Debug, Clone)]
 pub struct Station {
@@ -8,7 +10,6 @@ pub struct Station {
     pub lon: Option<f64>,
     pub name: String,
     pub ds100: String,
-    //TODO: Perhaps include meta and platforms
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -39,8 +40,115 @@ impl Station {
             ds100: station.ds100.ok_or(StationBuildError::MissingDS100)?, // TODO: Maybe handle differently
         })
     }
+    /// How well `query` finds this station, lower is better, `None` if it does not match.
+    ///
+    /// `0` the EVA or DS100 (ignoring case), `1` the whole name, `2` the start of the name
+    /// or a `*` pattern, `3` all words of the query appear in the name.
+    /// Names are compared after [`normalize_station_name`].
+    pub fn search_rank(&self, query: &str) -> Option<u8> {
+        let query = query.trim();
+        if self.id.to_string() == query || self.ds100.eq_ignore_ascii_case(query) {
+            return Some(0);
+        }
+
+        let name = normalize_station_name(&self.name);
+        if query.contains('*') {
+            let pattern: Vec<String> = query.split('*').map(normalize_station_name).collect();
+            return wildcard_matches(&pattern.join("*"), &name).then_some(2);
+        }
+
+        let query = normalize_station_name(query);
+        if query.is_empty() {
+            None
+        } else if name == query {
+            Some(1)
+        } else if name.starts_with(&query) {
+            Some(2)
+        } else if query.split(' ').all(|word| name.contains(word)) {
+            Some(3)
+        } else {
+            None
+        }
+    }
+}
+
+/// Stations IRIS lists as belonging together, e.g. Hamburg Hbf and its S-Bahn platforms.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StationGroup {
+    pub station_id: i32,
+    /// Related EVAs from IRIS `meta` or `meta_evas`, sorted. Not necessarily imported stations.
+    pub member_ids: Vec<i32>,
+}
+
+impl StationGroup {
+    pub fn new(station_id: i32, member_ids: impl IntoIterator<Item = i32>) -> Self {
+        let mut member_ids: Vec<i32> = member_ids.into_iter().filter(|id| *id != station_id).collect();
+        member_ids.sort_unstable();
+        member_ids.dedup();
+        StationGroup { station_id, member_ids }
+    }
+
+    /// Invalid `meta` entries are skipped.
+    pub fn from_iris(station: &iris::dto::IRISStation) -> Result<Self, StationBuildError> {
+        let station_id = station.eva.parse::<i32>().map_err(StationBuildError::IdParsingError)?;
+        let meta = station.meta.iter().flatten().filter_map(|eva| eva.parse::<i32>().ok());
+        Ok(StationGroup::new(station_id, meta))
+    }
+
+    pub fn from_info(station: &iris::dto::StationInfo) -> Self {
+        // TODO: Danger: cast from u32/u64 to i32, like `Station::from_info`
+        StationGroup::new(station.eva as i32, station.meta_evas.iter().map(|eva| *eva as i32))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.member_ids.is_empty()
+    }
 }
 
+/// Folds a station name for accent- and umlaut-tolerant search: lowercase ASCII words separated
+/// by single spaces. Diacritics are dropped, `ß` becomes `ss` and the transliterations `ae`, `oe`
+/// and `ue` collapse to their vowel, so "München", "Munchen" and "Muenchen" become "munchen".
+pub fn normalize_station_name(name: &str) -> String {
+    let mut folded = String::with_capacity(name.len());
+    for c in name.chars().flat_map(char::to_lowercase) {
+        match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => folded.push('a'),
+            'æ' => folded.push_str("ae"),
+            'ç' | 'č' | 'ć' => folded.push('c'),
+            'è' | 'é' | 'ê' | 'ë' | 'ě' => folded.push('e'),
+            'ì' | 'í' | 'î' | 'ï' => folded.push('i'),
+            'ł' => folded.push('l'),
+            'ñ' | 'ń' | 'ň' => folded.push('n'),
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' => folded.push('o'),
+            'œ' => folded.push_str("oe"),
+            'ř' => folded.push('r'),
+            'ß' => folded.push_str("ss"),
+            'š' | 'ś' => folded.push('s'),
+            'ù' | 'ú' | 'û' | 'ü' | 'ů' => folded.push('u'),
+            'ý' | 'ÿ' => folded.push('y'),
+            'ž' | 'ź' | 'ż' => folded.push('z'),
+            c if c.is_ascii_alphanumeric() => folded.push(c),
+            _ => folded.push(' '),
+        }
+    }
+    folded
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace("ae", "a")
+        .replace("oe", "o")
+        .replace("ue", "u")
+}
+
+/// Stations matching `query`, best matches first (see [`Station::search_rank`]), then by name.
+pub fn search_stations(stations: impl IntoIterator<Item = Station>, query: &str, limit: usize) -> Vec<Station> {
+    let mut found: Vec<(u8, Station)> = stations
+        .into_iter()
+        .filter_map(|s| s.search_rank(query).map(|rank| (rank, s)))
+        .collect();
+    found.sort_by(|(rank_a, a), (rank_b, b)| rank_a.cmp(rank_b).then_with(|| a.name.cmp(&b.name)));
+    found.into_iter().take(limit).map(|(_, s)| s).collect()
+}
 
 #[cfg(test)]
 mod tests {
@@ -75,4 +183,50 @@ mod tests {
         assert_eq!("AH", station.ds100);
         assert_eq!(Some(53.5511), station.lat);
     }
+
+    fn station(id: i32, ds100: &str, name: &str) -> Station {
+        Station { id, lat: None, lon: None, name: name.to_string(), ds100: ds100.to_string() }
+    }
+
+    #[test]
+    fn normalize_station_name_folds_accents_and_umlauts() {
+        assert_eq!("munchen hbf", normalize_station_name("München Hbf"));
+        assert_eq!("munchen hbf", normalize_station_name("Muenchen  Hbf"));
+        assert_eq!("frankfurt main hbf", normalize_station_name("Frankfurt (Main) Hbf"));
+        assert_eq!("giessen", normalize_station_name("Gießen"));
+        assert_eq!("geneve", normalize_station_name("Genève"));
+    }
+
+    #[test]
+    fn search_stations_ranks_codes_before_names() {
+        let stations = vec![
+            station(8000261, "MH", "München Hbf"),
+            station(8004128, "MOP", "München Ost"),
+            station(8000105, "FF", "Frankfurt (Main) Hbf"),
+            station(8098261, "MHT", "Münchenhof"),
+        ];
+        let names = |query: &str| -> Vec<String> { search_stations(stations.clone(), query, 10).into_iter().map(|s| s.name).collect() };
+
+        assert_eq!(vec!["München Hbf", "München Ost", "Münchenhof"], names("Munchen"));
+        assert_eq!(vec!["München Ost"], names("muenchen ost"));
+        assert_eq!(vec!["München Hbf"], names("mh"));
+        assert_eq!(vec!["Frankfurt (Main) Hbf"], names("8000105"));
+        assert_eq!(vec!["Frankfurt (Main) Hbf"], names("main hbf"));
+        assert_eq!(vec!["München Hbf", "Münchenhof"], names("Mün*h*f"));
+        assert!(names("").is_empty());
+        assert_eq!(1, search_stations(stations.clone(), "München", 1).len());
+    }
+
+    #[test]
+    fn station_group_keeps_related_evas() {
+        let mut info = sample_info(Some("AH"));
+        info.eva = 8002549;
+        info.meta_evas = vec![8098549, 694887, 8002549, 8098549];
+        assert_eq!(StationGroup { station_id: 8002549, member_ids: vec![694887, 8098549] }, StationGroup::from_info(&info));
+
+        let body = std::fs::read_to_string("tests/data/iris/station_AH.xml").unwrap();
+        let iris_station = iris::fetch::parse_station(&body, "AH").unwrap();
+        let group = StationGroup::from_iris(&iris_station).unwrap();
+        assert_eq!(vec![694887, 8071065, 8076116, 8098549], group.member_ids);
+    }
 }
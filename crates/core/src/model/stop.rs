@@ -28,18 +28,54 @@ impl Stop {
     }
 }
 
+impl Stop {
+    pub fn movement(&self, kind: MovementKind) -> Option<&Movement> {
+        match kind {
+            MovementKind::Arrival => self.arrival.as_ref(),
+            MovementKind::Departure => self.departure.as_ref(),
+        }
+    }
+
+    /// Movements of this stop with a changed platform.
+    pub fn platform_changes(&self) -> Vec<(MovementKind, &Movement)> {
+        [MovementKind::Arrival, MovementKind::Departure]
+            .into_iter()
+            .filter_map(|kind| self.movement(kind).map(|m| (kind, m)))
+            .filter(|(_, m)| m.platform_change().is_some())
+            .collect()
+    }
+
+    /// Movements of this stop that are cancelled.
+    pub fn cancelled_movements(&self) -> Vec<(MovementKind, &Movement)> {
+        [MovementKind::Arrival, MovementKind::Departure]
+            .into_iter()
+            .filter_map(|kind| self.movement(kind).map(|m| (kind, m)))
+            .filter(|(_, m)| m.is_cancelled())
+            .collect()
+    }
+}
+
 impl HasStopGetter for Stop {
     fn get_stop(&self) -> &Stop {
         self
     }
 }
 
+impl<S: HasStopGetter> HasStopGetter for &S {
+    fn get_stop(&self) -> &Stop {
+        (*self).get_stop()
+    }
+}
+
 
 #[derive(Debug, Clone)]
 pub struct StopUpdate {
     pub id: String,
     pub arrival: Option<Movement>,
     pub departure: Option<Movement>,
+    /// Clears changed fields (`ct`, `cp`, `cs`, ...) the given movements do not set, instead of
+    /// keeping them. Used for `fchg`, which always carries all current changes of a stop.
+    pub replaces_changes: bool,
 }
 
 impl From<&Stop> for StopUpdate {
@@ -47,11 +83,19 @@ impl From<&Stop> for StopUpdate {
         StopUpdate {
             id: value.id.clone(),
             arrival: value.arrival.clone(),
-            departure: value.departure.clone()
+            departure: value.departure.clone(),
+            replaces_changes: false,
         }
     }
 }
 
+impl StopUpdate {
+    /// Update replacing all changes of the movements of `stop`, e.g. a withdrawn cancellation.
+    pub fn replacing_changes(stop: &Stop) -> Self {
+        StopUpdate { replaces_changes: true, ..StopUpdate::from(stop) }
+    }
+}
+
 
 #[derive(Debug, Clone)]
 pub struct StopWithStation {
@@ -66,23 +110,128 @@ impl HasStopGetter for StopWithStation {
 }
 
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Movement {
     pub platform: Option<String>,
+    pub changed_platform: Option<String>,
     pub planned: Option<NaiveDateTime>,
     pub current: Option<NaiveDateTime>,
     pub planned_path: Option<Vec<String>>,
     pub changed_path: Option<Vec<String>>,
+    pub line: Option<String>,
+    pub changed_line: Option<String>,
+    pub planned_status: Option<EventStatus>,
+    pub changed_status: Option<EventStatus>,
+    /// When the changed status was last set, for cancellations the cancellation time.
+    pub status_changed_at: Option<NaiveDateTime>,
+    pub hidden: Option<bool>,
+    /// Trip ids of wings joined or split at this stop.
+    pub wings: Option<Vec<String>>,
+    /// Trip id of the train this one turns into or came from.
+    pub transition: Option<String>,
+    pub planned_distant_endpoint: Option<String>,
+    pub changed_distant_endpoint: Option<String>,
+    pub distant_change: Option<i32>,
 }
 
 impl Movement {
     pub fn from_iris_movement(movement: &iris::dto::Movement) -> Self {
         Movement {
             platform: movement.platform.clone(),
+            changed_platform: movement.changed_platform.clone(),
             planned: movement.planned,
             current: movement.current,
             planned_path: movement.ppth.clone(),
             changed_path: movement.cpth.clone(),
+            line: movement.line.clone(),
+            changed_line: movement.changed_line.clone(),
+            planned_status: movement.ps.as_deref().and_then(EventStatus::from_code),
+            changed_status: movement.cs.as_deref().and_then(EventStatus::from_code),
+            status_changed_at: movement.clt,
+            hidden: movement.hi.map(|hi| hi == 1),
+            wings: movement.wings.clone(),
+            transition: movement.tra.clone(),
+            planned_distant_endpoint: movement.pde.clone(),
+            changed_distant_endpoint: movement.cde.clone(),
+            distant_change: movement.dc,
+        }
+    }
+
+    /// Planned and changed platform if IRIS moved this movement to another platform.
+    pub fn platform_change(&self) -> Option<(&str, &str)> {
+        match (self.platform.as_deref(), self.changed_platform.as_deref()) {
+            (Some(planned), Some(changed)) if planned != changed => Some((planned, changed)),
+            _ => None,
+        }
+    }
+
+    /// Changed status if known, otherwise the planned one.
+    pub fn status(&self) -> Option<EventStatus> {
+        self.changed_status.or(self.planned_status)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.status() == Some(EventStatus::Cancelled)
+    }
+
+    /// Added movements keep `ps="a"` even after IRIS changes them later on.
+    pub fn is_added(&self) -> bool {
+        self.status() == Some(EventStatus::Added) || self.planned_status == Some(EventStatus::Added)
+    }
+}
+
+/// IRIS event status (`ps`/`cs`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventStatus {
+    Planned,
+    Added,
+    Cancelled,
+}
+
+impl EventStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventStatus::Planned => "p",
+            EventStatus::Added => "a",
+            EventStatus::Cancelled => "c",
+        }
+    }
+
+    pub fn from_code(value: &str) -> Option<EventStatus> {
+        match value {
+            "p" => Some(EventStatus::Planned),
+            "a" => Some(EventStatus::Added),
+            "c" => Some(EventStatus::Cancelled),
+            other => {
+                warn!("Unknown EventStatus: {}", other);
+                None
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MovementKind {
+    Arrival,
+    Departure,
+}
+
+impl MovementKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MovementKind::Arrival => "arrival",
+            MovementKind::Departure => "departure",
+        }
+    }
+
+    pub fn from_code(value: &str) -> Option<MovementKind> {
+        match value {
+            "arrival" => Some(MovementKind::Arrival),
+            "departure" => Some(MovementKind::Departure),
+            other => {
+                warn!("Unknown MovementKind: {}", other);
+                None
+            }
         }
     }
 }
@@ -148,18 +297,12 @@ mod tests {
             train_id: "train".to_string(),
             station_id: 42,
             arrival: arrival.map(|planned| Movement {
-                platform: None,
                 planned: Some(planned),
-                current: None,
-                planned_path: None,
-                changed_path: None,
+                ..Default::default()
             }),
             departure: departure.map(|planned| Movement {
-                platform: None,
                 planned: Some(planned),
-                current: None,
-                planned_path: None,
-                changed_path: None,
+                ..Default::default()
             }),
         }
     }
@@ -193,4 +336,74 @@ mod tests {
         assert!(next_stops.is_empty());
         assert!(past_stops.is_empty());
     }
+
+    #[test]
+    fn movement_from_iris_movement_keeps_all_attributes() {
+        let iris_movement = iris::dto::Movement {
+            platform: Some("5".to_string()),
+            changed_platform: Some("6".to_string()),
+            line: Some("7".to_string()),
+            changed_line: Some("7X".to_string()),
+            hi: Some(1),
+            ps: Some("p".to_string()),
+            cs: Some("c".to_string()),
+            clt: Some(NaiveDateTime::parse_from_str("2025-09-10 09:55:00", "%Y-%m-%d %H:%M:%S").unwrap()),
+            wings: Some(vec!["123-2509101000".to_string()]),
+            tra: Some("456-2509101000-3".to_string()),
+            pde: Some("Bremen Hbf".to_string()),
+            cde: Some("Rotenburg(Wümme)".to_string()),
+            dc: Some(1),
+            ..Default::default()
+        };
+
+        let movement = Movement::from_iris_movement(&iris_movement);
+
+        assert_eq!(Some("6"), movement.changed_platform.as_deref());
+        assert_eq!(Some("7X"), movement.changed_line.as_deref());
+        assert_eq!(Some(true), movement.hidden);
+        assert_eq!(Some(EventStatus::Planned), movement.planned_status);
+        assert_eq!(Some(EventStatus::Cancelled), movement.changed_status);
+        assert!(movement.is_cancelled());
+        assert!(!movement.is_added());
+        assert_eq!(iris_movement.clt, movement.status_changed_at);
+        assert_eq!(Some(vec!["123-2509101000".to_string()]), movement.wings);
+        assert_eq!(Some("456-2509101000-3"), movement.transition.as_deref());
+        assert_eq!(Some("Bremen Hbf"), movement.planned_distant_endpoint.as_deref());
+        assert_eq!(Some("Rotenburg(Wümme)"), movement.changed_distant_endpoint.as_deref());
+        assert_eq!(Some(1), movement.distant_change);
+    }
+
+    #[test]
+    fn movement_status_prefers_changed_status() {
+        let added = Movement {
+            planned_status: Some(EventStatus::Added),
+            changed_status: Some(EventStatus::Planned),
+            ..Default::default()
+        };
+        assert_eq!(Some(EventStatus::Planned), added.status());
+        assert!(added.is_added());
+        assert!(!added.is_cancelled());
+
+        let mut stop = build_stop("cancelled", Some(NaiveDateTime::parse_from_str("2025-09-10 11:00:00", "%Y-%m-%d %H:%M:%S").unwrap()), None);
+        assert!(stop.cancelled_movements().is_empty());
+        stop.arrival.as_mut().unwrap().changed_status = Some(EventStatus::Cancelled);
+        let cancelled = stop.cancelled_movements();
+        assert_eq!(1, cancelled.len());
+        assert_eq!(MovementKind::Arrival, cancelled[0].0);
+    }
+
+    #[test]
+    fn platform_change_ignores_unchanged_platforms() {
+        let mut movement = Movement {
+            platform: Some("5".to_string()),
+            ..Default::default()
+        };
+        assert!(movement.platform_change().is_none());
+
+        movement.changed_platform = Some("5".to_string());
+        assert!(movement.platform_change().is_none());
+
+        movement.changed_platform = Some("7".to_string());
+        assert_eq!(Some(("5", "7")), movement.platform_change());
+    }
 }
@@ -0,0 +1,80 @@
+use chrono::NaiveDateTime;
+
+use super::stop::{EventStatus, MovementKind};
+
+/// Forecast of one movement as reported by IRIS at `observed_at`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StopObservation {
+    pub stop_id: String,
+    pub kind: MovementKind,
+    pub observed_at: NaiveDateTime,
+    pub current: Option<NaiveDateTime>,
+    pub platform: Option<String>,
+    pub status: Option<EventStatus>,
+}
+
+impl StopObservation {
+    /// Builds an observation from a changes movement, `None` if it carries no forecast.
+    pub fn from_iris_movement(
+        stop_id: &str,
+        kind: MovementKind,
+        movement: &iris::dto::Movement,
+        observed_at: NaiveDateTime,
+    ) -> Option<Self> {
+        let observation = StopObservation {
+            stop_id: stop_id.to_string(),
+            kind,
+            observed_at,
+            current: movement.current,
+            platform: movement.changed_platform.clone(),
+            status: movement.cs.as_deref().and_then(EventStatus::from_code),
+        };
+
+        if observation.current.is_none() && observation.platform.is_none() && observation.status.is_none() {
+            return None;
+        }
+        Some(observation)
+    }
+
+    /// Same forecast time, platform and status, regardless of when it was observed.
+    pub fn same_forecast(&self, other: &StopObservation) -> bool {
+        self.current == other.current && self.platform == other.platform && self.status == other.status
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dt(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn from_iris_movement_skips_movements_without_forecast() {
+        let movement = iris::dto::Movement {
+            planned: Some(dt("2025-09-10 08:00:00")),
+            ..Default::default()
+        };
+
+        assert!(StopObservation::from_iris_movement("stop", MovementKind::Arrival, &movement, dt("2025-09-10 07:00:00")).is_none());
+    }
+
+    #[test]
+    fn same_forecast_ignores_observation_time() {
+        let movement = iris::dto::Movement {
+            current: Some(dt("2025-09-10 08:05:00")),
+            changed_platform: Some("7".to_string()),
+            ..Default::default()
+        };
+
+        let first = StopObservation::from_iris_movement("stop", MovementKind::Departure, &movement, dt("2025-09-10 07:00:00")).unwrap();
+        let mut second = first.clone();
+        second.observed_at = dt("2025-09-10 07:20:00");
+        assert!(first.same_forecast(&second));
+
+        second.current = Some(dt("2025-09-10 08:25:00"));
+        assert!(!first.same_forecast(&second));
+    }
+}
@@ -1,10 +1,11 @@
 use chrono::{NaiveDate};
-use iris::{self, dto::get_first_stop_departure_from_stop_id};
+use iris::{self, dto::StopId};
 
 
 #[derive(Debug, Clone)]
 pub struct Train {
-    pub id: String, // Custom ID: format: number-date
+    pub id: String, // Journey key: format: tripId-date
+    pub trip_id: String,
     pub operator: Option<String>,
     pub category: String,
     pub number: String,
@@ -21,23 +22,24 @@ pub enum TrainBuildError {
     MissingNumber,
     #[error("missing category")]
     MissingCategory,
-    #[error("invalid stop date {0}")]
-    InvalidStopDate(String),
+    #[error("invalid stop id {0}")]
+    InvalidStopId(String),
 }
 
 impl Train {
-    pub fn new_id(number: &str, date: &NaiveDate) -> String {
-        format!("{}-{}", number, date.format("%y%m%d"))
+    /// Journey key from the IRIS trip id and the date of the first departure.
+    /// Unlike the train number, trip ids do not collide between operators.
+    pub fn new_id(trip_id: &str, date: &NaiveDate) -> String {
+        format!("{}-{}", trip_id, date.format("%y%m%d"))
     }
     pub fn from_stop(stop: &iris::dto::Stop) -> Result<Self, TrainBuildError> {
         let tl = stop.tl.as_ref().ok_or(TrainBuildError::MissingTL)?;
         let number = tl.number.as_ref().ok_or(TrainBuildError::MissingNumber)?;
 
-        let date = get_first_stop_departure_from_stop_id(stop)
-            .ok_or(TrainBuildError::InvalidStopDate(stop.id.clone()))?
-            .date();
+        let stop_id = StopId::parse(&stop.id).ok_or(TrainBuildError::InvalidStopId(stop.id.clone()))?;
+        let date = stop_id.first_departure.date();
 
-        let id = Self::new_id(number, &date);
+        let id = Self::new_id(&stop_id.trip_id, &date);
 
         let arr = &stop.arrival;
         let dep = &stop.departure;
@@ -53,6 +55,7 @@ impl Train {
         Ok(
             Train {
                 id,
+                trip_id: stop_id.trip_id,
                 number: number.clone(),
                 category: tl.category.as_deref().ok_or(TrainBuildError::MissingCategory)?.to_owned(),
                 line,
@@ -86,15 +89,7 @@ mod tests {
             arrival: Some(IrisMovement {
                 planned: Some(planned),
                 current: None,
-                platform: None,
-                line: None,
-                hi: None,
-                ppth: None,
-                cpth: None,
-                cs: None,
-                clt: None,
-                wings: None,
-                msgs: Vec::new(),
+                ..Default::default()
             }),
             departure: None,
         }
@@ -106,7 +101,8 @@ mod tests {
 
         let train = Train::from_stop(&stop).expect("expected train to build");
 
-        assert_eq!("123-250910", train.id);
+        assert_eq!("test-stop-250910", train.id);
+        assert_eq!("test-stop", train.trip_id);
         assert_eq!(Some("DB".to_string()), train.operator);
         assert_eq!("ICE", train.category);
         assert_eq!("123", train.number);
@@ -133,4 +129,29 @@ mod tests {
         let err = Train::from_stop(&stop_missing_category).unwrap_err();
         assert!(matches!(err, TrainBuildError::MissingCategory));
     }
+
+    #[test]
+    fn train_from_stop_distinguishes_operators_with_same_number() {
+        let first = base_stop();
+        let mut second = base_stop();
+        second.id = "-42-2509100800-3".to_string();
+        second.tl.as_mut().unwrap().operator = Some("ME".to_string());
+
+        let first = Train::from_stop(&first).unwrap();
+        let second = Train::from_stop(&second).unwrap();
+
+        assert_eq!(first.number, second.number);
+        assert_eq!(first.date, second.date);
+        assert_ne!(first.id, second.id);
+        assert_eq!("-42-250910", second.id);
+    }
+
+    #[test]
+    fn train_from_stop_requires_valid_stop_id() {
+        let mut stop = base_stop();
+        stop.id = "invalid".to_string();
+
+        let err = Train::from_stop(&stop).unwrap_err();
+        assert!(matches!(err, TrainBuildError::InvalidStopId(_)));
+    }
 }
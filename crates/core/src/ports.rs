@@ -1,6 +1,6 @@
-use chrono::NaiveDate;
+use chrono::{NaiveDate, NaiveDateTime};
 
-use crate::model::{Message, Station, StatusCode, Stop, StopUpdate, StopWithStation, Train};
+use crate::model::{Message, PlatformUsage, Station, StationGroup, StationPlatforms, StatusCode, Stop, StopObservation, StopUpdate, StopWithStation, Train};
 
 #[derive(thiserror::Error, Debug)]
 pub enum PortError {
@@ -10,9 +10,9 @@ pub enum PortError {
     // Conflict,
     #[error("Invalid input")]
     InvalidInput,
-    #[error("Connection error")]
-    MalformedData,
     #[error("Malformed data")]
+    MalformedData,
+    #[error("Connection error")]
     Connection,
     #[error("Database error")]
     Database,
@@ -20,6 +20,35 @@ pub enum PortError {
     Custom(#[from] Box<dyn std::error::Error>),
 }
 
+impl PortError {
+    /// Only a lost connection may succeed on retry.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, PortError::Connection)
+    }
+}
+
+/// Outcome of an upsert. Values with the same id are only written once, the last one wins.
+#[derive(Debug, Clone)]
+pub struct Upserted<T> {
+    pub inserted: Vec<T>,
+    pub updated: Vec<T>,
+    /// Values that matched the stored ones, as given. Nothing was written for them.
+    pub unchanged: Vec<T>,
+}
+
+impl<T> Default for Upserted<T> {
+    fn default() -> Self {
+        Upserted { inserted: Vec::new(), updated: Vec::new(), unchanged: Vec::new() }
+    }
+}
+
+impl<T> Upserted<T> {
+    /// Inserted followed by updated values.
+    pub fn into_written(self) -> Vec<T> {
+        self.inserted.into_iter().chain(self.updated).collect()
+    }
+}
+
 pub trait Port<T, ID> {
     fn persist(&self, value: &T) -> Result<T, PortError>;
     fn persist_all(&self, values: &[T]) -> Result<Vec<T>, PortError>;
@@ -29,12 +58,30 @@ pub trait Port<T, ID> {
 
 pub trait StationPort: Port<Station, i32> + Send + Sync {
     fn get_by_ds100(&self, ds100: &str) -> Result<Station, PortError>;
+    /// At most `limit` stations found by EVA, DS100 or an accent-tolerant name search,
+    /// best matches first, see [`Station::search_rank`].
+    fn search(&self, query: &str, limit: usize) -> Result<Vec<Station>, PortError>;
+    /// Runs the station inserts of an SQL file, returns the stations that were not stored before.
     fn import_from_sql(&self, path: &str) -> Result<Vec<Station>, PortError>;
+    /// Replaces the members of each group's station, which must exist. Returns the persisted groups.
+    fn persist_groups(&self, groups: &[StationGroup]) -> Result<Vec<StationGroup>, PortError>;
+    /// `station` followed by the known stations grouped with it, listed as a member by
+    /// `station` or listing `station` as a member.
+    fn get_group(&self, station: &Station) -> Result<Vec<Station>, PortError>;
+    /// Replaces the platforms of each station, which must exist. Returns the persisted platforms.
+    fn persist_platforms(&self, platforms: &[StationPlatforms]) -> Result<Vec<StationPlatforms>, PortError>;
+    /// Persisted platforms of `station`, empty if none are known.
+    fn get_platforms(&self, station: &Station) -> Result<StationPlatforms, PortError>;
 }
 
 pub trait TrainPort: Port<Train, String> + Send + Sync {
-    fn get_by_station_and_date(&self, station: &Station, date: &NaiveDate) -> Result<Vec<Train>, PortError>;
+    /// Trains on `date` stopping at any of `stations`, each once.
+    fn get_by_stations_and_date(&self, stations: &[Station], date: &NaiveDate) -> Result<Vec<Train>, PortError>;
     fn get_by_date(&self, date: &NaiveDate) -> Result<Vec<Train>, PortError>;
+    /// Trains running with `number` on `date`, more than one if operators share the number.
+    fn get_by_number_and_date(&self, number: &str, date: &NaiveDate) -> Result<Vec<Train>, PortError>;
+    /// Inserts new trains and updates operator, category, number and line of known ones.
+    fn upsert_all(&self, trains: &[Train]) -> Result<Upserted<Train>, PortError>;
 }
 
 
@@ -42,17 +89,96 @@ pub trait StopPort: Port<Stop, String> + Send + Sync {
     fn get_for_date(&self, date: &NaiveDate) -> Result<Vec<Stop>, PortError>;
     fn get_for_train(&self, train_id: &str) -> Result<Vec<Stop>, PortError>;
     fn get_for_train_with_station(&self, train_id: &str) -> Result<Vec<StopWithStation>, PortError>;
+    fn get_by_ids(&self, ids: &[String]) -> Result<Vec<Stop>, PortError>;
 
-    fn get_by_station_and_date(&self, station: &Station, date: &NaiveDate) -> Result<Vec<Stop>, PortError>;
+    /// Stops at any of `stations`, e.g. a [`StationPort::get_group`], of trains running on `date`.
+    fn get_by_stations_and_date(&self, stations: &[Station], date: &NaiveDate) -> Result<Vec<Stop>, PortError>;
+    /// Stops at `stations` on `date` with a cancelled arrival or departure.
+    fn get_cancelled_by_stations_and_date(&self, stations: &[Station], date: &NaiveDate) -> Result<Vec<Stop>, PortError>;
+    /// Stops at `stations` on `date` with a changed arrival or departure platform.
+    fn get_platform_changes_by_stations_and_date(&self, stations: &[Station], date: &NaiveDate) -> Result<Vec<Stop>, PortError>;
+    /// Planned platforms of all stored stops at `station` with the number of arrivals and
+    /// departures at them and of those moved to another platform, see [`PlatformUsage::from_counts`].
+    fn get_platform_usage(&self, station: &Station) -> Result<Vec<PlatformUsage>, PortError>;
 
+    /// Inserts new stops and updates the planned fields of known ones.
+    /// Current-time fields written by the change import are kept.
+    fn upsert_all(&self, stops: &[Stop]) -> Result<Upserted<Stop>, PortError>;
     fn update(&self, update: &StopUpdate) -> Result<Stop, PortError>;
     fn update_many(&self, updates: &[StopUpdate]) -> Result<Vec<Stop>, PortError>;
 }
 
+pub trait StopObservationPort: Send + Sync {
+    /// Persist observations that differ from the latest known one of their movement.
+    /// Returns the observations that were recorded.
+    fn record_all(&self, observations: &[StopObservation]) -> Result<Vec<StopObservation>, PortError>;
+    /// Timeline of a stop, oldest first.
+    fn get_for_stop(&self, stop_id: &str) -> Result<Vec<StopObservation>, PortError>;
+    /// Timeline of all stops of a train, oldest first.
+    fn get_for_train(&self, train_id: &str) -> Result<Vec<StopObservation>, PortError>;
+}
+
 
 pub trait MessagePort: Port<Message, String> + Send + Sync {
     fn get_by_date_and_code(&self, date: &NaiveDate, code: i32) -> Result<Vec<Message>, PortError>;
     fn get_by_train_id(&self, train_id: &str) -> Result<Vec<Message>, PortError>;
+    /// Station-wide messages, i.e. messages of the station that are not tied to a train.
+    fn get_station_messages(&self, station_id: i32) -> Result<Vec<Message>, PortError>;
+    /// Messages attached to any of the stops, see [`Message::stops`].
+    fn get_by_stop_ids(&self, stop_ids: &[String]) -> Result<Vec<Message>, PortError>;
+    /// Messages in effect at `at` (local time), see [`Message::is_active_at`].
+    fn get_active_at(&self, at: &NaiveDateTime) -> Result<Vec<Message>, PortError>;
+    /// Inserts new messages and refreshes known ones with their station and stop links.
+    /// Known messages always count as updated, their `last_seen` moves on.
+    fn upsert_all(&self, messages: &[Message]) -> Result<Upserted<Message>, PortError>;
+    /// Marks messages of a station whose id is not in `seen_ids` as no longer listed by it and
+    /// revokes the still relevant ones that no other station lists either.
+    ///
+    /// Relevant are messages that are not yet revoked, whose `valid_to` has not passed and
+    /// that are station-wide or belong to a train that still stops at the station after `now`.
+    /// Returns the revoked messages.
+    fn revoke_missing(&self, station_id: i32, seen_ids: &[String], now: &NaiveDateTime) -> Result<Vec<Message>, PortError>;
 }
 
 pub trait StatusCodePort: Port<StatusCode, i16> + Send + Sync {}
+
+/// Revocation of messages missing from an `fchg` document, see [`MessagePort::revoke_missing`].
+#[derive(Debug, Clone)]
+pub struct MessageRevocation {
+    pub station_id: i32,
+    pub seen_ids: Vec<String>,
+    pub now: NaiveDateTime,
+}
+
+/// All writes of one station import, applied in this order by [`UnitOfWorkPort::commit`].
+#[derive(Debug, Clone, Default)]
+pub struct UnitOfWork {
+    pub trains: Vec<Train>,
+    pub stops: Vec<Stop>,
+    pub stop_updates: Vec<StopUpdate>,
+    pub messages: Vec<Message>,
+    pub observations: Vec<StopObservation>,
+    pub revocation: Option<MessageRevocation>,
+}
+
+/// What [`UnitOfWorkPort::commit`] wrote, with the same semantics as the single port methods.
+#[derive(Debug, Clone, Default)]
+pub struct UnitOfWorkResult {
+    /// See [`TrainPort::upsert_all`].
+    pub trains: Upserted<Train>,
+    /// See [`StopPort::upsert_all`].
+    pub stops: Upserted<Stop>,
+    /// See [`StopPort::update_many`].
+    pub updated_stops: Vec<Stop>,
+    /// See [`MessagePort::upsert_all`].
+    pub messages: Upserted<Message>,
+    /// See [`StopObservationPort::record_all`].
+    pub observations: Vec<StopObservation>,
+    /// See [`MessagePort::revoke_missing`].
+    pub revoked_messages: Vec<Message>,
+}
+
+pub trait UnitOfWorkPort: Send + Sync {
+    /// Applies all writes of `work` atomically. On error nothing is written.
+    fn commit(&self, work: &UnitOfWork) -> Result<UnitOfWorkResult, PortError>;
+}
@@ -0,0 +1,228 @@
+use std::{fmt, ops::AddAssign, time::Duration};
+
+use chrono::{NaiveDate, NaiveDateTime};
+use iris::fetch::IrisClientStats;
+
+use crate::{ingest::ChangesSyncMode, model::Station};
+
+/// What happened to one entity type during an import.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EntityCounts {
+    /// Built from IRIS (or the configured source).
+    pub fetched: usize,
+    /// Could not be built from the source data and were skipped.
+    pub build_errors: usize,
+    pub inserted: usize,
+    pub updated: usize,
+    /// Already stored without changes.
+    pub unchanged: usize,
+}
+
+impl AddAssign for EntityCounts {
+    fn add_assign(&mut self, other: Self) {
+        self.fetched += other.fetched;
+        self.build_errors += other.build_errors;
+        self.inserted += other.inserted;
+        self.updated += other.updated;
+        self.unchanged += other.unchanged;
+    }
+}
+
+impl fmt::Display for EntityCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} fetched, {} inserted, {} updated, {} unchanged, {} build errors",
+            self.fetched, self.inserted, self.updated, self.unchanged, self.build_errors
+        )
+    }
+}
+
+/// Result of one import entry point, usually for a single station.
+#[derive(Debug, Clone, Default)]
+pub struct ImportReport {
+    /// EVA of the imported station, `None` for station and status code imports.
+    pub station_id: Option<i32>,
+    pub ds100: Option<String>,
+    /// How changes were synced, `None` if no changes were fetched.
+    pub mode: Option<ChangesSyncMode>,
+    /// When the changes were fetched (local time), `None` if no changes were fetched.
+    pub fetched_at: Option<NaiveDateTime>,
+    /// Hour windows of the planned timetable that were fetched.
+    pub windows: Vec<(NaiveDate, u16)>,
+    /// Fetched windows without any stops.
+    pub empty_windows: Vec<(NaiveDate, u16)>,
+    pub stations: EntityCounts,
+    pub status_codes: EntityCounts,
+    pub trains: EntityCounts,
+    pub stops: EntityCounts,
+    pub messages: EntityCounts,
+    pub observations: EntityCounts,
+    /// Messages that disappeared from `fchg`.
+    pub revoked_messages: usize,
+    /// Time spent waiting for IRIS.
+    pub fetch_duration: Duration,
+    /// Time spent persisting.
+    pub persist_duration: Duration,
+    pub duration: Duration,
+}
+
+impl ImportReport {
+    pub fn for_station(station: &Station) -> Self {
+        ImportReport {
+            station_id: Some(station.id),
+            ds100: Some(station.ds100.clone()),
+            ..Default::default()
+        }
+    }
+
+    /// Adds counts and durations of `other`, keeping station and mode of `self`.
+    pub fn merge(&mut self, other: &ImportReport) {
+        self.windows.extend(other.windows.iter().copied());
+        self.empty_windows.extend(other.empty_windows.iter().copied());
+        self.stations += other.stations;
+        self.status_codes += other.status_codes;
+        self.trains += other.trains;
+        self.stops += other.stops;
+        self.messages += other.messages;
+        self.observations += other.observations;
+        self.revoked_messages += other.revoked_messages;
+        self.fetch_duration += other.fetch_duration;
+        self.persist_duration += other.persist_duration;
+        self.duration += other.duration;
+    }
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ds100) = &self.ds100 {
+            write!(f, "{}: ", ds100)?;
+        }
+        write!(
+            f,
+            "{} windows ({} empty); trains: {}; stops: {}; messages: {} ({} revoked); observations: {}",
+            self.windows.len(),
+            self.empty_windows.len(),
+            self.trains,
+            self.stops,
+            self.messages,
+            self.revoked_messages,
+            self.observations,
+        )?;
+        if self.stations.fetched > 0 {
+            write!(f, "; stations: {}", self.stations)?;
+        }
+        if self.status_codes.fetched > 0 {
+            write!(f, "; status codes: {}", self.status_codes)?;
+        }
+        write!(
+            f,
+            " in {:.1?} (fetch {:.1?}, persist {:.1?})",
+            self.duration, self.fetch_duration, self.persist_duration
+        )
+    }
+}
+
+/// A station that could not be imported during a run.
+#[derive(Debug, Clone)]
+pub struct StationFailure {
+    /// `None` if the station could not be looked up.
+    pub station_id: Option<i32>,
+    pub ds100: String,
+    pub error: String,
+}
+
+/// Result of importing many stations.
+#[derive(Debug, Clone)]
+pub struct RunReport {
+    pub started_at: NaiveDateTime,
+    pub stations: Vec<ImportReport>,
+    pub failures: Vec<StationFailure>,
+    /// The run stopped early because the IRIS circuit breaker opened.
+    pub aborted: bool,
+    /// Retries, throttling and circuit breaker decisions of the IRIS client during the run,
+    /// `None` if the client keeps no statistics.
+    pub iris: Option<IrisClientStats>,
+    pub duration: Duration,
+}
+
+impl RunReport {
+    pub fn new(started_at: NaiveDateTime) -> Self {
+        RunReport {
+            started_at,
+            stations: Vec::new(),
+            failures: Vec::new(),
+            aborted: false,
+            iris: None,
+            duration: Duration::ZERO,
+        }
+    }
+
+    pub fn add_failure(&mut self, station: &Station, error: &dyn std::error::Error) {
+        self.failures.push(StationFailure {
+            station_id: Some(station.id),
+            ds100: station.ds100.clone(),
+            error: error.to_string(),
+        });
+    }
+
+    /// Sum of all station reports.
+    pub fn totals(&self) -> ImportReport {
+        let mut totals = ImportReport::default();
+        for report in self.stations.iter() {
+            totals.merge(report);
+        }
+        totals.duration = self.duration;
+        totals
+    }
+}
+
+impl fmt::Display for RunReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} stations imported, {} failed{}; {}",
+            self.stations.len(),
+            self.failures.len(),
+            if self.aborted { " (aborted)" } else { "" },
+            self.totals()
+        )?;
+        if let Some(iris) = &self.iris {
+            write!(f, "; IRIS: {}", iris)?;
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(ds100: &str, trains: usize, windows: u16) -> ImportReport {
+        ImportReport {
+            ds100: Some(ds100.to_string()),
+            windows: (0..windows).map(|h| (NaiveDate::from_ymd_opt(2025, 9, 10).unwrap(), h)).collect(),
+            trains: EntityCounts { fetched: trains, inserted: trains, ..Default::default() },
+            fetch_duration: Duration::from_millis(100),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn run_report_totals_sum_station_reports() {
+        let started_at = NaiveDate::from_ymd_opt(2025, 9, 10).unwrap().and_hms_opt(8, 0, 0).unwrap();
+        let mut run = RunReport::new(started_at);
+        run.stations.push(report("AH", 3, 2));
+        run.stations.push(report("FF", 5, 3));
+        run.duration = Duration::from_secs(2);
+
+        let totals = run.totals();
+
+        assert_eq!(EntityCounts { fetched: 8, inserted: 8, ..Default::default() }, totals.trains);
+        assert_eq!(5, totals.windows.len());
+        assert_eq!(Duration::from_millis(200), totals.fetch_duration);
+        assert_eq!(Duration::from_secs(2), totals.duration);
+        assert_eq!(None, totals.ds100);
+    }
+}
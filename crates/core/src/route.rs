@@ -0,0 +1,268 @@
+use std::collections::HashSet;
+
+use iris::dto::StopId;
+
+use crate::model::{HasStopGetter, Movement, Stop, StopWithStation};
+
+/// Whether a route entry is one of our polled stops or only known from the paths of other stops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteSource {
+    Observed,
+    Inferred,
+}
+
+#[derive(Debug, Clone)]
+pub struct RouteEntry {
+    /// Station name as used in IRIS paths.
+    pub name: String,
+    pub source: RouteSource,
+    /// The polled stop at this station, `None` for inferred entries.
+    pub stop: Option<StopWithStation>,
+    /// Only part of the changed path (`cpth`).
+    pub added: bool,
+    /// Dropped from the changed path (`cpth`) or cancelled.
+    pub skipped: bool,
+}
+
+/// Sorts stops along the journey: by the sequence number of their IRIS stop id,
+/// falling back to the planned arrival or departure time.
+pub fn sort_stops<S: HasStopGetter>(stops: &mut [S]) {
+    stops.sort_by_key(|s| {
+        let stop = s.get_stop();
+        let seq = StopId::parse(&stop.id).map(|id| id.seq);
+        let planned = stop.arrival.as_ref().and_then(|a| a.planned)
+            .or(stop.departure.as_ref().and_then(|d| d.planned));
+        (seq, planned)
+    });
+}
+
+/// Merges the paths of all known stops of a train into one ordered route,
+/// including stations we do not poll.
+///
+/// The planned route is built from `ppth`, the changed route from `cpth` (falling back to `ppth`).
+/// Stations only in the changed route are flagged as added, stations missing from it as skipped.
+pub fn build_route(stops: &[StopWithStation]) -> Vec<RouteEntry> {
+    let mut sorted: Vec<&StopWithStation> = stops.iter().collect();
+    sort_stops(&mut sorted);
+
+    let planned = merge_paths(&sorted, |m| m.planned_path.as_ref());
+
+    let not_cancelled: Vec<&StopWithStation> = sorted
+        .iter()
+        .copied()
+        .filter(|s| !is_cancelled(&s.stop))
+        .collect();
+    let changed = merge_paths(&not_cancelled, |m| m.changed_path.as_ref().or(m.planned_path.as_ref()));
+
+    merge_planned_and_changed(&planned, &changed)
+        .into_iter()
+        .map(|(name, added, skipped)| {
+            let stop = sorted.iter().find(|s| s.station.name == name).map(|s| (*s).clone());
+            RouteEntry {
+                source: if stop.is_some() { RouteSource::Observed } else { RouteSource::Inferred },
+                skipped: skipped || stop.as_ref().is_some_and(|s| is_cancelled(&s.stop)),
+                name,
+                stop,
+                added,
+            }
+        })
+        .collect()
+}
+
+/// All present movements are cancelled.
+fn is_cancelled(stop: &Stop) -> bool {
+    let movements: Vec<&Movement> = stop.arrival.iter().chain(stop.departure.iter()).collect();
+    !movements.is_empty() && movements.iter().all(|m| m.is_cancelled())
+}
+
+/// Stations in `path` after `name`, `None` if `name` is not part of it.
+fn after_name(path: &[String], name: &str) -> Option<Vec<String>> {
+    path.iter().rposition(|n| n == name).map(|pos| path[pos + 1..].to_vec())
+}
+
+/// Stations in `path` before `name`, `None` if `name` is not part of it.
+fn until_name(path: &[String], name: &str) -> Option<Vec<String>> {
+    path.iter().position(|n| n == name).map(|pos| path[..pos].to_vec())
+}
+
+/// Uses the polled stops as anchors and fills the gaps in between with their paths.
+fn merge_paths(stops: &[&StopWithStation], path_of: fn(&Movement) -> Option<&Vec<String>>) -> Vec<String> {
+    let before = |s: &StopWithStation| s.stop.arrival.as_ref().and_then(path_of).cloned();
+    let after = |s: &StopWithStation| s.stop.departure.as_ref().and_then(path_of).cloned();
+
+    let mut route: Vec<String> = Vec::new();
+    let mut prev: Option<&StopWithStation> = None;
+
+    for stop in stops {
+        let name = &stop.station.name;
+        if route.contains(name) {
+            continue;
+        }
+
+        let between = match prev {
+            None => before(stop).unwrap_or_default(),
+            Some(prev) => before(stop)
+                .and_then(|b| after_name(&b, &prev.station.name))
+                .or_else(|| after(prev).and_then(|a| until_name(&a, name)))
+                .unwrap_or_default(),
+        };
+
+        for station in between {
+            if !route.contains(&station) {
+                route.push(station);
+            }
+        }
+        route.push(name.clone());
+        prev = Some(stop);
+    }
+
+    if let Some(prev) = prev {
+        for station in after(prev).unwrap_or_default() {
+            if !route.contains(&station) {
+                route.push(station);
+            }
+        }
+    }
+
+    route
+}
+
+/// Interleaves both routes, returns `(name, added, skipped)`.
+fn merge_planned_and_changed(planned: &[String], changed: &[String]) -> Vec<(String, bool, bool)> {
+    let planned_set: HashSet<&String> = planned.iter().collect();
+    let mut merged = Vec::with_capacity(planned.len().max(changed.len()));
+    let mut j = 0;
+
+    for station in planned {
+        match changed[j..].iter().position(|c| c == station) {
+            Some(k) => {
+                for added in changed[j..j + k].iter().filter(|c| !planned_set.contains(c)) {
+                    merged.push((added.clone(), true, false));
+                }
+                merged.push((station.clone(), false, false));
+                j += k + 1;
+            }
+            None => merged.push((station.clone(), false, true)),
+        }
+    }
+
+    for added in changed[j..].iter().filter(|c| !planned_set.contains(c)) {
+        merged.push((added.clone(), true, false));
+    }
+
+    merged
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDateTime;
+    use crate::model::{EventStatus, Station};
+
+    fn path(names: &[&str]) -> Option<Vec<String>> {
+        Some(names.iter().map(|n| n.to_string()).collect())
+    }
+
+    fn movement(planned_path: Option<Vec<String>>, changed_path: Option<Vec<String>>) -> Movement {
+        Movement {
+            planned: Some(NaiveDateTime::parse_from_str("2025-09-10 08:00:00", "%Y-%m-%d %H:%M:%S").unwrap()),
+            planned_path,
+            changed_path,
+            ..Default::default()
+        }
+    }
+
+    fn stop(seq: u32, station: &str, arrival: Option<Movement>, departure: Option<Movement>) -> StopWithStation {
+        StopWithStation {
+            stop: Stop {
+                id: format!("trip-2509100700-{}", seq),
+                train_id: "trip-250910".to_string(),
+                station_id: seq as i32,
+                arrival,
+                departure,
+            },
+            station: Station {
+                id: seq as i32,
+                lat: None,
+                lon: None,
+                name: station.to_string(),
+                ds100: station.to_string(),
+            },
+        }
+    }
+
+    fn names(route: &[RouteEntry]) -> Vec<&str> {
+        route.iter().map(|e| e.name.as_str()).collect()
+    }
+
+    #[test]
+    fn build_route_orders_stops_and_infers_unpolled_stations() {
+        let hamburg = stop(
+            3,
+            "Hamburg Hbf",
+            Some(movement(path(&["Kiel Hbf", "Neumünster"]), None)),
+            Some(movement(path(&["Bremen Hbf", "Osnabrück Hbf", "Münster Hbf"]), None)),
+        );
+        let bremen = stop(
+            4,
+            "Bremen Hbf",
+            Some(movement(path(&["Kiel Hbf", "Neumünster", "Hamburg Hbf"]), None)),
+            Some(movement(path(&["Osnabrück Hbf", "Münster Hbf"]), None)),
+        );
+
+        let route = build_route(&[bremen, hamburg]);
+
+        assert_eq!(
+            vec!["Kiel Hbf", "Neumünster", "Hamburg Hbf", "Bremen Hbf", "Osnabrück Hbf", "Münster Hbf"],
+            names(&route)
+        );
+        assert_eq!(RouteSource::Inferred, route[0].source);
+        assert_eq!(RouteSource::Observed, route[2].source);
+        assert_eq!(Some("trip-2509100700-3"), route[2].stop.as_ref().map(|s| s.stop.id.as_str()));
+        assert_eq!(RouteSource::Observed, route[3].source);
+        assert!(route.iter().all(|e| !e.added && !e.skipped));
+    }
+
+    #[test]
+    fn build_route_flags_added_and_skipped_stations() {
+        let hamburg = stop(
+            1,
+            "Hamburg Hbf",
+            None,
+            Some(movement(
+                path(&["Hamburg-Harburg", "Buchholz", "Bremen Hbf"]),
+                path(&["Hamburg-Harburg", "Lüneburg", "Bremen Hbf"]),
+            )),
+        );
+
+        let route = build_route(&[hamburg]);
+
+        assert_eq!(
+            vec!["Hamburg Hbf", "Hamburg-Harburg", "Buchholz", "Lüneburg", "Bremen Hbf"],
+            names(&route)
+        );
+        assert!(route[2].skipped);
+        assert!(route[3].added);
+        assert!(!route[4].added && !route[4].skipped);
+    }
+
+    #[test]
+    fn build_route_marks_cancelled_stops_as_skipped() {
+        let hamburg = stop(
+            1,
+            "Hamburg Hbf",
+            None,
+            Some(movement(path(&["Bremen Hbf", "Osnabrück Hbf"]), None)),
+        );
+        let mut bremen_arrival = movement(path(&["Hamburg Hbf"]), None);
+        bremen_arrival.changed_status = Some(EventStatus::Cancelled);
+        let bremen = stop(2, "Bremen Hbf", Some(bremen_arrival), None);
+
+        let route = build_route(&[hamburg, bremen]);
+
+        assert_eq!(vec!["Hamburg Hbf", "Bremen Hbf", "Osnabrück Hbf"], names(&route));
+        assert!(route[1].skipped);
+        assert_eq!(RouteSource::Observed, route[1].source);
+    }
+}
@@ -1,144 +1,386 @@
 use std::{
+    collections::{HashMap, VecDeque},
     env,
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        Arc, Mutex,
     },
     thread,
 };
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use chrono::{Utc, TimeDelta};
+use chrono::{NaiveDateTime, Utc, TimeDelta};
 use chrono_tz::Europe::Berlin;
+use iris::fetch::{IrisClient, IrisClientStats};
 
 use crate::{
+    filter::FilterPolicy,
     import::{
-        import_iris_changes, import_iris_changes_for_station_by_ds100, import_iris_data,
-        import_iris_data_for_station_by_ds100, import_station_data, import_status_codes,
+        import_iris_data, import_iris_data_for_station_by_ds100, import_iris_recent_changes,
+        import_iris_recent_changes_for_station_by_ds100, import_station_data, import_station_platforms, import_status_codes,
+        ImportError,
     },
-    ports::{MessagePort, StationPort, StatusCodePort, StopPort, TrainPort},
+    ingest::RECENT_CHANGES_WINDOW,
+    ports::{StationPort, StatusCodePort, StopPort, UnitOfWorkPort},
+    report::{ImportReport, RunReport, StationFailure},
+    utils::get_secs_env,
 };
 
+/// Below [`RECENT_CHANGES_WINDOW`], so consecutive polls can use `rchg` deltas.
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(60);
+const DEFAULT_FULL_CHANGES_INTERVAL: Duration = Duration::from_secs(20 * 60);
+const FULL_IMPORT_INTERVAL: Duration = Duration::from_secs(8 * 60 * 60);
+/// Number of run reports kept, a bit more than an hour with the default poll interval.
+const MAX_KEPT_REPORTS: usize = 72;
+
+/// Timing of the import loop started by [`ImportService::start`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PollSettings {
+    /// Pause between two polls.
+    pub poll_interval: Duration,
+    /// How often all changes (`fchg`) are synced, recent changes (`rchg`) are used in between.
+    pub full_changes_interval: Duration,
+    /// How often timetables are imported.
+    pub full_import_interval: Duration,
+    /// Oldest last poll that still allows `rchg` deltas.
+    pub recent_changes_max_age: TimeDelta,
+}
+
+impl Default for PollSettings {
+    fn default() -> Self {
+        PollSettings {
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            full_changes_interval: DEFAULT_FULL_CHANGES_INTERVAL,
+            full_import_interval: FULL_IMPORT_INTERVAL,
+            recent_changes_max_age: RECENT_CHANGES_WINDOW,
+        }
+    }
+}
+
+impl PollSettings {
+    /// Reads `CHANGES_POLL_INTERVAL_SECS`, `FULL_CHANGES_INTERVAL_SECS` and `RECENT_CHANGES_MAX_AGE_SECS`,
+    /// falling back to the defaults for unset or invalid values.
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        PollSettings {
+            poll_interval: get_secs_env("CHANGES_POLL_INTERVAL_SECS", default.poll_interval),
+            full_changes_interval: get_secs_env("FULL_CHANGES_INTERVAL_SECS", default.full_changes_interval),
+            full_import_interval: default.full_import_interval,
+            recent_changes_max_age: TimeDelta::from_std(get_secs_env(
+                "RECENT_CHANGES_MAX_AGE_SECS",
+                default.recent_changes_max_age.to_std().unwrap(),
+            ))
+            .unwrap_or(default.recent_changes_max_age),
+        }
+    }
+
+    /// Whether two consecutive polls are close enough for `rchg` deltas.
+    pub fn uses_recent_changes(&self) -> bool {
+        TimeDelta::from_std(self.poll_interval).is_ok_and(|interval| interval <= self.recent_changes_max_age)
+    }
+}
+
 /// Periodic importer orchestrating station discovery, timetables, and messages.
 pub struct ImportService {
+    pub iris_client: Arc<dyn IrisClient>,
     pub station_repo: Arc<dyn StationPort>,
-    pub message_repo: Arc<dyn MessagePort>,
-    pub train_repo: Arc<dyn TrainPort>,
     pub stop_repo: Arc<dyn StopPort>,
     pub status_code_repo: Arc<dyn StatusCodePort>,
+    /// Writes the data of one station import atomically.
+    pub unit_of_work: Arc<dyn UnitOfWorkPort>,
+    /// Decides which stations and trains are imported.
+    pub filter_policy: Arc<FilterPolicy>,
+
+    /// Reports of the most recent runs, oldest first.
+    reports: Arc<Mutex<VecDeque<RunReport>>>,
 
     /// Cooperative shutdown flag for the background loop.
     stop_ch: Arc<AtomicBool>,
 }
 
+/// Appends `report` to `reports`, dropping the oldest ones beyond [`MAX_KEPT_REPORTS`].
+fn keep_report(reports: &Mutex<VecDeque<RunReport>>, report: RunReport) {
+    info!("Import run: {}", report);
+    let mut reports = reports.lock().unwrap_or_else(|poison| poison.into_inner());
+    if reports.len() >= MAX_KEPT_REPORTS {
+        reports.pop_front();
+    }
+    reports.push_back(report);
+}
+
+/// Wraps the result of a single station import into a run report.
+fn single_station_run(
+    started_at: NaiveDateTime,
+    ds100: &str,
+    result: Result<ImportReport, ImportError>,
+    started: Instant,
+) -> RunReport {
+    let mut run = RunReport::new(started_at);
+    match result {
+        Ok(report) => run.stations.push(report),
+        Err(err) => {
+            error!("Error importing iris data for {}: {}", ds100, err);
+            run.failures.push(StationFailure { station_id: None, ds100: ds100.to_string(), error: err.to_string() });
+        }
+    }
+    run.duration = started.elapsed();
+    run
+}
+
 impl ImportService {
-    /// Create a new service. All repos must be `Send + Sync + 'static`.
+    /// Create a new service. The client and all repos must be `Send + Sync + 'static`.
     pub fn new(
+        iris_client: Arc<dyn IrisClient>,
         station_repo: Arc<dyn StationPort>,
-        message_repo: Arc<dyn MessagePort>,
-        train_repo: Arc<dyn TrainPort>,
         stop_repo: Arc<dyn StopPort>,
         status_code_repo: Arc<dyn StatusCodePort>,
+        unit_of_work: Arc<dyn UnitOfWorkPort>,
+        filter_policy: FilterPolicy,
     ) -> Self {
         Self {
+            iris_client,
             station_repo,
-            message_repo,
-            train_repo,
             stop_repo,
             status_code_repo,
+            unit_of_work,
+            filter_policy: Arc::new(filter_policy),
+            reports: Arc::new(Mutex::new(VecDeque::new())),
             stop_ch: Arc::new(AtomicBool::new(false)),
         }
     }
 
-    /// Start a detached 20-minute loop:
+    /// Start a detached loop, polling every `CHANGES_POLL_INTERVAL_SECS` (default 1 min):
     /// - One-off: `import_station_data` and `import_status_codes` (startup).
     /// - Every ~8 h: full timetable import (12 h on first run, then 8 h windows).
-    /// - Otherwise: messages-only import for the current local date.
+    /// - Once after the first timetable import: platforms and groups of stations without known
+    ///   platforms, one IRIS station lookup each.
+    /// - Otherwise: changes-only import. Recent changes (`rchg`) of a station are applied as deltas
+    ///   while its last successful fetch is younger than `RECENT_CHANGES_MAX_AGE_SECS`
+    ///   (default 2 min), all changes (`fchg`) are synced every `FULL_CHANGES_INTERVAL_SECS`
+    ///   (default 20 min) and whenever a station missed a poll.
     ///
     /// Errors are logged and do not stop the loop.
     pub fn start(&self) {
-        import_station_data(self.station_repo.as_ref()).unwrap(); // TODO: Make this daily.
-        import_status_codes(self.status_code_repo.as_ref()).unwrap();
+        let stations = import_station_data(self.station_repo.as_ref(), &self.filter_policy).unwrap(); // TODO: Make this daily.
+        info!("Station import: {}", stations);
+        let status_codes = import_status_codes(self.status_code_repo.as_ref()).unwrap();
+        info!("Status code import: {}", status_codes);
+
+        let settings = PollSettings::from_env();
+        if !settings.uses_recent_changes() {
+            warn!(
+                "Polling every {:?} is slower than the recent changes window of {}, only full changes will be synced",
+                settings.poll_interval, settings.recent_changes_max_age
+            );
+        }
 
         let stop_ch_clone = Arc::clone(&self.stop_ch);
-        let station_repo = Arc::clone(&self.station_repo);
-        let message_repo = Arc::clone(&self.message_repo);
-        let train_repo = Arc::clone(&self.train_repo);
-        let stop_repo = Arc::clone(&self.stop_repo);
+        let mut import_loop = self.import_loop(settings, env::var("SINGLE_STATION").ok());
 
         thread::spawn(move || {
-            // 3 iterations ≈ 1 hour (20 min sleeps). Threshold 8*3 = 24 iterations ≈ 8 h.
-            let mut loop_count = 0;
-            let mut first_run = true;
-            let single_station = env::var("SINGLE_STATION").ok();
-
             while !stop_ch_clone.load(Ordering::Relaxed) {
                 // Local wall time for IRIS calls/logging.
                 let now = Utc::now().with_timezone(&Berlin).naive_local();
+                let started = Instant::now();
 
-                if loop_count >= 8 * 3 || first_run {
-                    loop_count = 0;
-
-                    // First run: import 12 h from now. Afterwards: 8 h window shifted by 8 h.
-                    let (start, hours_in_advance) = if first_run {
-                        first_run = false;
-                        (now, 12)
-                    } else {
-                        (now + TimeDelta::hours(8), 8)
-                    };
-
-                    if let Some(ds100) = &single_station {
-                        if let Err(err) = import_iris_data_for_station_by_ds100(
-                            ds100,
-                            &start,
-                            hours_in_advance,
-                            message_repo.as_ref(),
-                            train_repo.as_ref(),
-                            stop_repo.as_ref(),
-                        ) {
-                            error!("Error importing iris data: {}", err);
-                        }
-                    } else if let Err(err) = import_iris_data(
-                        &start,
-                        hours_in_advance,
-                        station_repo.as_ref(),
-                        message_repo.as_ref(),
-                        train_repo.as_ref(),
-                        stop_repo.as_ref(),
-                    ) {
-                        error!("Error importing iris data: {}", err);
-                    }
-                } else {
-                    // Messages-only import for today.
-                    if let Some(ds100) = &single_station {
-                        if let Err(err) = import_iris_changes_for_station_by_ds100(
-                            ds100,
-                            &now.date(),
-                            message_repo.as_ref(),
-                            stop_repo.as_ref(),
-                        ) {
-                            error!("Error importing iris messages: {}", err);
-                        }
-                    } else if let Err(err) = import_iris_changes(
-                        &now.date(),
-                        station_repo.as_ref(),
-                        message_repo.as_ref(),
-                        stop_repo.as_ref(),
-                    ) {
-                        error!("Error importing iris messages: {}", err);
-                    }
-                }
-
-                loop_count += 1;
-                thread::sleep(Duration::from_secs(20 * 60));
+                import_loop.poll(&now);
+
+                // Keep the cadence, slow polls count towards the interval.
+                thread::sleep(settings.poll_interval.saturating_sub(started.elapsed()));
             }
 
             println!("Thread stopping gracefully.");
         });
     }
 
+    /// The steps of the loop run by [`ImportService::start`], one [`ImportLoop::poll`] at a time.
+    /// Imports only `single_station` if set, otherwise all persisted stations.
+    pub fn import_loop(&self, settings: PollSettings, single_station: Option<String>) -> ImportLoop {
+        ImportLoop {
+            iris_client: Arc::clone(&self.iris_client),
+            station_repo: Arc::clone(&self.station_repo),
+            stop_repo: Arc::clone(&self.stop_repo),
+            unit_of_work: Arc::clone(&self.unit_of_work),
+            filter_policy: Arc::clone(&self.filter_policy),
+            reports: Arc::clone(&self.reports),
+            settings,
+            single_station,
+            last_full_import: None,
+            last_full_changes: None,
+            platforms_imported: false,
+            last_polls: HashMap::new(),
+            single_station_last_poll: None,
+        }
+    }
+
+    /// Reports of the most recent runs, oldest first.
+    pub fn reports(&self) -> Vec<RunReport> {
+        self.reports.lock().unwrap_or_else(|poison| poison.into_inner()).iter().cloned().collect()
+    }
+
+    /// Report of the most recent run, if any has finished yet.
+    pub fn last_report(&self) -> Option<RunReport> {
+        self.reports.lock().unwrap_or_else(|poison| poison.into_inner()).back().cloned()
+    }
+
+    /// Request statistics of the IRIS client, if it keeps any.
+    pub fn iris_stats(&self) -> Option<IrisClientStats> {
+        self.iris_client.stats()
+    }
+
     /// Request cooperative shutdown (takes effect after the current sleep).
     pub fn stop(&self) {
         self.stop_ch.store(true, Ordering::Relaxed);
     }
 }
+
+/// State of the import loop between two polls, see [`ImportService::start`].
+pub struct ImportLoop {
+    iris_client: Arc<dyn IrisClient>,
+    station_repo: Arc<dyn StationPort>,
+    stop_repo: Arc<dyn StopPort>,
+    unit_of_work: Arc<dyn UnitOfWorkPort>,
+    filter_policy: Arc<FilterPolicy>,
+    reports: Arc<Mutex<VecDeque<RunReport>>>,
+    settings: PollSettings,
+    single_station: Option<String>,
+    last_full_import: Option<NaiveDateTime>,
+    last_full_changes: Option<NaiveDateTime>,
+    platforms_imported: bool,
+    /// Fetch time of the last successful changes sync per station id, a timetable import includes one.
+    last_polls: HashMap<i32, NaiveDateTime>,
+    single_station_last_poll: Option<NaiveDateTime>,
+}
+
+impl ImportLoop {
+    /// Runs one iteration of the loop at `now` and keeps its reports.
+    pub fn poll(&mut self, now: &NaiveDateTime) {
+        let full_import_due = self.last_full_import.is_none_or(|last| elapsed(&last, now) >= self.settings.full_import_interval);
+        let before = self.iris_client.stats();
+        let run = if full_import_due {
+            self.import_timetables(now)
+        } else {
+            if self.last_full_changes.is_none_or(|last| elapsed(&last, now) >= self.settings.full_changes_interval) {
+                // Forgetting the last polls falls back to a full `fchg` sync for every station.
+                self.last_polls.clear();
+                self.single_station_last_poll = None;
+                self.last_full_changes = Some(*now);
+            }
+            self.import_changes(now)
+        };
+
+        if let Some(mut run) = run {
+            run.iris = self.iris_stats_since(before);
+            keep_report(&self.reports, run);
+        }
+
+        if !self.platforms_imported {
+            self.platforms_imported = true;
+            self.import_platforms();
+        }
+    }
+
+    /// First run: import 12 h from now. Afterwards: 8 h window shifted by 8 h.
+    fn import_timetables(&mut self, now: &NaiveDateTime) -> Option<RunReport> {
+        let started = Instant::now();
+        let (start, hours_in_advance) = match self.last_full_import {
+            None => (*now, 12),
+            Some(_) => (*now + TimeDelta::hours(8), 8),
+        };
+        self.last_full_import = Some(*now);
+        self.last_full_changes = Some(*now);
+
+        let run = if let Some(ds100) = &self.single_station {
+            let result = import_iris_data_for_station_by_ds100(
+                ds100,
+                &start,
+                hours_in_advance,
+                self.iris_client.as_ref(),
+                &self.filter_policy,
+                self.unit_of_work.as_ref(),
+            );
+            if let Ok(report) = &result {
+                self.single_station_last_poll = report.fetched_at;
+            }
+            single_station_run(*now, ds100, result, started)
+        } else {
+            import_iris_data(
+                &start,
+                hours_in_advance,
+                self.iris_client.as_ref(),
+                &self.filter_policy,
+                self.station_repo.as_ref(),
+                self.unit_of_work.as_ref(),
+            )
+            .inspect_err(|err| error!("Error importing iris data: {}", err))
+            .ok()?
+        };
+
+        // The timetable import synced all changes (`fchg`) of every imported station.
+        for report in &run.stations {
+            if let (Some(station_id), Some(fetched_at)) = (report.station_id, report.fetched_at) {
+                self.last_polls.insert(station_id, fetched_at);
+            }
+        }
+        Some(run)
+    }
+
+    /// Changes-only import.
+    fn import_changes(&mut self, now: &NaiveDateTime) -> Option<RunReport> {
+        let started = Instant::now();
+        if let Some(ds100) = &self.single_station {
+            let result = import_iris_recent_changes_for_station_by_ds100(
+                ds100,
+                self.single_station_last_poll.as_ref(),
+                self.settings.recent_changes_max_age,
+                self.iris_client.as_ref(),
+                self.stop_repo.as_ref(),
+                self.unit_of_work.as_ref(),
+            );
+            if let Ok(report) = &result {
+                self.single_station_last_poll = report.fetched_at;
+            }
+            Some(single_station_run(*now, ds100, result, started))
+        } else {
+            import_iris_recent_changes(
+                now,
+                &mut self.last_polls,
+                self.settings.recent_changes_max_age,
+                self.iris_client.as_ref(),
+                self.station_repo.as_ref(),
+                self.stop_repo.as_ref(),
+                self.unit_of_work.as_ref(),
+            )
+            .inspect_err(|err| error!("Error importing iris messages: {}", err))
+            .ok()
+        }
+    }
+
+    /// Statistics of the IRIS client since `before` was taken.
+    fn iris_stats_since(&self, before: Option<IrisClientStats>) -> Option<IrisClientStats> {
+        Some(self.iris_client.stats()?.since(&before?))
+    }
+
+    fn import_platforms(&self) {
+        let before = self.iris_client.stats();
+        let stations = match &self.single_station {
+            Some(ds100) => self.station_repo.get_by_ds100(ds100).map(|s| vec![s]),
+            None => self.station_repo.get_all(),
+        };
+        match stations
+            .map_err(ImportError::from)
+            .and_then(|stations| import_station_platforms(&stations, self.iris_client.as_ref(), self.station_repo.as_ref()))
+        {
+            Ok(mut run) => {
+                run.iris = self.iris_stats_since(before);
+                keep_report(&self.reports, run)
+            }
+            Err(err) => error!("Error importing station platforms: {}", err),
+        }
+    }
+}
+
+/// Time between `since` and `now`, zero if the clock went backwards.
+fn elapsed(since: &NaiveDateTime, now: &NaiveDateTime) -> Duration {
+    (*now - *since).to_std().unwrap_or(Duration::ZERO)
+}
@@ -1,6 +1,7 @@
-use std::env;
+use std::{env, time};
 
-use chrono::{NaiveDateTime, Duration, Timelike};
+use chrono::{DateTime, NaiveDateTime, Duration, Timelike, Utc};
+use chrono_tz::Europe::Berlin;
 
 const HOUR_DURATION: Duration = Duration::hours(1);
 
@@ -38,6 +39,25 @@ pub fn get_bool_env(flag: &str) -> bool {
     flag
 }
 
+/// Current local time at the IRIS stations (Europe/Berlin).
+pub fn now_local() -> NaiveDateTime {
+    to_local(&Utc::now())
+}
+
+/// `time` as local time at the IRIS stations (Europe/Berlin).
+pub fn to_local(time: &DateTime<Utc>) -> NaiveDateTime {
+    time.with_timezone(&Berlin).naive_local()
+}
+
+/// Reads a number of seconds from `name`, `default` if unset or invalid.
+pub fn get_secs_env(name: &str, default: time::Duration) -> time::Duration {
+    env::var(name)
+        .ok()
+        .and_then(|v| v.parse::<u64>().inspect_err(|e| warn!("Invalid {}: {}", name, e)).ok())
+        .map(time::Duration::from_secs)
+        .unwrap_or(default)
+}
+
 
 #[cfg(test)]
 mod tests {
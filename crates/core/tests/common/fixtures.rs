@@ -0,0 +1,62 @@
+//! Entities around one ICE at Hamburg Hbf on 2025-09-10, shared with the web route tests.
+
+use chrono::{NaiveDate, NaiveDateTime};
+use wrapper_core::model::{Message, Movement, Station, Stop, Train};
+
+pub fn station() -> Station {
+    Station { id: 8002549, lat: None, lon: None, name: "Hamburg Hbf".to_string(), ds100: "AH".to_string() }
+}
+
+pub fn date() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2025, 9, 10).unwrap()
+}
+
+pub fn dt(hour: u32, min: u32) -> NaiveDateTime {
+    date().and_hms_opt(hour, min, 0).unwrap()
+}
+
+pub fn train(trip_id: &str) -> Train {
+    Train {
+        id: format!("{}-2025-09-10", trip_id),
+        trip_id: trip_id.to_string(),
+        operator: None,
+        category: "ICE".to_string(),
+        number: "123".to_string(),
+        line: None,
+        date: date(),
+    }
+}
+
+/// Arrives at 07:00 and departs at 07:05 from platform 5.
+pub fn stop(id: &str, train: &Train, station_id: i32) -> Stop {
+    Stop {
+        id: id.to_string(),
+        train_id: train.id.clone(),
+        station_id,
+        arrival: Some(Movement { planned: Some(dt(7, 0)), platform: Some("5".to_string()), ..Default::default() }),
+        departure: Some(Movement { planned: Some(dt(7, 5)), platform: Some("5".to_string()), ..Default::default() }),
+    }
+}
+
+/// Delay message with status code 36 at the station, pass `None` as `train_id` for a station-wide one.
+pub fn message(id: &str, train_id: Option<String>) -> Message {
+    Message {
+        id: id.to_string(),
+        iris_id: id.to_string(),
+        train_id,
+        valid_from: None,
+        valid_to: None,
+        priority: None,
+        category: None,
+        code: Some(36),
+        timestamp: dt(6, 0),
+        m_type: None,
+        last_updated: None,
+        first_seen: None,
+        last_seen: None,
+        revoked_at: None,
+        superseded_by: None,
+        stations: vec![station().id],
+        stops: Vec::new(),
+    }
+}
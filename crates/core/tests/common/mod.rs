@@ -1,3 +1,8 @@
+// Every test crate uses only some of the helpers.
+#![allow(dead_code)]
+
+pub mod fixtures;
+
 use testcontainers::Container;
 use testcontainers_modules::{postgres::{self, Postgres}, testcontainers::runners::SyncRunner};
 
@@ -1,27 +1,248 @@
+#[cfg(feature = "postgres")]
 mod common;
 
-use std::{collections::HashSet, env};
+use std::{env, fs, sync::Arc, time::Duration};
 
-use wrapper_core::{data::{establish_pg_pool, run_migrations}, model::Train, ports::Port, data::repos::{MessageRepo, StationRepo, StopRepo, TrainRepo}, import::{import_iris_data_for_station_by_ds100, import_station_data}};
+use wrapper_core::{filter::FilterPolicy, ports::{Port, PortError, StationPort, StopPort}, data::memory::{MemoryDb, MemoryMessageRepo, MemoryStationRepo, MemoryStopRepo, MemoryTrainRepo, MemoryUnitOfWorkRepo}, import::{import_iris_data_for_station_by_ds100, ImportError, import_station_data, import_station_details, import_station_platforms, replay_archived_documents}, model::Station};
 
-use chrono::{Local};
+use chrono::{NaiveDate, NaiveDateTime};
+use iris::{archive::{ArchiveQuery, FileArchive}, dto::{IRISStationError, IRISTimetableError}, fetch::FileIrisClient, mock::MockIrisServer};
 
-use crate::common::{setup_test_postgres};
+const HAMBURG: i32 = 8002549;
 
-fn find_duplicates<T: Eq + std::hash::Hash + Clone>(items: &[T]) -> Vec<T> {
-    let mut seen = HashSet::new();
-    let mut duplicates = HashSet::new();
+fn read_data(name: &str) -> String {
+    fs::read_to_string(format!("tests/data/iris/{}", name)).unwrap()
+}
+
+fn date() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2025, 9, 10).unwrap()
+}
+
+fn start() -> NaiveDateTime {
+    date().and_hms_opt(11, 0, 0).unwrap()
+}
+
+/// Serves Hamburg Hbf with a recorded plan for 11:00, no trains at 12:00 and recorded changes.
+fn mock_iris() -> MockIrisServer {
+    let server = MockIrisServer::start().unwrap();
+    server
+        .station("AH", &read_data("station_AH.xml"))
+        .plan(HAMBURG, &date(), 11, &read_data("plan_8002549_2509101100.xml"))
+        .empty_plan(HAMBURG, &date(), 12)
+        .fchg(HAMBURG, &read_data("fchg_8002549.xml"));
+    server
+}
+
+#[test]
+fn import_iris_data_from_mock_succeeds_offline() {
+    // Setup
+    let _ = pretty_env_logger::try_init();
+    env::set_var("STATIONS_SRC", "SQL:./tests/data/stations.sql");
+
+    let db = MemoryDb::new();
+    let train_repo = MemoryTrainRepo::new(db.clone());
+    let stop_repo = MemoryStopRepo::new(db.clone());
+    let message_repo = MemoryMessageRepo::new(db.clone());
+    let unit_of_work = MemoryUnitOfWorkRepo::new(db.clone());
+    let _ = import_station_data(&MemoryStationRepo::new(db.clone()), &FilterPolicy::default()).unwrap();
+
+    let server = mock_iris();
+
+    // Test
+    let report = import_iris_data_for_station_by_ds100("AH", &start(), 1, &server.client(), &FilterPolicy::default(), &unit_of_work).unwrap();
+
+    assert_eq!(Some(HAMBURG), report.station_id);
+    assert_eq!(vec![(date(), 11), (date(), 12)], report.windows);
+    assert_eq!(vec![(date(), 12)], report.empty_windows);
+    assert_eq!(3, report.trains.fetched);
+    assert_eq!(3, report.stops.fetched);
+    assert!(report.messages.fetched > 0);
+
+    assert_eq!(report.trains.inserted, train_repo.get_all().unwrap().len());
+    assert_eq!(report.stops.inserted, stop_repo.get_all().unwrap().len());
+    assert_eq!(report.messages.inserted, message_repo.get_all().unwrap().len());
+    assert_eq!(
+        vec!["/timetable/station/AH", "/timetable/plan/8002549/250910/11", "/timetable/plan/8002549/250910/12", "/timetable/fchg/8002549"],
+        server.requests(),
+    );
+
+    // A second run leaves the stored plan untouched.
+    let report = import_iris_data_for_station_by_ds100("AH", &start(), 1, &server.client(), &FilterPolicy::default(), &unit_of_work).unwrap();
+    assert_eq!(0, report.trains.inserted + report.trains.updated);
+    assert_eq!(3, report.trains.unchanged);
+    assert_eq!(0, report.messages.inserted);
+    assert_eq!(report.messages.fetched, report.messages.updated);
+}
+
+#[test]
+fn import_iris_data_from_mock_fails_on_error_status_and_writes_nothing() {
+    // Setup
+    let _ = pretty_env_logger::try_init();
+    env::set_var("STATIONS_SRC", "SQL:./tests/data/stations.sql");
+
+    let db = MemoryDb::new();
+    let unit_of_work = MemoryUnitOfWorkRepo::new(db.clone());
+    let _ = import_station_data(&MemoryStationRepo::new(db.clone()), &FilterPolicy::default()).unwrap();
+
+    let server = mock_iris();
+    server.respond("/timetable/plan/8002549/250910/12", 503, "");
+
+    // Test
+    let err = import_iris_data_for_station_by_ds100("AH", &start(), 1, &server.client(), &FilterPolicy::default(), &unit_of_work).unwrap_err();
+
+    assert!(err.is_retryable());
+    assert!(MemoryTrainRepo::new(db.clone()).get_all().unwrap().is_empty());
+    assert!(MemoryStopRepo::new(db.clone()).get_all().unwrap().is_empty());
+}
+
+#[test]
+fn import_errors_are_classified_for_retries() {
+    let circuit_open = ImportError::from(IRISStationError::CircuitOpen(Duration::from_secs(1)));
+    assert!(circuit_open.is_retryable() && circuit_open.is_circuit_open());
+    let circuit_open = ImportError::from(IRISTimetableError::CircuitOpen(Duration::from_secs(1)));
+    assert!(circuit_open.is_retryable() && circuit_open.is_circuit_open());
+
+    let unavailable = ImportError::from(IRISTimetableError::RequestFailed(503, String::new()));
+    assert!(unavailable.is_retryable() && !unavailable.is_circuit_open());
+    assert!(ImportError::from(PortError::Connection).is_retryable());
+
+    assert!(!ImportError::from(IRISStationError::NotFound("AH".to_string())).is_retryable());
+    assert!(!ImportError::from(PortError::Database).is_retryable());
+}
+
+#[test]
+fn import_iris_data_from_files_matches_the_api_import() {
+    // Setup
+    let _ = pretty_env_logger::try_init();
+    env::set_var("STATIONS_SRC", "SQL:./tests/data/stations.sql");
+
+    let db = MemoryDb::new();
+    let unit_of_work = MemoryUnitOfWorkRepo::new(db.clone());
+    let _ = import_station_data(&MemoryStationRepo::new(db.clone()), &FilterPolicy::default()).unwrap();
+    let client = FileIrisClient::new("tests/data/iris");
 
-    for item in items {
-        if !seen.insert(item) {
-            duplicates.insert(item.clone());
-        }
-    }
-    duplicates.into_iter().collect()
+    // Test
+    let report = import_iris_data_for_station_by_ds100("AH", &start(), 1, &client, &FilterPolicy::default(), &unit_of_work).unwrap();
+
+    // Same documents as served by `mock_iris`, the missing 12:00 plan counts as empty.
+    let api_db = MemoryDb::new();
+    let _ = import_station_data(&MemoryStationRepo::new(api_db.clone()), &FilterPolicy::default()).unwrap();
+    let server = mock_iris();
+    let api_report = import_iris_data_for_station_by_ds100("AH", &start(), 1, &server.client(), &FilterPolicy::default(), &MemoryUnitOfWorkRepo::new(api_db)).unwrap();
+    assert_eq!(Some(HAMBURG), report.station_id);
+    assert_eq!(api_report.empty_windows, report.empty_windows);
+    assert_eq!(api_report.trains.fetched, report.trains.fetched);
+    assert_eq!(api_report.stops.fetched, report.stops.fetched);
+    assert_eq!(api_report.messages.fetched, report.messages.fetched);
+    assert_eq!(report.trains.inserted, MemoryTrainRepo::new(db.clone()).get_all().unwrap().len());
+}
+
+#[test]
+fn import_station_platforms_counts_platform_usage() {
+    // Setup
+    let _ = pretty_env_logger::try_init();
+    env::set_var("STATIONS_SRC", "SQL:./tests/data/stations.sql");
+
+    let db = MemoryDb::new();
+    let station_repo = MemoryStationRepo::new(db.clone());
+    let stop_repo = MemoryStopRepo::new(db.clone());
+    let _ = import_station_data(&station_repo, &FilterPolicy::default()).unwrap();
+    let client = FileIrisClient::new("tests/data/iris");
+    import_iris_data_for_station_by_ds100("AH", &start(), 1, &client, &FilterPolicy::default(), &MemoryUnitOfWorkRepo::new(db.clone())).unwrap();
+    let hamburg = station_repo.get_by_id(HAMBURG).unwrap();
+
+    // Test
+    let run = import_station_platforms(std::slice::from_ref(&hamburg), &client, &station_repo).unwrap();
+
+    assert!(run.failures.is_empty());
+    assert_eq!(1, run.totals().stations.inserted);
+    let platforms = station_repo.get_platforms(&hamburg).unwrap();
+    assert_eq!(25, platforms.platforms.len());
+
+    let usage = platforms.with_usage(&stop_repo.get_platform_usage(&hamburg).unwrap());
+    let count = |name: &str| usage.iter().find(|u| u.platform == name).map(|u| (u.used, u.changed)).unwrap();
+    assert_eq!(platforms.platforms.len(), usage.len());
+    // The recorded changes move both movements at 14 to 13, one stop is planned at "5a/b".
+    assert_eq!((2, 2), count("14"));
+    assert_eq!((1, 0), count("5a"));
+    assert_eq!((1, 0), count("5b"));
+    assert_eq!((0, 0), count("S"));
+
+    // Known platforms are not fetched again.
+    let run = import_station_platforms(&[hamburg], &client, &station_repo).unwrap();
+    assert!(run.stations.is_empty());
 }
 
+#[test]
+fn import_station_details_clears_groups_iris_no_longer_lists() {
+    let db = MemoryDb::new();
+    let station_repo = MemoryStationRepo::new(db.clone());
+    let hamburg = Station { id: HAMBURG, lat: None, lon: None, name: "Hamburg Hbf".to_string(), ds100: "AH".to_string() };
+    let s_bahn = Station { id: 8098549, lat: None, lon: None, name: "Hamburg Hbf (S)".to_string(), ds100: "AHS".to_string() };
+    station_repo.persist_all(&[hamburg.clone(), s_bahn]).unwrap();
+    let server = MockIrisServer::start().unwrap();
+    let station_xml = read_data("station_AH.xml");
+    server.station("AH", &station_xml);
+
+    let group_ids = || station_repo.get_group(&hamburg).unwrap().iter().map(|s| s.id).collect::<Vec<i32>>();
+    import_station_details(&hamburg, &server.client(), &station_repo).unwrap();
+    assert_eq!(vec![HAMBURG, 8098549], group_ids());
+
+    server.station("AH", &station_xml.replace(r#" meta="694887|8071065|8076116|8098549""#, ""));
+    import_station_details(&hamburg, &server.client(), &station_repo).unwrap();
+    assert_eq!(vec![HAMBURG], group_ids());
+}
+
+#[test]
+fn replay_archived_documents_restores_the_import() {
+    // Setup
+    let _ = pretty_env_logger::try_init();
+    env::set_var("STATIONS_SRC", "SQL:./tests/data/stations.sql");
+
+    let root = env::temp_dir().join(format!("core-replay-archive-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let archive = Arc::new(FileArchive::new(&root));
+
+    let db = MemoryDb::new();
+    let _ = import_station_data(&MemoryStationRepo::new(db.clone()), &FilterPolicy::default()).unwrap();
+    let server = mock_iris();
+    let client = server.client().with_archive(archive.clone());
+    let imported = import_iris_data_for_station_by_ds100("AH", &start(), 1, &client, &FilterPolicy::default(), &MemoryUnitOfWorkRepo::new(db.clone())).unwrap();
+
+    let replay_db = MemoryDb::new();
+    let station_repo = MemoryStationRepo::new(replay_db.clone());
+    let _ = import_station_data(&station_repo, &FilterPolicy::default()).unwrap();
+
+    // Test
+    let run = replay_archived_documents(
+        archive.as_ref(),
+        &ArchiveQuery::default(),
+        &FilterPolicy::default(),
+        &station_repo,
+        &MemoryStopRepo::new(replay_db.clone()),
+        &MemoryUnitOfWorkRepo::new(replay_db.clone()),
+    ).unwrap();
+
+    assert!(run.failures.is_empty());
+    // Station lookup is skipped: two plans and the changes.
+    assert_eq!(3, run.stations.len());
+    let totals = run.totals();
+    assert_eq!(imported.trains.inserted, totals.trains.inserted);
+    assert_eq!(imported.stops.inserted, totals.stops.inserted);
+    assert_eq!(imported.messages.inserted, totals.messages.inserted);
+    assert_eq!(MemoryTrainRepo::new(db.clone()).get_all().unwrap().len(), MemoryTrainRepo::new(replay_db.clone()).get_all().unwrap().len());
+    assert_eq!(MemoryMessageRepo::new(db.clone()).get_all().unwrap().len(), MemoryMessageRepo::new(replay_db.clone()).get_all().unwrap().len());
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[cfg(feature = "postgres")]
 #[test]
 fn import_iris_data_for_single_station_succeeds() {
+    use wrapper_core::{data::{establish_pg_pool, run_migrations}, data::repos::{MessageRepo, StationRepo, StopRepo, TrainRepo, UnitOfWorkRepo}};
+
+    use crate::common::setup_test_postgres;
+
     // Setup
     let _ = pretty_env_logger::try_init();
     env::set_var("STATIONS_SRC", "SQL:./tests/data/stations.sql");
@@ -36,45 +257,34 @@ fn import_iris_data_for_single_station_succeeds() {
     let stop_repo = StopRepo::new(pool.clone());
     let message_repo = MessageRepo::new(pool.clone());
     let station_repo = StationRepo::new(pool.clone());
+    let unit_of_work = UnitOfWorkRepo::new(pool.clone());
 
-    let _ = import_station_data(&station_repo).unwrap();
-
-    // Test
-
-    let date = Local::now().naive_local();
-    let (trains, stops, messages) = import_iris_data_for_station_by_ds100("AH", &date, 12, &message_repo, &train_repo, &stop_repo).unwrap();
-
-    // let station_id = stops.first().unwrap().station_id;
+    let server = mock_iris();
 
-    assert!(trains.len() > 0);
-    assert!(stops.len() > 0);
-    assert!(messages.len() > 0);
+    let _ = import_station_data(&station_repo, &FilterPolicy::default()).unwrap();
 
+    // Test
 
-    let train_ids: Vec<String> = trains.iter().map(|t| t.id.clone()).collect();
-    let duplicates = find_duplicates(&train_ids);
-    println!("TrainIds: {:?}", duplicates);
+    let report = import_iris_data_for_station_by_ds100("AH", &start(), 1, &server.client(), &FilterPolicy::default(), &unit_of_work).unwrap();
+    println!("{}", report);
 
-    let filtered_trains: Vec<&Train> = trains.iter().filter(|t| duplicates.contains(&t.id)).collect();
-    println!("Duplicate Trains:{:#?}", filtered_trains);
+    assert_eq!(Some("AH"), report.ds100.as_deref());
+    assert_eq!(2, report.windows.len());
+    assert!(report.trains.fetched > 0);
+    assert!(report.stops.fetched > 0);
+    assert!(report.messages.fetched > 0);
 
+    // Duplicates in the fetched data are only inserted once
     let result = train_repo.get_all().unwrap();
-
-    assert_eq!(trains.len() - duplicates.len(), result.len());
-
-    // Insert stops and test length, account for duplicates
-    let stop_ids: Vec<String> = stops.iter().map(|t| t.id.clone()).collect();
-    let duplicates = find_duplicates(&stop_ids);
+    assert_eq!(report.trains.inserted, result.len());
+    assert!(report.trains.inserted <= report.trains.fetched);
 
     let result = stop_repo.get_all().unwrap();
+    assert_eq!(report.stops.inserted, result.len());
+    assert!(report.stops.inserted <= report.stops.fetched);
 
-    assert_eq!(stops.len() - duplicates.len(), result.len());
-
-    let _ = message_repo.get_all().unwrap();
-
-    // No sense in comparing messages, as there are many duplicates in the data
-
-    // TODO: Think about other ways to validate the data
+    let result = message_repo.get_all().unwrap();
+    assert_eq!(report.messages.inserted, result.len());
 }
 
 
@@ -93,7 +303,7 @@ fn import_iris_data_succeeds() { // More like a ratelimit tester for the iris en
     let message_repo = MessageRepo::new(pool.clone());
     let station_repo = StationRepo::new(pool.clone());
 
-    let _ = import_station_data(&station_repo).unwrap();
+    let _ = import_station_data(&station_repo, &FilterPolicy::default()).unwrap();
 
     // Test
 
@@ -104,9 +314,9 @@ fn import_iris_data_succeeds() { // More like a ratelimit tester for the iris en
     let stops = stop_repo.get_all().unwrap();
     let messages = message_repo.get_all().unwrap();
 
-    assert!(trains.len() > 0);
-    assert!(stops.len() > 0);
-    assert!(messages.len() > 0);
+    assert!(!trains.is_empty());
+    assert!(!stops.is_empty());
+    assert!(!messages.is_empty());
 }*/
 // TODO: Test wrong station code
 // TODO: Test wrong date code
@@ -0,0 +1,406 @@
+mod common;
+
+use chrono::TimeDelta;
+use testcontainers::Container;
+use testcontainers_modules::postgres::Postgres;
+use wrapper_core::{
+    data::memory::{MemoryDb, MemoryMessageRepo, MemoryStationRepo, MemoryStopObservationRepo, MemoryStopRepo, MemoryTrainRepo, MemoryUnitOfWorkRepo},
+    model::{EventStatus, Message, MessageScope, MessageStop, Movement, MovementKind, PlatformUsage, Station, StationGroup, StationPlatforms, Stop, StopObservation, StopUpdate},
+    ports::{MessagePort, PortError, StationPort, StopObservationPort, StopPort, TrainPort, UnitOfWork, UnitOfWorkPort},
+};
+
+use crate::common::fixtures::{dt, message, station, stop, train};
+
+/// All ports of one backend on a fresh database.
+struct Ports {
+    stations: Box<dyn StationPort>,
+    trains: Box<dyn TrainPort>,
+    stops: Box<dyn StopPort>,
+    messages: Box<dyn MessagePort>,
+    observations: Box<dyn StopObservationPort>,
+    unit_of_work: Box<dyn UnitOfWorkPort>,
+    /// Keeps the database of the Postgres backend running.
+    _container: Option<Container<Postgres>>,
+}
+
+fn memory_ports() -> Ports {
+    let db = MemoryDb::new();
+    Ports {
+        stations: Box::new(MemoryStationRepo::new(db.clone())),
+        trains: Box::new(MemoryTrainRepo::new(db.clone())),
+        stops: Box::new(MemoryStopRepo::new(db.clone())),
+        messages: Box::new(MemoryMessageRepo::new(db.clone())),
+        observations: Box::new(MemoryStopObservationRepo::new(db.clone())),
+        unit_of_work: Box::new(MemoryUnitOfWorkRepo::new(db)),
+        _container: None,
+    }
+}
+
+/// A migrated database in a fresh file, every pooled connection would get its own `:memory:` database.
+#[cfg(feature = "sqlite")]
+fn sqlite_ports() -> Ports {
+    use std::{env, fs, process, sync::atomic::{AtomicUsize, Ordering}};
+    use wrapper_core::data::{establish_sqlite_pool, run_sqlite_migrations, sqlite::{SqliteMessageRepo, SqliteStationRepo, SqliteStopObservationRepo, SqliteStopRepo, SqliteTrainRepo, SqliteUnitOfWorkRepo}};
+
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let path = env::temp_dir().join(format!("wrapper-core-{}-{}.db", process::id(), COUNTER.fetch_add(1, Ordering::Relaxed)));
+    let _ = fs::remove_file(&path);
+    let pool = establish_sqlite_pool(path.to_str().unwrap());
+    run_sqlite_migrations(pool.clone());
+    Ports {
+        stations: Box::new(SqliteStationRepo::new(pool.clone())),
+        trains: Box::new(SqliteTrainRepo::new(pool.clone())),
+        stops: Box::new(SqliteStopRepo::new(pool.clone())),
+        messages: Box::new(SqliteMessageRepo::new(pool.clone())),
+        observations: Box::new(SqliteStopObservationRepo::new(pool.clone())),
+        unit_of_work: Box::new(SqliteUnitOfWorkRepo::new(pool)),
+        _container: None,
+    }
+}
+
+#[cfg(feature = "postgres")]
+fn postgres_ports() -> Ports {
+    use wrapper_core::data::{establish_pg_pool, repos::{MessageRepo, StationRepo, StopObservationRepo, StopRepo, TrainRepo, UnitOfWorkRepo}, run_migrations};
+
+    let (container, db_url) = common::setup_test_postgres();
+    let pool = establish_pg_pool(&db_url);
+    run_migrations(pool.clone());
+    Ports {
+        stations: Box::new(StationRepo::new(pool.clone())),
+        trains: Box::new(TrainRepo::new(pool.clone())),
+        stops: Box::new(StopRepo::new(pool.clone())),
+        messages: Box::new(MessageRepo::new(pool.clone())),
+        observations: Box::new(StopObservationRepo::new(pool.clone())),
+        unit_of_work: Box::new(UnitOfWorkRepo::new(pool)),
+        _container: Some(container),
+    }
+}
+
+/// The backends implement the ports with the same semantics, so they share one suite.
+macro_rules! port_tests {
+    ($backend:ident, $ports:path) => {
+        mod $backend {
+            use super::*;
+
+            /// The stop of the `trip` train at Hamburg Hbf.
+            fn hamburg_stop() -> Stop {
+                stop("trip-2509100700-1", &train("trip"), station().id)
+            }
+
+            /// Ports with the station, the train and its stop.
+            fn seeded_ports() -> Ports {
+                let ports = $ports();
+                ports.stations.persist(&station()).unwrap();
+                ports.unit_of_work
+                    .commit(&UnitOfWork { trains: vec![train("trip")], stops: vec![hamburg_stop()], ..Default::default() })
+                    .unwrap();
+                ports
+            }
+
+            #[test]
+            fn unit_of_work_commits_all_writes() {
+                let ports = $ports();
+                ports.stations.persist(&station()).unwrap();
+
+                let work = UnitOfWork { trains: vec![train("trip")], stops: vec![hamburg_stop()], ..Default::default() };
+                let result = ports.unit_of_work.commit(&work).unwrap();
+
+                assert_eq!(1, result.trains.inserted.len());
+                assert_eq!(1, result.stops.inserted.len());
+                assert_eq!(1, ports.trains.get_all().unwrap().len());
+                assert_eq!(1, ports.stops.get_all().unwrap().len());
+
+                // Known entities without changes are not written again.
+                let result = ports.unit_of_work.commit(&work).unwrap();
+                assert!(result.trains.inserted.is_empty() && result.trains.updated.is_empty());
+                assert_eq!(1, result.trains.unchanged.len());
+                assert_eq!(1, result.stops.unchanged.len());
+            }
+
+            #[test]
+            fn unit_of_work_updates_changed_plan_and_keeps_current_fields() {
+                let ports = seeded_ports();
+                let planned = hamburg_stop();
+
+                // Written by the change import.
+                let mut changed = planned.clone();
+                changed.arrival.as_mut().unwrap().current = Some(dt(7, 5));
+                ports.stops.update(&StopUpdate::from(&changed)).unwrap();
+
+                let mut replanned = planned.clone();
+                replanned.arrival.as_mut().unwrap().platform = Some("7".to_string());
+                let mut relined = train("trip");
+                relined.line = Some("RE7".to_string());
+                let result = ports.unit_of_work.commit(&UnitOfWork { trains: vec![relined], stops: vec![replanned], ..Default::default() }).unwrap();
+
+                assert_eq!(1, result.trains.updated.len());
+                assert_eq!(Some("RE7".to_string()), result.trains.updated[0].line);
+                assert_eq!(1, result.stops.updated.len());
+
+                let arrival = ports.stops.get_by_id(planned.id.clone()).unwrap().arrival.unwrap();
+                assert_eq!(Some("7".to_string()), arrival.platform);
+                assert_eq!(Some(dt(7, 5)), arrival.current);
+            }
+
+            #[test]
+            fn unit_of_work_writes_nothing_on_error() {
+                let ports = $ports();
+
+                // The station of the stop is unknown, so inserting the stop fails after the train was written.
+                let work = UnitOfWork { trains: vec![train("trip")], stops: vec![hamburg_stop()], ..Default::default() };
+                assert!(matches!(ports.unit_of_work.commit(&work), Err(PortError::Database)));
+
+                assert!(ports.trains.get_all().unwrap().is_empty());
+                assert!(ports.stops.get_all().unwrap().is_empty());
+            }
+
+            #[test]
+            fn persist_skips_known_rows() {
+                let ports = $ports();
+
+                assert_eq!(1, ports.stations.persist_all(&[station(), station()]).unwrap().len());
+                assert!(matches!(ports.stations.persist(&station()), Err(PortError::NotFound)));
+                assert!(matches!(ports.stations.get_by_id(1), Err(PortError::NotFound)));
+                assert_eq!("Hamburg Hbf", ports.stations.get_by_ds100("AH").unwrap().name);
+            }
+
+            #[test]
+            fn update_many_skips_empty_updates_and_fails_on_unknown_stops() {
+                let ports = seeded_ports();
+
+                let mut changed = hamburg_stop();
+                changed.arrival.as_mut().unwrap().current = Some(dt(7, 10));
+                let empty = StopUpdate { id: changed.id.clone(), arrival: None, departure: None, replaces_changes: false };
+                assert_eq!(1, ports.stops.update_many(&[StopUpdate::from(&changed), empty.clone()]).unwrap().len());
+                assert!(matches!(ports.stops.update(&empty), Err(PortError::InvalidInput)));
+
+                // All updates or none.
+                let mut later = changed.clone();
+                later.arrival.as_mut().unwrap().current = Some(dt(7, 20));
+                let mut unknown = changed.clone();
+                unknown.id = "unknown".to_string();
+                let result = ports.stops.update_many(&[StopUpdate::from(&later), StopUpdate::from(&unknown)]);
+                assert!(matches!(result, Err(PortError::NotFound)));
+                assert_eq!(Some(dt(7, 10)), ports.stops.get_by_id(changed.id.clone()).unwrap().arrival.unwrap().current);
+            }
+
+            #[test]
+            fn replacing_changes_clears_a_withdrawn_cancellation() {
+                let ports = seeded_ports();
+                let planned = hamburg_stop();
+                let date = train("trip").date;
+
+                let mut cancelled = planned.clone();
+                let arrival = cancelled.arrival.as_mut().unwrap();
+                arrival.changed_status = Some(EventStatus::Cancelled);
+                arrival.changed_platform = Some("7".to_string());
+                ports.stops.update(&StopUpdate::replacing_changes(&cancelled)).unwrap();
+                assert_eq!(1, ports.stops.get_cancelled_by_stations_and_date(&[station()], &date).unwrap().len());
+                assert_eq!(1, ports.stops.get_platform_changes_by_stations_and_date(&[station()], &date).unwrap().len());
+
+                // Deltas keep changes they do not mention.
+                let mut delayed = planned.clone();
+                delayed.arrival.as_mut().unwrap().current = Some(dt(7, 5));
+                ports.stops.update(&StopUpdate::from(&delayed)).unwrap();
+                assert_eq!(1, ports.stops.get_cancelled_by_stations_and_date(&[station()], &date).unwrap().len());
+
+                // A full sync without the cancellation withdraws it.
+                ports.stops.update(&StopUpdate::replacing_changes(&delayed)).unwrap();
+                assert!(ports.stops.get_cancelled_by_stations_and_date(&[station()], &date).unwrap().is_empty());
+                assert!(ports.stops.get_platform_changes_by_stations_and_date(&[station()], &date).unwrap().is_empty());
+                let arrival = ports.stops.get_by_id(planned.id.clone()).unwrap().arrival.unwrap();
+                assert_eq!(None, arrival.changed_status);
+                assert_eq!(Some("5".to_string()), arrival.platform);
+                assert_eq!(Some(dt(7, 5)), arrival.current);
+            }
+
+            #[test]
+            fn messages_are_upserted_and_revoked() {
+                let ports = seeded_ports();
+                let messages = &ports.messages;
+
+                let mut delay = message("delay", Some(train("trip").id));
+                delay.stops = vec![MessageStop { stop_id: hamburg_stop().id, scope: MessageScope::Arrival }];
+                let notice = message("notice", None);
+                messages.persist_all(&[delay.clone(), notice.clone()]).unwrap();
+                assert!(matches!(messages.persist_all(&[notice.clone(), notice.clone()]), Err(PortError::Database)));
+                assert!(matches!(messages.persist(&message("ghost", Some("unknown".to_string()))), Err(PortError::Database)));
+
+                let mut updated = delay.clone();
+                updated.code = Some(43);
+                updated.stops = Vec::new();
+                let stored = messages.persist(&updated).unwrap();
+                assert_eq!(Some(43), stored.code);
+                assert_eq!(1, stored.stops.len(), "links are only added");
+                assert_eq!(1, messages.get_by_stop_ids(&[hamburg_stop().id]).unwrap().len());
+                assert_eq!(1, messages.get_station_messages(station().id).unwrap().len());
+
+                let upserted = messages.upsert_all(&[notice.clone(), message("closure", None)]).unwrap();
+                assert_eq!(vec!["closure".to_string()], upserted.inserted.iter().map(|m| m.id.clone()).collect::<Vec<_>>());
+                assert_eq!(vec!["notice".to_string()], upserted.updated.iter().map(|m| m.id.clone()).collect::<Vec<_>>());
+
+                // The train still stops after 06:30, both messages are revoked unless seen.
+                let revoked = messages.revoke_missing(station().id, &["notice".to_string(), "closure".to_string()], &dt(6, 30)).unwrap();
+                assert_eq!(vec!["delay".to_string()], revoked.iter().map(|m| m.id.clone()).collect::<Vec<_>>());
+                assert!(messages.get_active_at(&dt(6, 30)).unwrap().iter().all(|m| m.id != "delay"));
+
+                // Seeing the message again clears the revocation.
+                assert!(messages.persist(&delay).unwrap().revoked_at.is_none());
+            }
+
+            #[test]
+            fn shared_messages_stay_active_while_any_station_lists_them() {
+                let ports = $ports();
+                let altona = Station { id: 8002553, lat: None, lon: None, name: "Hamburg-Altona".to_string(), ds100: "ADF".to_string() };
+                ports.stations.persist_all(&[station(), altona.clone()]).unwrap();
+                let messages = &ports.messages;
+                let at = |station_id| Message { stations: vec![station_id], ..message("shared", None) };
+                messages.persist_all(&[at(station().id)]).unwrap();
+                messages.persist_all(&[at(altona.id)]).unwrap();
+
+                // Altona drops the message while Hamburg keeps listing it, on every poll.
+                for _ in 0..2 {
+                    assert!(messages.revoke_missing(altona.id, &[], &dt(6, 30)).unwrap().is_empty());
+                    messages.persist_all(&[at(station().id)]).unwrap();
+                    assert!(messages.get_by_id("shared".to_string()).unwrap().revoked_at.is_none());
+                }
+
+                // Revoked once no station lists it anymore.
+                let revoked = messages.revoke_missing(station().id, &[], &dt(6, 30)).unwrap();
+                assert_eq!(vec!["shared".to_string()], revoked.iter().map(|m| m.id.clone()).collect::<Vec<_>>());
+
+                // Listed again by Altona.
+                assert!(messages.persist(&at(altona.id)).unwrap().revoked_at.is_none());
+            }
+
+            #[test]
+            fn later_revisions_supersede_earlier_ones() {
+                let ports = $ports();
+                ports.stations.persist(&station()).unwrap();
+                let messages = &ports.messages;
+                // IRIS republishes r1234 with a timestamp on another day, which gets a new id.
+                let revision = |id: &str, timestamp| Message { iris_id: "r1234".to_string(), timestamp, valid_to: Some(dt(23, 0)), ..message(id, None) };
+                let yesterday = dt(6, 0) - TimeDelta::days(1);
+                messages.persist_all(&[revision("r1234-20250909", yesterday)]).unwrap();
+                let stored = messages.persist_all(&[revision("r1234-20250910", dt(6, 0))]).unwrap();
+
+                assert!(stored[0].superseded_by.is_none());
+                assert_eq!(Some("r1234-20250910".to_string()), messages.get_by_id("r1234-20250909".to_string()).unwrap().superseded_by);
+                let active = messages.get_active_at(&dt(12, 0)).unwrap();
+                assert_eq!(vec!["r1234-20250910".to_string()], active.iter().map(|m| m.id.clone()).collect::<Vec<_>>());
+
+                // An earlier revision seen late, e.g. in a replayed document, is superseded right away.
+                let stored = messages.persist(&revision("r1234-20250908", yesterday - TimeDelta::days(1))).unwrap();
+                assert_eq!(Some("r1234-20250910".to_string()), stored.superseded_by);
+            }
+
+            #[test]
+            fn observations_skip_repeated_forecasts() {
+                let ports = seeded_ports();
+                let observations = &ports.observations;
+
+                let observation = |observed_at, current| StopObservation {
+                    stop_id: hamburg_stop().id,
+                    kind: MovementKind::Arrival,
+                    observed_at,
+                    current: Some(current),
+                    platform: None,
+                    status: None,
+                };
+                let recorded = observations.record_all(&[
+                    observation(dt(6, 0), dt(7, 5)),
+                    observation(dt(6, 10), dt(7, 5)),
+                    observation(dt(6, 20), dt(7, 10)),
+                ]).unwrap();
+
+                assert_eq!(2, recorded.len());
+                assert_eq!(recorded, observations.get_for_train(&train("trip").id).unwrap());
+                let mut unknown = observation(dt(6, 30), dt(7, 15));
+                unknown.stop_id = "unknown".to_string();
+                assert!(matches!(observations.record_all(&[unknown]), Err(PortError::Database)));
+            }
+
+            #[test]
+            fn imports_stations_from_sql() {
+                let ports = $ports();
+
+                let imported = ports.stations.import_from_sql("tests/data/stations.sql").unwrap();
+
+                assert_eq!(imported.len(), ports.stations.get_all().unwrap().len());
+                let koblenz = ports.stations.get_by_ds100("KKO").unwrap();
+                assert_eq!(8000206, koblenz.id);
+                assert_eq!(Some(50.350929), koblenz.lat);
+                assert!(ports.stations.import_from_sql("tests/data/stations.sql").unwrap().is_empty());
+            }
+
+            #[test]
+            fn searches_stations_ignoring_umlauts() {
+                let ports = $ports();
+                ports.stations.import_from_sql("tests/data/stations.sql").unwrap();
+
+                let found = ports.stations.search("Munchen", 5).unwrap();
+                assert_eq!("München Hbf", found[0].name);
+                assert!(found.len() <= 5 && found.iter().all(|s| s.name.starts_with("München")));
+                assert_eq!(vec![8000261], ports.stations.search("mh", 5).unwrap().iter().map(|s| s.id).collect::<Vec<_>>());
+                assert!(ports.stations.search("Atlantis", 5).unwrap().is_empty());
+            }
+
+            #[test]
+            fn station_groups_cover_stops_of_all_members() {
+                let ports = $ports();
+                let s_bahn = Station { id: 8098549, lat: None, lon: None, name: "Hamburg Hbf (S)".to_string(), ds100: "AHS".to_string() };
+                ports.stations.persist_all(&[station(), s_bahn.clone()]).unwrap();
+
+                // Unknown members are kept, but only known stations are returned.
+                let ids = |stations: Vec<Station>| stations.iter().map(|s| s.id).collect::<Vec<i32>>();
+                ports.stations.persist_groups(&[StationGroup::new(station().id, [s_bahn.id, 694887])]).unwrap();
+                assert_eq!(vec![station().id, s_bahn.id], ids(ports.stations.get_group(&station()).unwrap()));
+                assert_eq!(vec![s_bahn.id, station().id], ids(ports.stations.get_group(&s_bahn).unwrap()));
+                assert!(matches!(ports.stations.persist_groups(&[StationGroup::new(1, [s_bahn.id])]), Err(PortError::Database)));
+
+                let train = train("trip");
+                let s_bahn_stop = stop("trip-2509100710-2", &train, s_bahn.id);
+                let work = UnitOfWork { trains: vec![train.clone()], stops: vec![hamburg_stop(), s_bahn_stop], ..Default::default() };
+                ports.unit_of_work.commit(&work).unwrap();
+
+                let group = ports.stations.get_group(&station()).unwrap();
+                assert_eq!(2, ports.stops.get_by_stations_and_date(&group, &train.date).unwrap().len());
+                assert_eq!(1, ports.stops.get_by_stations_and_date(&[station()], &train.date).unwrap().len());
+                let trains = ports.trains.get_by_stations_and_date(&group, &train.date).unwrap();
+                assert_eq!(vec![train.id.clone()], trains.iter().map(|t| t.id.clone()).collect::<Vec<String>>());
+
+                // Persisting a group replaces its members.
+                ports.stations.persist_groups(&[StationGroup::new(station().id, [])]).unwrap();
+                assert_eq!(vec![station().id], ids(ports.stations.get_group(&station()).unwrap()));
+            }
+
+            #[test]
+            fn platforms_are_replaced_and_counted_per_section() {
+                let ports = $ports();
+                ports.stations.persist(&station()).unwrap();
+                assert!(ports.stations.get_platforms(&station()).unwrap().is_empty());
+
+                ports.stations.persist_platforms(&[StationPlatforms::new(station().id, ["5a/b", "6"])]).unwrap();
+                ports.stations.persist_platforms(&[StationPlatforms::new(station().id, ["5a/b", "5"])]).unwrap();
+                assert_eq!(vec!["5", "5a", "5b"], ports.stations.get_platforms(&station()).unwrap().platforms);
+                assert!(matches!(ports.stations.persist_platforms(&[StationPlatforms::new(1, ["1"])]), Err(PortError::Database)));
+
+                let mut moved = hamburg_stop();
+                moved.arrival = Some(Movement { platform: Some("5a/b".to_string()), changed_platform: Some("6".to_string()), ..Default::default() });
+                moved.departure = Some(Movement { platform: Some("5a/b".to_string()), changed_platform: Some("5a/b".to_string()), ..Default::default() });
+                ports.unit_of_work.commit(&UnitOfWork { trains: vec![train("trip")], stops: vec![moved], ..Default::default() }).unwrap();
+
+                assert_eq!(vec![
+                    PlatformUsage { platform: "5a".to_string(), used: 2, changed: 1 },
+                    PlatformUsage { platform: "5b".to_string(), used: 2, changed: 1 },
+                ], ports.stops.get_platform_usage(&station()).unwrap());
+            }
+        }
+    };
+}
+
+port_tests!(memory, memory_ports);
+#[cfg(feature = "sqlite")]
+port_tests!(sqlite, sqlite_ports);
+#[cfg(feature = "postgres")]
+port_tests!(postgres, postgres_ports);
@@ -0,0 +1,179 @@
+use std::{
+    collections::HashMap,
+    sync::{atomic::{AtomicBool, AtomicU64, Ordering}, Arc, Mutex},
+};
+
+use chrono::{NaiveDate, NaiveDateTime, TimeDelta};
+use iris::{
+    dto::{IRISStation, IRISStationError, IRISTimetableError, Timetable},
+    fetch::{IrisClient, IrisClientStats},
+};
+use wrapper_core::{
+    data::memory::{MemoryDb, MemoryStationRepo, MemoryStatusCodeRepo, MemoryStopRepo, MemoryUnitOfWorkRepo},
+    filter::FilterPolicy,
+    import::import_iris_recent_changes,
+    ingest::{ChangesSyncMode, RECENT_CHANGES_WINDOW},
+    model::Station,
+    ports::Port,
+    service::{ImportService, PollSettings},
+    utils::now_local,
+};
+
+const HAMBURG: i32 = 8002549;
+const ALTONA: i32 = 8002553;
+
+/// Answers every plan as empty and every changes document without stops, recording the kind of each request.
+/// Recent changes fail while `failing` is set.
+#[derive(Default)]
+struct RecordingClient {
+    requests: Mutex<Vec<&'static str>>,
+    sent: AtomicU64,
+    failing: AtomicBool,
+}
+
+impl RecordingClient {
+    fn take_requests(&self) -> Vec<&'static str> {
+        std::mem::take(&mut *self.requests.lock().unwrap())
+    }
+
+    fn record(&self, kind: &'static str) {
+        self.requests.lock().unwrap().push(kind);
+        self.sent.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn changes(&self, kind: &'static str, eva: i32) -> Result<Timetable, IRISTimetableError> {
+        self.record(kind);
+        Ok(Timetable { station: "Hamburg Hbf".to_string(), eva: Some(eva.to_string()), stops: Vec::new() })
+    }
+}
+
+impl IrisClient for RecordingClient {
+    fn get_timetable(&self, eva: i32, _date: &NaiveDate, _hour: u16) -> Result<Timetable, IRISTimetableError> {
+        self.record("plan");
+        Err(IRISTimetableError::EmptyTimetable(eva))
+    }
+
+    fn get_timetable_changes(&self, eva: i32) -> Result<Timetable, IRISTimetableError> {
+        self.changes("fchg", eva)
+    }
+
+    fn get_recent_timetable_changes(&self, eva: i32) -> Result<Timetable, IRISTimetableError> {
+        if self.failing.load(Ordering::SeqCst) {
+            self.record("rchg");
+            return Err(IRISTimetableError::RequestFailed(503, String::new()));
+        }
+        self.changes("rchg", eva)
+    }
+
+    fn get_station(&self, ds100: &str) -> Result<IRISStation, IRISStationError> {
+        self.record("station");
+        Err(IRISStationError::NotFound(ds100.to_string()))
+    }
+
+    fn search_stations(&self, _pattern: &str) -> Result<Vec<IRISStation>, IRISStationError> {
+        Ok(Vec::new())
+    }
+
+    fn stats(&self) -> Option<IrisClientStats> {
+        Some(IrisClientStats { requests: self.sent.load(Ordering::SeqCst), ..Default::default() })
+    }
+}
+
+fn start() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2025, 9, 10).unwrap().and_hms_opt(11, 0, 0).unwrap()
+}
+
+fn station(id: i32, name: &str, ds100: &str) -> Station {
+    Station { id, lat: None, lon: None, name: name.to_string(), ds100: ds100.to_string() }
+}
+
+fn last_mode(service: &ImportService) -> Option<ChangesSyncMode> {
+    service.reports().iter().rev().find_map(|run| run.stations.first().and_then(|report| report.mode))
+}
+
+#[test]
+fn import_loop_polls_recent_changes_with_the_default_settings() {
+    let db = MemoryDb::new();
+    let station_repo = MemoryStationRepo::new(db.clone());
+    station_repo.persist(&station(HAMBURG, "Hamburg Hbf", "AH")).unwrap();
+    let client = Arc::new(RecordingClient::default());
+    let service = ImportService::new(
+        client.clone(),
+        Arc::new(station_repo),
+        Arc::new(MemoryStopRepo::new(db.clone())),
+        Arc::new(MemoryStatusCodeRepo::new(db.clone())),
+        Arc::new(MemoryUnitOfWorkRepo::new(db.clone())),
+        FilterPolicy::default(),
+    );
+    let settings = PollSettings::default();
+    assert!(settings.uses_recent_changes());
+    let mut import_loop = service.import_loop(settings, None);
+    let interval = TimeDelta::from_std(settings.poll_interval).unwrap();
+
+    // The first poll imports timetables including all changes, then the platforms.
+    import_loop.poll(&start());
+    let mut expected = vec!["plan"; 13];
+    expected.extend(["fchg", "station"]);
+    assert_eq!(expected, client.take_requests());
+
+    // The timetable import counts as a poll, so the next ones only fetch recent changes.
+    import_loop.poll(&(start() + interval));
+    assert_eq!(vec!["rchg"], client.take_requests());
+    assert_eq!(Some(ChangesSyncMode::Recent), last_mode(&service));
+    // Each run reports the requests of the client during the run.
+    assert_eq!(1, service.last_report().unwrap().iris.unwrap().requests);
+    import_loop.poll(&(start() + interval * 2));
+    assert_eq!(vec!["rchg"], client.take_requests());
+
+    // All changes are synced again once the full changes interval has passed.
+    let full_changes = TimeDelta::from_std(settings.full_changes_interval).unwrap();
+    import_loop.poll(&(start() + full_changes));
+    assert_eq!(vec!["fchg"], client.take_requests());
+    assert_eq!(Some(ChangesSyncMode::Full), last_mode(&service));
+    import_loop.poll(&(start() + full_changes + interval));
+    assert_eq!(vec!["rchg"], client.take_requests());
+
+    // A failed poll keeps the last successful fetch, the next one continues with recent changes
+    // while that fetch is within the window.
+    client.failing.store(true, Ordering::SeqCst);
+    import_loop.poll(&(start() + full_changes + interval * 2));
+    assert_eq!(vec!["rchg"], client.take_requests());
+    assert_eq!(1, service.last_report().unwrap().failures.len());
+    client.failing.store(false, Ordering::SeqCst);
+    import_loop.poll(&(start() + full_changes + interval * 3));
+    assert_eq!(vec!["rchg"], client.take_requests());
+}
+
+#[test]
+fn recent_changes_are_chosen_per_station_by_their_last_fetch() {
+    let db = MemoryDb::new();
+    let station_repo = MemoryStationRepo::new(db.clone());
+    station_repo.persist_all(&[station(HAMBURG, "Hamburg Hbf", "AH"), station(ALTONA, "Hamburg-Altona", "ADF")]).unwrap();
+    let client = RecordingClient::default();
+    let before = now_local();
+    let mut last_polls = HashMap::from([
+        (HAMBURG, before - RECENT_CHANGES_WINDOW - TimeDelta::seconds(1)),
+        (ALTONA, before - TimeDelta::seconds(30)),
+    ]);
+
+    // The start of the run does not matter, only when each station was fetched.
+    let run = import_iris_recent_changes(
+        &start(),
+        &mut last_polls,
+        RECENT_CHANGES_WINDOW,
+        &client,
+        &station_repo,
+        &MemoryStopRepo::new(db.clone()),
+        &MemoryUnitOfWorkRepo::new(db.clone()),
+    )
+    .unwrap();
+
+    let modes: HashMap<i32, Option<ChangesSyncMode>> = run.stations.iter().map(|r| (r.station_id.unwrap(), r.mode)).collect();
+    assert_eq!(Some(ChangesSyncMode::Full), modes[&HAMBURG]);
+    assert_eq!(Some(ChangesSyncMode::Recent), modes[&ALTONA]);
+    for report in &run.stations {
+        let fetched_at = report.fetched_at.unwrap();
+        assert!(before <= fetched_at && fetched_at <= now_local());
+        assert_eq!(fetched_at, last_polls[&report.station_id.unwrap()]);
+    }
+}
@@ -0,0 +1,240 @@
+use std::{env, path::PathBuf, sync::Arc, time::Duration};
+
+use chrono::{NaiveDate, Utc};
+
+use crate::{
+    document_archive::{DocumentArchive, DocumentKind, FileArchive, RawDocument},
+    resilience::IrisClientStats,
+    station_dto::{IRISStation, IRISStationError},
+    station_fetch::{parse_station, parse_stations},
+    timetable_dto::{IRISTimetableError, Timetable},
+    timetable_fetch::parse_timetable,
+};
+
+pub const DEFAULT_BASE_URL: &str = "https://iris.noncd.db.de/iris-tts";
+pub const DEFAULT_USER_AGENT: &str = concat!("db-iris-wrapper/", env!("CARGO_PKG_VERSION"));
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Source of IRIS timetable and station documents.
+///
+/// The default implementation is [`HttpIrisClient`], other implementations can
+/// point the importer at mirrors, caches or local stand-ins.
+pub trait IrisClient: Send + Sync {
+    /// Planned timetable (`/timetable/plan/{eva}/{yymmdd}/{HH}`) for one hour.
+    fn get_timetable(&self, eva: i32, date: &NaiveDate, hour: u16) -> Result<Timetable, IRISTimetableError>;
+    /// All known changes (`/timetable/fchg/{eva}`) for a station.
+    fn get_timetable_changes(&self, eva: i32) -> Result<Timetable, IRISTimetableError>;
+    /// Changes that became known within the last two minutes (`/timetable/rchg/{eva}`).
+    fn get_recent_timetable_changes(&self, eva: i32) -> Result<Timetable, IRISTimetableError>;
+    /// Station lookup by DS100 (`/timetable/station/{ds100}`).
+    fn get_station(&self, ds100: &str) -> Result<IRISStation, IRISStationError>;
+    /// Stations found by `/timetable/station/{pattern}`, `pattern` is an EVA, DS100 or name and
+    /// may contain `*` wildcards, e.g. `Hamburg*`.
+    fn search_stations(&self, pattern: &str) -> Result<Vec<IRISStation>, IRISStationError>;
+    /// Station lookup by EVA.
+    fn get_station_by_eva(&self, eva: i32) -> Result<IRISStation, IRISStationError> {
+        self.search_stations(&eva.to_string())?
+            .into_iter()
+            .find(|s| s.eva == eva.to_string())
+            .ok_or_else(|| IRISStationError::NotFound(eva.to_string()))
+    }
+    /// Request statistics, if the client keeps any.
+    fn stats(&self) -> Option<IrisClientStats> {
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpIrisClientConfig {
+    /// Base URL without trailing slash, e.g. `https://iris.noncd.db.de/iris-tts`
+    pub base_url: String,
+    pub timeout: Duration,
+    pub user_agent: String,
+    /// Keeps every fetched document in a [`FileArchive`] at this directory.
+    pub archive_dir: Option<PathBuf>,
+}
+
+impl Default for HttpIrisClientConfig {
+    fn default() -> Self {
+        HttpIrisClientConfig {
+            base_url: DEFAULT_BASE_URL.to_string(),
+            timeout: DEFAULT_TIMEOUT,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            archive_dir: None,
+        }
+    }
+}
+
+impl HttpIrisClientConfig {
+    /// Reads `IRIS_BASE_URL`, `IRIS_TIMEOUT_SECS`, `IRIS_USER_AGENT` and `IRIS_ARCHIVE_DIR`,
+    /// falling back to the defaults for unset or invalid values.
+    pub fn from_env() -> Self {
+        let default = Self::default();
+
+        let base_url = env::var("IRIS_BASE_URL")
+            .map(|url| url.trim_end_matches('/').to_string())
+            .unwrap_or(default.base_url);
+        let timeout = env::var("IRIS_TIMEOUT_SECS")
+            .ok()
+            .and_then(|secs| secs.parse::<u64>().inspect_err(|e| warn!("Invalid IRIS_TIMEOUT_SECS: {}", e)).ok())
+            .map(Duration::from_secs)
+            .unwrap_or(default.timeout);
+        let user_agent = env::var("IRIS_USER_AGENT").unwrap_or(default.user_agent);
+        let archive_dir = env::var("IRIS_ARCHIVE_DIR").ok().filter(|dir| !dir.is_empty()).map(PathBuf::from);
+
+        HttpIrisClientConfig { base_url, timeout, user_agent, archive_dir }
+    }
+}
+
+pub struct HttpIrisClient {
+    config: HttpIrisClientConfig,
+    agent: ureq::Agent,
+    archive: Option<Arc<dyn DocumentArchive>>,
+}
+
+impl Default for HttpIrisClient {
+    fn default() -> Self {
+        Self::new(HttpIrisClientConfig::default())
+    }
+}
+
+impl HttpIrisClient {
+    pub fn new(config: HttpIrisClientConfig) -> Self {
+        let agent = ureq::AgentBuilder::new()
+            .timeout(config.timeout)
+            .user_agent(&config.user_agent)
+            .build();
+        let archive = config.archive_dir.clone().map(|dir| {
+            info!("Archiving IRIS documents in {}", dir.display());
+            Arc::new(FileArchive::new(dir)) as Arc<dyn DocumentArchive>
+        });
+        Self { config, agent, archive }
+    }
+
+    /// Keeps every fetched document in `archive`, replacing the one from `archive_dir`.
+    pub fn with_archive(mut self, archive: Arc<dyn DocumentArchive>) -> Self {
+        self.archive = Some(archive);
+        self
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(HttpIrisClientConfig::from_env())
+    }
+
+    pub fn config(&self) -> &HttpIrisClientConfig {
+        &self.config
+    }
+
+    /// GET `{base_url}{path}`, returns status and body.
+    /// Non-2xx responses are returned as `Ok` so callers can decide how to handle them.
+    fn get<E>(&self, path: &str) -> Result<(u16, String), E>
+    where
+        E: From<Box<ureq::Error>> + From<std::io::Error>,
+    {
+        let url = format!("{}{}", self.config.base_url, path);
+        info!("URL: {}", url);
+
+        let response = match self.agent.get(&url).call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(_, response)) => response,
+            Err(err) => {
+                error!("Error fetching {}: {}", url, err);
+                return Err(Box::new(err).into());
+            }
+        };
+
+        let status = response.status();
+        let body = response.into_string()?;
+        Ok((status, body))
+    }
+
+    /// Stores a fetched document before it is parsed. Failures are only logged,
+    /// the archive must not break imports.
+    fn archive(&self, kind: DocumentKind, station: String, window: Option<(NaiveDate, u16)>, status: u16, body: &str) {
+        let Some(archive) = &self.archive else {
+            return;
+        };
+        let doc = RawDocument { kind, station, window, fetched_at: Utc::now(), status, body: body.to_string() };
+        if let Err(err) = archive.store(&doc) {
+            warn!("Failed to archive {} document of {}: {}", kind, doc.station, err);
+        }
+    }
+}
+
+impl IrisClient for HttpIrisClient {
+    fn get_timetable(&self, eva: i32, date: &NaiveDate, hour: u16) -> Result<Timetable, IRISTimetableError> {
+        let date_str = date.format("%y%m%d").to_string();
+        info!("Fetching timetable for station {} on {} at {:02}", eva, date_str, hour);
+
+        let (status, body) = self.get::<IRISTimetableError>(&format!("/timetable/plan/{}/{}/{:02}", eva, date_str, hour))?;
+        self.archive(DocumentKind::Plan, eva.to_string(), Some((*date, hour)), status, &body);
+        if status != 200 {
+            warn!("Fetching timetable resulted in status code {}", status);
+            return Err(IRISTimetableError::RequestFailed(status, body));
+        }
+
+        parse_timetable(&body, hour.into())
+    }
+
+    fn get_timetable_changes(&self, eva: i32) -> Result<Timetable, IRISTimetableError> {
+        info!("Fetching timetable messages for station {}", eva);
+
+        let (status, body) = self.get::<IRISTimetableError>(&format!("/timetable/fchg/{}", eva))?;
+        self.archive(DocumentKind::Fchg, eva.to_string(), None, status, &body);
+        if status != 200 {
+            warn!("Fetching timetable changes resulted in status code {}", status);
+            return Err(IRISTimetableError::RequestFailed(status, body));
+        }
+
+        parse_timetable(&body, eva)
+    }
+
+    fn get_recent_timetable_changes(&self, eva: i32) -> Result<Timetable, IRISTimetableError> {
+        info!("Fetching recent timetable changes for station {}", eva);
+
+        let (status, body) = self.get::<IRISTimetableError>(&format!("/timetable/rchg/{}", eva))?;
+        self.archive(DocumentKind::Rchg, eva.to_string(), None, status, &body);
+        if status != 200 {
+            warn!("Fetching recent timetable changes resulted in status code {}", status);
+            return Err(IRISTimetableError::RequestFailed(status, body));
+        }
+
+        parse_timetable(&body, eva)
+    }
+
+    fn get_station(&self, ds100: &str) -> Result<IRISStation, IRISStationError> {
+        let (status, body) = self.get::<IRISStationError>(&format!("/timetable/station/{}", encode_path_segment(ds100)))?;
+        self.archive(DocumentKind::Station, ds100.to_string(), None, status, &body);
+        if status != 200 {
+            warn!("Fetching station resulted in status code {}", status);
+            return Err(IRISStationError::RequestFailed(status, body));
+        }
+
+        parse_station(&body, ds100)
+    }
+
+    fn search_stations(&self, pattern: &str) -> Result<Vec<IRISStation>, IRISStationError> {
+        info!("Searching stations for {}", pattern);
+
+        let (status, body) = self.get::<IRISStationError>(&format!("/timetable/station/{}", encode_path_segment(pattern)))?;
+        self.archive(DocumentKind::Station, pattern.to_string(), None, status, &body);
+        if status != 200 {
+            warn!("Searching stations resulted in status code {}", status);
+            return Err(IRISStationError::RequestFailed(status, body));
+        }
+
+        parse_stations(&body)
+    }
+}
+
+/// Percent-encodes everything but unreserved characters and the `*` wildcard.
+pub(crate) fn encode_path_segment(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'*' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
@@ -0,0 +1,248 @@
+use std::{fmt, fs, io, path::{Component, Path, PathBuf}, str::FromStr};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+
+const FETCHED_AT_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+const WINDOW_FORMAT: &str = "%y%m%d%H";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ArchiveError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("invalid archive entry {0}")]
+    InvalidEntry(String),
+}
+
+/// IRIS endpoint a document was fetched from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DocumentKind {
+    /// `/timetable/station/{ds100}`
+    Station,
+    /// `/timetable/plan/{eva}/{yymmdd}/{HH}`
+    Plan,
+    /// `/timetable/fchg/{eva}`
+    Fchg,
+    /// `/timetable/rchg/{eva}`
+    Rchg,
+}
+
+impl DocumentKind {
+    pub const ALL: [DocumentKind; 4] = [DocumentKind::Station, DocumentKind::Plan, DocumentKind::Fchg, DocumentKind::Rchg];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DocumentKind::Station => "station",
+            DocumentKind::Plan => "plan",
+            DocumentKind::Fchg => "fchg",
+            DocumentKind::Rchg => "rchg",
+        }
+    }
+}
+
+impl fmt::Display for DocumentKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for DocumentKind {
+    type Err = ArchiveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DocumentKind::ALL
+            .into_iter()
+            .find(|k| k.as_str() == s)
+            .ok_or_else(|| ArchiveError::InvalidEntry(s.to_string()))
+    }
+}
+
+/// A response body as returned by IRIS, before parsing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawDocument {
+    pub kind: DocumentKind,
+    /// EVA for timetables, DS100 or search pattern for station lookups.
+    pub station: String,
+    /// Date and hour of a planned timetable.
+    pub window: Option<(NaiveDate, u16)>,
+    pub fetched_at: DateTime<Utc>,
+    pub status: u16,
+    pub body: String,
+}
+
+impl RawDocument {
+    /// EVA of a timetable document.
+    pub fn eva(&self) -> Option<i32> {
+        match self.kind {
+            DocumentKind::Station => None,
+            _ => self.station.parse().ok(),
+        }
+    }
+}
+
+/// Filter for [`DocumentArchive::load`], unset fields match everything.
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveQuery {
+    pub kinds: Option<Vec<DocumentKind>>,
+    /// EVA, DS100 or search pattern, see [`RawDocument::station`].
+    pub station: Option<String>,
+    /// Inclusive.
+    pub fetched_from: Option<DateTime<Utc>>,
+    /// Exclusive.
+    pub fetched_until: Option<DateTime<Utc>>,
+}
+
+impl ArchiveQuery {
+    pub fn matches(&self, doc: &RawDocument) -> bool {
+        self.kinds.as_ref().is_none_or(|kinds| kinds.contains(&doc.kind))
+            && self.station.as_ref().is_none_or(|s| *s == doc.station)
+            && self.fetched_from.is_none_or(|from| doc.fetched_at >= from)
+            && self.fetched_until.is_none_or(|until| doc.fetched_at < until)
+    }
+}
+
+/// Storage for raw IRIS responses, so they can be inspected and ingested again later.
+pub trait DocumentArchive: Send + Sync {
+    fn store(&self, doc: &RawDocument) -> Result<(), ArchiveError>;
+    /// Matching documents ordered by fetch time.
+    fn load(&self, query: &ArchiveQuery) -> Result<Vec<RawDocument>, ArchiveError>;
+}
+
+/// Keeps each document in its own file:
+/// `{root}/{kind}/{station}/{fetched_at}[_{yymmddHH}]_{status}.xml`, times in UTC.
+pub struct FileArchive {
+    root: PathBuf,
+}
+
+impl FileArchive {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn file_name(doc: &RawDocument) -> String {
+        let mut name = doc.fetched_at.format(FETCHED_AT_FORMAT).to_string();
+        if let Some((date, hour)) = doc.window {
+            let window = date.and_hms_opt(hour.into(), 0, 0).unwrap_or_default();
+            name.push('_');
+            name.push_str(&window.format(WINDOW_FORMAT).to_string());
+        }
+        format!("{}_{}.xml", name, doc.status)
+    }
+
+    fn read_entry(kind: DocumentKind, station: &str, path: &Path) -> Result<RawDocument, ArchiveError> {
+        let invalid = || ArchiveError::InvalidEntry(path.display().to_string());
+        let name = path.file_name().and_then(|n| n.to_str()).and_then(|n| n.strip_suffix(".xml")).ok_or_else(invalid)?;
+
+        let parts: Vec<&str> = name.split('_').collect();
+        let (fetched_at, window, status) = match parts.as_slice() {
+            [fetched_at, status] => (fetched_at, None, status),
+            [fetched_at, window, status] => (fetched_at, Some(window), status),
+            _ => return Err(invalid()),
+        };
+        let fetched_at = NaiveDateTime::parse_from_str(fetched_at, FETCHED_AT_FORMAT).map_err(|_| invalid())?.and_utc();
+        let window = match window {
+            Some(w) => {
+                let start = NaiveDateTime::parse_from_str(&format!("{}00", w), "%y%m%d%H%M").map_err(|_| invalid())?;
+                Some((start.date(), chrono::Timelike::hour(&start) as u16))
+            }
+            None => None,
+        };
+        let status = status.parse().map_err(|_| invalid())?;
+
+        Ok(RawDocument {
+            kind,
+            station: station.to_string(),
+            window,
+            fetched_at,
+            status,
+            body: fs::read_to_string(path)?,
+        })
+    }
+}
+
+impl DocumentArchive for FileArchive {
+    fn store(&self, doc: &RawDocument) -> Result<(), ArchiveError> {
+        // Station searches are keyed by their pattern, which must not leave the kind directory.
+        let mut components = Path::new(&doc.station).components();
+        if !matches!((components.next(), components.next()), (Some(Component::Normal(_)), None)) {
+            return Err(ArchiveError::InvalidEntry(doc.station.clone()));
+        }
+        let dir = self.root.join(doc.kind.as_str()).join(&doc.station);
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join(Self::file_name(doc)), &doc.body)?;
+        Ok(())
+    }
+
+    fn load(&self, query: &ArchiveQuery) -> Result<Vec<RawDocument>, ArchiveError> {
+        let mut docs = Vec::new();
+        for kind in DocumentKind::ALL {
+            if query.kinds.as_ref().is_some_and(|kinds| !kinds.contains(&kind)) {
+                continue;
+            }
+            let kind_dir = self.root.join(kind.as_str());
+            if !kind_dir.is_dir() {
+                continue;
+            }
+            for station_dir in fs::read_dir(kind_dir)? {
+                let station_dir = station_dir?.path();
+                let Some(station) = station_dir.file_name().and_then(|n| n.to_str()).map(str::to_string) else {
+                    continue;
+                };
+                if query.station.as_ref().is_some_and(|s| *s != station) {
+                    continue;
+                }
+                for entry in fs::read_dir(&station_dir)? {
+                    let doc = Self::read_entry(kind, &station, &entry?.path())?;
+                    if query.matches(&doc) {
+                        docs.push(doc);
+                    }
+                }
+            }
+        }
+        docs.sort_by(|a, b| a.fetched_at.cmp(&b.fetched_at).then(a.kind.cmp(&b.kind)));
+        Ok(docs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn doc(kind: DocumentKind, window: Option<(NaiveDate, u16)>, secs: i64) -> RawDocument {
+        RawDocument {
+            kind,
+            station: "8002549".to_string(),
+            window,
+            fetched_at: DateTime::from_timestamp(1_757_494_800 + secs, 250_000_000).unwrap(),
+            status: 200,
+            body: "<timetable/>".to_string(),
+        }
+    }
+
+    #[test]
+    fn file_archive_round_trips_documents_in_fetch_order() {
+        let root = env::temp_dir().join(format!("iris-archive-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let archive = FileArchive::new(&root);
+
+        let date = NaiveDate::from_ymd_opt(2025, 9, 10).unwrap();
+        let fchg = doc(DocumentKind::Fchg, None, 60);
+        let plan = doc(DocumentKind::Plan, Some((date, 11)), 0);
+        archive.store(&fchg).unwrap();
+        archive.store(&plan).unwrap();
+
+        assert_eq!(vec![plan.clone(), fchg.clone()], archive.load(&ArchiveQuery::default()).unwrap());
+        assert_eq!(Some(8002549), plan.eva());
+
+        let query = ArchiveQuery { fetched_from: Some(fchg.fetched_at), ..Default::default() };
+        assert_eq!(vec![fchg], archive.load(&query).unwrap());
+        let query = ArchiveQuery { station: Some("AH".to_string()), ..Default::default() };
+        assert!(archive.load(&query).unwrap().is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,113 @@
+use std::{fs, io, path::{Path, PathBuf}};
+
+use chrono::NaiveDate;
+
+use crate::{
+    client::IrisClient,
+    station_dto::{IRISStation, IRISStationError},
+    station_fetch::{parse_station, parse_stations, station_matches},
+    timetable_dto::{IRISTimetableError, Timetable},
+    timetable_fetch::parse_timetable,
+};
+
+/// Reads IRIS documents from a directory instead of the API, e.g. dumps from partners.
+///
+/// Expected file names, as IRIS would return them for the corresponding request:
+/// - `plan_{eva}_{yymmddHH}00.xml` planned timetable of one hour
+/// - `fchg_{eva}.xml` all known changes
+/// - `rchg_{eva}.xml` recent changes
+/// - `station_{ds100}.xml` station lookup
+///
+/// Missing timetables and changes count as empty, like hours without trains.
+/// A missing station file is [`IRISStationError::NotFound`], searches go through all station files.
+#[derive(Debug, Clone)]
+pub struct FileIrisClient {
+    dir: PathBuf,
+}
+
+impl FileIrisClient {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Contents of `name`, `None` if the file does not exist.
+    fn read(&self, name: &str) -> io::Result<Option<String>> {
+        let path = self.dir.join(name);
+        debug!("Reading {}", path.display());
+        match fs::read_to_string(&path) {
+            Ok(body) => Ok(Some(body)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => {
+                error!("Error reading {}: {}", path.display(), err);
+                Err(err)
+            }
+        }
+    }
+
+    fn read_timetable(&self, name: &str, empty_id: i32) -> Result<Timetable, IRISTimetableError> {
+        match self.read(name)? {
+            Some(body) => parse_timetable(&body, empty_id),
+            None => Err(IRISTimetableError::EmptyTimetable(empty_id)),
+        }
+    }
+}
+
+pub fn plan_file_name(eva: i32, date: &NaiveDate, hour: u16) -> String {
+    format!("plan_{}_{}{:02}00.xml", eva, date.format("%y%m%d"), hour)
+}
+
+pub fn fchg_file_name(eva: i32) -> String {
+    format!("fchg_{}.xml", eva)
+}
+
+pub fn rchg_file_name(eva: i32) -> String {
+    format!("rchg_{}.xml", eva)
+}
+
+pub fn station_file_name(ds100: &str) -> String {
+    format!("station_{}.xml", ds100)
+}
+
+impl IrisClient for FileIrisClient {
+    fn get_timetable(&self, eva: i32, date: &NaiveDate, hour: u16) -> Result<Timetable, IRISTimetableError> {
+        self.read_timetable(&plan_file_name(eva, date, hour), hour.into())
+    }
+
+    fn get_timetable_changes(&self, eva: i32) -> Result<Timetable, IRISTimetableError> {
+        self.read_timetable(&fchg_file_name(eva), eva)
+    }
+
+    fn get_recent_timetable_changes(&self, eva: i32) -> Result<Timetable, IRISTimetableError> {
+        self.read_timetable(&rchg_file_name(eva), eva)
+    }
+
+    fn get_station(&self, ds100: &str) -> Result<IRISStation, IRISStationError> {
+        match self.read(&station_file_name(ds100))? {
+            Some(body) => parse_station(&body, ds100),
+            None => Err(IRISStationError::NotFound(ds100.to_string())),
+        }
+    }
+
+    fn search_stations(&self, pattern: &str) -> Result<Vec<IRISStation>, IRISStationError> {
+        let mut found = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let is_station_file = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("station_") && n.ends_with(".xml"));
+            if !is_station_file {
+                continue;
+            }
+            let stations = parse_stations(&fs::read_to_string(&path)?)?;
+            found.extend(stations.into_iter().filter(|s| station_matches(s, pattern)));
+        }
+        found.sort_by(|a, b| a.name.cmp(&b.name));
+        found.dedup_by(|a, b| a.eva == b.eva);
+        Ok(found)
+    }
+}
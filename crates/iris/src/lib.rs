@@ -1,6 +1,12 @@
 #[macro_use] extern crate log;
 
 
+mod client;
+mod document_archive;
+mod file_client;
+#[cfg(feature = "mock")]
+mod mock_server;
+mod resilience;
 mod station_dto;
 mod station_fetch;
 mod timetable_dto;
@@ -8,11 +14,25 @@ mod timetable_fetch;
 
 
 pub mod fetch {
+    pub use crate::client::{*};
+    pub use crate::file_client::{*};
+    pub use crate::resilience::{*};
     pub use crate::timetable_fetch::{*};
     pub use crate::station_fetch::{*};
 }
 
+/// Raw IRIS responses kept for inspection and replay.
+pub mod archive {
+    pub use crate::document_archive::{*};
+}
+
 pub mod dto {
     pub use crate::station_dto::{*};
     pub use crate::timetable_dto::{*};
 }
+
+/// Local IRIS stand-in for offline tests.
+#[cfg(feature = "mock")]
+pub mod mock {
+    pub use crate::mock_server::{*};
+}
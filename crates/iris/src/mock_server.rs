@@ -0,0 +1,191 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use chrono::NaiveDate;
+
+use crate::client::{encode_path_segment, HttpIrisClient, HttpIrisClientConfig};
+
+/// Body IRIS returns for hours and stations without data.
+pub const EMPTY_TIMETABLE: &str = "<?xml version='1.0' encoding='UTF-8'?>\n<timetable/>\n";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+type Routes = Arc<Mutex<HashMap<String, MockResponse>>>;
+
+/// Local stand-in for the IRIS API serving recorded documents over HTTP.
+///
+/// Responses are registered per path, unknown paths are answered with `404`.
+/// Point an [`HttpIrisClient`] at [`MockIrisServer::base_url`] or use [`MockIrisServer::client`].
+/// The server stops when dropped.
+pub struct MockIrisServer {
+    addr: SocketAddr,
+    routes: Routes,
+    requests: Arc<Mutex<Vec<String>>>,
+    shutdown: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MockIrisServer {
+    /// Binds to a free port on localhost and serves requests on a background thread.
+    pub fn start() -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let routes: Routes = Arc::default();
+        let requests: Arc<Mutex<Vec<String>>> = Arc::default();
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let handle = {
+            let (routes, requests, shutdown) = (routes.clone(), requests.clone(), shutdown.clone());
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if shutdown.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    match stream {
+                        Ok(stream) => {
+                            if let Err(e) = serve(stream, &routes, &requests) {
+                                warn!("Mock IRIS server failed to answer: {}", e);
+                            }
+                        }
+                        Err(e) => warn!("Mock IRIS server failed to accept: {}", e),
+                    }
+                }
+            })
+        };
+        info!("Mock IRIS server listening on {}", addr);
+
+        Ok(Self { addr, routes, requests, shutdown, handle: Some(handle) })
+    }
+
+    /// `http://127.0.0.1:{port}`, usable as `IRIS_BASE_URL`.
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// A client with the default config pointed at this server.
+    pub fn client(&self) -> HttpIrisClient {
+        HttpIrisClient::new(HttpIrisClientConfig { base_url: self.base_url(), ..Default::default() })
+    }
+
+    /// Answers GET `path` with `status` and `body`, replacing an earlier response.
+    pub fn respond(&self, path: &str, status: u16, body: &str) -> &Self {
+        let response = MockResponse { status, body: body.to_string() };
+        self.routes.lock().unwrap_or_else(|e| e.into_inner()).insert(path.to_string(), response);
+        self
+    }
+
+    /// Serves `xml` at `/timetable/station/{pattern}`, `pattern` being a DS100, EVA, name or wildcard.
+    pub fn station(&self, pattern: &str, xml: &str) -> &Self {
+        self.respond(&station_path(pattern), 200, xml)
+    }
+
+    /// Serves `xml` at `/timetable/plan/{eva}/{yymmdd}/{HH}`.
+    pub fn plan(&self, eva: i32, date: &NaiveDate, hour: u16, xml: &str) -> &Self {
+        self.respond(&plan_path(eva, date, hour), 200, xml)
+    }
+
+    /// Serves `<timetable/>` for an hour without trains.
+    pub fn empty_plan(&self, eva: i32, date: &NaiveDate, hour: u16) -> &Self {
+        self.plan(eva, date, hour, EMPTY_TIMETABLE)
+    }
+
+    /// Serves `xml` at `/timetable/fchg/{eva}`.
+    pub fn fchg(&self, eva: i32, xml: &str) -> &Self {
+        self.respond(&fchg_path(eva), 200, xml)
+    }
+
+    /// Serves `xml` at `/timetable/rchg/{eva}`.
+    pub fn rchg(&self, eva: i32, xml: &str) -> &Self {
+        self.respond(&rchg_path(eva), 200, xml)
+    }
+
+    /// Paths requested so far, in order.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+impl Drop for MockIrisServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // Wakes the blocking accept so the thread sees the flag.
+        let _ = TcpStream::connect(self.addr);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Percent-encoded like the request of [`HttpIrisClient`].
+pub fn station_path(pattern: &str) -> String {
+    format!("/timetable/station/{}", encode_path_segment(pattern))
+}
+
+pub fn plan_path(eva: i32, date: &NaiveDate, hour: u16) -> String {
+    format!("/timetable/plan/{}/{}/{:02}", eva, date.format("%y%m%d"), hour)
+}
+
+pub fn fchg_path(eva: i32) -> String {
+    format!("/timetable/fchg/{}", eva)
+}
+
+pub fn rchg_path(eva: i32) -> String {
+    format!("/timetable/rchg/{}", eva)
+}
+
+/// Answers a single request and closes the connection.
+fn serve(stream: TcpStream, routes: &Routes, requests: &Mutex<Vec<String>>) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(&stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Headers are not needed, but have to be read before answering.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && header != "\r\n" {
+        header.clear();
+    }
+
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/").to_string();
+    debug!("Mock IRIS server: {}", request_line.trim_end());
+    let response = routes.lock().unwrap_or_else(|e| e.into_inner())
+        .get(&path)
+        .cloned()
+        .unwrap_or_else(|| MockResponse { status: 404, body: String::new() });
+    requests.lock().unwrap_or_else(|e| e.into_inner()).push(path);
+
+    let mut stream = &stream;
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: text/xml; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        reason(response.status),
+        response.body.len(),
+    )?;
+    stream.write_all(response.body.as_bytes())?;
+    stream.flush()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        404 => "Not Found",
+        410 => "Gone",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Unknown",
+    }
+}
@@ -0,0 +1,557 @@
+use std::{
+    collections::hash_map::RandomState,
+    env, fmt,
+    hash::{BuildHasher, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use chrono::NaiveDate;
+
+use crate::{
+    client::IrisClient,
+    station_dto::{IRISStation, IRISStationError},
+    timetable_dto::{IRISTimetableError, Timetable},
+};
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt, `0` disables retrying.
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for every further retry.
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Share of the delay that is randomized, `0.0..=1.0`.
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: 0.5,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Exponential backoff for the given retry (starting at 0) with jitter applied.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let exp = self.base_delay.saturating_mul(2u32.saturating_pow(retry));
+        let delay = exp.min(self.max_delay);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        delay.mul_f64(1.0 - jitter + jitter * random_unit())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ResilienceConfig {
+    pub retry: RetryPolicy,
+    /// Requests per second across all fetches, `0.0` disables the limiter.
+    pub requests_per_second: f64,
+    /// Consecutive failed requests after which the circuit opens.
+    pub failure_threshold: u32,
+    /// How long the circuit stays open before a trial request is let through.
+    pub cooldown: Duration,
+}
+
+impl Default for ResilienceConfig {
+    fn default() -> Self {
+        ResilienceConfig {
+            retry: RetryPolicy::default(),
+            requests_per_second: 10.0,
+            failure_threshold: 5,
+            cooldown: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+fn parse_env<T: std::str::FromStr>(name: &str) -> Option<T>
+where
+    T::Err: std::fmt::Display,
+{
+    env::var(name)
+        .ok()
+        .and_then(|v| v.parse::<T>().inspect_err(|e| warn!("Invalid {}: {}", name, e)).ok())
+}
+
+impl ResilienceConfig {
+    /// Reads `IRIS_MAX_RETRIES`, `IRIS_RETRY_BASE_MS`, `IRIS_RETRY_MAX_MS`, `IRIS_RETRY_JITTER`,
+    /// `IRIS_REQUESTS_PER_SECOND`, `IRIS_CIRCUIT_FAILURES` and `IRIS_CIRCUIT_COOLDOWN_SECS`,
+    /// falling back to the defaults for unset or invalid values.
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        ResilienceConfig {
+            retry: RetryPolicy {
+                max_retries: parse_env("IRIS_MAX_RETRIES").unwrap_or(default.retry.max_retries),
+                base_delay: parse_env("IRIS_RETRY_BASE_MS").map(Duration::from_millis).unwrap_or(default.retry.base_delay),
+                max_delay: parse_env("IRIS_RETRY_MAX_MS").map(Duration::from_millis).unwrap_or(default.retry.max_delay),
+                jitter: parse_env("IRIS_RETRY_JITTER").unwrap_or(default.retry.jitter),
+            },
+            requests_per_second: parse_env("IRIS_REQUESTS_PER_SECOND").unwrap_or(default.requests_per_second),
+            failure_threshold: parse_env("IRIS_CIRCUIT_FAILURES").unwrap_or(default.failure_threshold),
+            cooldown: parse_env("IRIS_CIRCUIT_COOLDOWN_SECS").map(Duration::from_secs).unwrap_or(default.cooldown),
+        }
+    }
+}
+
+/// Uniformly distributed value in `0.0..1.0`, good enough for jitter.
+fn random_unit() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    if let Ok(now) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        hasher.write_u128(now.as_nanos());
+    }
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Spaces requests evenly so no more than `requests_per_second` are sent.
+pub struct RateLimiter {
+    min_interval: Option<Duration>,
+    next_slot: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_second: f64) -> Self {
+        let min_interval = (requests_per_second > 0.0).then(|| Duration::from_secs_f64(1.0 / requests_per_second));
+        RateLimiter { min_interval, next_slot: Mutex::new(Instant::now()) }
+    }
+
+    /// Blocks until the next request may be sent, returns how long it waited.
+    pub fn acquire(&self) -> Duration {
+        let Some(min_interval) = self.min_interval else {
+            return Duration::ZERO;
+        };
+
+        let wait = {
+            let mut next_slot = self.next_slot.lock().unwrap_or_else(|poison| poison.into_inner());
+            let now = Instant::now();
+            let slot = (*next_slot).max(now);
+            *next_slot = slot + min_interval;
+            slot - now
+        };
+
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+        wait
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests pass.
+    Closed,
+    /// Requests are rejected until the cooldown has passed.
+    Open { until: Instant },
+    /// Cooldown has passed and a trial request is in flight, it decides whether the circuit
+    /// closes again. Other requests are rejected meanwhile.
+    HalfOpen,
+}
+
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    inner: Mutex<(CircuitState, u32)>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        CircuitBreaker {
+            failure_threshold,
+            cooldown,
+            inner: Mutex::new((CircuitState::Closed, 0)),
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.inner.lock().unwrap_or_else(|poison| poison.into_inner()).0
+    }
+
+    /// `Err(remaining)` while the circuit is open, `Err(Duration::ZERO)` while the half-open
+    /// trial request is in flight.
+    pub fn check(&self) -> Result<(), Duration> {
+        let mut inner = self.inner.lock().unwrap_or_else(|poison| poison.into_inner());
+        match inner.0 {
+            CircuitState::Open { until } => {
+                let now = Instant::now();
+                if now < until {
+                    return Err(until - now);
+                }
+                info!("IRIS circuit half-open, letting a trial request through");
+                inner.0 = CircuitState::HalfOpen;
+                Ok(())
+            }
+            CircuitState::HalfOpen => Err(Duration::ZERO),
+            CircuitState::Closed => Ok(()),
+        }
+    }
+
+    pub fn record_success(&self) {
+        let mut inner = self.inner.lock().unwrap_or_else(|poison| poison.into_inner());
+        if inner.0 != CircuitState::Closed {
+            info!("IRIS circuit closed again");
+        }
+        *inner = (CircuitState::Closed, 0);
+    }
+
+    /// Returns `true` if this failure opened the circuit.
+    pub fn record_failure(&self) -> bool {
+        let mut inner = self.inner.lock().unwrap_or_else(|poison| poison.into_inner());
+        inner.1 += 1;
+        let should_open = inner.0 == CircuitState::HalfOpen || inner.1 >= self.failure_threshold.max(1);
+        if should_open && !matches!(inner.0, CircuitState::Open { .. }) {
+            warn!(
+                "IRIS circuit opened after {} consecutive failures, pausing requests for {:?}",
+                inner.1, self.cooldown
+            );
+            inner.0 = CircuitState::Open { until: Instant::now() + self.cooldown };
+            return true;
+        }
+        false
+    }
+}
+
+/// Counters of the decisions taken by [`ResilientIrisClient`].
+#[derive(Debug, Default)]
+pub struct ResilienceStats {
+    requests: AtomicU64,
+    retries: AtomicU64,
+    failures: AtomicU64,
+    throttled: AtomicU64,
+    throttled_ms: AtomicU64,
+    circuit_opened: AtomicU64,
+    rejected: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IrisClientStats {
+    /// Requests sent, including retries.
+    pub requests: u64,
+    pub retries: u64,
+    /// Calls that failed after all retries.
+    pub failures: u64,
+    /// Requests delayed by the rate limiter and the total delay.
+    pub throttled: u64,
+    pub throttled_ms: u64,
+    pub circuit_opened: u64,
+    /// Calls rejected while the circuit was open.
+    pub rejected: u64,
+}
+
+impl IrisClientStats {
+    /// Counts since `earlier`, e.g. of a single import run.
+    pub fn since(&self, earlier: &IrisClientStats) -> IrisClientStats {
+        IrisClientStats {
+            requests: self.requests.saturating_sub(earlier.requests),
+            retries: self.retries.saturating_sub(earlier.retries),
+            failures: self.failures.saturating_sub(earlier.failures),
+            throttled: self.throttled.saturating_sub(earlier.throttled),
+            throttled_ms: self.throttled_ms.saturating_sub(earlier.throttled_ms),
+            circuit_opened: self.circuit_opened.saturating_sub(earlier.circuit_opened),
+            rejected: self.rejected.saturating_sub(earlier.rejected),
+        }
+    }
+}
+
+impl fmt::Display for IrisClientStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} requests, {} retries, {} failures, {} throttled ({} ms), circuit opened {} times, {} rejected",
+            self.requests, self.retries, self.failures, self.throttled, self.throttled_ms, self.circuit_opened, self.rejected
+        )
+    }
+}
+
+impl ResilienceStats {
+    pub fn snapshot(&self) -> IrisClientStats {
+        IrisClientStats {
+            requests: self.requests.load(Ordering::Relaxed),
+            retries: self.retries.load(Ordering::Relaxed),
+            failures: self.failures.load(Ordering::Relaxed),
+            throttled: self.throttled.load(Ordering::Relaxed),
+            throttled_ms: self.throttled_ms.load(Ordering::Relaxed),
+            circuit_opened: self.circuit_opened.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+trait ResilientError: std::fmt::Display {
+    fn is_retryable(&self) -> bool;
+    fn circuit_open(remaining: Duration) -> Self;
+}
+
+impl ResilientError for IRISTimetableError {
+    fn is_retryable(&self) -> bool {
+        IRISTimetableError::is_retryable(self)
+    }
+    fn circuit_open(remaining: Duration) -> Self {
+        IRISTimetableError::CircuitOpen(remaining)
+    }
+}
+
+impl ResilientError for IRISStationError {
+    fn is_retryable(&self) -> bool {
+        IRISStationError::is_retryable(self)
+    }
+    fn circuit_open(remaining: Duration) -> Self {
+        IRISStationError::CircuitOpen(remaining)
+    }
+}
+
+/// Wraps another [`IrisClient`] with retries, a shared rate limit and a circuit breaker.
+pub struct ResilientIrisClient<C: IrisClient> {
+    inner: C,
+    retry: RetryPolicy,
+    limiter: RateLimiter,
+    breaker: CircuitBreaker,
+    stats: ResilienceStats,
+}
+
+impl<C: IrisClient> ResilientIrisClient<C> {
+    pub fn new(inner: C, config: ResilienceConfig) -> Self {
+        ResilientIrisClient {
+            inner,
+            retry: config.retry,
+            limiter: RateLimiter::new(config.requests_per_second),
+            breaker: CircuitBreaker::new(config.failure_threshold, config.cooldown),
+            stats: ResilienceStats::default(),
+        }
+    }
+
+    pub fn circuit_state(&self) -> CircuitState {
+        self.breaker.state()
+    }
+
+    fn call<T, E: ResilientError>(&self, what: &str, f: impl Fn(&C) -> Result<T, E>) -> Result<T, E> {
+        if let Err(remaining) = self.breaker.check() {
+            self.stats.rejected.fetch_add(1, Ordering::Relaxed);
+            debug!("Rejecting {}, IRIS circuit open for another {:?}", what, remaining);
+            return Err(E::circuit_open(remaining));
+        }
+
+        let mut retry = 0;
+        loop {
+            let waited = self.limiter.acquire();
+            if !waited.is_zero() {
+                self.stats.throttled.fetch_add(1, Ordering::Relaxed);
+                self.stats.throttled_ms.fetch_add(waited.as_millis() as u64, Ordering::Relaxed);
+                debug!("Rate limited {} for {:?}", what, waited);
+            }
+
+            self.stats.requests.fetch_add(1, Ordering::Relaxed);
+            let err = match f(&self.inner) {
+                Ok(value) => {
+                    self.breaker.record_success();
+                    return Ok(value);
+                }
+                Err(err) if !err.is_retryable() => {
+                    // The request reached IRIS, e.g. an empty timetable or a parse error.
+                    self.breaker.record_success();
+                    return Err(err);
+                }
+                Err(err) => err,
+            };
+
+            if retry >= self.retry.max_retries {
+                self.stats.failures.fetch_add(1, Ordering::Relaxed);
+                error!("Giving up on {} after {} retries: {}", what, retry, err);
+                if self.breaker.record_failure() {
+                    self.stats.circuit_opened.fetch_add(1, Ordering::Relaxed);
+                }
+                return Err(err);
+            }
+
+            let delay = self.retry.delay(retry);
+            warn!("{} failed ({}), retry {}/{} in {:?}", what, err, retry + 1, self.retry.max_retries, delay);
+            self.stats.retries.fetch_add(1, Ordering::Relaxed);
+            thread::sleep(delay);
+            retry += 1;
+        }
+    }
+}
+
+impl<C: IrisClient> IrisClient for ResilientIrisClient<C> {
+    fn get_timetable(&self, eva: i32, date: &NaiveDate, hour: u16) -> Result<Timetable, IRISTimetableError> {
+        self.call(&format!("plan {} {} {:02}", eva, date, hour), |c| c.get_timetable(eva, date, hour))
+    }
+
+    fn get_timetable_changes(&self, eva: i32) -> Result<Timetable, IRISTimetableError> {
+        self.call(&format!("fchg {}", eva), |c| c.get_timetable_changes(eva))
+    }
+
+    fn get_recent_timetable_changes(&self, eva: i32) -> Result<Timetable, IRISTimetableError> {
+        self.call(&format!("rchg {}", eva), |c| c.get_recent_timetable_changes(eva))
+    }
+
+    fn get_station(&self, ds100: &str) -> Result<IRISStation, IRISStationError> {
+        self.call(&format!("station {}", ds100), |c| c.get_station(ds100))
+    }
+
+    fn search_stations(&self, pattern: &str) -> Result<Vec<IRISStation>, IRISStationError> {
+        self.call(&format!("station search {}", pattern), |c| c.search_stations(pattern))
+    }
+
+    fn stats(&self) -> Option<IrisClientStats> {
+        Some(self.stats.snapshot())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicU32;
+
+    /// Fails `failures` times with a 503, then returns an empty timetable.
+    struct FlakyClient {
+        failures: u32,
+        calls: AtomicU32,
+    }
+
+    impl IrisClient for FlakyClient {
+        fn get_timetable(&self, eva: i32, _date: &NaiveDate, _hour: u16) -> Result<Timetable, IRISTimetableError> {
+            self.get_timetable_changes(eva)
+        }
+
+        fn get_timetable_changes(&self, eva: i32) -> Result<Timetable, IRISTimetableError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            if call < self.failures {
+                return Err(IRISTimetableError::RequestFailed(503, String::new()));
+            }
+            Ok(Timetable { station: "Hamburg Hbf".to_string(), eva: Some(eva.to_string()), stops: Vec::new() })
+        }
+
+        fn get_recent_timetable_changes(&self, eva: i32) -> Result<Timetable, IRISTimetableError> {
+            self.get_timetable_changes(eva)
+        }
+
+        fn get_station(&self, ds100: &str) -> Result<IRISStation, IRISStationError> {
+            Err(IRISStationError::NotFound(ds100.to_string()))
+        }
+
+        fn search_stations(&self, _pattern: &str) -> Result<Vec<IRISStation>, IRISStationError> {
+            Ok(Vec::new())
+        }
+    }
+
+    fn config(max_retries: u32, failure_threshold: u32) -> ResilienceConfig {
+        ResilienceConfig {
+            retry: RetryPolicy {
+                max_retries,
+                base_delay: Duration::from_millis(1),
+                max_delay: Duration::from_millis(2),
+                jitter: 0.5,
+            },
+            requests_per_second: 0.0,
+            failure_threshold,
+            cooldown: Duration::from_secs(60),
+        }
+    }
+
+    fn flaky(failures: u32) -> FlakyClient {
+        FlakyClient { failures, calls: AtomicU32::new(0) }
+    }
+
+    #[test]
+    fn retry_delay_grows_exponentially_and_is_capped() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(500),
+            jitter: 0.0,
+        };
+
+        assert_eq!(Duration::from_millis(100), policy.delay(0));
+        assert_eq!(Duration::from_millis(200), policy.delay(1));
+        assert_eq!(Duration::from_millis(400), policy.delay(2));
+        assert_eq!(Duration::from_millis(500), policy.delay(3));
+    }
+
+    #[test]
+    fn retry_delay_jitter_stays_within_bounds() {
+        let policy = RetryPolicy { jitter: 1.0, ..RetryPolicy::default() };
+        for _ in 0..100 {
+            assert!(policy.delay(0) <= policy.base_delay);
+        }
+    }
+
+    #[test]
+    fn resilient_client_retries_transient_failures() {
+        let client = ResilientIrisClient::new(flaky(2), config(3, 5));
+
+        assert!(client.get_timetable_changes(8002549).is_ok());
+
+        let stats = client.stats().unwrap();
+        assert_eq!(3, stats.requests);
+        assert_eq!(2, stats.retries);
+        assert_eq!(0, stats.failures);
+    }
+
+    #[test]
+    fn resilient_client_does_not_retry_permanent_errors() {
+        let client = ResilientIrisClient::new(flaky(0), config(3, 5));
+
+        assert!(matches!(client.get_station("XX"), Err(IRISStationError::NotFound(_))));
+        assert_eq!(1, client.stats().unwrap().requests);
+        assert_eq!(CircuitState::Closed, client.circuit_state());
+    }
+
+    #[test]
+    fn resilient_client_opens_circuit_after_repeated_failures() {
+        let client = ResilientIrisClient::new(flaky(u32::MAX), config(0, 2));
+
+        assert!(client.get_timetable_changes(1).is_err());
+        assert_eq!(CircuitState::Closed, client.circuit_state());
+        assert!(client.get_timetable_changes(1).is_err());
+        assert!(matches!(client.circuit_state(), CircuitState::Open { .. }));
+
+        let err = client.get_timetable_changes(1).unwrap_err();
+        assert!(matches!(err, IRISTimetableError::CircuitOpen(_)));
+
+        let stats = client.stats().unwrap();
+        assert_eq!(2, stats.requests);
+        assert_eq!(1, stats.circuit_opened);
+        assert_eq!(1, stats.rejected);
+
+        assert!(client.get_timetable_changes(1).is_err());
+        let run = client.stats().unwrap().since(&stats);
+        assert_eq!(IrisClientStats { rejected: 1, ..Default::default() }, run);
+    }
+
+    #[test]
+    fn half_open_circuit_lets_a_single_trial_request_through() {
+        let breaker = CircuitBreaker::new(1, Duration::ZERO);
+        assert!(breaker.record_failure());
+
+        assert!(breaker.check().is_ok());
+        assert_eq!(CircuitState::HalfOpen, breaker.state());
+        assert_eq!(Err(Duration::ZERO), breaker.check());
+
+        assert!(breaker.record_failure());
+        assert!(breaker.check().is_ok());
+        assert_eq!(Err(Duration::ZERO), breaker.check());
+
+        breaker.record_success();
+        assert_eq!(CircuitState::Closed, breaker.state());
+        assert!(breaker.check().is_ok());
+        assert!(breaker.check().is_ok());
+    }
+
+    #[test]
+    fn rate_limiter_spaces_requests() {
+        let limiter = RateLimiter::new(100.0);
+        let start = Instant::now();
+        for _ in 0..5 {
+            limiter.acquire();
+        }
+        assert!(start.elapsed() >= Duration::from_millis(40));
+    }
+}
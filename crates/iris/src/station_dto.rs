@@ -12,6 +12,8 @@
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Deserializer, Serialize};
 
+use crate::timetable_dto::is_retryable_status;
+
 
 
 #[derive(thiserror::Error, Debug)]
@@ -28,12 +30,28 @@ pub enum IRISStationError {
     Json(#[from] serde_json::Error),
     #[error("invalid src format {0}")]
     InvalidSourceFormat(String),
+    #[error("status {0}, error_response: {1}")]
+    RequestFailed(u16, String),
+    #[error("IRIS circuit open, retry in {0:?}")]
+    CircuitOpen(std::time::Duration),
+}
+
+impl IRISStationError {
+    /// Network and IO failures, `429` and `5xx` responses are worth retrying.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            IRISStationError::Network(_) | IRISStationError::Io(_) => true,
+            IRISStationError::RequestFailed(status, _) => is_retryable_status(*status),
+            _ => false,
+        }
+    }
 }
 
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Stations {
-    #[serde(rename = "station")]
+    /// Empty for lookups without results (`<stations/>`).
+    #[serde(rename = "station", default)]
     pub stations: Vec<IRISStation>,
 }
 
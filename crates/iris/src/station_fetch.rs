@@ -4,20 +4,9 @@ use quick_xml::de::from_str;
 use crate::{dto::IRISStationError, station_dto::{IRISStation, StationInfo, StationInfosPayload, Stations}};
 
 
-pub fn get_station(id: &str) -> Result<IRISStation, IRISStationError> {
-    let body: String = ureq::get(&format!(
-        "https://iris.noncd.db.de/iris-tts/timetable/station/{}",
-        id
-    ))
-    //.set("Example-Header", "header value")
-    .call().map_err(Box::new)?
-    .into_string()?;
-
-    debug!("body: {}", body);
-
-    let stations: Stations = from_str(&body)?;
-    let station = stations
-        .stations
+/// Parse a `/timetable/station/{id}` body and pick the station with the given DS100.
+pub fn parse_station(body: &str, id: &str) -> Result<IRISStation, IRISStationError> {
+    let station = parse_stations(body)?
         .into_iter()
         .find(|s| s.ds100 == id)
         .ok_or_else(|| IRISStationError::NotFound(id.to_owned()))?;
@@ -25,6 +14,44 @@ pub fn get_station(id: &str) -> Result<IRISStation, IRISStationError> {
     Ok(station)
 }
 
+/// Parse a `/timetable/station/{pattern}` body with any number of stations.
+pub fn parse_stations(body: &str) -> Result<Vec<IRISStation>, IRISStationError> {
+    debug!("body: {}", body);
+
+    let stations: Stations = from_str(body)?;
+    Ok(stations.stations)
+}
+
+/// Whether an IRIS station lookup for `pattern` finds `station`: `pattern` is its EVA,
+/// DS100 or name, ignoring case. `*` matches any number of characters.
+pub fn station_matches(station: &IRISStation, pattern: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    [&station.eva, &station.ds100, &station.name]
+        .into_iter()
+        .any(|value| wildcard_matches(&pattern, &value.to_lowercase()))
+}
+
+/// Whether `value` matches `pattern` as a whole, `*` matches any number of characters.
+pub fn wildcard_matches(pattern: &str, value: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = value.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No wildcard
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
 
 pub fn get_station_infos(path: &str, from_api: bool) -> Result<Vec<StationInfo>, IRISStationError> {
     info!("Fetching stations from bahnvorhersage");
@@ -14,6 +14,23 @@ pub enum IRISTimetableError {
     RequestFailed(u16, String),
     #[error("empty timetable for {0}")]
     EmptyTimetable(i32),
+    #[error("IRIS circuit open, retry in {0:?}")]
+    CircuitOpen(std::time::Duration),
+}
+
+impl IRISTimetableError {
+    /// Network and IO failures, `429` and `5xx` responses are worth retrying.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            IRISTimetableError::Network(_) | IRISTimetableError::Io(_) => true,
+            IRISTimetableError::RequestFailed(status, _) => is_retryable_status(*status),
+            _ => false,
+        }
+    }
+}
+
+pub(crate) fn is_retryable_status(status: u16) -> bool {
+    status == 429 || (500..=599).contains(&status)
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -27,7 +44,7 @@ pub struct Timetable {
     pub stops: Vec<Stop>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct Stop {
     #[serde(rename = "@id")]
     pub id: String,
@@ -52,22 +69,37 @@ pub struct Stop {
     pub departure: Option<Movement>,
 }
 
-// Every stop id has a format like 436096027952993164-2509191659-18
-// where the middle part is the departure time in the format "yymmddHHMM"
-// from the first station on the trains journey
-pub fn get_first_stop_departure_from_stop_id(stop: &Stop) -> Option<NaiveDateTime> {
-    let splits: Vec<&str> = stop.id.split("-").collect();
-    let departure = match splits.len() {
-        0 => None,
-        1 => None,
-        n => Some(splits[n-2])
-    }?;
+/// Parts of a stop id like `436096027952993164-2509191659-18`:
+/// `<tripId>-<yymmddHHMM>-<seq>`, where the middle part is the departure time
+/// at the first station of the journey and `seq` the position of the stop.
+/// Trip ids can contain `-` themselves (e.g. `-5366651459802461238`), so the id is split from the right.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StopId {
+    pub trip_id: String,
+    pub first_departure: NaiveDateTime,
+    pub seq: u32,
+}
+
+impl StopId {
+    pub fn parse(id: &str) -> Option<StopId> {
+        let mut parts = id.rsplitn(3, '-');
+        let seq = parts.next()?.parse::<u32>().ok()?;
+        let departure = parts.next()?;
+        let trip_id = parts.next().filter(|t| !t.is_empty())?;
 
+        let first_departure = parse_yymmdd_hhmm(departure)
+            .inspect_err(|e| error!("Error parsing first stop departure time: {} - {}", e, departure))
+            .ok()?;
 
-    parse_yymmdd_hhmm(departure).inspect_err(|e| error!("Error parsing first stop departure time: {} - {}", e, departure)).ok()
+        Some(StopId { trip_id: trip_id.to_string(), first_departure, seq })
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+pub fn get_first_stop_departure_from_stop_id(stop: &Stop) -> Option<NaiveDateTime> {
+    StopId::parse(&stop.id).map(|id| id.first_departure)
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct TrainLine {
     #[serde(rename = "@f")]
     pub f: Option<String>, // e.g., "F","N","D"
@@ -83,7 +115,7 @@ pub struct TrainLine {
 
 // ---------- Movement (ar/dp) ----------
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct Movement {
     // planned or current time (packed "yymmddHHMM")
     #[serde(rename = "@pt", default, deserialize_with = "opt_pt")]
@@ -91,27 +123,51 @@ pub struct Movement {
     #[serde(rename = "@ct", default, deserialize_with = "opt_pt")]
     pub current: Option<NaiveDateTime>,
 
-    // platform/line/flags
+    // planned/changed platform
     #[serde(rename = "@pp")]
     pub platform: Option<String>,
+    #[serde(rename = "@cp")]
+    pub changed_platform: Option<String>,
+
+    // line, changed line (e.g. "3" for S-Bahn, "45S" for buses)
     #[serde(rename = "@l")]
     pub line: Option<String>,
+    #[serde(rename = "@cl")]
+    pub changed_line: Option<String>,
+
+    // hidden: 1 if the event should not be shown (e.g. passengers cannot board/alight)
     #[serde(rename = "@hi")]
     pub hi: Option<u8>,
 
-
+    // planned/changed path, stations separated by '|'
     #[serde(rename = "@ppth", default, deserialize_with = "opt_pipe_list")]
     pub ppth: Option<Vec<String>>,
     #[serde(rename = "@cpth", default, deserialize_with = "opt_pipe_list")]
     pub cpth: Option<Vec<String>>,
 
-    // additional attributes seen in file
+    // planned/changed status: "p" (planned), "a" (added), "c" (cancelled)
+    #[serde(rename = "@ps")]
+    pub ps: Option<String>,
     #[serde(rename = "@cs")]
     pub cs: Option<String>,
+    // time of the last status change (packed)
     #[serde(rename = "@clt", default, deserialize_with = "opt_pt")]
     pub clt: Option<NaiveDateTime>,
-    #[serde(rename = "@wings")]
-    pub wings: Option<String>,
+
+    // trip ids of wings (trains joined/split at this stop), separated by '|'
+    #[serde(rename = "@wings", default, deserialize_with = "opt_pipe_list")]
+    pub wings: Option<Vec<String>>,
+    // trip id of the train this one turns into (or came from)
+    #[serde(rename = "@tra")]
+    pub tra: Option<String>,
+
+    // planned/changed distant endpoint, distant change (see IRIS docs)
+    #[serde(rename = "@pde")]
+    pub pde: Option<String>,
+    #[serde(rename = "@cde")]
+    pub cde: Option<String>,
+    #[serde(rename = "@dc")]
+    pub dc: Option<i32>,
 
     // nested messages within ar/dp
     #[serde(rename = "m", default)]
@@ -0,0 +1,39 @@
+use chrono::NaiveDate;
+use iris::{dto::{IRISStationError, IRISTimetableError}, fetch::{plan_file_name, FileIrisClient, IrisClient}};
+
+fn date() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2025, 9, 10).unwrap()
+}
+
+#[test]
+fn file_client_reads_documents_named_by_eva_and_hour() {
+    let client = FileIrisClient::new("tests/data");
+
+    assert_eq!("plan_8002549_2509101100.xml", plan_file_name(8002549, &date(), 11));
+    assert_eq!("8002549", client.get_station("AH").unwrap().eva);
+    assert_eq!(3, client.get_timetable(8002549, &date(), 11).unwrap().stops.len());
+    assert_eq!(3, client.get_timetable_changes(8002549).unwrap().stops.len());
+}
+
+#[test]
+fn file_client_treats_missing_timetables_as_empty() {
+    let client = FileIrisClient::new("tests/data");
+
+    assert!(matches!(client.get_timetable(8002549, &date(), 12), Err(IRISTimetableError::EmptyTimetable(12))));
+    assert!(matches!(client.get_recent_timetable_changes(8002549), Err(IRISTimetableError::EmptyTimetable(8002549))));
+    assert!(matches!(client.get_station("XX"), Err(IRISStationError::NotFound(_))));
+}
+
+#[test]
+fn file_client_searches_station_files() {
+    let client = FileIrisClient::new("tests/data");
+
+    let names = |pattern: &str| -> Vec<String> { client.search_stations(pattern).unwrap().into_iter().map(|s| s.name).collect() };
+    assert_eq!(vec!["Hamburg Hbf"], names("hamburg hbf"));
+    assert_eq!(vec!["Hamburg Hbf"], names("Ham*"));
+    assert_eq!(vec!["Hamburg Hbf"], names("*burg*"));
+    assert_eq!(vec!["Hamburg Hbf"], names("8002549"));
+    assert!(names("Hamburg").is_empty());
+    assert!(names("*Altona").is_empty());
+    assert_eq!("AH", client.get_station_by_eva(8002549).unwrap().ds100);
+}
@@ -0,0 +1,102 @@
+use std::{env, fs, sync::Arc};
+
+use chrono::NaiveDate;
+use iris::{archive::{ArchiveQuery, DocumentArchive, DocumentKind, FileArchive}, dto::{IRISStationError, IRISTimetableError}, fetch::IrisClient, mock::MockIrisServer};
+
+fn read_data(name: &str) -> String {
+    fs::read_to_string(format!("tests/data/{}", name)).unwrap()
+}
+
+fn date() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2025, 9, 10).unwrap()
+}
+
+#[test]
+fn mock_serves_recorded_documents() {
+    let server = MockIrisServer::start().unwrap();
+    server
+        .station("AH", &read_data("station_AH.xml"))
+        .plan(8002549, &date(), 11, &read_data("plan_8002549_2509101100.xml"))
+        .fchg(8002549, &read_data("fchg_8002549.xml"));
+    let client = server.client();
+
+    let station = client.get_station("AH").unwrap();
+    assert_eq!("8002549", station.eva);
+    assert_eq!(3, client.get_timetable(8002549, &date(), 11).unwrap().stops.len());
+    assert_eq!(3, client.get_timetable_changes(8002549).unwrap().stops.len());
+    assert_eq!(
+        vec!["/timetable/station/AH", "/timetable/plan/8002549/250910/11", "/timetable/fchg/8002549"],
+        server.requests(),
+    );
+}
+
+#[test]
+fn mock_serves_empty_timetables_and_error_statuses() {
+    let server = MockIrisServer::start().unwrap();
+    server
+        .empty_plan(8002549, &date(), 12)
+        .respond("/timetable/fchg/8002549", 503, "maintenance");
+    let client = server.client();
+
+    assert!(matches!(client.get_timetable(8002549, &date(), 12), Err(IRISTimetableError::EmptyTimetable(12))));
+    match client.get_timetable_changes(8002549) {
+        Err(IRISTimetableError::RequestFailed(503, body)) => assert_eq!("maintenance", body),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    // Nothing registered
+    assert!(matches!(client.get_timetable(8002549, &date(), 13), Err(IRISTimetableError::RequestFailed(404, _))));
+    assert!(matches!(client.get_station("XX"), Err(IRISStationError::RequestFailed(404, _))));
+}
+
+#[test]
+fn mock_serves_station_searches() {
+    let server = MockIrisServer::start().unwrap();
+    let station = read_data("station_AH.xml");
+    server
+        .station("Hamburg Hbf", &station)
+        .station("8002549", &station)
+        .station("Zürich*", "<?xml version='1.0' encoding='UTF-8'?>\n<stations/>\n");
+    let client = server.client();
+
+    assert_eq!(1, client.search_stations("Hamburg Hbf").unwrap().len());
+    assert_eq!("AH", client.get_station_by_eva(8002549).unwrap().ds100);
+    assert!(client.search_stations("Zürich*").unwrap().is_empty());
+    assert_eq!(
+        vec!["/timetable/station/Hamburg%20Hbf", "/timetable/station/8002549", "/timetable/station/Z%C3%BCrich*"],
+        server.requests(),
+    );
+}
+
+#[test]
+fn client_archives_fetched_documents() {
+    let root = env::temp_dir().join(format!("iris-mock-archive-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let archive = Arc::new(FileArchive::new(&root));
+
+    let server = MockIrisServer::start().unwrap();
+    let plan = read_data("plan_8002549_2509101100.xml");
+    server
+        .plan(8002549, &date(), 11, &plan)
+        .respond("/timetable/fchg/8002549", 503, "maintenance")
+        .station("Hamburg Hbf", &read_data("station_AH.xml"));
+    let client = server.client().with_archive(archive.clone());
+
+    client.get_timetable(8002549, &date(), 11).unwrap();
+    assert!(client.get_timetable_changes(8002549).is_err());
+    client.search_stations("Hamburg Hbf").unwrap();
+    // Patterns that would leave the archive directory are not stored.
+    assert!(client.search_stations("../AH").is_err());
+
+    let searches = archive.load(&ArchiveQuery { kinds: Some(vec![DocumentKind::Station]), ..Default::default() }).unwrap();
+    assert_eq!(1, searches.len());
+    assert_eq!(("Hamburg Hbf", 200), (searches[0].station.as_str(), searches[0].status));
+
+    let docs = archive.load(&ArchiveQuery { kinds: Some(vec![DocumentKind::Plan, DocumentKind::Fchg]), ..Default::default() }).unwrap();
+    assert_eq!(2, docs.len());
+    assert_eq!((DocumentKind::Plan, Some((date(), 11)), 200), (docs[0].kind, docs[0].window, docs[0].status));
+    assert_eq!(plan, docs[0].body);
+    assert_eq!((DocumentKind::Fchg, 503, "maintenance"), (docs[1].kind, docs[1].status, docs[1].body.as_str()));
+    assert_eq!(Some(8002549), docs[1].eva());
+
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,98 @@
+use std::fs;
+
+use chrono::NaiveDateTime;
+use iris::{dto::{IRISTimetableError, StopId}, fetch::parse_timetable};
+
+fn read_data(name: &str) -> String {
+    fs::read_to_string(format!("tests/data/{}", name)).unwrap()
+}
+
+fn dt(s: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+}
+
+#[test]
+fn parse_plan_reads_movement_attributes() {
+    let timetable = parse_timetable(&read_data("plan_8002549_2509101100.xml"), 11).unwrap();
+
+    assert_eq!("Hamburg Hbf", timetable.station);
+    assert_eq!(3, timetable.stops.len());
+
+    let ice = &timetable.stops[0];
+    let tl = ice.tl.as_ref().unwrap();
+    assert_eq!(Some("ICE"), tl.category.as_deref());
+    assert_eq!(Some("1007"), tl.number.as_deref());
+
+    let arrival = ice.arrival.as_ref().unwrap();
+    assert_eq!(Some(dt("2025-09-10 11:00")), arrival.planned);
+    assert_eq!(Some("14"), arrival.platform.as_deref());
+    assert_eq!(Some(vec!["Kiel Hbf".to_string(), "Neumünster".to_string(), "Hamburg Dammtor".to_string()]), arrival.ppth);
+
+    let departure = ice.departure.as_ref().unwrap();
+    assert_eq!(Some(vec!["-7874571842864554321-2509101051".to_string()]), departure.wings);
+    assert_eq!(5, departure.ppth.as_ref().unwrap().len());
+
+    let re = timetable.stops[1].departure.as_ref().unwrap();
+    assert_eq!(Some("5a/b"), re.platform.as_deref());
+    assert_eq!(Some("7"), re.line.as_deref());
+    assert_eq!(Some("-2286917416148743091-2509101043-22"), re.tra.as_deref());
+
+    let hidden = timetable.stops[2].arrival.as_ref().unwrap();
+    assert_eq!(Some(1), hidden.hi);
+    assert_eq!(Some("München Hbf"), hidden.pde.as_deref());
+    assert!(timetable.stops[2].departure.is_none());
+}
+
+#[test]
+fn parse_changes_reads_changed_attributes() {
+    let timetable = parse_timetable(&read_data("fchg_8002549.xml"), 8002549).unwrap();
+
+    assert_eq!(Some("8002549"), timetable.eva.as_deref());
+    assert_eq!(3, timetable.stops.len());
+
+    let delayed = &timetable.stops[0];
+    assert_eq!(1, delayed.msgs.len());
+    let arrival = delayed.arrival.as_ref().unwrap();
+    assert_eq!(Some(dt("2025-09-10 11:07")), arrival.current);
+    assert_eq!(Some("13"), arrival.changed_platform.as_deref());
+    assert!(arrival.planned.is_none());
+    assert_eq!(Some(36), arrival.msgs[0].code);
+    let departure = delayed.departure.as_ref().unwrap();
+    assert_eq!(Some("ICE 1007"), departure.changed_line.as_deref());
+
+    let cancelled = timetable.stops[1].departure.as_ref().unwrap();
+    assert_eq!(Some("c"), cancelled.cs.as_deref());
+    assert_eq!(Some("p"), cancelled.ps.as_deref());
+    assert_eq!(Some(dt("2025-09-10 09:55")), cancelled.clt);
+    assert_eq!(Some("Rotenburg(Wümme)"), cancelled.cde.as_deref());
+    assert_eq!(Some(1), cancelled.dc);
+
+    let added = timetable.stops[2].departure.as_ref().unwrap();
+    assert_eq!(Some("a"), added.ps.as_deref());
+    assert_eq!(Some("a"), added.cs.as_deref());
+    assert_eq!(Some(vec!["Hamburg-Harburg".to_string(), "Buchholz(Nordheide)".to_string()]), added.cpth);
+}
+
+#[test]
+fn parse_empty_timetable_returns_empty_error() {
+    let err = parse_timetable(&read_data("empty.xml"), 11).unwrap_err();
+    assert!(matches!(err, IRISTimetableError::EmptyTimetable(11)));
+
+    let err = parse_timetable("<timetable/>", 8002549).unwrap_err();
+    assert!(matches!(err, IRISTimetableError::EmptyTimetable(8002549)));
+}
+
+#[test]
+fn stop_id_is_split_from_the_right() {
+    let id = StopId::parse("-5366651459802461238-2509101051-4").unwrap();
+    assert_eq!("-5366651459802461238", id.trip_id);
+    assert_eq!(dt("2025-09-10 10:51"), id.first_departure);
+    assert_eq!(4, id.seq);
+
+    let id = StopId::parse("8170436720427113217-2509101043-1").unwrap();
+    assert_eq!("8170436720427113217", id.trip_id);
+
+    assert!(StopId::parse("2509101043-1").is_none());
+    assert!(StopId::parse("trip-notadate-1").is_none());
+    assert!(StopId::parse("trip-2509101043-x").is_none());
+}
@@ -1,10 +1,11 @@
-use chrono::NaiveDate;
+use chrono::{NaiveDate, NaiveDateTime};
 use rocket::{form::{self, FromFormField}, request::FromParam};
 use rocket_okapi::okapi::schemars;
 use rocket_okapi::okapi::schemars::JsonSchema;
 
 
 const DATE_FMT: &str = "%y%m%d"; // e.g., 2025-09-07
+const DATE_TIME_FMT: &str = "%y%m%d%H%M"; // e.g., 2025-09-07 14:30, same as IRIS
 
 #[derive(Debug, JsonSchema)]
 pub struct DateParam(pub NaiveDate);
@@ -26,3 +27,16 @@ impl<'r> FromFormField<'r> for DateParam {
             .map_err(|_| form::Error::validation("invalid date").into())
     }
 }
+
+/// Local (Berlin) date and time as `yymmddHHMM`.
+#[derive(Debug)]
+pub struct DateTimeParam(pub NaiveDateTime);
+
+impl DateTimeParam {
+    /// Optional query fields fall back to `None` on invalid values, so routes parse these themselves.
+    pub fn parse(value: &str) -> Option<Self> {
+        NaiveDateTime::parse_from_str(value, DATE_TIME_FMT)
+            .map(DateTimeParam)
+            .ok()
+    }
+}
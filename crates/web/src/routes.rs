@@ -1,5 +1,6 @@
 pub mod index;
 pub mod stations;
 pub mod trains;
+pub mod stops;
 pub mod messages;
 pub mod status_codes;
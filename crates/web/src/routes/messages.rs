@@ -1,3 +1,5 @@
+use chrono::Utc;
+use chrono_tz::Europe::Berlin;
 use rocket::{get, response::status, serde::json::Json, Route, State};
 use rocket::http::Status;
 use rocket_okapi::okapi::openapi3::OpenApi;
@@ -6,7 +8,7 @@ use rocket_okapi::{openapi, openapi_get_routes_spec};
 
 use crate::common::JsonResult;
 use crate::views::MessageView;
-use crate::{common::{error::ErrorBody, params::DateParam}, service::AppService};
+use crate::{common::{error::ErrorBody, params::{DateParam, DateTimeParam}}, service::AppService};
 
 
 #[openapi(tag = "Messages")]
@@ -40,9 +42,34 @@ fn messages_for_train(train_id: &str, st: &State<AppService>) -> JsonResult<Vec<
 
 }
 
+/// Messages in effect at `at` (`yymmddHHMM`, local time), defaults to now.
+#[openapi(tag = "Messages")]
+#[get("/active?<at>")]
+fn active_messages(at: Option<&str>, st: &State<AppService>) -> JsonResult<Vec<MessageView>> {
+    let at = match at {
+        Some(at) => DateTimeParam::parse(at).map(|at| at.0).ok_or_else(|| {
+            status::Custom(Status::BadRequest, Json(ErrorBody {
+                code: 400,
+                error: "Invalid time".to_string(),
+                message: format!("{} is no yymmddHHMM time", at),
+            }))
+        })?,
+        None => Utc::now().with_timezone(&Berlin).naive_local(),
+    };
+    let messages = st.message_repo.get_active_at(&at).map_err(|e| {
+        status::Custom(Status::InternalServerError, Json(ErrorBody {
+            code: 500,
+            error: "Failed to fetch active messages".to_string(),
+            message: e.to_string(),
+        }))
+    })?;
+
+    Ok(Json(messages.iter().map(|m| MessageView::from_model(m, &st.api_base)).collect()))
+}
+
 
 pub fn routes() -> (Vec<Route>, OpenApi) {
     openapi_get_routes_spec![
-        messages_for_date_and_code, messages_for_train
+        messages_for_date_and_code, messages_for_train, active_messages
     ]
 }
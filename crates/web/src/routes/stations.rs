@@ -1,10 +1,12 @@
+use chrono::Utc;
+use chrono_tz::Europe::Berlin;
 use rocket::{get, response::status, serde::json::Json, Route, State};
 use rocket::http::Status;
 use rocket_okapi::okapi::openapi3::OpenApi;
 use rocket_okapi::{openapi, openapi_get_routes_spec};
 
 use crate::common::JsonResult;
-use crate::views::{StationView, StopView, TrainView};
+use crate::views::{CancelledMovementView, MessageView, PlatformChangeView, PlatformView, StationView, StopView, TrainView};
 use crate::{common::{error::ErrorBody, params::DateParam}, service::AppService};
 
 #[openapi(tag = "Stations")]
@@ -21,6 +23,32 @@ fn stations(st: &State<AppService>) -> JsonResult<Vec<StationView>> {
     Ok(Json(stations.iter().map(StationView::from_model).collect()))
 }
 
+const DEFAULT_SEARCH_LIMIT: usize = 20;
+
+/// Stations by EVA, DS100 or name. Names ignore case, accents and umlauts ("Munchen" finds
+/// "München Hbf") and may contain `*` wildcards. Best matches first.
+#[openapi(tag = "Stations")]
+#[get("/search?<q>&<limit>")]
+fn search_stations(q: &str, limit: Option<usize>, st: &State<AppService>) -> JsonResult<Vec<StationView>> {
+    if q.trim().is_empty() {
+        return Err(status::Custom(Status::BadRequest, Json(ErrorBody {
+            code: 400,
+            error: "Empty search query".to_string(),
+            message: "q must not be empty".to_string(),
+        })));
+    }
+
+    let stations = st.station_repo.search(q, limit.unwrap_or(DEFAULT_SEARCH_LIMIT)).map_err(|e| {
+        status::Custom(Status::InternalServerError, Json(ErrorBody {
+            code: 500,
+            error: "Failed to search stations".to_string(),
+            message: e.to_string(),
+        }))
+    })?;
+
+    Ok(Json(stations.iter().map(StationView::from_model).collect()))
+}
+
 #[openapi(tag = "Stations")]
 #[get("/<ds100>")]
 fn station(ds100: &str, st: &State<AppService>) -> JsonResult<StationView> {
@@ -36,9 +64,33 @@ fn station(ds100: &str, st: &State<AppService>) -> JsonResult<StationView> {
     }
 }
 
+/// The station followed by the stations IRIS groups with it, e.g. the S-Bahn platforms of a hub.
+/// Trains, stops, cancellations and platform changes cover all of them with `include_group=true`.
+#[openapi(tag = "Stations")]
+#[get("/<ds100>/group")]
+fn station_group(ds100: &str, st: &State<AppService>) -> JsonResult<Vec<StationView>> {
+    let station = st.station_repo.get_by_ds100(ds100).map_err(|e| {
+        status::Custom(Status::NotFound, Json(ErrorBody {
+            code: 404,
+            error: "Station not found".to_string(),
+            message: e.to_string(),
+        }))
+    })?;
+
+    let stations = st.station_repo.get_group(&station).map_err(|e| {
+        status::Custom(Status::InternalServerError, Json(ErrorBody {
+            code: 500,
+            error: format!("Failed to fetch station group of {}", station.name),
+            message: e.to_string(),
+        }))
+    })?;
+
+    Ok(Json(stations.iter().map(StationView::from_model).collect()))
+}
+
 #[openapi(tag = "Stations")]
-#[get("/<ds100>/trains/<date>")] // TODO: Document that stops is empty
-fn trains_for_station(ds100: &str, date: DateParam, st: &State<AppService>) -> JsonResult<Vec<TrainView>> {
+#[get("/<ds100>/trains/<date>?<include_group>")] // TODO: Document that stops is empty
+fn trains_for_station(ds100: &str, date: DateParam, include_group: Option<bool>, st: &State<AppService>) -> JsonResult<Vec<TrainView>> {
     let station = st.station_repo.get_by_ds100(ds100).map_err(|e| {
         status::Custom(Status::InternalServerError, Json(ErrorBody {
             code: 500,
@@ -47,7 +99,15 @@ fn trains_for_station(ds100: &str, date: DateParam, st: &State<AppService>) -> J
         }))
     })?;
 
-    let trains = st.train_repo.get_by_station_and_date(&station, &date.0).map_err(|e| {
+    let stations = st.stations_for(&station, include_group.unwrap_or(false)).map_err(|e| {
+        status::Custom(Status::InternalServerError, Json(ErrorBody {
+            code: 500,
+            error: "Failed to fetch station group".to_string(),
+            message: e.to_string(),
+        }))
+    })?;
+
+    let trains = st.train_repo.get_by_stations_and_date(&stations, &date.0).map_err(|e| {
         status::Custom(Status::InternalServerError, Json(ErrorBody {
             code: 500,
             error: "Failed to fetch trains".to_string(),
@@ -59,8 +119,8 @@ fn trains_for_station(ds100: &str, date: DateParam, st: &State<AppService>) -> J
 }
 
 #[openapi(tag = "Stations")]
-#[get("/<ds100>/stops/<date>")] // TODO: Document that stops is empty
-fn stops_for_station(ds100: &str, date: DateParam, st: &State<AppService>) -> JsonResult<Vec<StopView>> {
+#[get("/<ds100>/stops/<date>?<include_messages>&<include_group>")] // TODO: Document that stops is empty
+fn stops_for_station(ds100: &str, date: DateParam, include_messages: Option<bool>, include_group: Option<bool>, st: &State<AppService>) -> JsonResult<Vec<StopView>> {
     let station = st.station_repo.get_by_ds100(ds100).map_err(|e| {
         status::Custom(Status::InternalServerError, Json(ErrorBody {
             code: 500,
@@ -69,7 +129,15 @@ fn stops_for_station(ds100: &str, date: DateParam, st: &State<AppService>) -> Js
         }))
     })?;
 
-    let trains = st.stop_repo.get_by_station_and_date(&station, &date.0).map_err(|e| {
+    let stations = st.stations_for(&station, include_group.unwrap_or(false)).map_err(|e| {
+        status::Custom(Status::InternalServerError, Json(ErrorBody {
+            code: 500,
+            error: "Failed to fetch station group".to_string(),
+            message: e.to_string(),
+        }))
+    })?;
+
+    let trains = st.stop_repo.get_by_stations_and_date(&stations, &date.0).map_err(|e| {
         status::Custom(Status::InternalServerError, Json(ErrorBody {
             code: 500,
             error: format!("Failed to fetch stops for {}", station.name),
@@ -77,13 +145,155 @@ fn stops_for_station(ds100: &str, date: DateParam, st: &State<AppService>) -> Js
         }))
     })?;
 
-    Ok(Json(trains.iter().map(|s| StopView::from_model(s, None, false)).collect()))
+    let views = trains.iter().map(|s| StopView::from_model(s, None, false));
+    if !include_messages.unwrap_or(false) {
+        return Ok(Json(views.collect()));
+    }
+
+    let stop_ids: Vec<String> = trains.iter().map(|s| s.id.clone()).collect();
+    let (messages, codes) = st.messages_for_stops(&stop_ids).map_err(|e| {
+        status::Custom(Status::InternalServerError, Json(ErrorBody {
+            code: 500,
+            error: format!("Failed to fetch messages for {}", station.name),
+            message: e.to_string(),
+        }))
+    })?;
+
+    Ok(Json(views.map(|v| v.with_messages(&messages, &codes)).collect()))
+}
+
+#[openapi(tag = "Stations")]
+#[get("/<ds100>/cancellations/<date>?<include_group>")]
+fn cancellations_for_station(ds100: &str, date: DateParam, include_group: Option<bool>, st: &State<AppService>) -> JsonResult<Vec<CancelledMovementView>> {
+    let station = st.station_repo.get_by_ds100(ds100).map_err(|e| {
+        status::Custom(Status::InternalServerError, Json(ErrorBody {
+            code: 500,
+            error: "Failed to fetch station info".to_string(),
+            message: e.to_string(),
+        }))
+    })?;
+
+    let stations = st.stations_for(&station, include_group.unwrap_or(false)).map_err(|e| {
+        status::Custom(Status::InternalServerError, Json(ErrorBody {
+            code: 500,
+            error: "Failed to fetch station group".to_string(),
+            message: e.to_string(),
+        }))
+    })?;
+
+    let stops = st.stop_repo.get_cancelled_by_stations_and_date(&stations, &date.0).map_err(|e| {
+        status::Custom(Status::InternalServerError, Json(ErrorBody {
+            code: 500,
+            error: format!("Failed to fetch cancellations for {}", station.name),
+            message: e.to_string(),
+        }))
+    })?;
+
+    let mut cancellations: Vec<CancelledMovementView> = stops
+        .iter()
+        .flat_map(|s| s.cancelled_movements().into_iter().map(move |(kind, m)| CancelledMovementView::from_model(s, kind, m)))
+        .collect();
+    cancellations.sort_by_key(|c| c.planned);
+
+    Ok(Json(cancellations))
+}
+
+#[openapi(tag = "Stations")]
+#[get("/<ds100>/platform-changes?<include_group>")]
+fn platform_changes_for_station(ds100: &str, include_group: Option<bool>, st: &State<AppService>) -> JsonResult<Vec<PlatformChangeView>> {
+    let station = st.station_repo.get_by_ds100(ds100).map_err(|e| {
+        status::Custom(Status::InternalServerError, Json(ErrorBody {
+            code: 500,
+            error: "Failed to fetch station info".to_string(),
+            message: e.to_string(),
+        }))
+    })?;
+
+    let stations = st.stations_for(&station, include_group.unwrap_or(false)).map_err(|e| {
+        status::Custom(Status::InternalServerError, Json(ErrorBody {
+            code: 500,
+            error: "Failed to fetch station group".to_string(),
+            message: e.to_string(),
+        }))
+    })?;
+
+    let today = Utc::now().with_timezone(&Berlin).date_naive();
+    let stops = st.stop_repo.get_platform_changes_by_stations_and_date(&stations, &today).map_err(|e| {
+        status::Custom(Status::InternalServerError, Json(ErrorBody {
+            code: 500,
+            error: format!("Failed to fetch platform changes for {}", station.name),
+            message: e.to_string(),
+        }))
+    })?;
+
+    let mut changes: Vec<PlatformChangeView> = stops
+        .iter()
+        .flat_map(|s| s.platform_changes().into_iter().filter_map(move |(kind, m)| PlatformChangeView::from_model(s, kind, m)))
+        .collect();
+    changes.sort_by_key(|c| c.planned);
+
+    Ok(Json(changes))
+}
+
+/// Platforms IRIS lists for the station and those seen in stored stops, with how often
+/// arrivals and departures are planned at them and moved away from them.
+#[openapi(tag = "Stations")]
+#[get("/<ds100>/platforms")]
+fn platforms_for_station(ds100: &str, st: &State<AppService>) -> JsonResult<Vec<PlatformView>> {
+    let station = st.station_repo.get_by_ds100(ds100).map_err(|e| {
+        status::Custom(Status::NotFound, Json(ErrorBody {
+            code: 404,
+            error: "Station not found".to_string(),
+            message: e.to_string(),
+        }))
+    })?;
+
+    let platforms = st.station_repo.get_platforms(&station).map_err(|e| {
+        status::Custom(Status::InternalServerError, Json(ErrorBody {
+            code: 500,
+            error: format!("Failed to fetch platforms of {}", station.name),
+            message: e.to_string(),
+        }))
+    })?;
+
+    let usage = st.stop_repo.get_platform_usage(&station).map_err(|e| {
+        status::Custom(Status::InternalServerError, Json(ErrorBody {
+            code: 500,
+            error: format!("Failed to count platform usage of {}", station.name),
+            message: e.to_string(),
+        }))
+    })?;
+
+    Ok(Json(platforms.with_usage(&usage).iter().map(|u| PlatformView::from_model(u, &platforms)).collect()))
+}
+
+#[openapi(tag = "Stations")]
+#[get("/<ds100>/messages")]
+fn messages_for_station(ds100: &str, st: &State<AppService>) -> JsonResult<Vec<MessageView>> {
+    let station = st.station_repo.get_by_ds100(ds100).map_err(|e| {
+        status::Custom(Status::NotFound, Json(ErrorBody {
+            code: 404,
+            error: "Station not found".to_string(),
+            message: e.to_string(),
+        }))
+    })?;
+
+    let messages = st.message_repo.get_station_messages(station.id).map_err(|e| {
+        status::Custom(Status::InternalServerError, Json(ErrorBody {
+            code: 500,
+            error: format!("Failed to fetch messages for {}", station.name),
+            message: e.to_string(),
+        }))
+    })?;
+
+    Ok(Json(messages.iter().map(|m| MessageView::from_model(m, &st.api_base)).collect()))
 }
 
 
 pub fn routes() -> (Vec<Route>, OpenApi) {
     openapi_get_routes_spec![
-        station, trains_for_station, stops_for_station, stations
+        station, search_stations, station_group, trains_for_station, stops_for_station, cancellations_for_station, platform_changes_for_station,
+        platforms_for_station, messages_for_station, stations
     ]
 }
 
@@ -0,0 +1,45 @@
+use rocket::{get, response::status, serde::json::Json, Route, State};
+use rocket::http::Status;
+use rocket_okapi::okapi::openapi3::OpenApi;
+use rocket_okapi::{openapi, openapi_get_routes_spec};
+
+
+use crate::common::JsonResult;
+use crate::views::StopObservationView;
+use crate::{common::error::ErrorBody, service::AppService};
+
+
+#[openapi(tag = "Stops")]
+#[get("/<stop_id>/observations")]
+fn observations_for_stop(stop_id: &str, st: &State<AppService>) -> JsonResult<Vec<StopObservationView>> {
+    let observations = st.stop_observation_repo.get_for_stop(stop_id).map_err(|e| {
+        status::Custom(Status::InternalServerError, Json(ErrorBody {
+            code: 500,
+            error: format!("Failed to fetch observations for stop {}", stop_id),
+            message: e.to_string(),
+        }))
+    })?;
+
+    Ok(Json(observations.iter().map(StopObservationView::from_model).collect()))
+}
+
+#[openapi(tag = "Stops")]
+#[get("/train/<train_id>/observations")]
+fn observations_for_train(train_id: &str, st: &State<AppService>) -> JsonResult<Vec<StopObservationView>> {
+    let observations = st.stop_observation_repo.get_for_train(train_id).map_err(|e| {
+        status::Custom(Status::InternalServerError, Json(ErrorBody {
+            code: 500,
+            error: format!("Failed to fetch observations for train {}", train_id),
+            message: e.to_string(),
+        }))
+    })?;
+
+    Ok(Json(observations.iter().map(StopObservationView::from_model).collect()))
+}
+
+
+pub fn routes() -> (Vec<Route>, OpenApi) {
+    openapi_get_routes_spec![
+        observations_for_stop, observations_for_train
+    ]
+}
@@ -2,7 +2,7 @@ use rocket::{get, response::status, serde::json::Json, Route, State};
 use rocket::http::Status;
 use rocket_okapi::okapi::openapi3::OpenApi;
 use rocket_okapi::{openapi, openapi_get_routes_spec};
-use wrapper_core::model::{StopWithStation, Train};
+use wrapper_core::model::StopWithStation;
 
 use crate::common::JsonResult;
 use crate::views::{TrainView};
@@ -25,8 +25,8 @@ fn trains(date: DateParam, st: &State<AppService>) -> JsonResult<Vec<TrainView>>
 }
 
 #[openapi(tag = "Trains")]
-#[get("/<id>?<include_stops>")]
-fn train_by_id(id: &str, include_stops: Option<bool>, st: &State<AppService>) -> JsonResult<TrainView> {
+#[get("/<id>?<include_stops>&<include_messages>")]
+fn train_by_id(id: &str, include_stops: Option<bool>, include_messages: Option<bool>, st: &State<AppService>) -> JsonResult<TrainView> {
     let train = st.train_repo.get_by_id(id.to_string()).map_err(|e| {
         status::Custom(Status::NotFound, Json(ErrorBody {
             code: 404,
@@ -50,14 +50,53 @@ fn train_by_id(id: &str, include_stops: Option<bool>, st: &State<AppService>) ->
         }
     };
 
-    Ok(Json(TrainView::from_model(&train, &stops)))
+    let view = TrainView::from_model(&train, &stops);
+    if !include_messages.unwrap_or(false) {
+        return Ok(Json(view));
+    }
+
+    let stop_ids: Vec<String> = stops.iter().map(|s| s.stop.id.clone()).collect();
+    let (messages, codes) = st.messages_for_stops(&stop_ids).map_err(|e| {
+        status::Custom(Status::InternalServerError, Json(ErrorBody {
+            code: 500,
+            error: format!("Failed to fetch messages for train {}", train.id),
+            message: e.to_string(),
+        }))
+    })?;
+
+    Ok(Json(view.with_messages(&messages, &codes)))
 }
 
+/// Lookup by train number, responds with `409` if several journeys share the number on that date.
 #[openapi(tag = "Trains")]
-#[get("/<number>/<date>?<include_stops>")]
-fn train(number: &str, include_stops: Option<bool>, date: DateParam, st: &State<AppService>) -> JsonResult<TrainView> {
-    let id = Train::new_id(number, &date.0);
-    train_by_id(&id, include_stops, st)
+#[get("/<number>/<date>?<include_stops>&<include_messages>")]
+fn train(number: &str, include_stops: Option<bool>, include_messages: Option<bool>, date: DateParam, st: &State<AppService>) -> JsonResult<TrainView> {
+    let trains = st.train_repo.get_by_number_and_date(number, &date.0).map_err(|e| {
+        status::Custom(Status::InternalServerError, Json(ErrorBody {
+            code: 500,
+            error: "Failed to fetch trains".to_string(),
+            message: e.to_string(),
+        }))
+    })?;
+
+    match trains.as_slice() {
+        [] => Err(status::Custom(Status::NotFound, Json(ErrorBody {
+            code: 404,
+            error: "Train not found".to_string(),
+            message: format!("No train {} on {}", number, date.0),
+        }))),
+        [train] => train_by_id(&train.id, include_stops, include_messages, st),
+        trains => Err(status::Custom(Status::Conflict, Json(ErrorBody {
+            code: 409,
+            error: "Ambiguous train number".to_string(),
+            message: format!(
+                "Train {} runs as several journeys on {}: {}",
+                number,
+                date.0,
+                trains.iter().map(|t| t.id.as_str()).collect::<Vec<&str>>().join(", ")
+            ),
+        }))),
+    }
 }
 
 
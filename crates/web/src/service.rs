@@ -1,6 +1,6 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
-use wrapper_core::ports::{MessagePort, StationPort, StatusCodePort, StopPort, TrainPort};
+use wrapper_core::{data::memory::{MemoryDb, MemoryMessageRepo, MemoryStationRepo, MemoryStatusCodeRepo, MemoryStopObservationRepo, MemoryStopRepo, MemoryTrainRepo}, model::{Message, Station, StatusCode}, ports::{MessagePort, PortError, StationPort, StatusCodePort, StopObservationPort, StopPort, TrainPort}};
 
 pub struct AppService {
     pub api_base: String,
@@ -8,5 +8,41 @@ pub struct AppService {
     pub message_repo: Arc<dyn MessagePort>,
     pub train_repo: Arc<dyn TrainPort>,
     pub stop_repo: Arc<dyn StopPort>,
-    pub status_code_repo: Arc<dyn StatusCodePort>
+    pub status_code_repo: Arc<dyn StatusCodePort>,
+    pub stop_observation_repo: Arc<dyn StopObservationPort>,
 } // TODO: Read more on static
+
+impl AppService {
+    /// Service backed by the in-memory repos sharing `db`, runs the API without a database.
+    pub fn in_memory(api_base: String, db: &MemoryDb) -> Self {
+        AppService {
+            api_base,
+            station_repo: Arc::new(MemoryStationRepo::new(db.clone())),
+            message_repo: Arc::new(MemoryMessageRepo::new(db.clone())),
+            train_repo: Arc::new(MemoryTrainRepo::new(db.clone())),
+            stop_repo: Arc::new(MemoryStopRepo::new(db.clone())),
+            status_code_repo: Arc::new(MemoryStatusCodeRepo::new(db.clone())),
+            stop_observation_repo: Arc::new(MemoryStopObservationRepo::new(db.clone())),
+        }
+    }
+
+    /// Messages attached to the stops together with all status codes to resolve their texts.
+    pub fn messages_for_stops(&self, stop_ids: &[String]) -> Result<(Vec<Message>, HashMap<i16, StatusCode>), PortError> {
+        let messages = self.message_repo.get_by_stop_ids(stop_ids)?;
+        let codes = self.status_code_repo
+            .get_all()?
+            .into_iter()
+            .map(|c| (c.code, c))
+            .collect();
+        Ok((messages, codes))
+    }
+
+    /// `station` alone, or followed by the stations of its group if `include_group` is set.
+    pub fn stations_for(&self, station: &Station, include_group: bool) -> Result<Vec<Station>, PortError> {
+        if include_group {
+            self.station_repo.get_group(station)
+        } else {
+            Ok(vec![station.clone()])
+        }
+    }
+}
@@ -1,13 +1,17 @@
+use std::collections::HashMap;
+
 use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 use chrono_tz::Europe::Berlin;
 use rocket_okapi::okapi::schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use wrapper_core::model::{Message, Station, StatusCode, {split_stops_by_time, Movement, Stop, StopWithStation}, Train};
+use wrapper_core::model::{Message, MessageScope, PlatformUsage, Station, StationPlatforms, StatusCode, {split_stops_by_time, EventStatus, Movement, MovementKind, Stop, StopObservation, StopWithStation}, Train};
+use wrapper_core::route::{build_route, sort_stops, RouteEntry, RouteSource};
 
 #[derive(Clone, Debug)]
 #[derive(Serialize, Deserialize, JsonSchema)]
 pub struct TrainView {
     pub id: String,
+    pub trip_id: String,
     pub operator: Option<String>,
     pub category: String,
     pub number: String,
@@ -16,20 +20,25 @@ pub struct TrainView {
     pub next_stop: Option<StopView>,
     pub past_stops: Vec<StopView>,
     pub next_stops: Vec<StopView>,
+    /// Full route merged from the paths of all known stops.
+    pub route: Vec<RouteStopView>,
 }
 
 impl TrainView {
     pub fn from_model(train: &Train, stops: &[StopWithStation]) -> Self {
-        // TODO: Sort stops by time
+        let mut stops = stops.to_vec();
+        sort_stops(&mut stops);
+
         let now = Utc::now().with_timezone(&Berlin).naive_local();
         let (next_stop, next_stops, past_stops) = split_stops_by_time(
-            stops,
+            &stops,
             &now,
             |sws| StopView::from_model(&sws.stop, Some(&sws.station), true)
         );
 
         TrainView {
             id: train.id.clone(),
+            trip_id: train.trip_id.clone(),
             operator: train.operator.clone(),
             category: train.category.clone(),
             number: train.number.clone(),
@@ -38,6 +47,43 @@ impl TrainView {
             next_stop,
             next_stops,
             past_stops,
+            route: build_route(&stops).iter().map(RouteStopView::from_model).collect(),
+        }
+    }
+
+    /// Embeds the messages of all stops, see [`StopView::with_messages`].
+    pub fn with_messages(mut self, messages: &[Message], codes: &HashMap<i16, StatusCode>) -> Self {
+        let embed = |stop: StopView| stop.with_messages(messages, codes);
+
+        self.next_stop = self.next_stop.map(embed);
+        self.past_stops = self.past_stops.into_iter().map(embed).collect();
+        self.next_stops = self.next_stops.into_iter().map(embed).collect();
+        for entry in self.route.iter_mut() {
+            entry.stop = entry.stop.take().map(embed);
+        }
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct RouteStopView {
+    pub name: String,
+    /// `true` if this is one of our polled stops, `false` if only known from paths.
+    pub observed: bool,
+    pub added: bool,
+    pub skipped: bool,
+    pub stop: Option<StopView>,
+}
+
+impl RouteStopView {
+    pub fn from_model(entry: &RouteEntry) -> Self {
+        RouteStopView {
+            name: entry.name.clone(),
+            observed: entry.source == RouteSource::Observed,
+            added: entry.added,
+            skipped: entry.skipped,
+            stop: entry.stop.as_ref().map(|sws| StopView::from_model(&sws.stop, Some(&sws.station), true)),
         }
     }
 }
@@ -51,6 +97,8 @@ pub struct StopView {
 
     pub arrival: Option<MovementView>,
     pub departure: Option<MovementView>,
+    /// Only set if messages were requested.
+    pub messages: Option<Vec<StopMessageView>>,
 }
 
 impl StopView {
@@ -68,6 +116,63 @@ impl StopView {
             station: station.map(StationView::from_model),
             arrival: stop.arrival.as_ref().map(movement_builder),
             departure: stop.departure.as_ref().map(movement_builder),
+            messages: None,
+        }
+    }
+
+    /// Embeds the messages attached to this stop, resolving their codes with `codes`.
+    /// A message attached to several movements of the stop is listed once per movement.
+    pub fn with_messages(mut self, messages: &[Message], codes: &HashMap<i16, StatusCode>) -> Self {
+        let mut stop_messages: Vec<StopMessageView> = messages
+            .iter()
+            .flat_map(|m| {
+                m.scopes_for_stop(&self.id)
+                    .into_iter()
+                    .map(move |scope| StopMessageView::from_model(m, scope, codes))
+            })
+            .collect();
+        stop_messages.sort_by_key(|m| m.timestamp);
+
+        self.messages = Some(stop_messages);
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct StopMessageView {
+    pub id: String,
+    /// `stop`, `arrival` or `departure`
+    pub scope: String,
+    pub code: Option<i32>,
+    /// Text of the status code, if known.
+    pub code_text: Option<String>,
+    pub category: Option<String>,
+    pub m_type: Option<String>,
+    pub priority: Option<i16>,
+    pub timestamp: NaiveDateTime,
+    pub valid_from: Option<NaiveDateTime>,
+    pub valid_to: Option<NaiveDateTime>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl StopMessageView {
+    pub fn from_model(message: &Message, scope: MessageScope, codes: &HashMap<i16, StatusCode>) -> Self {
+        StopMessageView {
+            id: message.id.clone(),
+            scope: scope.as_str().to_string(),
+            code: message.code,
+            code_text: message.code
+                .and_then(|c| i16::try_from(c).ok())
+                .and_then(|c| codes.get(&c))
+                .map(|c| c.long_text.clone()),
+            category: message.category.clone(),
+            m_type: message.m_type.clone(),
+            priority: message.priority,
+            timestamp: message.timestamp,
+            valid_from: message.valid_from,
+            valid_to: message.valid_to,
+            revoked_at: message.revoked_at,
         }
     }
 }
@@ -76,29 +181,173 @@ impl StopView {
 #[derive(Serialize, Deserialize, JsonSchema)]
 pub struct MovementView {
     pub platform: Option<String>,
+    pub changed_platform: Option<String>,
     pub planned: Option<NaiveDateTime>,
     pub current: Option<NaiveDateTime>,
     pub planned_path: Option<Vec<String>>,
     pub changed_path: Option<Vec<String>>,
+    pub line: Option<String>,
+    pub changed_line: Option<String>,
+    /// `Planned`, `Added` or `Cancelled`
+    pub planned_status: Option<String>,
+    pub changed_status: Option<String>,
+    pub status_changed_at: Option<NaiveDateTime>,
+    pub cancelled: bool,
+    pub added: bool,
+    pub hidden: Option<bool>,
+    pub wings: Option<Vec<String>>,
+    pub transition: Option<String>,
+    pub planned_distant_endpoint: Option<String>,
+    pub changed_distant_endpoint: Option<String>,
+    pub distant_change: Option<i32>,
 }
 
 impl MovementView {
+    /// Like [`MovementView::from_model`], but without paths and wings.
     pub fn from_model_simple(movement: &Movement) -> Self {
         MovementView {
-            platform: movement.platform.clone(),
-            planned: movement.planned,
-            current: movement.current,
             planned_path: None,
             changed_path: None,
+            wings: None,
+            ..Self::from_model(movement)
         }
     }
     pub fn from_model(movement: &Movement) -> Self {
         MovementView {
             platform: movement.platform.clone(),
+            changed_platform: movement.changed_platform.clone(),
             planned: movement.planned,
             current: movement.current,
             planned_path: movement.planned_path.clone(),
             changed_path: movement.changed_path.clone(),
+            line: movement.line.clone(),
+            changed_line: movement.changed_line.clone(),
+            planned_status: movement.planned_status.map(event_status_name),
+            changed_status: movement.changed_status.map(event_status_name),
+            status_changed_at: movement.status_changed_at,
+            cancelled: movement.is_cancelled(),
+            added: movement.is_added(),
+            hidden: movement.hidden,
+            wings: movement.wings.clone(),
+            transition: movement.transition.clone(),
+            planned_distant_endpoint: movement.planned_distant_endpoint.clone(),
+            changed_distant_endpoint: movement.changed_distant_endpoint.clone(),
+            distant_change: movement.distant_change,
+        }
+    }
+}
+
+
+fn event_status_name(status: EventStatus) -> String {
+    match status {
+        EventStatus::Planned => "Planned",
+        EventStatus::Added => "Added",
+        EventStatus::Cancelled => "Cancelled",
+    }.to_string()
+}
+
+#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct CancelledMovementView {
+    pub stop_id: String,
+    pub train_id: String,
+    /// `arrival` or `departure`
+    pub kind: String,
+    pub planned: Option<NaiveDateTime>,
+    pub platform: Option<String>,
+    pub cancelled_at: Option<NaiveDateTime>,
+}
+
+impl CancelledMovementView {
+    pub fn from_model(stop: &Stop, kind: MovementKind, movement: &Movement) -> Self {
+        CancelledMovementView {
+            stop_id: stop.id.clone(),
+            train_id: stop.train_id.clone(),
+            kind: kind.as_str().to_string(),
+            planned: movement.planned,
+            platform: movement.platform.clone(),
+            cancelled_at: movement.status_changed_at,
+        }
+    }
+}
+
+
+#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct PlatformChangeView {
+    pub stop_id: String,
+    pub train_id: String,
+    /// `arrival` or `departure`
+    pub kind: String,
+    pub planned: Option<NaiveDateTime>,
+    pub current: Option<NaiveDateTime>,
+    pub old_platform: String,
+    pub new_platform: String,
+}
+
+impl PlatformChangeView {
+    pub fn from_model(stop: &Stop, kind: MovementKind, movement: &Movement) -> Option<Self> {
+        let (old_platform, new_platform) = movement.platform_change()?;
+        Some(PlatformChangeView {
+            stop_id: stop.id.clone(),
+            train_id: stop.train_id.clone(),
+            kind: kind.as_str().to_string(),
+            planned: movement.planned,
+            current: movement.current,
+            old_platform: old_platform.to_string(),
+            new_platform: new_platform.to_string(),
+        })
+    }
+}
+
+
+#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct PlatformView {
+    /// Platform or section, e.g. `5` or `5a`
+    pub platform: String,
+    /// Listed by IRIS for the station, otherwise only seen in stops.
+    pub listed: bool,
+    /// Stored arrivals and departures planned at this platform.
+    pub used: usize,
+    /// Of those, moved to another platform.
+    pub changed: usize,
+}
+
+impl PlatformView {
+    pub fn from_model(usage: &PlatformUsage, platforms: &StationPlatforms) -> Self {
+        PlatformView {
+            platform: usage.platform.clone(),
+            listed: platforms.platforms.contains(&usage.platform),
+            used: usage.used,
+            changed: usage.changed,
+        }
+    }
+}
+
+
+#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct StopObservationView {
+    pub stop_id: String,
+    /// `arrival` or `departure`
+    pub kind: String,
+    pub observed_at: NaiveDateTime,
+    pub current: Option<NaiveDateTime>,
+    pub platform: Option<String>,
+    /// `Planned`, `Added` or `Cancelled`
+    pub status: Option<String>,
+}
+
+impl StopObservationView {
+    pub fn from_model(observation: &StopObservation) -> Self {
+        StopObservationView {
+            stop_id: observation.stop_id.clone(),
+            kind: observation.kind.as_str().to_string(),
+            observed_at: observation.observed_at,
+            current: observation.current,
+            platform: observation.platform.clone(),
+            status: observation.status.map(event_status_name),
         }
     }
 }
@@ -132,8 +381,9 @@ impl StationView {
 #[derive(Serialize, Deserialize, JsonSchema)]
 pub struct MessageView {
     pub id: String,
-    pub train_id: String,
-    pub train: String,
+    /// `None` for station-wide messages.
+    pub train_id: Option<String>,
+    pub train: Option<String>,
 
     pub valid_from: Option<NaiveDateTime>,
     pub valid_to: Option<NaiveDateTime>,
@@ -144,6 +394,12 @@ pub struct MessageView {
     pub m_type: Option<String>,
 
     pub last_updated: Option<DateTime<Utc>>,
+    pub first_seen: Option<DateTime<Utc>>,
+    pub last_seen: Option<DateTime<Utc>>,
+    /// Set once the message was withdrawn by IRIS.
+    pub revoked_at: Option<DateTime<Utc>>,
+    /// Id of a later revision of the message that replaces it.
+    pub superseded_by: Option<String>,
 
     pub stations: Vec<i32>,
 }
@@ -153,7 +409,7 @@ impl MessageView {
         MessageView {
             id: message.id.clone(),
             train_id: message.train_id.clone(),
-            train: format!("{}/trains/{}?include_stops=true", api_base_path, message.train_id),
+            train: message.train_id.as_ref().map(|id| format!("{}/trains/{}?include_stops=true", api_base_path, id)),
             valid_from: message.valid_from,
             valid_to: message.valid_to,
             priority: message.priority,
@@ -162,6 +418,10 @@ impl MessageView {
             timestamp: message.timestamp,
             m_type: message.m_type.clone(),
             last_updated: message.last_updated,
+            first_seen: message.first_seen,
+            last_seen: message.last_seen,
+            revoked_at: message.revoked_at,
+            superseded_by: message.superseded_by.clone(),
             stations: message.stations.clone(),
         }
     }
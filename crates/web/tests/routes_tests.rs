@@ -0,0 +1,347 @@
+use chrono::Utc;
+use chrono_tz::Europe::Berlin;
+use rocket::{http::Status, local::blocking::Client, serde::json::Value};
+use web::service::AppService;
+use wrapper_core::{
+    data::memory::{MemoryDb, MemoryMessageRepo, MemoryStationRepo, MemoryStatusCodeRepo, MemoryStopObservationRepo, MemoryUnitOfWorkRepo},
+    model::{EventStatus, Message, MessageScope, MessageStop, MovementKind, Station, StationGroup, StationPlatforms, StatusCode, StopObservation, Train},
+    ports::{MessagePort, Port, StationPort, StopObservationPort, UnitOfWork, UnitOfWorkPort},
+};
+
+// Shares the fixtures of the core tests.
+#[path = "../../core/tests/common/fixtures.rs"]
+mod fixtures;
+
+use fixtures::{dt, message, station, stop, train};
+
+const API_BASE: &str = "http://localhost:8000/v1";
+
+fn client(db: &MemoryDb) -> Client {
+    // The frontend is not built for the tests, any existing directory will do.
+    std::env::set_var("STATIC_FILES_PATH", std::env::temp_dir());
+    Client::tracked(web::build(AppService::in_memory(API_BASE.to_string(), db))).unwrap()
+}
+
+fn get(client: &Client, uri: &str) -> (Status, Value) {
+    let response = client.get(uri).dispatch();
+    let status = response.status();
+    (status, response.into_json().unwrap_or(Value::Null))
+}
+
+fn ids(body: &Value, key: &str) -> Vec<Value> {
+    body.as_array().unwrap().iter().map(|v| v[key].clone()).collect()
+}
+
+fn seed(db: &MemoryDb, stations: &[Station], work: UnitOfWork) {
+    MemoryStationRepo::new(db.clone()).persist_all(stations).unwrap();
+    MemoryUnitOfWorkRepo::new(db.clone()).commit(&work).unwrap();
+}
+
+#[test]
+fn cancellations_list_cancelled_movements_of_the_day() {
+    let db = MemoryDb::new();
+    let train = train("trip");
+    let mut cancelled = stop("trip-2509100700-1", &train, station().id);
+    cancelled.departure.as_mut().unwrap().changed_status = Some(EventStatus::Cancelled);
+    cancelled.departure.as_mut().unwrap().status_changed_at = Some(dt(6, 40));
+    let running = stop("trip-2509100700-2", &train, station().id);
+    seed(&db, &[station()], UnitOfWork { trains: vec![train], stops: vec![cancelled, running], ..Default::default() });
+    let client = client(&db);
+
+    let (status, body) = get(&client, "/v1/stations/AH/cancellations/250910");
+    assert_eq!(Status::Ok, status);
+    assert_eq!(vec!["trip-2509100700-1"], ids(&body, "stop_id"));
+    assert_eq!("departure", body[0]["kind"]);
+    assert_eq!("2025-09-10T06:40:00", body[0]["cancelled_at"]);
+
+    let (status, body) = get(&client, "/v1/stations/AH/cancellations/250911");
+    assert_eq!(Status::Ok, status);
+    assert!(body.as_array().unwrap().is_empty());
+
+    // Dates are given as yymmdd.
+    assert_eq!(Status::NotFound, get(&client, "/v1/stations/AH/cancellations/2025-09-10").0);
+}
+
+#[test]
+fn platform_changes_list_todays_moved_movements() {
+    let db = MemoryDb::new();
+    let today = Train { date: Utc::now().with_timezone(&Berlin).date_naive(), ..train("today") };
+    let mut moved = stop("today-1", &today, station().id);
+    moved.arrival.as_mut().unwrap().changed_platform = Some("7".to_string());
+    // cp may repeat the planned platform.
+    moved.departure.as_mut().unwrap().changed_platform = Some("5".to_string());
+    let earlier = train("earlier");
+    let mut moved_earlier = stop("earlier-1", &earlier, station().id);
+    moved_earlier.arrival.as_mut().unwrap().changed_platform = Some("7".to_string());
+    let work = UnitOfWork { trains: vec![today, earlier], stops: vec![moved, moved_earlier], ..Default::default() };
+    seed(&db, &[station()], work);
+    let client = client(&db);
+
+    let (status, body) = get(&client, "/v1/stations/AH/platform-changes");
+    assert_eq!(Status::Ok, status);
+    assert_eq!(vec!["today-1"], ids(&body, "stop_id"));
+    assert_eq!("arrival", body[0]["kind"]);
+    assert_eq!(("5", "7"), (body[0]["old_platform"].as_str().unwrap(), body[0]["new_platform"].as_str().unwrap()));
+}
+
+#[test]
+fn observations_are_listed_per_stop_and_per_train() {
+    let db = MemoryDb::new();
+    let train = train("trip");
+    let (first, second) = (stop("trip-1", &train, station().id), stop("trip-2", &train, station().id));
+    seed(&db, &[station()], UnitOfWork { trains: vec![train.clone()], stops: vec![first, second], ..Default::default() });
+    let observation = |stop_id: &str, observed_at, current| StopObservation {
+        stop_id: stop_id.to_string(),
+        kind: MovementKind::Arrival,
+        observed_at,
+        current: Some(current),
+        platform: None,
+        status: Some(EventStatus::Planned),
+    };
+    MemoryStopObservationRepo::new(db.clone()).record_all(&[
+        observation("trip-1", dt(6, 0), dt(7, 5)),
+        observation("trip-1", dt(6, 20), dt(7, 10)),
+        observation("trip-2", dt(6, 20), dt(8, 0)),
+    ]).unwrap();
+    let client = client(&db);
+
+    let (status, body) = get(&client, "/v1/stops/trip-1/observations");
+    assert_eq!(Status::Ok, status);
+    assert_eq!(vec!["2025-09-10T07:05:00", "2025-09-10T07:10:00"], ids(&body, "current"));
+    assert_eq!(("arrival", "Planned"), (body[0]["kind"].as_str().unwrap(), body[0]["status"].as_str().unwrap()));
+
+    let (status, body) = get(&client, &format!("/v1/stops/train/{}/observations", train.id));
+    assert_eq!(Status::Ok, status);
+    assert_eq!(3, body.as_array().unwrap().len());
+    assert!(ids(&body, "stop_id").contains(&Value::from("trip-2")));
+
+    let (status, body) = get(&client, "/v1/stops/unknown/observations");
+    assert_eq!(Status::Ok, status);
+    assert!(body.as_array().unwrap().is_empty());
+}
+
+#[test]
+fn train_numbers_resolve_to_a_single_journey_or_conflict() {
+    let db = MemoryDb::new();
+    let (first, second) = (train("first"), train("second"));
+    let other = Train { number: "456".to_string(), ..train("other") };
+    seed(&db, &[station()], UnitOfWork { trains: vec![first.clone(), second.clone(), other.clone()], ..Default::default() });
+    let client = client(&db);
+
+    let (status, body) = get(&client, "/v1/trains/123/250910");
+    assert_eq!(Status::Conflict, status);
+    assert_eq!(409, body["code"]);
+    let message = body["message"].as_str().unwrap();
+    assert!(message.contains(&first.id) && message.contains(&second.id));
+
+    let (status, body) = get(&client, "/v1/trains/456/250910");
+    assert_eq!(Status::Ok, status);
+    assert_eq!(other.id, body["id"]);
+    assert_eq!(Status::NotFound, get(&client, "/v1/trains/456/250911").0);
+
+    let (status, body) = get(&client, &format!("/v1/trains/{}", first.id));
+    assert_eq!(Status::Ok, status);
+    assert_eq!("first", body["trip_id"]);
+    assert_eq!(Status::NotFound, get(&client, "/v1/trains/unknown").0);
+
+    // `on` is no train number.
+    let (status, body) = get(&client, "/v1/trains/on/250910");
+    assert_eq!(Status::Ok, status);
+    assert_eq!(3, body.as_array().unwrap().len());
+}
+
+#[test]
+fn trains_include_their_route_with_stops() {
+    let db = MemoryDb::new();
+    let train = train("trip");
+    let path = |names: &[&str]| Some(names.iter().map(|n| n.to_string()).collect::<Vec<String>>());
+    let mut hamburg = stop("trip-2509100700-5", &train, station().id);
+    let arrival = hamburg.arrival.as_mut().unwrap();
+    arrival.planned_path = path(&["Berlin Hbf", "Büchen"]);
+    arrival.changed_path = path(&["Berlin Hbf", "Ludwigslust"]);
+    hamburg.departure.as_mut().unwrap().planned_path = path(&["Hamburg Dammtor"]);
+    seed(&db, &[station()], UnitOfWork { trains: vec![train.clone()], stops: vec![hamburg], ..Default::default() });
+    let client = client(&db);
+
+    let (status, body) = get(&client, &format!("/v1/trains/{}", train.id));
+    assert_eq!(Status::Ok, status);
+    assert!(body["route"].as_array().unwrap().is_empty());
+
+    let (status, body) = get(&client, &format!("/v1/trains/{}?include_stops=true", train.id));
+    assert_eq!(Status::Ok, status);
+    let route = &body["route"];
+    assert_eq!(vec!["Berlin Hbf", "Büchen", "Ludwigslust", "Hamburg Hbf", "Hamburg Dammtor"], ids(route, "name"));
+    assert_eq!(vec![false, true, false, false, false], ids(route, "skipped"));
+    assert_eq!(vec![false, false, true, false, false], ids(route, "added"));
+    assert_eq!(vec![false, false, false, true, false], ids(route, "observed"));
+    assert_eq!("trip-2509100700-5", route[3]["stop"]["id"]);
+}
+
+#[test]
+fn station_messages_leave_out_train_messages() {
+    let db = MemoryDb::new();
+    let train = train("trip");
+    seed(&db, &[station()], UnitOfWork { trains: vec![train.clone()], ..Default::default() });
+    let closure = Message { timestamp: dt(6, 30), ..message("closure", None) };
+    MemoryMessageRepo::new(db.clone())
+        .persist_all(&[message("notice", None), closure, message("delay", Some(train.id.clone()))])
+        .unwrap();
+    let client = client(&db);
+
+    let (status, body) = get(&client, "/v1/stations/AH/messages");
+    assert_eq!(Status::Ok, status);
+    assert_eq!(vec!["closure", "notice"], ids(&body, "id"));
+    assert!(ids(&body, "train").iter().all(Value::is_null));
+
+    let (status, body) = get(&client, &format!("/v1/messages/train/{}", train.id));
+    assert_eq!(Status::Ok, status);
+    assert_eq!(vec!["delay"], ids(&body, "id"));
+    assert_eq!(format!("{}/trains/{}?include_stops=true", API_BASE, train.id), body[0]["train"]);
+
+    assert_eq!(Status::NotFound, get(&client, "/v1/stations/XX/messages").0);
+}
+
+#[test]
+fn active_messages_are_those_in_effect_at_the_given_time() {
+    let db = MemoryDb::new();
+    seed(&db, &[station()], UnitOfWork::default());
+    let message_repo = MemoryMessageRepo::new(db.clone());
+    let construction = Message { timestamp: dt(7, 30), valid_from: Some(dt(8, 0)), valid_to: Some(dt(10, 0)), ..message("construction", None) };
+    message_repo.persist_all(&[message("notice", None), construction, message("withdrawn", None)]).unwrap();
+    let seen = ["notice".to_string(), "construction".to_string()];
+    assert_eq!(1, message_repo.revoke_missing(station().id, &seen, &dt(6, 30)).unwrap().len());
+    let client = client(&db);
+
+    let (status, body) = get(&client, "/v1/messages/active?at=2509100700");
+    assert_eq!(Status::Ok, status);
+    assert_eq!(vec!["notice"], ids(&body, "id"));
+    assert_eq!(vec!["construction", "notice"], ids(&get(&client, "/v1/messages/active?at=2509100900").1, "id"));
+    assert!(get(&client, "/v1/messages/active?at=2509091200").1.as_array().unwrap().is_empty());
+    assert_eq!(Status::Ok, get(&client, "/v1/messages/active").0);
+
+    // `at` is given as yymmddHHMM.
+    let (status, body) = get(&client, "/v1/messages/active?at=2025-09-10T07:00");
+    assert_eq!(Status::BadRequest, status);
+    assert_eq!(400, body["code"]);
+}
+
+#[test]
+fn stops_embed_their_messages_on_request() {
+    let db = MemoryDb::new();
+    let train = train("trip");
+    let hamburg = stop("trip-2509100700-5", &train, station().id);
+    seed(&db, &[station()], UnitOfWork { trains: vec![train.clone()], stops: vec![hamburg.clone()], ..Default::default() });
+    MemoryStatusCodeRepo::new(db.clone())
+        .persist(&StatusCode { code: 36, c_type: None, long_text: "Verspätung eines vorausfahrenden Zuges".to_string() })
+        .unwrap();
+    let delay = Message {
+        stops: vec![MessageStop { stop_id: hamburg.id.clone(), scope: MessageScope::Arrival }],
+        ..message("delay", Some(train.id.clone()))
+    };
+    MemoryMessageRepo::new(db.clone()).persist_all(&[delay, message("notice", None)]).unwrap();
+    let client = client(&db);
+
+    let (status, body) = get(&client, "/v1/stations/AH/stops/250910");
+    assert_eq!(Status::Ok, status);
+    assert!(body[0]["messages"].is_null());
+
+    let (status, body) = get(&client, "/v1/stations/AH/stops/250910?include_messages=true");
+    assert_eq!(Status::Ok, status);
+    let messages = &body[0]["messages"];
+    assert_eq!(vec!["delay"], ids(messages, "id"));
+    assert_eq!("arrival", messages[0]["scope"]);
+    assert_eq!("Verspätung eines vorausfahrenden Zuges", messages[0]["code_text"]);
+
+    let (status, body) = get(&client, &format!("/v1/trains/{}?include_stops=true&include_messages=true", train.id));
+    assert_eq!(Status::Ok, status);
+    let route = body["route"].as_array().unwrap();
+    assert_eq!(vec!["delay"], ids(&route[0]["stop"]["messages"], "id"));
+}
+
+#[test]
+fn station_search_finds_codes_and_names() {
+    let db = MemoryDb::new();
+    let munich = |id, name: &str, ds100: &str| Station { id, lat: None, lon: None, name: name.to_string(), ds100: ds100.to_string() };
+    let stations = [station(), munich(8000261, "München Hbf", "MH"), munich(8000262, "München Ost", "MOP")];
+    seed(&db, &stations, UnitOfWork::default());
+    let client = client(&db);
+
+    let (status, body) = get(&client, "/v1/stations/search?q=Munchen");
+    assert_eq!(Status::Ok, status);
+    assert_eq!(vec![8000261, 8000262], ids(&body, "id"));
+    assert_eq!(vec![8000261], ids(&get(&client, "/v1/stations/search?q=Munchen&limit=1").1, "id"));
+    assert_eq!(vec![8000262], ids(&get(&client, "/v1/stations/search?q=mop").1, "id"));
+    assert_eq!(vec![8002549], ids(&get(&client, "/v1/stations/search?q=8002549").1, "id"));
+    assert_eq!(vec![8000262], ids(&get(&client, "/v1/stations/search?q=M*%20Ost").1, "id"));
+    assert!(get(&client, "/v1/stations/search?q=Atlantis").1.as_array().unwrap().is_empty());
+
+    let (status, body) = get(&client, "/v1/stations/search?q=%20");
+    assert_eq!(Status::BadRequest, status);
+    assert_eq!(400, body["code"]);
+
+    // Without a query `search` is taken as a DS100.
+    assert_eq!(Status::NotFound, get(&client, "/v1/stations/search").0);
+    assert_eq!("Hamburg Hbf", get(&client, "/v1/stations/AH").1["name"]);
+}
+
+#[test]
+fn station_groups_extend_station_routes_on_request() {
+    let db = MemoryDb::new();
+    let s_bahn = Station { id: 8098549, lat: None, lon: None, name: "Hamburg Hbf (S)".to_string(), ds100: "AHS".to_string() };
+    let (train, s_train) = (train("trip"), Train { number: "31".to_string(), category: "S".to_string(), ..train("s-trip") });
+    let mut cancelled = stop("s-trip-1", &s_train, s_bahn.id);
+    cancelled.arrival.as_mut().unwrap().changed_status = Some(EventStatus::Cancelled);
+    let work = UnitOfWork { trains: vec![train.clone(), s_train.clone()], stops: vec![stop("trip-1", &train, station().id), cancelled], ..Default::default() };
+    seed(&db, &[station(), s_bahn.clone()], work);
+    MemoryStationRepo::new(db.clone()).persist_groups(&[StationGroup::new(station().id, [s_bahn.id])]).unwrap();
+    let client = client(&db);
+
+    let (status, body) = get(&client, "/v1/stations/AH/group");
+    assert_eq!(Status::Ok, status);
+    assert_eq!(vec![station().id, s_bahn.id], ids(&body, "id"));
+    assert_eq!(vec![s_bahn.id, station().id], ids(&get(&client, "/v1/stations/AHS/group").1, "id"));
+    assert_eq!(Status::NotFound, get(&client, "/v1/stations/XX/group").0);
+
+    // Sorted ids of the stations alone and with their group.
+    let (train_id, s_train_id) = (train.id.as_str(), s_train.id.as_str());
+    for (uri, key, alone, grouped) in [
+        ("/v1/stations/AH/trains/250910", "id", vec![train_id], vec![s_train_id, train_id]),
+        ("/v1/stations/AH/stops/250910", "id", vec!["trip-1"], vec!["s-trip-1", "trip-1"]),
+        ("/v1/stations/AH/cancellations/250910", "stop_id", vec![], vec!["s-trip-1"]),
+    ] {
+        let found = |uri: &str| {
+            let (status, body) = get(&client, uri);
+            assert_eq!(Status::Ok, status, "{}", uri);
+            let mut found: Vec<String> = ids(&body, key).iter().map(|v| v.as_str().unwrap().to_string()).collect();
+            found.sort();
+            found
+        };
+        assert_eq!(alone, found(uri));
+        assert_eq!(alone, found(&format!("{}?include_group=false", uri)));
+        assert_eq!(grouped, found(&format!("{}?include_group=true", uri)));
+    }
+}
+
+#[test]
+fn platforms_combine_listed_and_used_platforms() {
+    let db = MemoryDb::new();
+    let train = train("trip");
+    let mut moved = stop("trip-1", &train, station().id);
+    moved.arrival.as_mut().unwrap().changed_platform = Some("7".to_string());
+    let mut unlisted = stop("trip-2", &train, station().id);
+    for movement in [unlisted.arrival.as_mut().unwrap(), unlisted.departure.as_mut().unwrap()] {
+        movement.platform = Some("12".to_string());
+    }
+    seed(&db, &[station()], UnitOfWork { trains: vec![train], stops: vec![moved, unlisted], ..Default::default() });
+    MemoryStationRepo::new(db.clone()).persist_platforms(&[StationPlatforms::new(station().id, ["5", "6"])]).unwrap();
+    let client = client(&db);
+
+    let (status, body) = get(&client, "/v1/stations/AH/platforms");
+    assert_eq!(Status::Ok, status);
+    assert_eq!(vec!["5", "6", "12"], ids(&body, "platform"));
+    assert_eq!(vec![true, true, false], ids(&body, "listed"));
+    assert_eq!(vec![2, 0, 2], ids(&body, "used"));
+    assert_eq!(vec![1, 0, 0], ids(&body, "changed"));
+
+    assert_eq!(Status::NotFound, get(&client, "/v1/stations/XX/platforms").0);
+}